@@ -0,0 +1,77 @@
+//! What serving the files of a build costs with on-the-fly compression, for scripts of
+//! 10 KB, 100 KB and 1 MB.
+//!
+//! `serve` sends the files through `configure_vite_with` as they are and compressed with
+//! gzip and Brotli, after the first request has compressed them, at the default level and
+//! at the highest.
+//!
+//! Run with `cargo bench --features compress`.
+
+use actix_web::{App, test};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use vite_actix::compression::Compression;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+const SIZES: [(&str, usize); 3] = [
+    ("10 KB", 10 * 1024),
+    ("100 KB", 100 * 1024),
+    ("1 MB", 1024 * 1024),
+];
+
+// A script of about `size` bytes, repetitive the way bundled code is.
+fn script(size: usize) -> String {
+    (0..)
+        .map(|i| format!("export function handler{i}(event) {{ return event.detail * {i}; }}\n"))
+        .scan(0, |len, line| {
+            *len += line.len();
+            (*len <= size).then_some(line)
+        })
+        .collect()
+}
+
+fn serve(c: &mut Criterion) {
+    let dist =
+        std::env::temp_dir().join(format!("vite-actix-bench-compress-{}", std::process::id()));
+    std::fs::create_dir_all(&dist).unwrap();
+    for (_, size) in SIZES {
+        std::fs::write(dist.join(format!("{}.js", size)), script(size)).unwrap();
+    }
+
+    let system = actix_web::rt::System::new();
+    let mut group = c.benchmark_group("serve");
+    for (name, size) in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        for (level_name, level) in [("default", Compression::default().level), ("best", 11)] {
+            let options = ProxyViteOptions::new()
+                .dev_mode(false)
+                .dist_directory(dist.to_str().unwrap())
+                .compression(Compression::default().level(level));
+            let app = system.block_on(test::init_service(App::new().configure_vite_with(options)));
+            let uri = format!("/{}.js", size);
+            for encoding in ["identity", "gzip", "br"] {
+                if encoding == "identity" && level_name != "default" {
+                    continue;
+                }
+                let id = format!("{} {}", encoding, level_name);
+                group.bench_function(BenchmarkId::new(id, name), |b| {
+                    b.iter(|| {
+                        system.block_on(async {
+                            let req = test::TestRequest::get()
+                                .uri(&uri)
+                                .insert_header(("accept-encoding", encoding))
+                                .to_request();
+                            black_box(test::call_and_read_body(&app, req).await)
+                        })
+                    })
+                });
+            }
+        }
+    }
+    group.finish();
+    std::fs::remove_dir_all(&dist).unwrap();
+}
+
+criterion_group!(benches, serve);
+criterion_main!(benches);
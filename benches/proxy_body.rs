@@ -0,0 +1,90 @@
+//! How much copying bodies costs the proxy, for bodies of 1 KB, 100 KB and 10 MB.
+//!
+//! `accumulate` compares collecting the chunks of a body into one contiguous buffer, as the
+//! proxy used to, with keeping the chunks as they are, as it does now. `proxy` sends the
+//! bodies through `configure_vite_with` to a `MockVite`, read whole first and streamed.
+//!
+//! Run with `cargo bench --features test-util`.
+
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{App, test};
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+use vite_actix::test::{MockResponse, MockVite};
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+const SIZES: [(&str, usize); 3] = [
+    ("1 KB", 1024),
+    ("100 KB", 100 * 1024),
+    ("10 MB", 10 * 1024 * 1024),
+];
+
+// The size of the chunks a body arrives in, as read from the socket.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+fn chunks(size: usize) -> Vec<Bytes> {
+    let body = Bytes::from(vec![b'x'; size]);
+    (0..size)
+        .step_by(CHUNK_SIZE)
+        .map(|start| body.slice(start..size.min(start + CHUNK_SIZE)))
+        .collect()
+}
+
+fn accumulate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("accumulate");
+    for (name, size) in SIZES {
+        let chunks = chunks(size);
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("contiguous", name), &chunks, |b, chunks| {
+            b.iter(|| {
+                let mut body = BytesMut::new();
+                for chunk in chunks {
+                    body.extend_from_slice(chunk);
+                }
+                black_box(body.freeze())
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("chunks", name), &chunks, |b, chunks| {
+            b.iter(|| {
+                let mut body = Vec::new();
+                for chunk in chunks {
+                    body.push(chunk.clone());
+                }
+                black_box(body)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn proxy(c: &mut Criterion) {
+    let system = actix_web::rt::System::new();
+    let vite = system.block_on(MockVite::start());
+    for (_, size) in SIZES {
+        vite.respond(format!("/{}.js", size), MockResponse::ok().body(vec![b'x'; size]));
+    }
+
+    let mut group = c.benchmark_group("proxy");
+    for (name, size) in SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        for (mode, stream) in [("buffered", false), ("streamed", true)] {
+            let options = vite.options().stream_responses(stream);
+            let app = system.block_on(test::init_service(App::new().configure_vite_with(options)));
+            let uri = format!("/{}.js", size);
+            group.bench_function(BenchmarkId::new(mode, name), |b| {
+                b.iter(|| {
+                    system.block_on(async {
+                        let req = test::TestRequest::get().uri(&uri).to_request();
+                        let body = test::call_and_read_body(&app, req).await;
+                        assert_eq!(body.len(), size);
+                        black_box(body)
+                    })
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, accumulate, proxy);
+criterion_main!(benches);
@@ -1,9 +1,12 @@
 use actix_web::{web, App, HttpResponse, HttpServer};
 use anyhow::Result;
 use log::{error, info};
+use std::sync::Arc;
+use std::time::Duration;
+use vite_actix::error::StartViteError;
 use vite_actix::proxy_vite_options::ProxyViteOptions;
-use vite_actix::start_vite_server;
 use vite_actix::vite_app_factory::ViteAppFactory;
+use vite_actix::{serve_with_vite, start_vite_server_managed, wait_for_vite_port};
 
 #[actix_web::main]
 async fn main() -> Result<()> {
@@ -11,29 +14,37 @@ async fn main() -> Result<()> {
         .filter_level(log::LevelFilter::Debug)
         .format_timestamp(None)
         .init();
-    if cfg!(debug_assertions) {
-        ProxyViteOptions::new()
-            .port(8779)
-            .working_directory("./examples/wwwroot/")
-//            .disable_logging() // Disable logging from the Vite server.
-            .log_level(log::Level::Debug) // Enables logging and sets the Vite server log level to "info".
-            .build()?;
 
-        std::thread::spawn(|| {
-            loop {
-                info!("Starting Vite server in development mode...");
-                let status = start_vite_server()
-                    .expect("Failed to start vite server")
-                    .wait()
-                    .expect("Vite server crashed!");
-                if !status.success() {
-                    error!("The vite server has crashed!");
-                } else {
-                    break;
-                }
+    ProxyViteOptions::new()
+        .port(8779)
+        .working_directory("./examples/wwwroot/")
+//            .disable_logging() // Disable logging from the Vite server.
+        .log_level(log::Level::Debug) // Enables logging and sets the Vite server log level to "info".
+        .print_startup_banner(true) // Log the Actix and Vite URLs once both are up.
+        .on_exit(Arc::new(|status| {
+            if !status.success() {
+                error!("The vite server has crashed!");
             }
-        });
-    }
+        }))
+        .build()?;
+
+    // Start the Vite server in development mode.
+    // The returned handle kills Vite when it is dropped.
+    info!("Starting Vite server in development mode...");
+    let vite = match start_vite_server_managed() {
+        Ok(vite) => vite,
+        Err(StartViteError::ExecutableNotFound { searched }) => {
+            eprintln!("Could not find the vite executable in {} directories.", searched.len());
+            eprintln!("Install it with `npm install -g vite`, or run `npm install` in ./examples/wwwroot/.");
+            std::process::exit(1);
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    // Wait for Vite to report its port before binding, so the first requests are not
+    // proxied to a port Vite moved away from.
+    let vite_port = wait_for_vite_port(Duration::from_secs(30))?;
+    info!("Vite is listening on port {}", vite_port);
 
     // Create the Actix web server instance.
     let server = HttpServer::new(move || {
@@ -45,12 +56,14 @@ async fn main() -> Result<()> {
             .configure_vite()
     })
     // Bind the Actix server to the address and port "127.0.0.1:8080".
-    .bind("127.0.0.1:8080".to_string())?
-    .run(); // Start the server asynchronously.
+    .bind("127.0.0.1:8080".to_string())?;
 
-    // Output the server information, indicating where the application is accessible.
-    println!("Server running at http://127.0.0.1:8080/");
+    // Report the bound address for the startup banner.
+    for addr in server.addrs() {
+        vite_actix::report_server_addr(addr);
+    }
+    let server = server.run(); // Start the server asynchronously.
 
-    // Await the server's completion and propagate any errors that occur.
-    Ok(server.await?)
+    // Run the server and stop Vite once the server has shut down (e.g. on Ctrl+C).
+    Ok(serve_with_vite(server, vite).await?)
 }
@@ -0,0 +1,51 @@
+//! Renders an Askama layout with the tags of a Vite build, run with
+//! `cargo run --example askama --features askama`.
+//!
+//! The manifest of `examples/manifests/vite5` stands in for a `vite build`. With
+//! `dev_mode(true)` and Vite running, the same layout loads the entry from the dev server.
+use actix_web::{App, HttpResponse, HttpServer, web};
+use anyhow::Result;
+use askama::Template;
+use vite_actix::askama::{ViteAssets, filters};
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+
+#[derive(Template)]
+#[template(path = "layout.html", config = "examples/askama.toml")]
+struct Layout {
+    title: &'static str,
+    assets: ViteAssets,
+}
+
+async fn index() -> actix_web::Result<HttpResponse> {
+    let page = Layout {
+        title: "Vite + Askama",
+        assets: ViteAssets::entry("src/main.tsx").with_react_refresh(),
+    };
+    let page = page
+        .render()
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page))
+}
+
+#[actix_web::main]
+async fn main() -> Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Debug)
+        .init();
+
+    ProxyViteOptions::new()
+        .dev_mode(false)
+        .serve_dist(false)
+        .dist_directory("examples/manifests/vite5")
+        .build()?;
+
+    let server = HttpServer::new(|| App::new().route("/", web::get().to(index)))
+        .bind("127.0.0.1:8080".to_string())?
+        .run();
+
+    println!("Server running at http://127.0.0.1:8080/");
+
+    Ok(server.await?)
+}
@@ -0,0 +1,41 @@
+//! Serves a production build embedded into the binary, run with
+//! `cargo run --release --example embedded --features embed`.
+//!
+//! `examples/embedded_dist` stands in for the `dist` directory of `vite build`. In release
+//! builds its files are part of the executable, so it runs from any directory.
+use actix_web::{App, HttpResponse, HttpServer, web};
+use anyhow::Result;
+use rust_embed::Embed;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+// The dist directory, relative to Cargo.toml.
+#[derive(Embed)]
+#[folder = "examples/embedded_dist/"]
+struct Assets;
+
+#[actix_web::main]
+async fn main() -> Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Debug)
+        .init();
+
+    // Serve the embedded files even in debug builds; there is no dist directory to check.
+    ProxyViteOptions::new()
+        .dev_mode(false)
+        .serve_dist(false)
+        .build()?;
+
+    let server = HttpServer::new(|| {
+        App::new()
+            .route("/api/", web::get().to(HttpResponse::Ok))
+            // Everything else comes from `Assets`, with `index.html` for client-side routes.
+            .configure_vite_embedded::<Assets>()
+    })
+    .bind("127.0.0.1:8080".to_string())?
+    .run();
+
+    println!("Server running at http://127.0.0.1:8080/");
+
+    Ok(server.await?)
+}
@@ -0,0 +1 @@
+document.getElementById("root").textContent = "Hello from the embedded build";
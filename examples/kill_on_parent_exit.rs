@@ -0,0 +1,32 @@
+//! Manual test for `ProxyViteOptions::kill_on_parent_exit`.
+//!
+//! Starts a managed Vite process and then waits forever. Kill this example without letting
+//! it shut down (End Task in Task Manager, closing the console window, or `kill -9`) and
+//! check that no `node`/`esbuild` process of the Vite tree is left behind.
+use anyhow::Result;
+use log::info;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::start_vite_server_managed;
+
+fn main() -> Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Debug)
+        .format_timestamp(None)
+        .init();
+
+    ProxyViteOptions::new()
+        .working_directory("./examples/wwwroot/")
+        .kill_on_parent_exit(true)
+        .build()?;
+
+    let vite = start_vite_server_managed()?;
+    info!(
+        "Vite is running as process {}, now kill process {} and check that Vite is gone",
+        vite.pid(),
+        std::process::id()
+    );
+
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,50 @@
+//! Renders pages on the server in dev mode, run with `cargo run --example ssr --features ssr`.
+//!
+//! Expects a Vite project in `examples/wwwroot` with `src/entry-server.tsx` exporting
+//! `render(url)`, and an `index.html` with an `<!--app-html-->` placeholder, as in Vite's
+//! SSR guide. Errors thrown by the entry show up in the browser with their stack trace.
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+use anyhow::Result;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::ssr::{DevSsr, SsrError};
+use vite_actix::start_vite_server_managed;
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+async fn render_page(req: HttpRequest, ssr: web::Data<DevSsr>) -> Result<HttpResponse, SsrError> {
+    let page = ssr.render(&req.uri().to_string()).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page.html))
+}
+
+#[actix_web::main]
+async fn main() -> Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Debug)
+        .init();
+
+    ProxyViteOptions::new()
+        .working_directory("examples/wwwroot")
+        .build()?;
+
+    // The dev server still serves the client side: modules, styles and HMR.
+    // Both it and the renderer stop when their handles are dropped.
+    let _vite = start_vite_server_managed()?;
+    let ssr = web::Data::new(DevSsr::start_with_template(
+        "/src/entry-server.tsx",
+        "index.html",
+    )?);
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(ssr.clone())
+            .route("/", web::get().to(render_page))
+            .configure_vite()
+    })
+    .bind("127.0.0.1:8080".to_string())?
+    .run();
+
+    println!("Server running at http://127.0.0.1:8080/");
+
+    Ok(server.await?)
+}
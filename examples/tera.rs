@@ -0,0 +1,50 @@
+//! Renders a Tera layout with the tags of a Vite build, run with
+//! `cargo run --example tera --features tera`.
+//!
+//! The manifest of `examples/manifests/vite5` stands in for a `vite build`. With
+//! `dev_mode(true)` and Vite running, the same layout loads the entry from the dev server.
+use actix_web::{App, HttpResponse, HttpServer, web};
+use anyhow::Result;
+use tera::{Context, Tera};
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+
+async fn index(tera: web::Data<Tera>) -> actix_web::Result<HttpResponse> {
+    let mut context = Context::new();
+    context.insert("title", "Vite + Tera");
+    // An entry missing from the build fails here, with its name in the error.
+    let page = tera
+        .render("layout.html.tera", &context)
+        .map_err(actix_web::error::ErrorInternalServerError)?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(page))
+}
+
+#[actix_web::main]
+async fn main() -> Result<()> {
+    env_logger::builder()
+        .filter_level(log::LevelFilter::Debug)
+        .init();
+
+    ProxyViteOptions::new()
+        .dev_mode(false)
+        .serve_dist(false)
+        .dist_directory("examples/manifests/vite5")
+        .build()?;
+
+    let mut tera = Tera::new("examples/templates/**/*.tera")?;
+    vite_actix::tera::register(&mut tera, ProxyViteOptions::global());
+    let tera = web::Data::new(tera);
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(tera.clone())
+            .route("/", web::get().to(index))
+    })
+    .bind("127.0.0.1:8080".to_string())?
+    .run();
+
+    println!("Server running at http://127.0.0.1:8080/");
+
+    Ok(server.await?)
+}
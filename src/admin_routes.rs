@@ -0,0 +1,174 @@
+//! Dev-only endpoints under `/__vite_actix`, answered by the proxy of `configure_vite`
+//! with [`ProxyViteOptions::enable_admin_routes`] instead of being forwarded to Vite.
+//!
+//! - `POST /__vite_actix/restart` restarts the process of
+//!   [`start_vite_server_managed`](crate::start_vite_server_managed), see
+//!   [`ViteServerHandle::restart`], and answers once the new one is ready with
+//!   `{"old_pid": 1234, "new_pid": 1240, "duration_ms": 850}`. Without the `spawn` feature,
+//!   it always answers `409 Conflict`.
+//! - `GET /__vite_actix/faults` answers the latency and errors the proxy injects as
+//!   [`Faults`], `PUT` replaces them with the JSON it is sent, and `DELETE` turns them off;
+//!   each answers with the faults injected from then on. See [`crate::fault_injection`].
+//! - `GET /__vite_actix/status` answers the status the dev toolbar polls, see
+//!   [`crate::dev_toolbar`].
+//!
+//! Only requests from this machine are answered, judged by the address of the connection,
+//! so a reverse proxy on the same host lets everyone through; set
+//! [`ProxyViteOptions::admin_secret`] to also require it in the [`SECRET_HEADER`] header.
+//! Refused requests get a `403` from other machines and a `401` without the secret, and
+//! every error is JSON of the form `{"error": "..."}`.
+//!
+//! [`ProxyViteOptions::enable_admin_routes`]: crate::proxy_vite_options::ProxyViteOptions::enable_admin_routes
+//! [`ProxyViteOptions::admin_secret`]: crate::proxy_vite_options::ProxyViteOptions::admin_secret
+//! [`ViteServerHandle::restart`]: crate::vite_server_handle::ViteServerHandle::restart
+//! [`Faults`]: crate::fault_injection::Faults
+
+use crate::fault_injection::Faults;
+use crate::proxy_vite_options::ProxyViteOptions;
+#[cfg(feature = "spawn")]
+use crate::vite_server_handle::ViteServerHandle;
+use crate::vite_state::ViteProcess;
+use actix_web::http::{Method, StatusCode, header};
+use actix_web::error::PayloadError;
+use actix_web::{HttpRequest, HttpResponse, web};
+use futures_util::Stream;
+use serde_json::json;
+#[cfg(feature = "spawn")]
+use std::time::Instant;
+
+/// The path every admin route is under.
+pub const ADMIN_PREFIX: &str = "/__vite_actix";
+
+/// The header that carries [`ProxyViteOptions::admin_secret`].
+///
+/// [`ProxyViteOptions::admin_secret`]: crate::proxy_vite_options::ProxyViteOptions::admin_secret
+pub const SECRET_HEADER: &str = "x-vite-actix-secret";
+
+// Whether `path`, as Vite would see it, is under `ADMIN_PREFIX`.
+pub(crate) fn is_admin_path(path: &str) -> bool {
+    crate::proxy_vite_options::has_path_prefix(path, ADMIN_PREFIX)
+}
+
+// Answer the admin request for `path`, with its body in `payload`, if `req` may make it.
+pub(crate) async fn handle(
+    req: &HttpRequest,
+    path: &str,
+    options: &ProxyViteOptions,
+    process: &ViteProcess,
+    payload: &mut (impl Stream<Item = Result<web::Bytes, PayloadError>> + Unpin),
+) -> HttpResponse {
+    if !req.peer_addr().is_some_and(|peer| peer.ip().is_loopback()) {
+        return error(
+            StatusCode::FORBIDDEN,
+            "Admin routes only answer requests from this machine",
+        );
+    }
+    if let Some(secret) = &options.admin_secret {
+        let given = req
+            .headers()
+            .get(SECRET_HEADER)
+            .map(|value| value.as_bytes());
+        if given != Some(secret.as_bytes()) {
+            return error(
+                StatusCode::UNAUTHORIZED,
+                "Missing or wrong X-Vite-Actix-Secret header",
+            );
+        }
+    }
+
+    match path.strip_prefix(ADMIN_PREFIX) {
+        Some("/restart") if req.method() == Method::POST => restart().await,
+        Some("/restart") => {
+            let mut res = error(StatusCode::METHOD_NOT_ALLOWED, "Use POST to restart Vite");
+            res.headers_mut()
+                .insert(header::ALLOW, header::HeaderValue::from_static("POST"));
+            res
+        }
+        Some("/faults") => faults(req.method(), options, payload).await,
+        Some("/status") if req.method() == Method::GET => {
+            crate::dev_toolbar::status(options, process)
+        }
+        _ => error(
+            StatusCode::NOT_FOUND,
+            &format!("No admin route at {}", path),
+        ),
+    }
+}
+
+// Restart the default managed process and report both processes.
+#[cfg(feature = "spawn")]
+async fn restart() -> HttpResponse {
+    let Some(vite) = ViteServerHandle::default_handle() else {
+        return error(
+            StatusCode::CONFLICT,
+            "No Vite process to restart, start it with start_vite_server_managed",
+        );
+    };
+    let old_pid = vite.pid();
+    let started = Instant::now();
+    match vite.restart_async().await {
+        Ok(()) => HttpResponse::Ok().json(json!({
+            "old_pid": old_pid,
+            "new_pid": vite.pid(),
+            "duration_ms": started.elapsed().as_millis() as u64,
+        })),
+        Err(e) => error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            &format!("Failed to restart Vite: {:#}", e),
+        ),
+    }
+}
+
+// Without the `spawn` feature, there is no managed process to restart.
+#[cfg(not(feature = "spawn"))]
+async fn restart() -> HttpResponse {
+    error(
+        StatusCode::CONFLICT,
+        "No Vite process to restart, vite-actix was built without the spawn feature",
+    )
+}
+
+// Report, replace or clear the faults the proxy injects.
+async fn faults(
+    method: &Method,
+    options: &ProxyViteOptions,
+    payload: &mut (impl Stream<Item = Result<web::Bytes, PayloadError>> + Unpin),
+) -> HttpResponse {
+    let injector = &options.fault_injection;
+    match *method {
+        Method::GET => {}
+        Method::PUT => {
+            let body = match crate::read_payload(payload).await {
+                Ok(body) => body.into_bytes(),
+                Err(e) => return error(StatusCode::BAD_REQUEST, &e.to_string()),
+            };
+            let faults: Faults = match serde_json::from_slice(&body) {
+                Ok(faults) => faults,
+                Err(e) => {
+                    return error(StatusCode::BAD_REQUEST, &format!("Invalid faults: {}", e));
+                }
+            };
+            if let Some(problem) = faults.problem() {
+                return error(StatusCode::BAD_REQUEST, &format!("Invalid faults: {}", problem));
+            }
+            injector.set(faults);
+        }
+        Method::DELETE => injector.set(Faults::default()),
+        _ => {
+            let mut res = error(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "Use GET, PUT or DELETE for the faults",
+            );
+            res.headers_mut().insert(
+                header::ALLOW,
+                header::HeaderValue::from_static("GET, PUT, DELETE"),
+            );
+            return res;
+        }
+    }
+    HttpResponse::Ok().json(injector.faults())
+}
+
+fn error(status: StatusCode, message: &str) -> HttpResponse {
+    HttpResponse::build(status).json(json!({ "error": message }))
+}
@@ -0,0 +1,182 @@
+//! Vite's asset tags in [Askama](https://askama.rs) templates, enabled with the `askama`
+//! feature.
+//!
+//! [`ViteAssets`] is a field of a template that renders the tags loading one or more
+//! entries, like [`crate::assets::tags_for`]. Whether they come from the dev server or the
+//! build is decided when the template renders, and the manifest of the build is loaded once
+//! and again only when it changes. It is marked as HTML-safe, so it is not escaped.
+//! [`ViteAssets::with_nonce`] puts a CSP nonce, escaped, on every tag.
+//!
+//! [`filters::vite_url`] turns the path of a single file into its URL, like
+//! [`crate::assets::asset_url`], e.g. `{{ "src/assets/logo.svg"|vite_url }}`. Askama looks
+//! custom filters up in a `filters` module next to the template, which a `use` brings in.
+//!
+//! # Example
+//! ```rust
+//! use askama::Template;
+//! use vite_actix::askama::{ViteAssets, filters};
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! #[derive(Template)]
+//! #[template(
+//!     ext = "html",
+//!     source = "{{ assets }}\n<img src=\"{{ \"src/assets/react.svg\"|vite_url }}\">"
+//! )]
+//! struct Page {
+//!     assets: ViteAssets,
+//! }
+//!
+//! // In dev mode, everything comes from the dev server.
+//! let dev = ProxyViteOptions::new().dev_mode(true);
+//! let page = Page {
+//!     assets: ViteAssets::entry("src/main.tsx").with_options(dev),
+//! };
+//! assert_eq!(
+//!     page.render().unwrap(),
+//!     "<script type=\"module\" src=\"/@vite/client\"></script>\n\
+//!      <script type=\"module\" src=\"/src/main.tsx\"></script>\n\
+//!      <img src=\"/src/assets/react.svg\">"
+//! );
+//!
+//! // Otherwise from the manifest of the build.
+//! let prod = ProxyViteOptions::new()
+//!     .dev_mode(false)
+//!     .dist_directory("examples/manifests/vite5");
+//! let page = Page {
+//!     assets: ViteAssets::entry("src/main.tsx").with_options(prod),
+//! };
+//! let html = page.render().unwrap();
+//! assert!(html.starts_with("<link rel=\"stylesheet\" href=\"/assets/main-DiwrgTda.css\">\n"));
+//! assert!(html.contains("<script type=\"module\" src=\"/assets/main-CuXyZ8bL.js\"></script>"));
+//!
+//! // With a CSP nonce, the preamble and every tag carry it.
+//! let dev = ProxyViteOptions::new().dev_mode(true);
+//! let page = Page {
+//!     assets: ViteAssets::entry("src/main.tsx")
+//!         .with_options(dev)
+//!         .with_react_refresh()
+//!         .with_nonce("n<1>"),
+//! };
+//! let html = page.render().unwrap();
+//! assert_eq!(html.matches("<script").count(), 3);
+//! assert_eq!(html.matches("<script type=\"module\" nonce=\"n&lt;1&gt;\">").count(), 1);
+//! assert_eq!(html.matches(" nonce=\"n&lt;1&gt;\"></script>").count(), 2);
+//! ```
+//!
+//! The filter reads the global options, so outside of dev mode it finds the built file:
+//! ```rust,standalone_crate
+//! use askama::Template;
+//! use vite_actix::askama::filters;
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! #[derive(Template)]
+//! #[template(ext = "html", source = "<img src=\"{{ path|vite_url }}\">")]
+//! struct Image<'a> {
+//!     path: &'a str,
+//! }
+//!
+//! ProxyViteOptions::new()
+//!     .dev_mode(false)
+//!     .dist_directory("examples/manifests/vite5")
+//!     .build()
+//!     .unwrap();
+//! let image = Image { path: "src/assets/react.svg" };
+//! assert_eq!(image.render().unwrap(), "<img src=\"/assets/react-CHdo91hT.svg\">");
+//! ```
+
+use crate::assets::AssetTags;
+use crate::html::react_refresh_preamble_with_nonce;
+use crate::proxy_vite_options::ProxyViteOptions;
+use askama::filters::HtmlSafe;
+use std::fmt::{self, Display, Formatter};
+
+/// The tags that load entries of the app into a page, rendered by its `Display`
+/// implementation, see [`crate::askama`].
+///
+/// Without [`ViteAssets::with_options`], the tags are for the global options as they are
+/// when the template renders. Entries the manifest does not have are left out, and logged.
+#[derive(Clone, Default)]
+pub struct ViteAssets {
+    entries: Vec<String>,
+    options: Option<ProxyViteOptions>,
+    react_refresh: bool,
+    nonce: Option<String>,
+}
+
+impl ViteAssets {
+    /// The tags of a single entry, e.g. `src/main.tsx`.
+    pub fn entry(entry: impl Into<String>) -> Self {
+        Self::entries([entry])
+    }
+
+    /// The tags of several entries, with the chunks they share loaded once.
+    pub fn entries<I, S>(entries: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            entries: entries.into_iter().map(Into::into).collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Render the tags for `options` instead of the global options.
+    pub fn with_options(mut self, options: ProxyViteOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Put the preamble of `@vitejs/plugin-react` in front of the tags, see
+    /// [`crate::html::react_refresh_preamble`].
+    pub fn with_react_refresh(mut self) -> Self {
+        self.react_refresh = true;
+        self
+    }
+
+    /// Put `nonce` on every tag, and on the preamble, see [`AssetTags::with_nonce`].
+    pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+}
+
+impl Display for ViteAssets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let options = match &self.options {
+            Some(options) => options.clone(),
+            None => ProxyViteOptions::global(),
+        };
+        if self.react_refresh {
+            let preamble = react_refresh_preamble_with_nonce(&options, self.nonce.as_deref());
+            if !preamble.is_empty() {
+                writeln!(f, "{}", preamble)?;
+            }
+        }
+        let entries: Vec<&str> = self.entries.iter().map(String::as_str).collect();
+        let mut tags = AssetTags::for_entries_with(&options, &entries);
+        if let Some(nonce) = &self.nonce {
+            tags = tags.with_nonce(nonce.clone());
+        }
+        write!(f, "{}", tags)
+    }
+}
+
+// The tags are HTML already, and every URL in them comes from the options or the manifest.
+impl HtmlSafe for ViteAssets {}
+
+/// Custom filters for Askama templates, brought in with
+/// `use vite_actix::askama::filters;` next to the template.
+pub mod filters {
+    use crate::assets::asset_url;
+    use askama::Values;
+    use std::fmt::Display;
+
+    /// The URL of a file of the app for the global options, see
+    /// [`crate::assets::asset_url`], e.g. `{{ "src/assets/logo.svg"|vite_url }}`. The URL is
+    /// escaped like any other value.
+    #[askama::filter_fn]
+    pub fn vite_url(path: impl Display, _: &dyn Values) -> askama::Result<String> {
+        Ok(asset_url(&path.to_string()).into_owned())
+    }
+}
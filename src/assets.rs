@@ -0,0 +1,598 @@
+//! The tags that load a set of Vite entry points into a page, for templates.
+//!
+//! # Example
+//! ```rust
+//! use vite_actix::assets::tags_for_with;
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! // In dev mode the scripts come from the dev server.
+//! let dev = ProxyViteOptions::new().dev_mode(true).asset_base("/static/");
+//! let tags = tags_for_with(&dev, &["src/main.tsx", "src/pages/Settings.tsx"]);
+//! assert_eq!(
+//!     tags.to_string(),
+//!     "<script type=\"module\" src=\"/static/@vite/client\"></script>\n\
+//!      <script type=\"module\" src=\"/static/src/main.tsx\"></script>\n\
+//!      <script type=\"module\" src=\"/static/src/pages/Settings.tsx\"></script>"
+//! );
+//!
+//! // Otherwise from the manifest: all stylesheets first, then the scripts, then a preload
+//! // for every chunk they import, each once.
+//! let prod = ProxyViteOptions::new()
+//!     .dev_mode(false)
+//!     .dist_directory("examples/manifests/vite5")
+//!     .asset_base("/static/");
+//! let tags = tags_for_with(&prod, &["src/main.tsx", "src/pages/Settings.tsx"]);
+//! assert_eq!(
+//!     tags.stylesheets(),
+//!     [
+//!         "/static/assets/main-DiwrgTda.css",
+//!         "/static/assets/shared-ChJ_j-JJ.css",
+//!         "/static/assets/vendor-BmQ1Xyca.css",
+//!         "/static/assets/Settings-C0pL8fVe.css",
+//!     ]
+//! );
+//! assert_eq!(
+//!     tags.scripts(),
+//!     ["/static/assets/main-CuXyZ8bL.js", "/static/assets/Settings-Bq0Zx3Yv.js"]
+//! );
+//! assert_eq!(
+//!     tags.preloads(),
+//!     ["/static/assets/shared-B7PI925R.js", "/static/assets/vendor-BTzA1Yrx.js"]
+//! );
+//! assert!(tags.to_string().starts_with(
+//!     "<link rel=\"stylesheet\" href=\"/static/assets/main-DiwrgTda.css\">\n"
+//! ));
+//! ```
+
+use crate::integrity;
+use crate::manifest::ViteManifest;
+use crate::proxy_vite_options::ProxyViteOptions;
+use log::{error, warn};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+
+// The inline scripts of `@vitejs/plugin-legacy`, as it injects them.
+const SAFARI10_NO_MODULE_FIX: &str = "!function(){var e=document,t=e.createElement(\"script\");if(!(\"noModule\"in t)&&\"onbeforeload\"in t){var n=!1;e.addEventListener(\"beforeload\",(function(e){if(e.target===t)n=!0;else if(!e.target.hasAttribute(\"nomodule\")||!n)return;e.preventDefault()}),!0),t.type=\"module\",t.src=\".\",e.head.appendChild(t),t.remove()}}();";
+const SYSTEM_JS_IMPORT: &str =
+    "System.import(document.getElementById('vite-legacy-entry').getAttribute('data-src'))";
+const DETECT_MODERN_BROWSER: &str = "import.meta.url;import(\"_\").catch(()=>1);(async function*(){})().next();if(location.protocol!=\"file:\"){window.__vite_is_modern_browser=true}";
+const DYNAMIC_FALLBACK: &str = "!function(){if(window.__vite_is_modern_browser)return;console.warn(\"vite: loading legacy chunks, syntax error above and the same error below should be ignored\");var e=document.getElementById(\"vite-legacy-polyfill\"),n=document.createElement(\"script\");n.src=e.src,n.onload=function(){System.import(document.getElementById('vite-legacy-entry').getAttribute('data-src'))},document.body.appendChild(n)}();";
+
+/// The URLs of the files a page needs for a set of entry points, rendered as HTML tags by
+/// its [`Display`] implementation: stylesheets, then scripts, then module preloads.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AssetTags {
+    stylesheets: Vec<String>,
+    scripts: Vec<String>,
+    preloads: Vec<String>,
+    // The chunks loaded with `import()`, only preloaded with `with_dynamic_imports`.
+    dynamic_preloads: Vec<String>,
+    // The files of `@vitejs/plugin-legacy`, if the build has them, and whether to load them.
+    legacy_files: Option<LegacyFiles>,
+    legacy: bool,
+    // The `integrity` values of the URLs, with `ProxyViteOptions::subresource_integrity`.
+    integrity: HashMap<String, String>,
+    // The CSP nonce of every tag, with `with_nonce`.
+    nonce: Option<String>,
+}
+
+// The files `@vitejs/plugin-legacy` adds to a build for the entries.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct LegacyFiles {
+    modern_polyfills: Option<String>,
+    polyfills: String,
+    entries: Vec<String>,
+}
+
+impl AssetTags {
+    /// The tags that load `entries`, e.g. `["src/main.tsx", "src/widget.ts"]`, into one
+    /// page, for the global options, see [`tags_for`].
+    pub fn for_entries(entries: &[&str]) -> Self {
+        Self::for_entries_with(&ProxyViteOptions::global(), entries)
+    }
+
+    /// Like [`AssetTags::for_entries`], but for `options` instead of the global options.
+    ///
+    /// The entries share their chunks: every stylesheet and preload is there once, even if
+    /// several entries import it, and a chunk that one entry runs as a script is not
+    /// preloaded for another. CSS that only belongs to a shared chunk is included too.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::assets::AssetTags;
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    ///
+    /// // A multi-page app, see `examples/manifests/mpa`.
+    /// let options = ProxyViteOptions::new()
+    ///     .dev_mode(false)
+    ///     .dist_directory("examples/manifests/mpa");
+    /// let entries = ["index.html", "about/index.html", "contact/index.html", "src/widget.ts"];
+    /// let tags = AssetTags::for_entries_with(&options, &entries);
+    /// assert_eq!(
+    ///     tags.stylesheets(),
+    ///     [
+    ///         "/assets/index-Dm9Fk4Vb.css",
+    ///         "/assets/layout-Ck3YxW0n.css",
+    ///         "/assets/contact-Bo7Wc2Ls.css",
+    ///         "/assets/widget-Ej8Hs3Qo.css",
+    ///     ]
+    /// );
+    /// assert_eq!(
+    ///     tags.scripts(),
+    ///     [
+    ///         "/assets/index-Cz6Hg1Jd.js",
+    ///         "/assets/about-Bv4Np6Tr.js",
+    ///         "/assets/contact-Ds0Qe5Hy.js",
+    ///         "/assets/widget-Ap2Ld6Rf.js",
+    ///     ]
+    /// );
+    /// assert_eq!(
+    ///     tags.preloads(),
+    ///     ["/assets/layout-C1kR9wPe.js", "/assets/react-BfT2cK8s.js", "/assets/api-Dq8Lm2Zx.js"]
+    /// );
+    ///
+    /// // Chunks loaded with `import()` are only preloaded on request.
+    /// let tags = tags.with_dynamic_imports();
+    /// assert_eq!(
+    ///     &tags.preloads()[3..],
+    ///     ["/assets/Chart-Ck7Jr0Xm.js", "/assets/chart-lib-B3nVt7Qa.js", "/assets/Modal-Bx1Ty8Ug.js"]
+    /// );
+    /// ```
+    pub fn for_entries_with(options: &ProxyViteOptions, entries: &[&str]) -> Self {
+        let url = |path: &str| format!("{}{}", options.asset_base, path.trim_start_matches('/'));
+        let mut tags = AssetTags::default();
+
+        // `vite preview` and `vite build --watch` serve the built files in dev mode too.
+        if options.dev_mode && options.command.is_dev_server() {
+            tags.scripts.push(url("@vite/client"));
+            for entry in entries {
+                AssetTags::push(&mut tags.scripts, url(entry));
+            }
+            return tags;
+        }
+
+        let manifest = match ViteManifest::cached(&options.dist_dir()) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                error!("Failed to load the Vite manifest: {:#}", e);
+                return tags;
+            }
+        };
+        for entry in entries {
+            let Some(chunk) = manifest.entry(entry) else {
+                error!("The Vite manifest has no entry {:?}", entry);
+                continue;
+            };
+            for css in manifest.css_for(entry) {
+                AssetTags::push(&mut tags.stylesheets, url(css));
+            }
+            AssetTags::push(&mut tags.scripts, url(&chunk.file));
+            for import in manifest.imports_for(entry) {
+                AssetTags::push(&mut tags.preloads, url(&import.file));
+            }
+            for import in manifest.dynamic_imports_for(entry) {
+                AssetTags::push(&mut tags.dynamic_preloads, url(&import.file));
+            }
+        }
+        if let Some(polyfills) = manifest.legacy_polyfills() {
+            tags.legacy_files = Some(LegacyFiles {
+                modern_polyfills: manifest.modern_polyfills().map(|chunk| url(&chunk.file)),
+                polyfills: url(&polyfills.file),
+                entries: entries
+                    .iter()
+                    .filter_map(|entry| manifest.legacy_entry(entry))
+                    .map(|chunk| url(&chunk.file))
+                    .collect(),
+            });
+        }
+        // A chunk that runs as a script needs no preload, and one that another entry imports
+        // statically is preloaded anyway.
+        tags.preloads.retain(|preload| !tags.scripts.contains(preload));
+        tags.dynamic_preloads
+            .retain(|preload| !tags.scripts.contains(preload) && !tags.preloads.contains(preload));
+
+        if options.subresource_integrity {
+            match integrity::cached(&options.dist_dir()) {
+                Ok(integrity) => {
+                    for (file, value) in integrity.iter() {
+                        tags.integrity.insert(url(file), value.clone());
+                    }
+                }
+                Err(e) => error!("Failed to compute the subresource integrity: {:#}", e),
+            }
+            let used = [&tags.stylesheets, &tags.scripts, &tags.preloads, &tags.dynamic_preloads];
+            let legacy_polyfills = tags.legacy_files.as_ref().map(|files| &files.polyfills);
+            tags.integrity.retain(|url, _| {
+                used.iter().any(|urls| urls.contains(url)) || legacy_polyfills == Some(url)
+            });
+        }
+        tags
+    }
+
+    /// Also load the build of `@vitejs/plugin-legacy` in browsers without ES modules, with
+    /// the tags the plugin puts into Vite's own `index.html`: its modern polyfills before the
+    /// scripts, if it built them, and after everything else the [`AssetTags::legacy_tags`].
+    /// Does nothing if the manifest has no legacy polyfills, e.g. in dev mode.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::assets::AssetTags;
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    ///
+    /// // A build with the plugin and `modernPolyfills: true`, see `examples/manifests/legacy`.
+    /// let options = ProxyViteOptions::new()
+    ///     .dev_mode(false)
+    ///     .dist_directory("examples/manifests/legacy");
+    /// let tags = AssetTags::for_entries_with(&options, &["index.html"]);
+    /// assert!(tags.legacy_tags().is_empty());
+    ///
+    /// let tags = tags.with_legacy();
+    /// assert_eq!(tags.scripts(), ["/assets/polyfills-Cu1dZ5Wp.js", "/assets/index-Bh5kX0Qe.js"]);
+    /// // The opening tags, in the plugin's order.
+    /// let legacy = tags.legacy_tags();
+    /// let opening: Vec<&str> = legacy.lines().map(|tag| &tag[..=tag.find('>').unwrap()]).collect();
+    /// assert_eq!(
+    ///     opening,
+    ///     [
+    ///         "<script type=\"module\">",
+    ///         "<script type=\"module\">",
+    ///         "<script nomodule>",
+    ///         "<script nomodule crossorigin id=\"vite-legacy-polyfill\" src=\"/assets/polyfills-legacy-B8tqZ1Ae.js\">",
+    ///         "<script nomodule crossorigin id=\"vite-legacy-entry\" data-src=\"/assets/index-legacy-C0pTz4Wm.js\">",
+    ///     ]
+    /// );
+    /// assert!(tags.to_string().ends_with(&tags.legacy_tags()));
+    ///
+    /// // With more than one entry, the others are imported directly.
+    /// let tags = AssetTags::for_entries_with(&options, &["index.html", "src/admin.ts"]).with_legacy();
+    /// assert!(tags.legacy_tags().ends_with(
+    ///     "<script nomodule crossorigin>System.import(\"/assets/admin-legacy-Dk7Rb1Yq.js\")</script>"
+    /// ));
+    /// ```
+    pub fn with_legacy(mut self) -> Self {
+        if let Some(files) = &self.legacy_files {
+            self.legacy = true;
+            if let Some(polyfills) = &files.modern_polyfills
+                && !self.scripts.contains(polyfills)
+            {
+                self.scripts.insert(0, polyfills.clone());
+            }
+        }
+        self
+    }
+
+    /// The tags `@vitejs/plugin-legacy` puts at the end of the page after
+    /// [`AssetTags::with_legacy`], one per line, or nothing. Modern browsers that lack
+    /// dynamic imports are detected and given the legacy build too, Safari 10 is kept from
+    /// running both, and the legacy polyfills and the legacy entries load with SystemJS.
+    pub fn legacy_tags(&self) -> String {
+        let Some(files) = self.legacy_files.as_ref().filter(|_| self.legacy) else {
+            return String::new();
+        };
+        let nonce = self.nonce_attribute();
+        let inline =
+            |kind: &str, script: &str| format!("<script {}{}>{}</script>", kind, nonce, script);
+        let mut tags = vec![
+            inline("type=\"module\"", DETECT_MODERN_BROWSER),
+            inline("type=\"module\"", DYNAMIC_FALLBACK),
+            inline("nomodule", SAFARI10_NO_MODULE_FIX),
+            format!(
+                "<script nomodule crossorigin id=\"vite-legacy-polyfill\" src=\"{}\"{}{}></script>",
+                escape(&files.polyfills),
+                match self.integrity(&files.polyfills) {
+                    Some(integrity) => format!(" integrity=\"{}\"", escape(integrity)),
+                    None => String::new(),
+                },
+                nonce
+            ),
+        ];
+        for (index, entry) in files.entries.iter().enumerate() {
+            // The dynamic fallback imports the entry with this id; the page can have one.
+            tags.push(if index == 0 {
+                format!(
+                    "<script nomodule crossorigin id=\"vite-legacy-entry\" data-src=\"{}\"{}>{}</script>",
+                    escape(entry),
+                    nonce,
+                    SYSTEM_JS_IMPORT
+                )
+            } else {
+                let url = serde_json::to_string(entry).unwrap_or_default();
+                format!(
+                    "<script nomodule crossorigin{}>System.import({})</script>",
+                    nonce,
+                    url.replace("</", "<\\/")
+                )
+            });
+        }
+        tags.join("\n")
+    }
+
+    /// Also preload the chunks the entries load with `import()`, and the chunks those
+    /// import, for pages that are known to need them right away.
+    pub fn with_dynamic_imports(mut self) -> Self {
+        for preload in std::mem::take(&mut self.dynamic_preloads) {
+            AssetTags::push(&mut self.preloads, preload);
+        }
+        self
+    }
+
+    /// Put `nonce` on every tag, for a Content-Security-Policy that only allows the scripts
+    /// and styles of the page with that `'nonce-…'`, see [`crate::html::csp_nonce`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::assets::AssetTags;
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    ///
+    /// let options = ProxyViteOptions::new()
+    ///     .dev_mode(false)
+    ///     .dist_directory("examples/manifests/legacy");
+    /// let tags = AssetTags::for_entries_with(&options, &["index.html", "src/admin.ts"])
+    ///     .with_legacy()
+    ///     .with_nonce("r4nd\"><script>alert(1)</script>");
+    /// assert_eq!(tags.nonce(), Some("r4nd\"><script>alert(1)</script>"));
+    ///
+    /// // Every tag has the nonce, escaped, and there is nothing else where it goes.
+    /// let escaped = "nonce=\"r4nd&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;\"";
+    /// let html = tags.to_string();
+    /// assert_eq!(html.lines().count(), 11);
+    /// for tag in html.lines() {
+    ///     let opening = &tag[..=tag.find('>').unwrap()];
+    ///     assert!(opening.ends_with(&format!(" {}>", escaped)), "{}", opening);
+    /// }
+    /// assert_eq!(html.matches("<script").count(), 9);
+    /// assert!(!html.contains("<script>alert"));
+    /// ```
+    pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+
+    /// The nonce set with [`AssetTags::with_nonce`].
+    pub fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+
+    /// The stylesheets of the entries and of every chunk they import, in the order of the
+    /// entries.
+    pub fn stylesheets(&self) -> &[String] {
+        &self.stylesheets
+    }
+
+    /// The module scripts to run, one per entry; in dev mode preceded by the Vite client.
+    pub fn scripts(&self) -> &[String] {
+        &self.scripts
+    }
+
+    /// The chunks the entries import statically, to fetch alongside the scripts; with
+    /// [`AssetTags::with_dynamic_imports`] also those they import with `import()`.
+    pub fn preloads(&self) -> &[String] {
+        &self.preloads
+    }
+
+    /// `<link rel="stylesheet">` tags, one per line.
+    pub fn stylesheet_tags(&self) -> String {
+        self.tags(&self.stylesheets, |url, integrity| {
+            format!("<link rel=\"stylesheet\" href=\"{}\"{}>", url, integrity)
+        })
+    }
+
+    /// `<script type="module">` tags, one per line.
+    pub fn script_tags(&self) -> String {
+        self.tags(&self.scripts, |url, integrity| {
+            format!("<script type=\"module\" src=\"{}\"{}></script>", url, integrity)
+        })
+    }
+
+    /// `<link rel="modulepreload">` tags, one per line.
+    pub fn preload_tags(&self) -> String {
+        self.tags(&self.preloads, |url, integrity| {
+            format!("<link rel=\"modulepreload\" href=\"{}\"{}>", url, integrity)
+        })
+    }
+
+    /// The `integrity` value of the file at `url`, e.g. `sha384-…`, with
+    /// [`ProxyViteOptions::subresource_integrity`].
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::assets::AssetTags;
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    ///
+    /// let options = ProxyViteOptions::new()
+    ///     .dev_mode(false)
+    ///     .dist_directory("examples/embedded_dist")
+    ///     .subresource_integrity(true);
+    /// let tags = AssetTags::for_entries_with(&options, &["src/main.ts"]);
+    ///
+    /// // From `openssl dgst -sha384 -binary assets/index-BxQw2a1z.js | base64`.
+    /// let script = "sha384-m5sq0YjLvn/6cLCPKTD1hD9jeLUTJBRQDrmMKnnXu8Nn6qx7fpx9kZp/ON5rLbi9";
+    /// assert_eq!(tags.integrity("/assets/index-BxQw2a1z.js"), Some(script));
+    /// assert_eq!(
+    ///     tags.script_tags(),
+    ///     format!(
+    ///         "<script type=\"module\" src=\"/assets/index-BxQw2a1z.js\" integrity=\"{}\" crossorigin=\"anonymous\"></script>",
+    ///         script
+    ///     )
+    /// );
+    /// assert_eq!(
+    ///     tags.stylesheet_tags(),
+    ///     "<link rel=\"stylesheet\" href=\"/assets/index-Cq3Zx9Lm.css\" \
+    ///      integrity=\"sha384-Un1VVPWRhFwdptqCbp6C11q09OsaPAuBIihjcY2/Ac6wrpxem4XVrPRAqT2Q2t3y\" \
+    ///      crossorigin=\"anonymous\">"
+    /// );
+    /// assert_eq!(
+    ///     tags.integrity("/assets/vendor-D4e5F6g7.js"),
+    ///     Some("sha384-RIgLKHVgit/h+BUuaoo7Hj0vC8XGFRvEZJi8CL35qQwkXnEBr2LwvGv9WPF4oOYR")
+    /// );
+    ///
+    /// // Values an SRI plugin wrote into the manifest are used as they are.
+    /// let options = ProxyViteOptions::new()
+    ///     .dev_mode(false)
+    ///     .dist_directory("examples/manifests/sri")
+    ///     .subresource_integrity(true);
+    /// let tags = AssetTags::for_entries_with(&options, &["src/main.ts"]);
+    /// assert!(tags.integrity("/assets/main-Xy3Pq9Rs.js").unwrap().starts_with("sha384-oqVuAfXR"));
+    ///
+    /// // A build missing a file of its manifest fails at startup.
+    /// let options = ProxyViteOptions::new()
+    ///     .dev_mode(false)
+    ///     .serve_dist(false)
+    ///     .dist_directory("examples/manifests/vite5")
+    ///     .subresource_integrity(true);
+    /// assert!(options.build().is_err());
+    /// ```
+    pub fn integrity(&self, url: &str) -> Option<&str> {
+        self.integrity.get(url).map(String::as_str)
+    }
+
+    // A tag per URL, given the escaped URL and its other attributes, one per line.
+    fn tags(&self, urls: &[String], tag: impl Fn(&str, &str) -> String) -> String {
+        urls.iter()
+            .map(|url| {
+                let attributes = self.integrity_attributes(url) + &self.nonce_attribute();
+                tag(&escape(url), &attributes)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // ` integrity="…" crossorigin="anonymous"` for a URL with a known integrity.
+    fn integrity_attributes(&self, url: &str) -> String {
+        match self.integrity(url) {
+            Some(integrity) => format!(
+                " integrity=\"{}\" crossorigin=\"anonymous\"",
+                escape(integrity)
+            ),
+            None => String::new(),
+        }
+    }
+
+    // ` nonce="…"` with a nonce.
+    fn nonce_attribute(&self) -> String {
+        nonce_attribute(self.nonce.as_deref())
+    }
+
+    fn push(list: &mut Vec<String>, url: String) {
+        if !list.contains(&url) {
+            list.push(url);
+        }
+    }
+}
+
+impl Display for AssetTags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let groups = [
+            self.stylesheet_tags(),
+            self.script_tags(),
+            self.preload_tags(),
+            self.legacy_tags(),
+        ];
+        let tags: Vec<&str> = groups
+            .iter()
+            .map(String::as_str)
+            .filter(|group| !group.is_empty())
+            .collect();
+        write!(f, "{}", tags.join("\n"))
+    }
+}
+
+/// The tags that load `entries`, e.g. `["src/main.tsx"]`, into a page, for the global
+/// options.
+///
+/// In dev mode, with the dev server, the page loads `/@vite/client` and the entries from
+/// the dev server. Otherwise it loads the built files, looked up in the manifest of the
+/// dist directory, which `vite build` only writes with `build.manifest` turned on. Entries
+/// the manifest does not have are left out, and logged. Every URL starts with
+/// [`ProxyViteOptions::asset_base`].
+pub fn tags_for(entries: &[&str]) -> AssetTags {
+    tags_for_with(&ProxyViteOptions::global(), entries)
+}
+
+/// Like [`tags_for`], but for `options` instead of the global options.
+pub fn tags_for_with(options: &ProxyViteOptions, entries: &[&str]) -> AssetTags {
+    AssetTags::for_entries_with(options, entries)
+}
+
+/// The URL a single file of the app is served at, e.g. for an image in a template, for the
+/// global options.
+///
+/// In dev mode, with the dev server, that is the source path itself, e.g.
+/// `/src/assets/logo.svg`. Otherwise it is the built file from the manifest of the dist
+/// directory, for assets, entries and CSS entries alike. Files of Vite's `public` directory
+/// are not in the manifest and keep their names; pass them as they are served, e.g.
+/// `favicon.svg`. Other paths are logged and kept as they are. Every URL starts with
+/// [`ProxyViteOptions::asset_base`], except for full URLs like `https://…`, which are
+/// returned unchanged.
+pub fn asset_url(path: &str) -> Cow<'_, str> {
+    asset_url_with(&ProxyViteOptions::global(), path)
+}
+
+/// Like [`asset_url`], but for `options` instead of the global options.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::assets::asset_url_with;
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+///
+/// let dev = ProxyViteOptions::new().dev_mode(true);
+/// assert_eq!(asset_url_with(&dev, "src/assets/react.svg"), "/src/assets/react.svg");
+///
+/// let prod = ProxyViteOptions::new()
+///     .dev_mode(false)
+///     .dist_directory("examples/manifests/vite5")
+///     .asset_base("/static/");
+/// // Assets imported by the code, and entries, including CSS entries.
+/// assert_eq!(asset_url_with(&prod, "src/assets/react.svg"), "/static/assets/react-CHdo91hT.svg");
+/// assert_eq!(asset_url_with(&prod, "/src/main.tsx"), "/static/assets/main-CuXyZ8bL.js");
+/// assert_eq!(asset_url_with(&prod, "src/styles/print.css"), "/static/assets/print-Dk3Lq0Tz.css");
+/// // Public files keep their names.
+/// assert_eq!(asset_url_with(&prod, "/favicon.svg"), "/static/favicon.svg");
+/// // Full URLs are left alone.
+/// assert_eq!(asset_url_with(&prod, "https://cdn.example.com/a.png"), "https://cdn.example.com/a.png");
+/// ```
+pub fn asset_url_with<'a>(options: &ProxyViteOptions, path: &'a str) -> Cow<'a, str> {
+    if path.starts_with("//") || path.contains("://") || path.starts_with("data:") {
+        return Cow::Borrowed(path);
+    }
+    let url = |path: &str| -> Cow<'a, str> {
+        Cow::Owned(format!("{}{}", options.asset_base, path.trim_start_matches('/')))
+    };
+
+    if options.dev_mode && options.command.is_dev_server() {
+        return url(path);
+    }
+
+    let dist_dir = options.dist_dir();
+    match ViteManifest::cached(&dist_dir) {
+        Ok(manifest) => {
+            if let Some(chunk) = manifest.entry(path) {
+                return url(&chunk.file);
+            }
+        }
+        Err(e) => error!("Failed to load the Vite manifest: {:#}", e),
+    }
+    // A file of the `public` directory, copied into the dist directory as it is.
+    if !dist_dir.join(path.trim_start_matches('/')).is_file() {
+        warn!("{:?} is neither in the Vite manifest nor in {}", path, dist_dir.display());
+    }
+    url(path)
+}
+
+// ` nonce="…"` for a tag with `nonce`, or nothing.
+pub(crate) fn nonce_attribute(nonce: Option<&str>) -> String {
+    match nonce {
+        Some(nonce) => format!(" nonce=\"{}\"", escape(nonce)),
+        None => String::new(),
+    }
+}
+
+// Escape a value for an HTML attribute.
+pub(crate) fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,92 @@
+//! Running `vite build` from a `build.rs`, with the `build-helper` feature, so that
+//! `cargo build --release` builds the frontend before [`rust-embed`] snapshots the dist
+//! directory, see [`crate::embedded`].
+//!
+//! [`run_vite_build`] runs the project's `vite build` the way
+//! [`ProxyViteOptions::build_if_missing`] does at runtime: with the same `vite` executable,
+//! in the working directory of the options, and with `--outDir` if
+//! [`ProxyViteOptions::dist_directory`] is set. Before it does, it tells cargo to run the
+//! build script again when the frontend changes, see [`rerun_if_changed`]. A build that
+//! fails or takes longer than [`ProxyViteOptions::build_timeout`] fails the build script
+//! with its output.
+//!
+//! Nothing is built when [`SKIP_BUILD_ENV`], `VITE_ACTIX_SKIP_BUILD`, is set to anything but
+//! `0` or `false`, e.g. on CI without Node.js, or on docs.rs, which sets `DOCS_RS`.
+//!
+//! ```rust,ignore
+//! // build.rs, with vite-actix in [build-dependencies] with the `build-helper` feature
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     vite_actix::build::run_vite_build(&ProxyViteOptions::new().working_directory("frontend"))
+//! }
+//! ```
+//!
+//! [`rust-embed`]: https://docs.rs/rust-embed
+//! [`ProxyViteOptions::build_if_missing`]: crate::proxy_vite_options::ProxyViteOptions::build_if_missing
+//! [`ProxyViteOptions::build_timeout`]: crate::proxy_vite_options::ProxyViteOptions::build_timeout
+//! [`ProxyViteOptions::dist_directory`]: crate::proxy_vite_options::ProxyViteOptions::dist_directory
+
+use crate::package_script::{LOCK_FILES, find_workspace_root};
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::skip_spawn::{env_value, is_truthy};
+use crate::startup_build::{SOURCES, run_build};
+use anyhow::Context;
+use std::path::Path;
+
+pub use crate::startup_build::SKIP_BUILD_ENV;
+
+/// Runs `vite build` for `options` from a build script, see [`crate::build`].
+///
+/// Prints the directives of [`rerun_if_changed`] first, so cargo knows when to run the
+/// build script again even if nothing is built.
+pub fn run_vite_build(options: &ProxyViteOptions) -> anyhow::Result<()> {
+    for directive in rerun_if_changed(options) {
+        println!("{}", directive);
+    }
+    if env_value(SKIP_BUILD_ENV).is_some_and(|value| is_truthy(&value)) {
+        println!(
+            "cargo:warning=Not running vite build: {} is set",
+            SKIP_BUILD_ENV
+        );
+        return Ok(());
+    }
+    if env_value("DOCS_RS").is_some() {
+        return Ok(());
+    }
+    run_build(options).with_context(|| {
+        format!(
+            "Failed to build the frontend in {}",
+            options.working_directory
+        )
+    })
+}
+
+/// The `cargo:` directives that make cargo run the build script again when the frontend of
+/// `options` changes: `src/`, `public/`, `index.html`, `vite.config.*`, `package.json` and
+/// the lock file, also that of the pnpm workspace the project is in, or when
+/// `VITE_ACTIX_SKIP_BUILD` does.
+///
+/// Only paths that exist are listed, since cargo considers a missing one changed on every
+/// build.
+pub fn rerun_if_changed(options: &ProxyViteOptions) -> Vec<String> {
+    let project = Path::new(&options.working_directory);
+    let mut paths: Vec<_> = SOURCES
+        .iter()
+        .chain(&["package.json"])
+        .chain(&LOCK_FILES)
+        .map(|file| project.join(file))
+        .collect();
+    let root = find_workspace_root(project);
+    if let Some(root) = root.filter(|root| project.canonicalize().ok().as_ref() != Some(root)) {
+        paths.extend(LOCK_FILES.iter().map(|file| root.join(file)));
+    }
+
+    let mut directives: Vec<String> = paths
+        .iter()
+        .filter(|path| path.exists())
+        .map(|path| format!("cargo:rerun-if-changed={}", path.display()))
+        .collect();
+    directives.push(format!("cargo:rerun-if-env-changed={}", SKIP_BUILD_ENV));
+    directives
+}
@@ -0,0 +1,262 @@
+#[cfg(feature = "compress")]
+use crate::compression::Compressor;
+use crate::html_entries::HtmlEntries;
+use crate::html_transform::HtmlFiles;
+use crate::navigation_fallback::{is_asset_path, prefers};
+use crate::proxy_vite_options::has_path_prefix;
+use crate::trailing_slash::{self, TrailingSlash};
+use actix_web::http::Method;
+use actix_web::http::header::{
+    ACCEPT, ACCEPT_ENCODING, ALLOW, HeaderValue, HttpDate, IF_MODIFIED_SINCE, IF_NONE_MATCH,
+};
+use actix_web::{HttpRequest, HttpResponse};
+use regex::Regex;
+use serde_json::json;
+#[cfg(feature = "prod-serve")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Cache policy for files that change on every rebuild, e.g. the output of `vite build --watch`.
+pub(crate) const CACHE_NO_CACHE: &str = "no-cache";
+
+// Cache policy for built files with a content hash in their name, which never change.
+pub(crate) const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// Where a production build is served from and how unknown paths are answered.
+pub(crate) struct BuiltDist {
+    pub dist_dir: PathBuf,
+    // The path the build is served under, like `/app`, or empty to serve it at the root.
+    pub serve_prefix: String,
+    // Answer page navigations to unknown paths with `index.html`, for client-side routing.
+    pub spa_fallback: bool,
+    // Path prefixes, like `/api`, that never fall back to `index.html`.
+    pub fallback_excludes: Vec<String>,
+    // The path of the built assets, like `/assets`, which never fall back to `index.html`.
+    pub assets_prefix: String,
+    // Files with a content hash in their name, or `None` if the pattern is invalid.
+    pub hashed: Option<Regex>,
+    // `Cache-Control` values for request paths matching a pattern, checked in order.
+    pub cache_overrides: Vec<(Regex, HeaderValue)>,
+    // Rewrites the HTML pages, if the options have a transform.
+    pub html: Option<Arc<HtmlFiles>>,
+    // Whether page routes are redirected to the form with or without a trailing slash.
+    pub trailing_slash: TrailingSlash,
+    // The HTML entries of a multi-page app that page navigations fall back to.
+    pub html_entries: HtmlEntries,
+    // Compresses files without a precompressed variant, if compression is on.
+    #[cfg(feature = "compress")]
+    pub compressor: Option<Arc<Compressor>>,
+    // More directories to serve files from, under a URL prefix, when the dist has no such file.
+    #[cfg(feature = "prod-serve")]
+    pub extra_dirs: Vec<(String, PathBuf)>,
+}
+
+impl BuiltDist {
+    // The path of the file for `request_path` in the dist directory, the part below the
+    // serve prefix, or `None` if it is outside of it.
+    pub(crate) fn route<'a>(&self, request_path: &'a str) -> Option<&'a str> {
+        has_path_prefix(request_path, &self.serve_prefix)
+            .then(|| &request_path[self.serve_prefix.len()..])
+    }
+
+    // Whether `req` is a page navigation that the app's client-side router should handle:
+    // an HTML request for a path that is not a file, see `is_asset_path`, outside the
+    // excluded prefixes.
+    pub(crate) fn falls_back(&self, req: &HttpRequest) -> bool {
+        let path = req.path();
+        let accepts_html = req
+            .headers()
+            .get(ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html"));
+        let excluded = self
+            .fallback_excludes
+            .iter()
+            .any(|prefix| has_path_prefix(path, prefix));
+        self.spa_fallback && accepts_html && !is_asset_path(path, &self.assets_prefix) && !excluded
+    }
+
+    // The answer to `req` for a file the build does not have, and that does not fall back
+    // to `index.html`: a 404, with a JSON body for clients that prefer one. A missing built
+    // asset is logged, as it usually means a page of another deployment refers to it.
+    pub(crate) fn not_found(&self, req: &HttpRequest) -> HttpResponse {
+        let path = req.path();
+        let hashed = self.hashed.as_ref().is_some_and(|hashed| hashed.is_match(path));
+        if hashed || has_path_prefix(path, &self.assets_prefix) {
+            log::warn!(
+                "The build in {} has no {}; is the page that refers to it from another deployment?",
+                self.dist_dir.display(),
+                path
+            );
+        }
+        let accept = req.headers().get(ACCEPT).and_then(|accept| accept.to_str().ok());
+        if accept.is_some_and(|accept| prefers(accept, "application/json")) {
+            return HttpResponse::NotFound().json(json!({ "error": "not found", "path": path }));
+        }
+        HttpResponse::NotFound().finish()
+    }
+
+    // The page a navigation to `path` falls back to, relative to the dist directory: the
+    // HTML entry of a multi-page app it is under, or `index.html`.
+    pub(crate) fn fallback_page(&self, path: &str) -> &str {
+        self.html_entries.entry_for(path).unwrap_or("index.html")
+    }
+
+    // The redirect of `req` to the form of its path the `trailing_slash` policy prefers, if
+    // it is a page route outside the excluded prefixes.
+    pub(crate) fn trailing_slash_redirect(&self, req: &HttpRequest) -> Option<HttpResponse> {
+        trailing_slash::redirect(req, req.path(), self.trailing_slash, &self.fallback_excludes)
+    }
+
+    // The `Cache-Control` value for the file at `request_path`: that of the first matching
+    // override, `no-cache` for HTML, so a new deployment is picked up right away, a year for
+    // hashed files, which never change, and `no-cache` for everything else.
+    pub(crate) fn cache_control(&self, request_path: &str) -> HeaderValue {
+        if let Some(value) = self.cache_override(request_path) {
+            return value;
+        }
+        let is_html = request_path.ends_with('/')
+            || request_path.ends_with(".html")
+            || request_path.ends_with(".htm");
+        let is_hashed = self
+            .hashed
+            .as_ref()
+            .is_some_and(|pattern| pattern.is_match(request_path));
+        if !is_html && is_hashed {
+            HeaderValue::from_static(CACHE_IMMUTABLE)
+        } else {
+            HeaderValue::from_static(CACHE_NO_CACHE)
+        }
+    }
+
+    // The `Cache-Control` value of the first override matching `request_path`, if any.
+    pub(crate) fn cache_override(&self, request_path: &str) -> Option<HeaderValue> {
+        self.cache_overrides
+            .iter()
+            .find(|(pattern, _)| pattern.is_match(request_path))
+            .map(|(_, value)| value.clone())
+    }
+
+    // The file of an extra directory for `request_path`, if one has it.
+    #[cfg(feature = "prod-serve")]
+    pub(crate) fn extra_file(&self, request_path: &str) -> Option<PathBuf> {
+        self.extra_dirs
+            .iter()
+            .filter(|(url_prefix, _)| has_path_prefix(request_path, url_prefix))
+            .filter_map(|(url_prefix, dir)| {
+                let rest = &request_path[url_prefix.trim_end_matches('/').len()..];
+                resolve_dist_path(dir, rest)
+            })
+            .find(|path| path.is_file())
+    }
+}
+
+// The `405 Method Not Allowed` for a request to the files of a build with a method that
+// does not read them. The default service gets every method, whatever its route says.
+pub(crate) fn reject_method(req: &HttpRequest) -> Option<HttpResponse> {
+    if req.method() == Method::GET || req.method() == Method::HEAD {
+        return None;
+    }
+    Some(
+        HttpResponse::MethodNotAllowed()
+            .insert_header((ALLOW, "GET, HEAD"))
+            .finish(),
+    )
+}
+
+// Whether the client's copy is still current, so the answer is a `304 Not Modified`: its
+// `If-None-Match` names `etag`, or, without one, it was fetched at `If-Modified-Since` or
+// after `last_modified`.
+pub(crate) fn is_fresh(req: &HttpRequest, etag: &str, last_modified: Option<SystemTime>) -> bool {
+    let headers = req.headers();
+    if headers.contains_key(IF_NONE_MATCH) {
+        return headers
+            .get_all(IF_NONE_MATCH)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == etag || tag == "*");
+    }
+    let since = headers
+        .get(IF_MODIFIED_SINCE)
+        .and_then(|since| since.to_str().ok())
+        .and_then(|since| since.parse::<HttpDate>().ok());
+    let seconds = |time: SystemTime| time.duration_since(UNIX_EPOCH).map(|time| time.as_secs());
+    match (last_modified, since) {
+        // HTTP dates have no fractions of a second.
+        (Some(modified), Some(since)) => match (seconds(modified), seconds(since.into())) {
+            (Ok(modified), Ok(since)) => modified <= since,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// Whether the client accepts the content coding `encoding`, e.g. `gzip`, by name or with
+// `*`, and not with `q=0`.
+pub(crate) fn accepts_encoding(req: &HttpRequest, encoding: &str) -> bool {
+    req.headers()
+        .get_all(ACCEPT_ENCODING)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|coding| {
+            let mut parts = coding.split(';');
+            let name = parts.next().unwrap_or_default().trim();
+            // `gzip;q=0` explicitly refuses gzip.
+            let refused = parts.any(|param| {
+                param.trim().strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0)
+            });
+            (name.eq_ignore_ascii_case(encoding) || name == "*") && !refused
+        })
+}
+
+// The request paths of the files in `dir`, served under `url_prefix`, that the dist
+// directory has too, which is what gets served for them.
+#[cfg(feature = "prod-serve")]
+pub(crate) fn shadowed_files(dist_dir: &Path, url_prefix: &str, dir: &Path) -> Vec<String> {
+    let mut shadowed = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), url_prefix.trim_end_matches('/').to_string())];
+    while let Some((dir, url)) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let url = format!("{}/{}", url, entry.file_name().to_string_lossy());
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push((path, url));
+            } else if dist_dir.join(url.trim_start_matches('/')).is_file() {
+                shadowed.push(url);
+            }
+        }
+    }
+    shadowed.sort();
+    shadowed
+}
+
+/// Maps a request path onto a file inside `dist_dir`.
+///
+/// The path is percent-decoded and split into segments; any segment that is empty, `.`,
+/// `..`, or contains a path separator makes the path invalid so requests can never
+/// escape the directory.
+#[cfg(feature = "prod-serve")]
+pub(crate) fn resolve_dist_path(dist_dir: &Path, request_path: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(request_path)
+        .decode_utf8()
+        .ok()?;
+
+    let mut path = dist_dir.to_path_buf();
+    for segment in decoded.split('/').filter(|segment| !segment.is_empty()) {
+        if segment == "." || segment == ".." || segment.contains('\\') || segment.contains(':') {
+            return None;
+        }
+        path.push(segment);
+    }
+
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    Some(path)
+}
@@ -0,0 +1,69 @@
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::error::PayloadError;
+use actix_web::web::{Bytes, BytesMut};
+use futures_util::Stream;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+// A body kept in the chunks it was read in, so buffering it copies nothing. It is sent on
+// chunk by chunk with its full length, and joined only when it has to be read as a whole.
+#[derive(Clone, Default)]
+pub(crate) struct ChunkedBody {
+    chunks: VecDeque<Bytes>,
+    len: usize,
+}
+
+impl ChunkedBody {
+    pub(crate) fn push(&mut self, chunk: Bytes) {
+        if !chunk.is_empty() {
+            self.len += chunk.len();
+            self.chunks.push_back(chunk);
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    // The body in one piece, copied only if it came in more than one chunk.
+    pub(crate) fn into_bytes(mut self) -> Bytes {
+        if self.chunks.len() <= 1 {
+            return self.chunks.pop_front().unwrap_or_default();
+        }
+        let mut joined = BytesMut::with_capacity(self.len);
+        for chunk in &self.chunks {
+            joined.extend_from_slice(chunk);
+        }
+        joined.freeze()
+    }
+
+    // The chunks as a payload, e.g. to hand the body to the application again.
+    pub(crate) fn into_stream(self) -> impl Stream<Item = Result<Bytes, PayloadError>> {
+        futures_util::stream::iter(self.chunks.into_iter().map(Ok))
+    }
+}
+
+impl MessageBody for ChunkedBody {
+    type Error = Infallible;
+
+    fn size(&self) -> BodySize {
+        BodySize::Sized(self.len as u64)
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        Poll::Ready(self.chunks.pop_front().map(Ok))
+    }
+
+    fn try_into_bytes(self) -> Result<Bytes, Self> {
+        if self.chunks.len() <= 1 {
+            Ok(self.into_bytes())
+        } else {
+            Err(self)
+        }
+    }
+}
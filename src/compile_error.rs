@@ -0,0 +1,115 @@
+use regex::Regex;
+use std::fmt::{self, Display, Formatter};
+use std::sync::OnceLock;
+
+/// An error Vite reported while transforming a module, e.g. a syntax error or an import that
+/// cannot be resolved.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViteCompileError {
+    /// The file the error is in, with line and column if Vite reported them, e.g.
+    /// `/app/src/main.ts:3:10`.
+    pub file: Option<String>,
+    /// What went wrong.
+    pub message: String,
+    /// The code frame pointing at the error, if Vite printed one.
+    pub frame: Option<String>,
+}
+
+impl Display for ViteCompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}: ", file)?;
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(frame) = &self.frame {
+            write!(f, "\n{}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+// Collects the lines of an error block Vite prints into a `ViteCompileError`.
+//
+// A block starts with a `[vite] Internal server error:` or `[vite] Pre-transform error:`
+// line, which Vite 6 writes as `[vite] (client) ...`, and continues with indented
+// `Plugin:`/`File:` lines, a code frame and a stack trace. It ends at the next line that is
+// not indented, or when the reader calls `finish`.
+#[derive(Default)]
+pub(crate) struct CompileErrorParser {
+    current: Option<ViteCompileError>,
+    // Whether the message still continues on the next line, e.g. after
+    // `Transform failed with 1 error:`.
+    message_open: bool,
+}
+
+impl CompileErrorParser {
+    // Feed the next line, without ANSI escape codes but with its indentation. Returns the
+    // previous error once this line shows that its block has ended.
+    pub(crate) fn push(&mut self, line: &str) -> Option<ViteCompileError> {
+        static START: OnceLock<Regex> = OnceLock::new();
+        static FRAME: OnceLock<Regex> = OnceLock::new();
+        let start = START.get_or_init(|| {
+            Regex::new(
+                r"\[vite\](?: \([\w-]+\))? (?:Internal server error|Pre-transform error): (?P<message>.*)$",
+            )
+            .unwrap()
+        });
+        let frame = FRAME.get_or_init(|| Regex::new(r"^\s*\d*\s*\|").unwrap());
+
+        if let Some(caps) = start.captures(line) {
+            let finished = self.finish();
+            let message = caps["message"].trim().to_string();
+            self.message_open = message.ends_with(':');
+            self.current = Some(ViteCompileError {
+                file: imported_from(&message),
+                message,
+                frame: None,
+            });
+            return finished;
+        }
+
+        let error = self.current.as_mut()?;
+        let indented = line.starts_with(char::is_whitespace);
+        let trimmed = line.trim();
+
+        if !indented && !trimmed.is_empty() {
+            if !self.message_open {
+                return self.finish();
+            }
+            error.message.push('\n');
+            error.message.push_str(trimmed);
+            self.message_open = trimmed.ends_with(':');
+            return None;
+        }
+        self.message_open = false;
+
+        if let Some(file) = trimmed.strip_prefix("File:") {
+            error.file = Some(file.trim().to_string());
+        } else if frame.is_match(line) {
+            let frame = error.frame.get_or_insert_with(String::new);
+            if !frame.is_empty() {
+                frame.push('\n');
+            }
+            frame.push_str(line.trim_end());
+        }
+        None
+    }
+
+    // Whether the lines pushed last belong to an error block that has not ended yet.
+    pub(crate) fn is_collecting(&self) -> bool {
+        self.current.is_some()
+    }
+
+    // The error being collected, if any, once its stream has ended.
+    pub(crate) fn finish(&mut self) -> Option<ViteCompileError> {
+        self.message_open = false;
+        self.current.take()
+    }
+}
+
+// The importing file named by a `Failed to resolve import "x" from "src/main.ts"` message.
+fn imported_from(message: &str) -> Option<String> {
+    static FROM: OnceLock<Regex> = OnceLock::new();
+    let regex = FROM.get_or_init(|| Regex::new(r#"\bfrom "(?P<file>[^"]+)""#).unwrap());
+    Some(regex.captures(message)?["file"].to_string())
+}
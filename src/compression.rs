@@ -0,0 +1,216 @@
+//! Compressing the files of the build on the fly, with the `compress` feature and
+//! [`ProxyViteOptions::compression`].
+//!
+//! Without a compression plugin, `vite build` writes no `.br` or `.gz` files, and large
+//! chunks are sent as they are. With compression on, a file of the dist directory without a
+//! precompressed sibling is compressed for clients that accept Brotli or gzip, Brotli
+//! first, if its type is text, like scripts, stylesheets, pages, SVG and JSON, and it is at
+//! least [`Compression::min_size`] bytes. Images, fonts and archives are compressed already
+//! and are sent as they are.
+//!
+//! Precompressed files win, as they are usually compressed harder at build time. Every
+//! response for a file that can be compressed carries `Vary: Accept-Encoding`, and a
+//! compressed one an `ETag` of its own. The compressed bytes are kept until the file
+//! changes, so each file is only compressed once per encoding. Range requests get the
+//! original file, and so do pages that [`ProxyViteOptions::html_transform`] rewrites.
+//!
+//! [`ProxyViteOptions::compression`]: crate::proxy_vite_options::ProxyViteOptions::compression
+//! [`ProxyViteOptions::html_transform`]: crate::proxy_vite_options::ProxyViteOptions::html_transform
+
+use crate::manifest::FileStamp;
+use crate::built_dist::{accepts_encoding, is_fresh};
+use crate::static_files::{file_etag, has_precompressed, set_validators};
+use actix_files::NamedFile;
+use actix_web::http::header::{ACCEPT_RANGES, ContentEncoding, HeaderValue, RANGE};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// The default of [`Compression::min_size`]: smaller files gain less than the headers
+/// cost.
+pub const DEFAULT_MIN_SIZE: u64 = 1024;
+
+// The encodings files are compressed with, in order of preference.
+const ENCODINGS: [ContentEncoding; 2] = [ContentEncoding::Brotli, ContentEncoding::Gzip];
+
+/// How the files of the build are compressed on the fly, see [`crate::compression`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Compression {
+    /// How hard to compress, from 0 to 9 for gzip and up to 11 for Brotli; higher levels
+    /// are capped for gzip. Defaults to 6.
+    pub level: u32,
+    /// The size in bytes below which files are sent as they are. Defaults to
+    /// [`DEFAULT_MIN_SIZE`].
+    pub min_size: u64,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            level: 6,
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+}
+
+impl Compression {
+    /// Sets [`Compression::level`].
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets [`Compression::min_size`].
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+}
+
+/// Whether a response with the media type `content_type` is worth compressing: text,
+/// scripts, JSON, XML, SVG and WebAssembly are, images, fonts, video and archives are
+/// compressed already.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::compression::is_compressible;
+///
+/// for compressible in ["text/javascript", "text/css; charset=utf-8", "image/svg+xml", "application/json", "application/manifest+json"] {
+///     assert!(is_compressible(compressible), "{}", compressible);
+/// }
+/// for compressed in ["font/woff2", "image/png", "image/jpeg", "application/zip", "video/mp4"] {
+///     assert!(!is_compressible(compressed), "{}", compressed);
+/// }
+/// ```
+pub fn is_compressible(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim();
+    essence.starts_with("text/")
+        || essence.ends_with("+json")
+        || essence.ends_with("+xml")
+        || matches!(
+            essence,
+            "application/javascript" | "application/json" | "application/xml" | "application/wasm"
+        )
+}
+
+// Compresses the files of a build for `serve_built_file`, keeping what it compressed.
+pub(crate) struct Compressor {
+    compression: Compression,
+    cache: Mutex<HashMap<(PathBuf, ContentEncoding), (FileStamp, Bytes)>>,
+}
+
+impl Compressor {
+    pub(crate) fn new(compression: Compression) -> Self {
+        Self {
+            compression,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Whether the file at `path` is compressed for clients that accept it: a big enough
+    // text file without a precompressed sibling.
+    pub(crate) fn applies(&self, path: &Path) -> bool {
+        let big_enough = std::fs::metadata(path)
+            .is_ok_and(|meta| meta.is_file() && meta.len() >= self.compression.min_size);
+        big_enough && is_compressible(&content_type(path)) && !has_precompressed(path)
+    }
+
+    // The compressed file at `path` for `req`, or `None` if the client accepts none of the
+    // encodings or asks for a range, or the file can't be read.
+    pub(crate) fn respond(
+        &self,
+        req: &HttpRequest,
+        path: &Path,
+        cache_control: HeaderValue,
+    ) -> Option<HttpResponse> {
+        if req.headers().contains_key(RANGE) {
+            return None;
+        }
+        let encoding = ENCODINGS
+            .into_iter()
+            .find(|encoding| accepts_encoding(req, encoding.as_str()))?;
+        let stamp = FileStamp::of(path).ok()?;
+        let file = NamedFile::open(path).ok()?.set_content_encoding(encoding);
+        let etag = file_etag(&file);
+        let modified = file.modified();
+
+        let mut res = if is_fresh(req, &etag, modified) {
+            HttpResponse::NotModified().finish()
+        } else {
+            let body = self.compressed(path, encoding, stamp)?;
+            // The type and encoding are those of the file, the body and length the compressed ones.
+            let mut res = file
+                .use_etag(false)
+                .use_last_modified(false)
+                .into_response(req)
+                .set_body(body)
+                .map_into_boxed_body();
+            res.headers_mut().remove(ACCEPT_RANGES);
+            res
+        };
+        set_validators(&mut res, &etag, modified, cache_control, true);
+        Some(res)
+    }
+
+    // The file at `path` compressed with `encoding`, from the cache while it is unchanged.
+    fn compressed(
+        &self,
+        path: &Path,
+        encoding: ContentEncoding,
+        stamp: FileStamp,
+    ) -> Option<Bytes> {
+        let key = (path.to_path_buf(), encoding);
+        if let Some((compressed_at, body)) = self
+            .cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&key)
+            && *compressed_at == stamp
+        {
+            return Some(body.clone());
+        }
+        let contents = std::fs::read(path).ok()?;
+        let body = match compress(&contents, encoding, self.compression.level) {
+            Ok(body) => Bytes::from(body),
+            Err(e) => {
+                log::warn!("Failed to compress {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key, (stamp, body.clone()));
+        Some(body)
+    }
+}
+
+// The media type a file is served with.
+fn content_type(path: &Path) -> String {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    actix_files::file_extension_to_mime(extension.unwrap_or_default()).to_string()
+}
+
+// `contents` compressed with `encoding` at `level`.
+fn compress(contents: &[u8], encoding: ContentEncoding, level: u32) -> std::io::Result<Vec<u8>> {
+    match encoding {
+        ContentEncoding::Brotli => {
+            let mut compressed = Vec::new();
+            {
+                let mut writer =
+                    brotli::CompressorWriter::new(&mut compressed, 4096, level.min(11), 22);
+                writer.write_all(contents)?;
+            }
+            Ok(compressed)
+        }
+        _ => {
+            let level = flate2::Compression::new(level.min(9));
+            let mut writer = flate2::write::GzEncoder::new(Vec::new(), level);
+            writer.write_all(contents)?;
+            writer.finish()
+        }
+    }
+}
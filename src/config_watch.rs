@@ -0,0 +1,78 @@
+use log::debug;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Config file names Vite looks for, in its own order of preference.
+const CONFIG_FILES: [&str; 6] = [
+    "vite.config.js",
+    "vite.config.mjs",
+    "vite.config.ts",
+    "vite.config.cjs",
+    "vite.config.mts",
+    "vite.config.cts",
+];
+
+// Editors often write a file several times per save, so changes this close together count once.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The `vite.config.*` file Vite would load from `working_directory`, if there is one.
+pub fn find_vite_config(working_directory: impl AsRef<Path>) -> Option<PathBuf> {
+    let working_directory = working_directory.as_ref();
+    CONFIG_FILES
+        .iter()
+        .map(|name| working_directory.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Watch `config` and call `on_change` after each (debounced) change to it.
+///
+/// The directory is watched instead of the file itself so editors that save by replacing the
+/// file are noticed too. Watching stops when the returned watcher is dropped.
+pub(crate) fn watch_config(
+    config: PathBuf,
+    on_change: impl Fn() + Send + 'static,
+) -> notify::Result<RecommendedWatcher> {
+    let directory = config
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from("."));
+    let last_change: Mutex<Option<Instant>> = Mutex::new(None);
+    let watched = config.clone();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                debug!("Error watching the Vite config: {}", e);
+                return;
+            }
+        };
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) || !event
+            .paths
+            .iter()
+            .any(|path| path.file_name() == watched.file_name())
+        {
+            return;
+        }
+
+        let Ok(mut last_change) = last_change.lock() else {
+            return;
+        };
+        if last_change.is_some_and(|last| last.elapsed() < DEBOUNCE) {
+            return;
+        }
+        *last_change = Some(Instant::now());
+        drop(last_change);
+
+        debug!("Vite config {} changed", watched.display());
+        on_change();
+    })?;
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+    debug!("Watching {} for changes", config.display());
+    Ok(watcher)
+}
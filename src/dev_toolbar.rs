@@ -0,0 +1,132 @@
+//! A small badge in the corner of every page proxied in dev mode, with
+//! [`ProxyViteOptions::dev_toolbar`], showing the state of Vite, the time of its last HMR
+//! update and, with [`ProxyViteOptions::enable_admin_routes`], a button that restarts it.
+//!
+//! The proxy adds a `<script>` to the `text/html` responses of Vite, in front of their
+//! `</body>`, after the [`ProxyViteOptions::html_transform`]. Like the transform, it needs
+//! the pages uncompressed, see [`ProxyViteOptions::decompress_upstream`]. The script draws
+//! the badge in a shadow root, so the styles of the page and the badge don't touch, and
+//! polls [`HEALTH_PATH`] and [`STATUS_PATH`] every two seconds; it loads nothing else.
+//! Without the option, nothing is added and [`STATUS_PATH`] is forwarded like any path.
+//!
+//! `GET` [`STATUS_PATH`] answers what the health route does not say, e.g.
+//! `{"last_hmr_update": 1760000000000, "restart": true, "port_mismatch": null}`: when Vite
+//! last sent an `hmr update` or a `page reload` to the browser, in milliseconds since the
+//! Unix epoch or `null`, whether the button can restart Vite, which needs the admin routes
+//! without an [`ProxyViteOptions::admin_secret`] the page could not know, and, when Vite
+//! listens on another port than the configured one, both, e.g.
+//! `{"configured": 3000, "actual": 3001}`.
+//!
+//! [`HEALTH_PATH`]: crate::health::HEALTH_PATH
+//! [`ProxyViteOptions::dev_toolbar`]: crate::proxy_vite_options::ProxyViteOptions::dev_toolbar
+//! [`ProxyViteOptions::enable_admin_routes`]: crate::proxy_vite_options::ProxyViteOptions::enable_admin_routes
+//! [`ProxyViteOptions::html_transform`]: crate::proxy_vite_options::ProxyViteOptions::html_transform
+//! [`ProxyViteOptions::decompress_upstream`]: crate::proxy_vite_options::ProxyViteOptions::decompress_upstream
+//! [`ProxyViteOptions::admin_secret`]: crate::proxy_vite_options::ProxyViteOptions::admin_secret
+
+use crate::admin_routes::ADMIN_PREFIX;
+use crate::assets::nonce_attribute;
+use crate::health::HEALTH_PATH;
+use crate::html_transform::{append_to_body, js_string};
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::vite_state::ViteProcess;
+use actix_web::HttpResponse;
+use actix_web::http::header::CACHE_CONTROL;
+use serde_json::json;
+use std::time::UNIX_EPOCH;
+
+/// The path of the status the toolbar polls.
+pub const STATUS_PATH: &str = "/__vite_actix/status";
+
+/// The attribute of the `<script>` tag of the toolbar.
+pub const TOOLBAR_MARKER: &str = "data-vite-actix-toolbar";
+
+// The script of the toolbar, with `__HEALTH__`, `__STATUS__` and `__RESTART__` for the
+// URLs of those routes, and `__NONCE__` for the nonce attribute of its styles.
+const TOOLBAR_SCRIPT: &str = r#"(() => {
+  const urls = { health: __HEALTH__, status: __STATUS__, restart: __RESTART__ };
+  const host = document.createElement("vite-actix-toolbar");
+  host.style.cssText = "all: initial; position: fixed; right: 12px; bottom: 12px; z-index: 2147483647;";
+  const root = host.attachShadow({ mode: "closed" });
+  root.innerHTML = `<style${__NONCE__}>
+    .bar { display: flex; align-items: center; gap: 8px; padding: 4px 10px; border-radius: 999px;
+      font: 12px/1.6 system-ui, sans-serif; color: #dfdfd6; background: rgba(27, 27, 31, 0.9);
+      box-shadow: 0 1px 4px rgba(0, 0, 0, 0.3); }
+    .dot { width: 8px; height: 8px; border-radius: 50%; background: #8e8e93; }
+    .dot.ready { background: #30d158; }
+    .dot.restarting, .dot.starting { background: #ffd60a; }
+    .dot.exited, .dot.failed, .dot.unreachable { background: #ff453a; }
+    .hmr { opacity: 0.7; }
+    button { font: inherit; color: inherit; background: #646cff; border: 0; border-radius: 999px;
+      padding: 0 8px; cursor: pointer; }
+    button:disabled { opacity: 0.5; cursor: default; }
+  </style>
+  <div class="bar" title="vite-actix"><span class="dot"></span><span class="state"></span>
+  <span class="hmr"></span><button type="button" hidden>Restart</button></div>`;
+  const dot = root.querySelector(".dot");
+  const state = root.querySelector(".state");
+  const hmr = root.querySelector(".hmr");
+  const button = root.querySelector("button");
+  let restarting = false;
+  const get = (url) => fetch(url, { cache: "no-store" })
+    .then((res) => res.json())
+    .catch(() => ({}));
+  const poll = async () => {
+    const [health, status] = await Promise.all([get(urls.health), get(urls.status)]);
+    const vite = restarting ? "restarting" : health.vite || health.mode || "unreachable";
+    dot.className = "dot " + vite;
+    state.textContent = "Vite " + vite;
+    hmr.textContent = status.last_hmr_update
+      ? "HMR " + new Date(status.last_hmr_update).toLocaleTimeString()
+      : "";
+    button.hidden = status.restart !== true;
+  };
+  button.addEventListener("click", async () => {
+    restarting = true;
+    button.disabled = true;
+    poll();
+    const res = await fetch(urls.restart, { method: "POST" }).catch(() => null);
+    button.title = res && !res.ok ? (await res.json().catch(() => ({}))).error || "" : "";
+    restarting = false;
+    button.disabled = false;
+    poll();
+  });
+  const show = () => {
+    document.body.appendChild(host);
+    poll();
+    setInterval(poll, 2000);
+  };
+  document.body ? show() : addEventListener("DOMContentLoaded", show);
+})();"#;
+
+// `page` with the toolbar, its script and styles with `nonce`. `prefix` is the path of the
+// scope the proxy is mounted in, which the routes it polls are under.
+pub(crate) fn inject(page: &str, prefix: &str, nonce: Option<&str>) -> String {
+    let url = |path: &str| js_string(&format!("{}{}", prefix, path));
+    let nonce = nonce_attribute(nonce);
+    let script = TOOLBAR_SCRIPT
+        .replace("__HEALTH__", &url(HEALTH_PATH))
+        .replace("__STATUS__", &url(STATUS_PATH))
+        .replace("__RESTART__", &url(&format!("{}/restart", ADMIN_PREFIX)))
+        .replace("__NONCE__", &js_string(&nonce));
+    let script = format!("<script {}{}>{}</script>", TOOLBAR_MARKER, nonce, script);
+    append_to_body(page, &script)
+}
+
+// Answer `STATUS_PATH` for the Vite server of `options`, reporting on its `process`.
+pub(crate) fn status(options: &ProxyViteOptions, process: &ViteProcess) -> HttpResponse {
+    let last_hmr_update = process
+        .last_hmr_update()
+        .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+        .map(|since_epoch| since_epoch.as_millis() as u64);
+    let port_mismatch = process
+        .port_mismatch()
+        .map(|(configured, actual)| json!({ "configured": configured, "actual": actual }));
+    HttpResponse::Ok()
+        .insert_header((CACHE_CONTROL, "no-store"))
+        .json(json!({
+            "last_hmr_update": last_hmr_update,
+            "restart": options.enable_admin_routes && options.admin_secret.is_none(),
+            "port_mismatch": port_mismatch,
+        }))
+}
@@ -0,0 +1,52 @@
+//! Serving the built frontend in dev mode while the managed Vite process is down, with
+//! [`ProxyViteOptions::fallback_to_dist`], for those who only work on the backend and
+//! whose Vite does not start.
+//!
+//! Once the process of the global options has exited or, with
+//! [`ProxyViteOptions::restart_on_crash`], failed for good, the proxy answers `GET`
+//! requests from the dist directory the way `configure_vite` does outside of dev mode,
+//! SPA fallback and all, instead of with an error, as long as the directory exists. It
+//! warns once that HMR is unavailable and the build may be stale. Every request checks the
+//! state again, so once Vite is restarted and ready, requests are proxied again. Other
+//! methods, and requests while the directory does not exist, still get the error.
+//!
+//! [`ProxyViteOptions::fallback_to_dist`]: crate::proxy_vite_options::ProxyViteOptions::fallback_to_dist
+//! [`ProxyViteOptions::restart_on_crash`]: crate::proxy_vite_options::ProxyViteOptions::restart_on_crash
+
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::static_files::serve_built_file;
+use actix_web::http::Method;
+use actix_web::{Error, HttpRequest, HttpResponse};
+use log::{info, warn};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Whether requests are answered from the dist directory, to only say so when that changes.
+static SERVING_DIST: AtomicBool = AtomicBool::new(false);
+
+// Answer `req` from the dist directory of `options` while Vite is down, or `None` if it
+// can't be.
+pub(crate) async fn serve(
+    req: HttpRequest,
+    options: &ProxyViteOptions,
+) -> Option<Result<HttpResponse, Error>> {
+    let dist_dir = options.dist_dir();
+    if req.method() != Method::GET || !dist_dir.is_dir() {
+        return None;
+    }
+    if !SERVING_DIST.swap(true, Ordering::SeqCst) {
+        warn!(
+            "Vite is not running, serving the build in {} instead; HMR is unavailable and \
+             the build may be stale until Vite is restarted",
+            dist_dir.display()
+        );
+    }
+    Some(serve_built_file(req, Arc::new(options.built_dist())).await)
+}
+
+// Note that Vite is running again, after requests were answered from the dist directory.
+pub(crate) fn resume() {
+    if SERVING_DIST.swap(false, Ordering::SeqCst) {
+        info!("Vite is running again, proxying to it instead of serving the build");
+    }
+}
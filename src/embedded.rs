@@ -0,0 +1,235 @@
+//! Serving a production build embedded into the binary, enabled with the `embed` feature.
+//!
+//! Derive [`rust_embed::Embed`] over the dist directory and register
+//! [`configure_vite_embedded`](crate::vite_app_factory::ViteAppFactory::configure_vite_embedded)
+//! in place of `configure_vite`, or [`serve_embedded`] as the default service yourself. The
+//! files are served like those of the dist directory: hashed files are cached for a year,
+//! everything else is revalidated, and page navigations to unknown paths get `index.html`.
+//! Every response carries an `ETag` made from the SHA-256 hash of the bytes sent, so each
+//! encoding has its own, and the `Last-Modified` date of the file. `If-None-Match`, or
+//! without it `If-Modified-Since`, is answered with a `304 Not Modified`.
+//!
+//! HTML pages go through [`ProxyViteOptions::html_transform`], if set.
+//!
+//! Unless [`ProxyViteOptions::asset_check`] is [`AssetCheck::Off`], the files are checked
+//! once with [`verify_embedded`] when they are first served, and missing ones are logged.
+//!
+//! Precompressed files next to the originals, like the `index-BxQw2a1z.js.gz` that
+//! `vite-plugin-compression` writes, are served with `Content-Encoding: gzip` to clients that
+//! accept it.
+
+use crate::html_transform::{HtmlFiles, is_html};
+use crate::manifest::{
+    AssetCheck, MANIFEST_PATHS, MissingAsset, ViteManifest, list, missing_assets,
+};
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::built_dist::{BuiltDist, accepts_encoding, is_fresh, reject_method};
+use actix_web::http::header::{
+    CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, HeaderValue, HttpDate, LAST_MODIFIED,
+    VARY,
+};
+use actix_web::{HttpRequest, HttpResponse, Route, web};
+use rust_embed::{Embed, EmbeddedFile};
+use std::any::TypeId;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// A route serving the files embedded in `E`, to register as the default service.
+///
+/// The SPA fallback, the trailing slash policy and the cache policy are taken from the global options when this is
+/// called, like `configure_vite` does for the dist directory.
+pub fn serve_embedded<E: Embed + 'static>() -> Route {
+    let options = ProxyViteOptions::global();
+    if options.asset_check != AssetCheck::Off {
+        log_missing_assets::<E>(options.index_asset_base());
+    }
+    let built = Arc::new(options.built_dist());
+    web::get().to(move |req| serve_embedded_file(req, built.clone(), PhantomData::<E>))
+}
+
+/// [`ViteManifest::verify`] for the files embedded in `E`: checks that every file the
+/// embedded manifest and `index.html` refer to is embedded too.
+///
+/// # Example
+/// ```rust
+/// use rust_embed::Embed;
+/// use vite_actix::embedded::verify_embedded;
+///
+/// #[derive(Embed)]
+/// #[folder = "examples/embedded_dist/"]
+/// struct Assets;
+///
+/// #[derive(Embed)]
+/// #[folder = "examples/manifests/vite5/"]
+/// struct ManifestOnly;
+///
+/// assert_eq!(verify_embedded::<Assets>(), Ok(()));
+/// let missing = verify_embedded::<ManifestOnly>().unwrap_err();
+/// assert_eq!(missing.len(), 10);
+/// assert_eq!(missing[0].to_string(), "assets/Settings-Bq0Zx3Yv.js (referenced by src/pages/Settings.tsx)");
+/// ```
+///
+/// [`ViteManifest::verify`]: crate::manifest::ViteManifest::verify
+pub fn verify_embedded<E: Embed>() -> Result<(), Vec<MissingAsset>> {
+    verify::<E>("/")
+}
+
+fn verify<E: Embed>(base: &str) -> Result<(), Vec<MissingAsset>> {
+    let manifest = embedded_manifest::<E>();
+    let index_html = E::get("index.html").map(|file| file.data.into_owned());
+    let index_html = index_html.map(|html| String::from_utf8_lossy(&html).into_owned());
+    let missing = missing_assets(manifest.as_ref(), index_html.as_deref(), base, |file| {
+        E::get(file).is_some()
+    });
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(missing),
+    }
+}
+
+// The manifest embedded in `E`, if there is one that parses.
+fn embedded_manifest<E: Embed>() -> Option<ViteManifest> {
+    let file = MANIFEST_PATHS.iter().find_map(|path| E::get(path))?;
+    let json = String::from_utf8_lossy(&file.data);
+    ViteManifest::parse(&json)
+        .inspect_err(|e| log::error!("Not checking the files of the embedded manifest: {:#}", e))
+        .ok()
+}
+
+// Log the files missing from `E`, once per type, as `serve_embedded` runs for every worker.
+fn log_missing_assets<E: Embed + 'static>(base: &str) {
+    static CHECKED: Mutex<Vec<TypeId>> = Mutex::new(Vec::new());
+    let mut checked = CHECKED.lock().unwrap_or_else(|e| e.into_inner());
+    if checked.contains(&TypeId::of::<E>()) {
+        return;
+    }
+    checked.push(TypeId::of::<E>());
+    if let Err(missing) = verify::<E>(base) {
+        log::error!(
+            "{} file(s) the embedded build refers to are not embedded:{}",
+            missing.len(),
+            list(&missing)
+        );
+    }
+}
+
+async fn serve_embedded_file<E: Embed>(
+    req: HttpRequest,
+    built: Arc<BuiltDist>,
+    _embed: PhantomData<E>,
+) -> HttpResponse {
+    let Some(path) = built.route(req.path()).and_then(embedded_path) else {
+        return HttpResponse::NotFound().finish();
+    };
+    if let Some(res) = reject_method(&req) {
+        return res;
+    }
+    if let Some(redirect) = built.trailing_slash_redirect(&req) {
+        return redirect;
+    }
+    match find_file::<E>(&path) {
+        Some((path, file)) => {
+            let cache_control = built.cache_control(&format!("/{}", path));
+            respond::<E>(&req, &path, file, cache_control, built.html.as_deref())
+        }
+        None if built.falls_back(&req) => match E::get(built.fallback_page(req.path())) {
+            Some(file) => {
+                let page = built.fallback_page(req.path());
+                let cache_control = built.cache_control(&format!("/{}", page));
+                respond::<E>(&req, page, file, cache_control, built.html.as_deref())
+            }
+            None => HttpResponse::NotFound().finish(),
+        },
+        None => built.not_found(&req),
+    }
+}
+
+// The embedded file for a path, trying the `index.html` of a directory.
+fn find_file<E: Embed>(path: &str) -> Option<(String, EmbeddedFile)> {
+    if !path.is_empty()
+        && !path.ends_with('/')
+        && let Some(file) = E::get(path)
+    {
+        return Some((path.to_string(), file));
+    }
+    let index = match path.trim_end_matches('/') {
+        "" => "index.html".to_string(),
+        dir => format!("{}/index.html", dir),
+    };
+    E::get(&index).map(|file| (index, file))
+}
+
+// Answer with `file`, or its precompressed `.gz` sibling if the client accepts gzip.
+fn respond<E: Embed>(
+    req: &HttpRequest,
+    path: &str,
+    file: EmbeddedFile,
+    cache_control: HeaderValue,
+    html: Option<&HtmlFiles>,
+) -> HttpResponse {
+    // A transformed page differs per request, so it has no ETag, and is never precompressed.
+    if let Some(html) = html
+        && is_html(Some(file.metadata.mimetype()))
+    {
+        let page = String::from_utf8_lossy(&file.data).into_owned();
+        return HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .insert_header((CACHE_CONTROL, cache_control))
+            .body(html.transform(req, page));
+    }
+
+    let gzipped = E::get(&format!("{}.gz", path));
+    let has_variants = gzipped.is_some();
+    let (body, encoding) = match gzipped {
+        Some(gzipped) if accepts_encoding(req, "gzip") => (gzipped, Some("gzip")),
+        _ => (file.clone(), None),
+    };
+
+    let etag = format!("\"{}\"", hex(&body.metadata.sha256_hash()));
+    let modified = file
+        .metadata
+        .last_modified()
+        .map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds));
+    let not_modified = is_fresh(req, &etag, modified);
+    let mut res = if not_modified {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    res.insert_header((ETAG, etag))
+        .insert_header((CACHE_CONTROL, cache_control));
+    if let Some(modified) = modified {
+        res.insert_header((LAST_MODIFIED, HttpDate::from(modified)));
+    }
+    if has_variants {
+        res.insert_header((VARY, "Accept-Encoding"));
+    }
+    if not_modified {
+        return res.finish();
+    }
+    // The type of the original file, not of the `.gz`.
+    res.insert_header((CONTENT_TYPE, file.metadata.mimetype()));
+    if let Some(encoding) = encoding {
+        res.insert_header((CONTENT_ENCODING, encoding));
+    }
+    res.body(body.data.into_owned())
+}
+
+// The embedded path for a request path: percent-decoded, without the leading slash, and
+// `None` if it tries to leave the embedded folder, which `rust-embed` reads from disk in
+// debug builds.
+fn embedded_path(request_path: &str) -> Option<String> {
+    let decoded = percent_encoding::percent_decode_str(request_path)
+        .decode_utf8()
+        .ok()?;
+    let path = decoded.trim_start_matches('/');
+    let escapes = path
+        .split('/')
+        .any(|segment| segment == ".." || segment.contains('\\') || segment.contains(':'));
+    (!escapes).then(|| path.to_string())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,268 @@
+use std::fmt::{Display, Formatter};
+use std::path::PathBuf;
+
+/// The reasons starting a Vite process can fail.
+///
+/// Returned by [`start_vite_server`](crate::start_vite_server),
+/// [`start_vite_server_managed`](crate::start_vite_server_managed) and
+/// [`ViteInstances::start`](crate::vite_instances::ViteInstances::start). It implements
+/// [`std::error::Error`], so `?` converts it into an [`anyhow::Error`] as before.
+///
+/// # Example
+/// ```rust,no_run
+/// # #[cfg(feature = "spawn")]
+/// # {
+/// use vite_actix::error::StartViteError;
+///
+/// match vite_actix::start_vite_server() {
+///     Ok(_vite) => {}
+///     Err(StartViteError::ExecutableNotFound { searched }) => {
+///         eprintln!("vite is not installed in {:?}, run `npm install` first", searched);
+///     }
+///     Err(error) => eprintln!("could not start Vite: {}", error),
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub enum StartViteError {
+    /// The `vite` executable was not found in any of the searched directories.
+    ExecutableNotFound { searched: Vec<PathBuf> },
+    /// The working directory is part of a Yarn Plug'n'Play install, whose vite has to be run
+    /// through yarn, but yarn was not found. Holds the directory of the `.pnp.cjs` file.
+    YarnNotFound { pnp_root: PathBuf },
+    /// The `where`/`which` lookup for the `vite` executable could not be run.
+    LookupFailed(std::io::Error),
+    /// The configured working directory does not exist.
+    WorkingDirMissing(PathBuf),
+    /// The Vite process could not be spawned.
+    SpawnFailed(std::io::Error),
+    /// The stdout or stderr of the Vite process could not be captured.
+    OutputCaptureFailed,
+    /// The port Vite should listen on is already in use.
+    PortInUse { port: u16, pid: Option<u32> },
+    /// No free port was found to move Vite to after a port conflict.
+    NoFreePort { from: u16 },
+    /// Vite listens on another port than the configured one, which `strict_port` forbids.
+    PortMismatch { configured: u16, actual: u16 },
+    /// Spawning was skipped by the configured skip-spawn detector, for the given reason.
+    SpawnSkipped(String),
+    /// Vite did not report that it was ready in time.
+    ReadyTimeout { captured: Vec<String> },
+    /// `vite build` at startup exited with the given status, with its output.
+    BuildFailed {
+        status: std::process::ExitStatus,
+        captured: Vec<String>,
+    },
+    /// `vite build` at startup did not finish within the timeout, with its output so far.
+    BuildTimeout {
+        timeout: std::time::Duration,
+        captured: Vec<String>,
+    },
+    /// No Vite instance was registered under the given name.
+    UnknownInstance(String),
+    /// A Vite instance with the given name is already running.
+    AlreadyRunning(String),
+}
+
+impl Display for StartViteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StartViteError::ExecutableNotFound { searched } => {
+                write!(
+                    f,
+                    "vite not found, make sure it's installed with npm install -g vite"
+                )?;
+                if !searched.is_empty() {
+                    let searched: Vec<String> = searched
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect();
+                    write!(f, " (searched: {})", searched.join(", "))?;
+                }
+                Ok(())
+            }
+            StartViteError::YarnNotFound { pnp_root } => write!(
+                f,
+                "{} uses Yarn Plug'n'Play, which has no node_modules/.bin/vite, so vite is \
+                 started through yarn, but yarn was not found; install it or add it to the PATH",
+                pnp_root.display()
+            ),
+            StartViteError::LookupFailed(e) => {
+                write!(f, "Failed to look up the vite executable: {}", e)
+            }
+            StartViteError::WorkingDirMissing(path) => write!(
+                f,
+                "The Vite working directory {} does not exist",
+                path.display()
+            ),
+            StartViteError::SpawnFailed(e) => write!(f, "Failed to spawn Vite: {}", e),
+            StartViteError::OutputCaptureFailed => {
+                write!(f, "Failed to capture the output of the Vite process")
+            }
+            StartViteError::PortInUse {
+                port,
+                pid: Some(pid),
+            } => write!(
+                f,
+                "Port {} is already in use by process {}, stop it or configure another port",
+                port, pid
+            ),
+            StartViteError::PortInUse { port, pid: None } => write!(
+                f,
+                "Port {} is already in use, stop the process using it or configure another port",
+                port
+            ),
+            StartViteError::NoFreePort { from } => {
+                write!(f, "No free port available for Vite above {}", from)
+            }
+            StartViteError::PortMismatch { configured, actual } => write!(
+                f,
+                "Vite listens on port {} instead of the configured port {}, which strict_port \
+                 does not allow",
+                actual, configured
+            ),
+            StartViteError::SpawnSkipped(reason) => {
+                write!(f, "Vite was not started: {}", reason)
+            }
+            StartViteError::ReadyTimeout { captured } => {
+                write!(f, "Timed out waiting for Vite to become ready")?;
+                if !captured.is_empty() {
+                    write!(f, ", captured output:\n{}", captured.join("\n"))?;
+                }
+                Ok(())
+            }
+            StartViteError::BuildFailed { status, captured } => {
+                write!(f, "vite build failed with {}", status)?;
+                if !captured.is_empty() {
+                    write!(f, ", output:\n{}", captured.join("\n"))?;
+                }
+                Ok(())
+            }
+            StartViteError::BuildTimeout { timeout, captured } => {
+                write!(f, "vite build did not finish within {:?}", timeout)?;
+                if !captured.is_empty() {
+                    write!(f, ", output so far:\n{}", captured.join("\n"))?;
+                }
+                Ok(())
+            }
+            StartViteError::UnknownInstance(name) => {
+                write!(f, "No Vite instance named '{}' was registered", name)
+            }
+            StartViteError::AlreadyRunning(name) => {
+                write!(f, "Vite instance '{}' is already running", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StartViteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StartViteError::LookupFailed(e) | StartViteError::SpawnFailed(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A problem with the options of
+/// [`try_configure_vite`](crate::vite_app_factory::ViteAppFactory::try_configure_vite), which
+/// [`configure_vite`](crate::vite_app_factory::ViteAppFactory::configure_vite) only logs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigProblem {
+    /// `build()` was never called, so the global options are the defaults.
+    NotBuilt,
+    /// The proxy has no port to forward to: none was set, `VITE_PORT` is not set either, and
+    /// it cannot be read from Vite's output, because the options are not the global ones or
+    /// Vite writes to the terminal.
+    NoPort,
+    /// The dist directory to serve does not exist.
+    DistMissing(PathBuf),
+    /// A directory of `extra_static_dir` does not exist.
+    ExtraStaticDirMissing(PathBuf),
+    /// A prefix of `exclude_prefix` that is not a path, e.g. `api` or `/api/*`.
+    InvalidExcludePrefix(String),
+    /// The `hashed_asset_pattern` or a `cache_control` override does not compile, with the
+    /// reason.
+    InvalidPattern(String),
+    /// Two options that decide the same thing in different ways are both set.
+    Conflict(&'static str, &'static str),
+    /// The faults of `inject_errors` cannot be injected, with the reason.
+    InvalidFaults(String),
+    /// Vite's port is the one the Actix server binds, see `actix_port`, so the proxy would
+    /// forward every request to itself.
+    ProxyLoop(u16),
+    /// A pattern of `proxy_routes` that Actix cannot route, with the pattern and Actix's
+    /// reason.
+    InvalidRoute(String, String),
+    /// The `hop_header` is not a header name.
+    InvalidHopHeader(String),
+}
+
+impl Display for ConfigProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigProblem::NotBuilt => write!(
+                f,
+                "The options were never built, call ProxyViteOptions::build() first"
+            ),
+            ConfigProblem::NoPort => write!(
+                f,
+                "The proxy has no port, set one with port() or VITE_PORT"
+            ),
+            ConfigProblem::DistMissing(path) => write!(
+                f,
+                "The dist directory {} does not exist; run `vite build` first, point \
+                 dist_directory() at the build, or use serve_dist(false)",
+                path.display()
+            ),
+            ConfigProblem::ExtraStaticDirMissing(path) => write!(
+                f,
+                "The extra static directory {} does not exist",
+                path.display()
+            ),
+            ConfigProblem::InvalidExcludePrefix(prefix) => write!(
+                f,
+                "The excluded prefix {:?} is not a path like /api",
+                prefix
+            ),
+            ConfigProblem::InvalidPattern(reason) => write!(f, "{}", reason),
+            ConfigProblem::Conflict(first, second) => write!(
+                f,
+                "{} and {} both decide which requests are proxied, use only one",
+                first, second
+            ),
+            ConfigProblem::InvalidFaults(reason) => write!(f, "Invalid inject_errors: {}", reason),
+            ConfigProblem::ProxyLoop(port) => write!(
+                f,
+                "Vite's port {} is also the port of the Actix server, so the proxy would \
+                 forward every request to itself; give Vite a port of its own",
+                port
+            ),
+            ConfigProblem::InvalidRoute(pattern, reason) => {
+                write!(f, "The proxy route {:?} is invalid: {}", pattern, reason)
+            }
+            ConfigProblem::InvalidHopHeader(name) => {
+                write!(f, "The hop header {:?} is not a header name", name)
+            }
+        }
+    }
+}
+
+/// Every problem [`try_configure_vite`](crate::vite_app_factory::ViteAppFactory::try_configure_vite)
+/// found with the options, in the order they were checked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViteConfigError {
+    pub problems: Vec<ConfigProblem>,
+}
+
+impl Display for ViteConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid Vite configuration:")?;
+        for problem in &self.problems {
+            write!(f, "\n- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ViteConfigError {}
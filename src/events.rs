@@ -0,0 +1,97 @@
+//! One stream of everything that happens to the managed Vite process, for code that would
+//! otherwise combine [`ProxyViteOptions::on_ready`], [`ProxyViteOptions::on_exit`], the
+//! compile errors of [`ViteServerHandle::subscribe_errors`] and the proxy's failures.
+//!
+//! [`subscribe`] receives the [`ViteEvent`]s of the process started with the global options,
+//! by [`start_vite_server_managed`] or [`start_vite_server`], and the proxy errors of the
+//! `configure_vite` proxy to it. [`ViteServerHandle::subscribe_events`] receives those of one
+//! handle, which for a process of [`start_vite_server_with`] are all but the proxy errors.
+//! The callback options are still called, at the same points the events are sent.
+//!
+//! # Order
+//! Events are sent in the order they happen. A process's [`ViteEvent::Spawned`] comes first,
+//! followed by [`ViteEvent::Restarted`] if it replaced another one, then the events read from
+//! its output in the order Vite printed it, with the [`ViteEvent::PortChanged`] of a banner
+//! before the [`ViteEvent::Ready`] it leads to. [`ViteEvent::Exited`] is sent once the
+//! process is gone; exits are noticed by polling, so it may come before the events of the
+//! last lines Vite printed right before exiting. A process respawned on another port after a
+//! port conflict has no `Exited`, only its `Spawned` and `Restarted`. Proxy errors may come
+//! in between any of these.
+//!
+//! Like the output, the events are only read from captured output, see
+//! [`ViteStdio::Piped`], and a subscriber that falls more than 256 events behind misses
+//! some.
+//!
+//! # Example
+//! ```rust,no_run
+//! use vite_actix::events::ViteEvent;
+//!
+//! # #[actix_web::main]
+//! # async fn main() -> anyhow::Result<()> {
+//! let mut events = vite_actix::events::subscribe();
+//! let _vite = vite_actix::start_vite_server_managed()?;
+//! while let Ok(event) = events.recv().await {
+//!     match event {
+//!         ViteEvent::Ready(info) => log::info!("Vite is ready on {:?}", info.port),
+//!         ViteEvent::CompileError(error) => log::warn!("{}", error),
+//!         ViteEvent::Exited(status) => log::warn!("Vite exited with {}", status),
+//!         _ => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`ProxyViteOptions::on_ready`]: crate::proxy_vite_options::ProxyViteOptions::on_ready
+//! [`ProxyViteOptions::on_exit`]: crate::proxy_vite_options::ProxyViteOptions::on_exit
+//! [`ViteServerHandle::subscribe_errors`]: crate::vite_server_handle::ViteServerHandle::subscribe_errors
+//! [`ViteServerHandle::subscribe_events`]: crate::vite_server_handle::ViteServerHandle::subscribe_events
+//! [`start_vite_server_managed`]: crate::start_vite_server_managed
+//! [`start_vite_server`]: crate::start_vite_server
+//! [`start_vite_server_with`]: crate::start_vite_server_with
+//! [`ViteStdio::Piped`]: crate::vite_stdio::ViteStdio::Piped
+
+use crate::compile_error::ViteCompileError;
+use crate::vite_state::{ViteReadyInfo, ViteShared};
+use std::process::ExitStatus;
+use tokio::sync::broadcast;
+
+/// Something that happened to a Vite process, see [`crate::events`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViteEvent {
+    /// A process was spawned with the given id.
+    Spawned { pid: u32 },
+    /// The process is ready, when the `on_ready` callback is called, see
+    /// [`crate::vite_state::ReadyPhase`].
+    Ready(ViteReadyInfo),
+    /// Vite reported a port other than the one the proxy forwarded to before, the configured
+    /// one at first.
+    PortChanged(u16),
+    /// Vite failed to compile a module.
+    CompileError(ViteCompileError),
+    /// The proxy could not forward a request to Vite.
+    ProxyError(ViteProxyErrorInfo),
+    /// The process exited with the given status.
+    Exited(ExitStatus),
+    /// The process `new_pid` was spawned in place of `old_pid`, after a crash, a restart or a
+    /// port conflict.
+    Restarted { old_pid: u32, new_pid: u32 },
+}
+
+/// A request the proxy could not forward to Vite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViteProxyErrorInfo {
+    /// The ID of the request, see [`crate::request_id`].
+    pub request_id: String,
+    /// The method of the request, e.g. `GET`.
+    pub method: String,
+    /// The path Vite was asked for.
+    pub path: String,
+    /// Why forwarding failed.
+    pub message: String,
+}
+
+/// Subscribes to the events of the process started with the global options from now on.
+pub fn subscribe() -> broadcast::Receiver<ViteEvent> {
+    ViteShared::global().subscribe_events()
+}
@@ -0,0 +1,279 @@
+//! Latency and errors the proxy adds to requests on purpose, to try loading spinners and
+//! error states of the frontend without a throttling proxy, set with
+//! [`ProxyViteOptions::inject_latency`] and [`ProxyViteOptions::inject_errors`].
+//!
+//! Every request the proxy forwards, except WebSocket upgrades, waits for a delay between
+//! the minimum and the maximum latency first, and the given share of them is answered with
+//! the error status instead of reaching Vite. With [`ProxyViteOptions::inject_faults_under`],
+//! only requests under those paths are affected, e.g. mock `/api` routes of a Vite plugin
+//! rather than the modules of the app. Outside of dev mode, nothing is proxied, so nothing is
+//! injected.
+//!
+//! With [`ProxyViteOptions::enable_admin_routes`], `GET /__vite_actix/faults` reports the
+//! [`Faults`] as JSON, `PUT` replaces them and `DELETE` turns them off, while the
+//! application runs.
+//!
+//! Which requests fail and how long each waits is drawn from a [`FaultSampler`], seeded with
+//! [`ProxyViteOptions::fault_seed`] for the same draws on every run.
+//!
+//! # Example
+//! ```rust
+//! use actix_web::http::StatusCode;
+//! use std::time::Duration;
+//! use vite_actix::fault_injection::{FaultSampler, Faults};
+//!
+//! let faults = Faults {
+//!     min_latency_ms: 100,
+//!     max_latency_ms: 300,
+//!     error_rate: 0.25,
+//!     error_status: 503,
+//!     paths: vec!["/api".to_string()],
+//! };
+//!
+//! // Only requests under the paths are affected.
+//! let mut sampler = FaultSampler::seeded(7);
+//! assert_eq!(sampler.sample(&faults, "/src/main.ts"), None);
+//! assert_eq!(sampler.sample(&faults, "/apis"), None);
+//!
+//! let draws: Vec<_> = (0..1000).filter_map(|_| sampler.sample(&faults, "/api/users")).collect();
+//! assert_eq!(draws.len(), 1000);
+//! assert!(draws.iter().all(|fault| {
+//!     (Duration::from_millis(100)..=Duration::from_millis(300)).contains(&fault.delay)
+//! }));
+//! let errors = draws.iter().filter(|fault| fault.status.is_some()).count();
+//! assert!((200..300).contains(&errors));
+//! assert!(draws.iter().all(|fault| fault.status.is_none_or(|s| s == StatusCode::SERVICE_UNAVAILABLE)));
+//!
+//! // The same seed draws the same faults.
+//! let again: Vec<_> = {
+//!     let mut sampler = FaultSampler::seeded(7);
+//!     sampler.sample(&faults, "/src/main.ts");
+//!     sampler.sample(&faults, "/apis");
+//!     (0..1000).filter_map(|_| sampler.sample(&faults, "/api/users")).collect()
+//! };
+//! assert_eq!(draws, again);
+//!
+//! // A rate of 0 never fails, one of 1 always does.
+//! for (rate, failed) in [(0.0, 0), (1.0, 100)] {
+//!     let faults = Faults { error_rate: rate, error_status: 500, ..Faults::default() };
+//!     let mut sampler = FaultSampler::seeded(1);
+//!     let errors = (0..100)
+//!         .filter(|_| sampler.sample(&faults, "/").unwrap().status.is_some())
+//!         .count();
+//!     assert_eq!(errors, failed);
+//! }
+//! ```
+//!
+//! [`ProxyViteOptions::inject_latency`]: crate::proxy_vite_options::ProxyViteOptions::inject_latency
+//! [`ProxyViteOptions::inject_errors`]: crate::proxy_vite_options::ProxyViteOptions::inject_errors
+//! [`ProxyViteOptions::inject_faults_under`]: crate::proxy_vite_options::ProxyViteOptions::inject_faults_under
+//! [`ProxyViteOptions::enable_admin_routes`]: crate::proxy_vite_options::ProxyViteOptions::enable_admin_routes
+//! [`ProxyViteOptions::fault_seed`]: crate::proxy_vite_options::ProxyViteOptions::fault_seed
+
+use actix_web::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::ops::{Range, RangeInclusive};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The header of the responses with an injected error.
+pub const FAULT_HEADER: &str = "x-vite-actix-fault";
+
+/// What the proxy injects, in the form the admin route reads and writes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Faults {
+    /// The shortest delay before a request is forwarded, in milliseconds.
+    pub min_latency_ms: u64,
+    /// The longest delay, in milliseconds; below `min_latency_ms`, the minimum is used.
+    pub max_latency_ms: u64,
+    /// The share of requests answered with `error_status`, from 0 to 1.
+    pub error_rate: f32,
+    /// The status of the injected errors.
+    pub error_status: u16,
+    /// The paths whose requests are affected, as prefixes; all of them if empty.
+    pub paths: Vec<String>,
+}
+
+impl Faults {
+    /// Whether the faults change anything at all.
+    pub fn is_active(&self) -> bool {
+        self.max_latency_ms > 0 || self.min_latency_ms > 0 || self.error_rate > 0.0
+    }
+
+    /// Whether requests to `path` are affected.
+    pub fn applies_to(&self, path: &str) -> bool {
+        self.paths.is_empty()
+            || self
+                .paths
+                .iter()
+                .any(|prefix| crate::proxy_vite_options::has_path_prefix(path, prefix))
+    }
+
+    // Why the faults cannot be injected, if they can't.
+    pub(crate) fn problem(&self) -> Option<String> {
+        if !(0.0..=1.0).contains(&self.error_rate) {
+            return Some(format!("error_rate {} is not between 0 and 1", self.error_rate));
+        }
+        if self.error_rate > 0.0 && StatusCode::from_u16(self.error_status).is_err() {
+            return Some(format!("error_status {} is not a status code", self.error_status));
+        }
+        None
+    }
+}
+
+/// What happens to one request, drawn by [`FaultSampler::sample`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fault {
+    /// How long to wait before forwarding or failing the request.
+    pub delay: Duration,
+    /// The status to answer with instead of forwarding, if the request fails.
+    pub status: Option<StatusCode>,
+}
+
+/// Draws the [`Fault`] of each request, the same ones for the same seed.
+#[derive(Clone, Debug)]
+pub struct FaultSampler {
+    state: u64,
+}
+
+impl FaultSampler {
+    /// A sampler that draws the same faults every time.
+    pub fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A sampler seeded from the clock.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self::seeded(nanos)
+    }
+
+    /// The fault of a request to `path`, or `None` if `faults` do not apply to it. Draws
+    /// nothing for paths they do not apply to.
+    pub fn sample(&mut self, faults: &Faults, path: &str) -> Option<Fault> {
+        if !faults.applies_to(path) {
+            return None;
+        }
+        let min = faults.min_latency_ms;
+        let max = faults.max_latency_ms.max(min);
+        let delay = min + self.next_u64() % (max - min).saturating_add(1).max(1);
+        let fails = self.next_f32() < faults.error_rate;
+        Some(Fault {
+            delay: Duration::from_millis(delay),
+            status: fails
+                .then(|| StatusCode::from_u16(faults.error_status).ok())
+                .flatten(),
+        })
+    }
+
+    // SplitMix64, which is enough to spread faults over requests.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // A number in `0..1`, from the top 24 bits of the next draw.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+/// The latency of [`ProxyViteOptions::inject_latency`]: a fixed `Duration`, or a range of
+/// them to draw from.
+///
+/// [`ProxyViteOptions::inject_latency`]: crate::proxy_vite_options::ProxyViteOptions::inject_latency
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Latency {
+    pub min: Duration,
+    pub max: Duration,
+}
+
+impl From<Duration> for Latency {
+    fn from(latency: Duration) -> Self {
+        Self {
+            min: latency,
+            max: latency,
+        }
+    }
+}
+
+impl From<Range<Duration>> for Latency {
+    fn from(range: Range<Duration>) -> Self {
+        Self {
+            min: range.start,
+            max: range.end,
+        }
+    }
+}
+
+impl From<RangeInclusive<Duration>> for Latency {
+    fn from(range: RangeInclusive<Duration>) -> Self {
+        Self {
+            min: *range.start(),
+            max: *range.end(),
+        }
+    }
+}
+
+/// The faults of a set of options and the sampler drawing them, shared by every clone of
+/// the options, so the admin route changes them for the running proxy.
+#[derive(Debug)]
+pub struct FaultInjector {
+    faults: Mutex<Faults>,
+    // Only the proxy draws faults.
+    #[cfg(feature = "proxy")]
+    sampler: Mutex<FaultSampler>,
+    seed: Option<u64>,
+}
+
+impl FaultInjector {
+    pub(crate) fn new(faults: Faults, seed: Option<u64>) -> Self {
+        Self {
+            faults: Mutex::new(faults),
+            #[cfg(feature = "proxy")]
+            sampler: Mutex::new(seed.map_or_else(FaultSampler::from_time, FaultSampler::seeded)),
+            seed,
+        }
+    }
+
+    /// The faults injected now.
+    pub fn faults(&self) -> Faults {
+        self.faults
+            .lock()
+            .map(|faults| faults.clone())
+            .unwrap_or_default()
+    }
+
+    /// Inject `faults` from now on.
+    pub fn set(&self, faults: Faults) {
+        if let Ok(mut current) = self.faults.lock() {
+            *current = faults;
+        }
+    }
+
+    pub(crate) fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    // The fault of a request to `path`, if it gets one.
+    #[cfg(feature = "proxy")]
+    pub(crate) fn sample(&self, path: &str) -> Option<Fault> {
+        let faults = self.faults();
+        if !faults.is_active() {
+            return None;
+        }
+        self.sampler.lock().ok()?.sample(&faults, path)
+    }
+}
+
+impl Default for FaultInjector {
+    fn default() -> Self {
+        Self::new(Faults::default(), None)
+    }
+}
@@ -0,0 +1,174 @@
+//! `GET /__vite_actix/health`, registered by `configure_vite` with
+//! [`ProxyViteOptions::enable_health_route`], for health checks that need to know whether
+//! the whole dev stack is usable rather than just whether Actix is up.
+//!
+//! In dev mode, it answers `200 OK` with `{"vite":"ready","port":5173,"pid":1234}` once the
+//! Vite process of the global options is ready. A Vite started elsewhere, which reports
+//! nothing, counts as ready once its port takes connections, with a `pid` of `null`.
+//! Otherwise it answers `503 Service Unavailable` with the state, one of `idle`,
+//! `starting`, `exited` and `failed`, along with the `exit_code` of a process that exited
+//! and the `last_error` Vite reported, see
+//! [`ViteServerHandle::recent_errors`](crate::vite_server_handle::ViteServerHandle::recent_errors).
+//! A process that failed for good also has the last lines it printed as `output`.
+//!
+//! Outside of dev mode it answers `{"mode":"static"}`, with a `200` if the dist directory,
+//! or the files of `configure_vite_embedded`, can be served and a `503` with an `error`
+//! if not. Unlike the admin routes, it answers everyone and needs no secret.
+//!
+//! The route is registered on the application, so it never reaches Vite. The proxy answers
+//! the path in dev mode even without the option, for the page it shows while Vite is
+//! starting, see [`crate::starting_page`].
+//!
+//! [`ProxyViteOptions::enable_health_route`]: crate::proxy_vite_options::ProxyViteOptions::enable_health_route
+
+use crate::proxy::ProxyTarget;
+use crate::proxy_vite_options::ProxyViteOptions;
+#[cfg(feature = "proxy")]
+use crate::vite_state::{ViteProcess, ViteState};
+use actix_web::{HttpResponse, Resource, Route, web};
+use serde_json::json;
+#[cfg(feature = "proxy")]
+use serde_json::Value;
+#[cfg(feature = "prod-serve")]
+use std::path::PathBuf;
+
+/// The path of the health route.
+pub const HEALTH_PATH: &str = "/__vite_actix/health";
+
+// What `configure_vite` serves outside of dev mode, to tell whether it can.
+#[derive(Clone)]
+pub(crate) enum Build {
+    // The dist directory.
+    #[cfg(feature = "prod-serve")]
+    Dist(PathBuf),
+    // Files embedded into the binary, servable if there are any.
+    #[cfg(feature = "embed")]
+    Embedded(fn() -> bool),
+    // Nothing, the build is served elsewhere.
+    Elsewhere,
+}
+
+impl Build {
+    // What `configure_vite` serves for `options`.
+    #[cfg(feature = "prod-serve")]
+    pub(crate) fn of(options: &ProxyViteOptions) -> Self {
+        if options.serve_dist {
+            Build::Dist(options.dist_dir())
+        } else {
+            Build::Elsewhere
+        }
+    }
+
+    // Without the `prod-serve` feature, `configure_vite` never serves the dist directory.
+    #[cfg(not(feature = "prod-serve"))]
+    pub(crate) fn of(_: &ProxyViteOptions) -> Self {
+        Build::Elsewhere
+    }
+
+    // The files of `E`.
+    #[cfg(feature = "embed")]
+    pub(crate) fn embedded<E: rust_embed::Embed>() -> Self {
+        Build::Embedded(|| E::iter().next().is_some())
+    }
+}
+
+// The health resource `configure_vite` registers, if the options ask for it. In dev mode it
+// reports on Vite for the options of `target`, otherwise on `build`.
+pub(crate) fn resource(
+    options: &ProxyViteOptions,
+    dev_mode: bool,
+    target: ProxyTarget,
+    build: Build,
+) -> Option<Resource> {
+    if !options.enable_health_route {
+        return None;
+    }
+    let route = dev_mode
+        .then(|| dev_route(target))
+        .flatten()
+        .unwrap_or_else(|| web::get().to(move || static_health(build.clone())));
+    Some(web::resource(HEALTH_PATH).route(route))
+}
+
+#[cfg(feature = "dev-proxy")]
+fn dev_route(target: ProxyTarget) -> Option<Route> {
+    let process = target.process();
+    Some(web::get().to(move || {
+        let process = process.clone();
+        let options = target.options();
+        async move { vite_health(options, &process).await }
+    }))
+}
+
+// Without the `dev-proxy` feature, `configure_vite` is never in dev mode.
+#[cfg(not(feature = "dev-proxy"))]
+fn dev_route(_: ProxyTarget) -> Option<Route> {
+    None
+}
+
+// Report on the Vite server of `options`, and its `process` if the crate started it.
+#[cfg(feature = "proxy")]
+pub(crate) async fn vite_health(options: ProxyViteOptions, process: &ViteProcess) -> HttpResponse {
+    let mut body = match process.state().unwrap_or(ViteState::Idle) {
+        ViteState::Ready | ViteState::FullyReady => {
+            return HttpResponse::Ok().json(json!({
+                "vite": "ready",
+                "port": options.port,
+                "pid": process.pid(),
+            }));
+        }
+        ViteState::Idle => match options.port {
+            Some(port) if answers(port).await => {
+                return HttpResponse::Ok().json(json!({
+                    "vite": "ready",
+                    "port": port,
+                    "pid": null,
+                }));
+            }
+            _ => json!({ "vite": "idle" }),
+        },
+        ViteState::Starting => json!({ "vite": "starting" }),
+        ViteState::Exited(status) => json!({ "vite": "exited", "exit_code": status.code() }),
+        ViteState::Failed { status, output } => json!({
+            "vite": "failed",
+            "exit_code": status.code(),
+            "output": output,
+        }),
+    };
+    if let Some(error) = process.last_error()
+        && let Value::Object(body) = &mut body
+    {
+        body.insert("last_error".into(), json!(error));
+    }
+    HttpResponse::ServiceUnavailable().json(body)
+}
+
+// Whether something takes connections on the port of a Vite started elsewhere.
+#[cfg(feature = "proxy")]
+async fn answers(port: u16) -> bool {
+    actix_web::rt::net::TcpStream::connect(("localhost", port))
+        .await
+        .is_ok()
+}
+
+// Report on the build `configure_vite` serves.
+async fn static_health(build: Build) -> HttpResponse {
+    let error: Option<String> = match build {
+        #[cfg(feature = "prod-serve")]
+        Build::Dist(dist_dir) if !dist_dir.is_dir() => Some(format!(
+            "The dist directory {} does not exist",
+            dist_dir.display()
+        )),
+        #[cfg(feature = "embed")]
+        Build::Embedded(has_files) if !has_files() => {
+            Some("No files are embedded".to_string())
+        }
+        _ => None,
+    };
+    match error {
+        None => HttpResponse::Ok().json(json!({ "mode": "static" })),
+        Some(error) => {
+            HttpResponse::ServiceUnavailable().json(json!({ "mode": "static", "error": error }))
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use actix_web::HttpResponse;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use std::hash::BuildHasher;
+use std::sync::OnceLock;
+
+// Why a request is not forwarded again.
+pub(crate) enum Loop {
+    // It carries the token of this process: it came back from the proxy itself.
+    Own,
+    // It went through this many proxies already, the most `max_hops` allows.
+    TooManyHops(usize),
+}
+
+impl Loop {
+    // The `508 Loop Detected` the request is answered with.
+    pub(crate) fn response(&self) -> HttpResponse {
+        let message = match self {
+            Loop::Own => String::from(
+                "vite-actix forwarded this request to itself; Vite needs a port of its own",
+            ),
+            Loop::TooManyHops(hops) => format!(
+                "This request went through {} vite-actix proxies already, is one of them \
+                 forwarding to another in a loop?",
+                hops
+            ),
+        };
+        HttpResponse::build(StatusCode::LOOP_DETECTED).body(message)
+    }
+}
+
+// The token this process stamps on the requests it forwards, to know them when they come
+// back. The hash of a `RandomState` differs from process to process.
+pub(crate) fn token() -> &'static str {
+    static TOKEN: OnceLock<String> = OnceLock::new();
+    TOKEN.get_or_init(|| {
+        let hash = std::collections::hash_map::RandomState::new().hash_one(std::process::id());
+        format!("{:016x}", hash)
+    })
+}
+
+// The hop header to forward a request with `headers` with: the tokens of the proxies it came
+// through, from its own `header`, and the token of this process. Fails if the token is among
+// them, or there are `max_hops` or more.
+pub(crate) fn stamp(
+    headers: &HeaderMap,
+    header: &HeaderName,
+    max_hops: usize,
+) -> Result<HeaderValue, Loop> {
+    let mut tokens: Vec<&str> = headers
+        .get_all(header)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .collect();
+    if tokens.contains(&token()) {
+        return Err(Loop::Own);
+    }
+    if tokens.len() >= max_hops {
+        return Err(Loop::TooManyHops(tokens.len()));
+    }
+    tokens.push(token());
+    // The tokens came from a valid header value and are hex, so the value is valid too.
+    Ok(HeaderValue::from_str(&tokens.join(", ")).unwrap_or(HeaderValue::from_static("")))
+}
@@ -0,0 +1,426 @@
+//! Tags for loading a Vite entry point into pages rendered by the server, e.g. with a
+//! template engine, instead of coming from Vite's `index.html`.
+//!
+//! # Example
+//! ```rust
+//! use vite_actix::html::vite_head_tags_with;
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! // In dev mode the page loads the Vite client and the entry from the dev server.
+//! let dev = ProxyViteOptions::new().dev_mode(true);
+//! assert_eq!(
+//!     vite_head_tags_with(&dev, "src/main.ts").to_string(),
+//!     "<script type=\"module\" src=\"/@vite/client\"></script>\n\
+//!      <script type=\"module\" src=\"/src/main.ts\"></script>"
+//! );
+//!
+//! // Pages using @vitejs/plugin-react also need its preamble, before any module script.
+//! let tags = vite_head_tags_with(&dev, "src/main.tsx").with_react_refresh();
+//! assert!(tags.to_string().starts_with("<script type=\"module\">\nimport "));
+//!
+//! // Otherwise the tags come from the manifest of the build, and there is no preamble.
+//! let prod = ProxyViteOptions::new()
+//!     .dev_mode(false)
+//!     .dist_directory("examples/embedded_dist");
+//! assert_eq!(
+//!     vite_head_tags_with(&prod, "src/main.ts").with_react_refresh().to_string(),
+//!     "<link rel=\"stylesheet\" href=\"/assets/index-Cq3Zx9Lm.css\">\n\
+//!      <script type=\"module\" src=\"/assets/index-BxQw2a1z.js\"></script>\n\
+//!      <link rel=\"modulepreload\" href=\"/assets/vendor-D4e5F6g7.js\">"
+//! );
+//! ```
+
+use crate::assets::{AssetTags, nonce_attribute, tags_for_with};
+use crate::html_transform::{HtmlTransform, insert_into_head, request_nonce};
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::vite_version::ViteVersion;
+use actix_web::HttpRequest;
+use log::warn;
+use serde::{Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+/// The tags returned by [`vite_head_tags`], rendered as HTML by their [`Display`]
+/// implementation. They serialize as that HTML too, so they can be put into the context of
+/// a template engine like Tera as they are.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ViteHeadTags {
+    tags: AssetTags,
+    // The script of the preamble for the options the tags were made for; empty outside of
+    // dev mode.
+    preamble: String,
+    react_refresh: bool,
+}
+
+impl ViteHeadTags {
+    /// Put the [`react_refresh_preamble`] in front of the tags, for pages using
+    /// `@vitejs/plugin-react`.
+    pub fn with_react_refresh(mut self) -> Self {
+        self.react_refresh = true;
+        self
+    }
+
+    /// Also load the build of `@vitejs/plugin-legacy` in browsers without ES modules, see
+    /// [`AssetTags::with_legacy`].
+    pub fn with_legacy(mut self) -> Self {
+        self.tags = self.tags.with_legacy();
+        self
+    }
+
+    /// Put `nonce` on every tag, the preamble included, see [`AssetTags::with_nonce`].
+    pub fn with_nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.tags = self.tags.with_nonce(nonce);
+        self
+    }
+
+    /// The tags of the entry itself.
+    pub fn assets(&self) -> &AssetTags {
+        &self.tags
+    }
+}
+
+impl Display for ViteHeadTags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.react_refresh && !self.preamble.is_empty() {
+            writeln!(f, "{}", module_script(&self.preamble, self.tags.nonce()))?;
+        }
+        write!(f, "{}", self.tags)
+    }
+}
+
+impl Serialize for ViteHeadTags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// The tags that load `entry`, e.g. `src/main.ts`, into a page, for the global options.
+///
+/// In dev mode, with the dev server, these load `/@vite/client`, for hot module
+/// replacement, and the entry from the dev server. Otherwise they load the built files of
+/// the entry, looked up in the manifest of the dist directory, which `vite build` only
+/// writes with `build.manifest` turned on. If the manifest or the entry can't be found, the
+/// error is logged and the tags are empty. For several entries at once, see
+/// [`tags_for`](crate::assets::tags_for).
+pub fn vite_head_tags(entry: &str) -> ViteHeadTags {
+    vite_head_tags_with(&ProxyViteOptions::global(), entry)
+}
+
+/// Like [`vite_head_tags`], but for `options` instead of the global options.
+pub fn vite_head_tags_with(options: &ProxyViteOptions, entry: &str) -> ViteHeadTags {
+    ViteHeadTags {
+        tags: tags_for_with(options, &[entry]),
+        preamble: react_refresh_script(options),
+        react_refresh: false,
+    }
+}
+
+/// The preamble `@vitejs/plugin-react` needs in pages that do not come from Vite's
+/// `index.html`, for the global options. Without it, the page fails with
+/// "@vitejs/plugin-react can't detect preamble".
+///
+/// Put it before any module script. It is empty unless Vite runs its dev server in dev
+/// mode, and matches what the plugin injects for the detected Vite version, see
+/// [`react_refresh_snippet`].
+pub fn react_refresh_preamble() -> String {
+    react_refresh_preamble_with(&ProxyViteOptions::global())
+}
+
+/// Like [`react_refresh_preamble`], but for `options` instead of the global options.
+pub fn react_refresh_preamble_with(options: &ProxyViteOptions) -> String {
+    react_refresh_preamble_with_nonce(options, None)
+}
+
+/// Like [`react_refresh_preamble_with`], with `nonce` on the `<script>`, see [`csp_nonce`].
+pub fn react_refresh_preamble_with_nonce(
+    options: &ProxyViteOptions,
+    nonce: Option<&str>,
+) -> String {
+    match react_refresh_script(options) {
+        script if script.is_empty() => script,
+        script => module_script(&script, nonce),
+    }
+}
+
+// The script of the preamble for `options`, or nothing outside of dev mode.
+fn react_refresh_script(options: &ProxyViteOptions) -> String {
+    if !(options.dev_mode && options.command.is_dev_server()) {
+        return String::new();
+    }
+    react_refresh_source(ViteVersion::current(), &options.asset_base)
+}
+
+/// The preamble `@vitejs/plugin-react` injects into Vite's `index.html`, with
+/// `@react-refresh` loaded from under `base`.
+///
+/// With Vite 6 the plugin imports `injectIntoGlobalHook` by name. Before, and when the
+/// version is not known, it is the default-import form from Vite's backend integration
+/// guide, which also sets `__vite_plugin_react_preamble_installed__`.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::html::react_refresh_snippet;
+/// use vite_actix::vite_version::ViteVersion;
+///
+/// for (version, fixture) in [("5.4.11", "vite5"), ("6.0.7", "vite6")] {
+///     let expected = std::fs::read_to_string(format!("examples/react_refresh/{}.html", fixture))?;
+///     let snippet = react_refresh_snippet(ViteVersion::parse(version), "/");
+///     assert_eq!(snippet, expected.trim_end());
+/// }
+/// assert!(react_refresh_snippet(None, "/app/").contains("from \"/app/@react-refresh\""));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn react_refresh_snippet(version: Option<ViteVersion>, base: &str) -> String {
+    module_script(&react_refresh_source(version, base), None)
+}
+
+// The script of `react_refresh_snippet`.
+fn react_refresh_source(version: Option<ViteVersion>, base: &str) -> String {
+    let runtime = format!("{}@react-refresh", base);
+    if version.is_some_and(|version| version.major >= 6) {
+        format!(
+            "import {{ injectIntoGlobalHook }} from \"{}\";\n\
+             injectIntoGlobalHook(window);\n\
+             window.$RefreshReg$ = () => {{}};\n\
+             window.$RefreshSig$ = () => (type) => type;",
+            runtime
+        )
+    } else {
+        format!(
+            "import RefreshRuntime from \"{}\"\n\
+             RefreshRuntime.injectIntoGlobalHook(window)\n\
+             window.$RefreshReg$ = () => {{}}\n\
+             window.$RefreshSig$ = () => (type) => type\n\
+             window.__vite_plugin_react_preamble_installed__ = true",
+            runtime
+        )
+    }
+}
+
+// `script` as an inline module script, with `nonce`.
+fn module_script(script: &str, nonce: Option<&str>) -> String {
+    format!(
+        "<script type=\"module\"{}>\n{}\n</script>",
+        nonce_attribute(nonce),
+        script
+    )
+}
+
+/// A `<script>window.__KEY__ = {...};</script>` tag handing `value` to the app in the page,
+/// e.g. the current user or feature flags, saving it a request on load.
+///
+/// `value` is serialized as JSON, which is a JS expression, with `<`, `>` and `&` written
+/// as `\u003c`, `\u003e` and `\u0026`, so no `</script>` or `<!--` in a string can end the
+/// script or change how it is parsed, and U+2028 and U+2029 escaped for older browsers that
+/// do not allow them in JS strings. Fails if `value` cannot be serialized as JSON, or if
+/// `key` is not made of ASCII letters, digits, `_` and `$`. To add the tag to every page,
+/// see [`initial_state_transform`].
+///
+/// # Example
+/// ```rust
+/// use serde_json::{Value, json};
+/// use vite_actix::html::initial_state;
+///
+/// let tag = initial_state("INITIAL_STATE", &json!({ "user": "ada", "flags": ["beta"] }))?;
+/// assert_eq!(
+///     tag,
+///     "<script>window.__INITIAL_STATE__ = {\"flags\":[\"beta\"],\"user\":\"ada\"};</script>"
+/// );
+///
+/// // Whatever is in the strings, the tag is the only script, and the value comes back.
+/// for hostile in [
+///     "</script><script>alert(1)</script>",
+///     "</SCRIPT >",
+///     "<!--<script>",
+///     "--></script>",
+///     "<![CDATA[ ]]>",
+///     "\u{2028}\u{2029}",
+///     "&lt;/script&gt;",
+///     "\\u003c/script>",
+/// ] {
+///     let value = json!({ hostile: [hostile, { "nested": hostile }] });
+///     let tag = initial_state("STATE", &value)?;
+///     let json = tag
+///         .strip_prefix("<script>window.__STATE__ = ")
+///         .and_then(|tag| tag.strip_suffix(";</script>"))
+///         .unwrap();
+///     for forbidden in ["<", ">", "&", "\u{2028}", "\u{2029}"] {
+///         assert!(!json.contains(forbidden), "{:?} in {}", forbidden, json);
+///     }
+///     assert_eq!(serde_json::from_str::<Value>(json)?, value);
+/// }
+///
+/// // Keys end up in the script as they are, so only identifier characters are allowed.
+/// assert!(initial_state("STATE = 1; alert(1); //", &1).is_err());
+/// assert!(initial_state("", &1).is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn initial_state<T: Serialize + ?Sized>(key: &str, value: &T) -> anyhow::Result<String> {
+    initial_state_with_nonce(key, value, None)
+}
+
+/// Like [`initial_state`], with `nonce` on the `<script>`, see [`csp_nonce`].
+pub fn initial_state_with_nonce<T: Serialize + ?Sized>(
+    key: &str,
+    value: &T,
+    nonce: Option<&str>,
+) -> anyhow::Result<String> {
+    let is_identifier = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    if key.is_empty() || !key.chars().all(is_identifier) {
+        anyhow::bail!("The initial state key {:?} is not a JS identifier", key);
+    }
+    let json = serde_json::to_string(value)?;
+    let mut escaped = String::with_capacity(json.len());
+    for c in json.chars() {
+        // Outside of strings, JSON has none of these, and inside, the escapes mean the same.
+        match c {
+            '<' => escaped.push_str("\\u003c"),
+            '>' => escaped.push_str("\\u003e"),
+            '&' => escaped.push_str("\\u0026"),
+            '\u{2028}' => escaped.push_str("\\u2028"),
+            '\u{2029}' => escaped.push_str("\\u2029"),
+            c => escaped.push(c),
+        }
+    }
+    Ok(format!(
+        "<script{}>window.__{}__ = {};</script>",
+        nonce_attribute(nonce),
+        key,
+        escaped
+    ))
+}
+
+/// An [`HtmlTransform`] that adds the [`initial_state`] tag for `key` with the value
+/// `state` returns for the request in front of the `</head>` of every page, before the
+/// scripts of the app run, whether Vite or the build served it. The tag gets the
+/// [`csp_nonce`] of the request.
+///
+/// Set it with
+/// [`ProxyViteOptions::html_transform`](crate::proxy_vite_options::ProxyViteOptions::html_transform),
+/// or call [`initial_state`] from your own transform to combine it with other changes. If
+/// the value cannot be serialized, the error is logged and the page is sent without it.
+///
+/// # Example
+/// ```rust,no_run
+/// # fn run() -> anyhow::Result<()> {
+/// use serde::Serialize;
+/// use vite_actix::html::initial_state_transform;
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+///
+/// #[derive(Serialize)]
+/// struct Session {
+///     user: Option<String>,
+/// }
+///
+/// // Pages get `<script>window.__SESSION__ = {"user":...};</script>`.
+/// let transform = initial_state_transform("SESSION", |req| Session {
+///     user: req
+///         .headers()
+///         .get("x-user")
+///         .and_then(|user| user.to_str().ok())
+///         .map(String::from),
+/// });
+/// ProxyViteOptions::new().html_transform(transform).build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn initial_state_transform<T, F>(key: impl Into<String>, state: F) -> HtmlTransform
+where
+    T: Serialize,
+    F: Fn(&HttpRequest) -> T + Send + Sync + 'static,
+{
+    let key = key.into();
+    Arc::new(move |req, page| {
+        match initial_state_with_nonce(&key, &state(req), csp_nonce(req).as_deref()) {
+            Ok(tag) => insert_into_head(&page, &tag),
+            Err(e) => {
+                let path = req.path();
+                warn!("Not adding the initial state {} to {}: {}", key, path, e);
+                page
+            }
+        }
+    })
+}
+
+/// The value Vite's `html.cspNonce` option should be set to, e.g.
+/// `html: { cspNonce: "__VITE_ACTIX_NONCE__" }` in `vite.config.ts`, to have the tags Vite
+/// adds to its pages carry the nonce of each request, see [`csp_nonce`].
+pub const NONCE_PLACEHOLDER: &str = "__VITE_ACTIX_NONCE__";
+
+/// The CSP nonce of `req`, from the provider set with
+/// [`ProxyViteOptions::csp_nonce`], for the tags an [`HtmlTransform`] adds to a page.
+///
+/// The provider is asked once per request, and only for pages; `None` without one, or
+/// outside of an [`HtmlTransform`]. With it, every script and style tag this crate adds to
+/// the pages it sends has the nonce: the dev toolbar, the script of the starting page, and
+/// [`initial_state_transform`]. The tags Vite itself puts into its pages get it when
+/// Vite's `html.cspNonce` is set to [`NONCE_PLACEHOLDER`], which is replaced with the
+/// nonce in every page, from the dev server and the build alike; in dev mode the Vite
+/// client also finds it there for the styles it injects. For pages rendered by the app, see
+/// [`ViteHeadTags::with_nonce`] and [`AssetTags::with_nonce`], and [`suggested_csp`] for the
+/// header to send with it.
+///
+/// # Example
+/// ```rust,no_run
+/// use actix_web::{HttpRequest, HttpResponse};
+/// use vite_actix::html::csp_nonce;
+///
+/// async fn widget(req: HttpRequest) -> HttpResponse {
+///     let nonce = csp_nonce(&req).unwrap_or_default();
+///     HttpResponse::Ok()
+///         .content_type("text/html")
+///         .body(format!(r#"<script nonce="{}">mountWidget()</script>"#, nonce))
+/// }
+/// ```
+///
+/// [`ProxyViteOptions::csp_nonce`]: crate::proxy_vite_options::ProxyViteOptions::csp_nonce
+pub fn csp_nonce(req: &HttpRequest) -> Option<String> {
+    request_nonce(req)
+}
+
+/// A `Content-Security-Policy` header value for pages with `nonce`, see [`csp_nonce`], that
+/// allows what this crate and Vite need under `options` and nothing else: scripts and
+/// styles with the nonce or from the same origin and, in dev mode with the dev server,
+/// WebSocket connections for hot module replacement, which older browsers do not count as
+/// `'self'`.
+///
+/// In dev mode, Vite's `html.cspNonce` has to be set to [`NONCE_PLACEHOLDER`] too, or the
+/// styles the Vite client injects are blocked. Extend the policy for anything else the app
+/// loads, e.g. images from a CDN. Fails if `nonce` is not base64, the only characters a
+/// nonce can have in the header.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::html::suggested_csp;
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+///
+/// let dev = ProxyViteOptions::new().dev_mode(true);
+/// assert_eq!(
+///     suggested_csp(&dev, "cjR1ZA==")?,
+///     "default-src 'self'; script-src 'self' 'nonce-cjR1ZA=='; \
+///      style-src 'self' 'nonce-cjR1ZA=='; connect-src 'self' ws: wss:; \
+///      object-src 'none'; base-uri 'self'"
+/// );
+///
+/// let prod = ProxyViteOptions::new().dev_mode(false);
+/// assert!(!suggested_csp(&prod, "cjR1ZA==")?.contains("ws:"));
+///
+/// // Nothing can end the directive.
+/// assert!(suggested_csp(&prod, "x'; script-src *").is_err());
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub fn suggested_csp(options: &ProxyViteOptions, nonce: &str) -> anyhow::Result<String> {
+    let is_base64 = |c: char| c.is_ascii_alphanumeric() || "+/-_=".contains(c);
+    if nonce.is_empty() || !nonce.chars().all(is_base64) {
+        anyhow::bail!("The CSP nonce {:?} is not base64", nonce);
+    }
+    let connect = match options.dev_mode && options.command.is_dev_server() {
+        true => "'self' ws: wss:",
+        false => "'self'",
+    };
+    Ok(format!(
+        "default-src 'self'; script-src 'self' 'nonce-{0}'; style-src 'self' 'nonce-{0}'; \
+         connect-src {1}; object-src 'none'; base-uri 'self'",
+        nonce, connect
+    ))
+}
@@ -0,0 +1,112 @@
+//! The HTML entries of a multi-page app, set with [`ProxyViteOptions::html_entries`].
+//!
+//! A single-page app has one `index.html`, which every page navigation falls back to. A
+//! multi-page app has several, e.g. `index.html`, `admin/index.html` and `kiosk.html`, each
+//! with a client-side router of its own. With the entries mapped to the path prefixes of
+//! their pages, a page navigation gets the entry of the longest matching prefix: the build
+//! falls back to that file instead of `index.html`, and in dev mode the navigation is
+//! forwarded to Vite under the path of the entry, which is where Vite serves it.
+//!
+//! Like the SPA fallback, only page navigations are mapped, see [`is_page_route`]; modules,
+//! assets and `fetch` calls are served as they are asked for. A navigation no prefix
+//! matches falls back to `index.html` in the build and is forwarded unchanged in dev mode.
+//!
+//! [`ProxyViteOptions::html_entries`]: crate::proxy_vite_options::ProxyViteOptions::html_entries
+//! [`is_page_route`]: crate::trailing_slash::is_page_route
+
+use crate::proxy_vite_options::has_path_prefix;
+#[cfg(feature = "proxy")]
+use crate::trailing_slash::is_page_route;
+#[cfg(feature = "proxy")]
+use actix_web::HttpRequest;
+#[cfg(feature = "proxy")]
+use actix_web::http::header::ACCEPT;
+
+/// The HTML entries of a multi-page app by the path prefix of their pages, see
+/// [`crate::html_entries`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HtmlEntries {
+    // `(prefix, file)` pairs, longest prefix first, the prefixes without a trailing slash.
+    entries: Vec<(String, String)>,
+}
+
+impl HtmlEntries {
+    /// Maps each path prefix, e.g. `/admin`, to its HTML entry, relative to the root of the
+    /// project or the dist directory, e.g. `admin/index.html`. `/` maps every other page.
+    pub fn new(entries: &[(&str, &str)]) -> Self {
+        let mut entries: Vec<(String, String)> = entries
+            .iter()
+            .map(|(prefix, file)| {
+                let prefix = format!("/{}", prefix.trim_matches('/'));
+                (prefix, file.trim_start_matches('/').to_string())
+            })
+            .collect();
+        entries.sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
+        Self { entries }
+    }
+
+    /// Whether no entries are mapped, as for a single-page app.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entry of the page at `path`, that of the longest prefix `path` is under, or `None`
+    /// if it is under none of them.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::html_entries::HtmlEntries;
+    ///
+    /// let entries = HtmlEntries::new(&[
+    ///     ("/", "index.html"),
+    ///     ("/admin", "admin/index.html"),
+    ///     ("/admin/reports/", "admin/reports.html"),
+    /// ]);
+    /// assert_eq!(entries.entry_for("/admin"), Some("admin/index.html"));
+    /// assert_eq!(entries.entry_for("/admin/users/42"), Some("admin/index.html"));
+    /// // Nested prefixes win over the ones they are under.
+    /// assert_eq!(entries.entry_for("/admin/reports/2024"), Some("admin/reports.html"));
+    /// // A prefix covers whole path segments only.
+    /// assert_eq!(entries.entry_for("/administrators"), Some("index.html"));
+    /// // The root entry covers everything else.
+    /// assert_eq!(entries.entry_for("/"), Some("index.html"));
+    /// assert_eq!(entries.entry_for("/settings/profile"), Some("index.html"));
+    ///
+    /// // Without one, other pages have no entry.
+    /// let entries = HtmlEntries::new(&[("/kiosk", "kiosk.html")]);
+    /// assert_eq!(entries.entry_for("/kiosk/"), Some("kiosk.html"));
+    /// assert_eq!(entries.entry_for("/settings"), None);
+    /// ```
+    pub fn entry_for(&self, path: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(prefix, _)| has_path_prefix(path, prefix))
+            .map(|(_, file)| file.as_str())
+    }
+}
+
+// The path and query to ask Vite for instead of those of `req`, by the path `route` it
+// has for Vite, if it is a page navigation with an entry.
+#[cfg(feature = "proxy")]
+pub(crate) fn rewrite(
+    req: &HttpRequest,
+    route: &str,
+    entries: &HtmlEntries,
+    excluded_prefixes: &[String],
+) -> Option<String> {
+    if entries.is_empty() {
+        return None;
+    }
+    let accept = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok());
+    if !is_page_route(req.method(), accept, route, excluded_prefixes) {
+        return None;
+    }
+    let entry = entries.entry_for(route)?;
+    Some(match req.query_string() {
+        "" => format!("/{}", entry),
+        query => format!("/{}?{}", entry, query),
+    })
+}
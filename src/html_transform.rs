@@ -0,0 +1,172 @@
+#[cfg(any(feature = "proxy", feature = "prod-serve", feature = "embed"))]
+use crate::assets::escape;
+#[cfg(any(feature = "proxy", feature = "prod-serve", feature = "embed"))]
+use crate::html::NONCE_PLACEHOLDER;
+#[cfg(feature = "prod-serve")]
+use crate::manifest::{FileStamp, read_stable};
+use actix_web::{HttpMessage, HttpRequest};
+#[cfg(feature = "prod-serve")]
+use log::warn;
+#[cfg(feature = "prod-serve")]
+use std::collections::HashMap;
+#[cfg(feature = "prod-serve")]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "prod-serve")]
+use std::sync::Mutex;
+
+/// Rewrites an HTML page before it is sent, set with
+/// [`ProxyViteOptions::html_transform`](crate::proxy_vite_options::ProxyViteOptions::html_transform).
+///
+/// Called with the request and the page, e.g. to add a CSP nonce or a
+/// `<script>window.__ENV__ = ...</script>` block, and returns the page to send.
+pub type HtmlTransform = Arc<dyn Fn(&HttpRequest, String) -> String + Send + Sync>;
+
+/// Returns the CSP nonce of a request, set with
+/// [`ProxyViteOptions::csp_nonce`](crate::proxy_vite_options::ProxyViteOptions::csp_nonce).
+///
+/// Called once for every page sent, e.g. to read the nonce a CSP middleware put into the
+/// request's extensions, see [`crate::html::csp_nonce`].
+pub type NonceProvider = Arc<dyn Fn(&HttpRequest) -> Option<String> + Send + Sync>;
+
+// The nonce of a request, once the provider was asked for it.
+#[derive(Clone)]
+struct RequestNonce(Option<String>);
+
+// The nonce of `req`, asking `provider` the first time.
+#[cfg(any(feature = "proxy", feature = "prod-serve", feature = "embed"))]
+pub(crate) fn provide_nonce(req: &HttpRequest, provider: Option<&NonceProvider>) -> Option<String> {
+    if let Some(RequestNonce(nonce)) = req.extensions().get::<RequestNonce>() {
+        return nonce.clone();
+    }
+    let nonce = provider.and_then(|provider| provider(req));
+    req.extensions_mut().insert(RequestNonce(nonce.clone()));
+    nonce
+}
+
+// The nonce `provide_nonce` found for `req`, if it was called.
+pub(crate) fn request_nonce(req: &HttpRequest) -> Option<String> {
+    req.extensions()
+        .get::<RequestNonce>()
+        .and_then(|RequestNonce(nonce)| nonce.clone())
+}
+
+// `page` with the nonce of `req` in place of the placeholder, then rewritten by `transform`.
+#[cfg(any(feature = "proxy", feature = "prod-serve", feature = "embed"))]
+pub(crate) fn apply(
+    req: &HttpRequest,
+    page: String,
+    transform: Option<&HtmlTransform>,
+    nonce: Option<&NonceProvider>,
+) -> String {
+    let page = match provide_nonce(req, nonce) {
+        Some(nonce) => page.replace(NONCE_PLACEHOLDER, &escape(&nonce)),
+        None => page,
+    };
+    match transform {
+        Some(transform) => transform(req, page),
+        None => page,
+    }
+}
+
+// Whether a response with this `Content-Type` is an HTML page.
+#[cfg(any(feature = "proxy", feature = "embed"))]
+pub(crate) fn is_html(content_type: Option<&str>) -> bool {
+    content_type.is_some_and(|content_type| {
+        content_type
+            .split(';')
+            .next()
+            .is_some_and(|mime| mime.trim().eq_ignore_ascii_case("text/html"))
+    })
+}
+
+// `script` added to `page` in front of its `</body>`, or at its end.
+#[cfg(feature = "proxy")]
+pub(crate) fn append_to_body(page: &str, script: &str) -> String {
+    match page.rfind("</body>") {
+        Some(end) => format!("{}{}{}", &page[..end], script, &page[end..]),
+        None => format!("{}{}", page, script),
+    }
+}
+
+// `tag` added to `page` in front of its `</head>`, or at its start.
+pub(crate) fn insert_into_head(page: &str, tag: &str) -> String {
+    match page.find("</head>") {
+        Some(end) => format!("{}{}{}", &page[..end], tag, &page[end..]),
+        None => format!("{}{}", tag, page),
+    }
+}
+
+// `value` as a JS string literal that can be put into a `<script>`.
+#[cfg(feature = "proxy")]
+pub(crate) fn js_string(value: &str) -> String {
+    // A JSON string is a JS string; `</` is escaped so the value cannot end the script.
+    serde_json::to_string(value)
+        .unwrap_or_default()
+        .replace("</", "<\\/")
+}
+
+// Whether the file at `path` is served as an HTML page.
+#[cfg(feature = "prod-serve")]
+pub(crate) fn is_html_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+        })
+}
+
+// Applies the transform and the CSP nonce to HTML files on disk. The files are only read again once they
+// change, or after `manifest::invalidate`, so a request only pays for the transform itself.
+#[cfg(any(feature = "prod-serve", feature = "embed"))]
+pub(crate) struct HtmlFiles {
+    transform: Option<HtmlTransform>,
+    nonce: Option<NonceProvider>,
+    #[cfg(feature = "prod-serve")]
+    cache: Mutex<HashMap<PathBuf, (FileStamp, Arc<str>)>>,
+}
+
+#[cfg(any(feature = "prod-serve", feature = "embed"))]
+impl HtmlFiles {
+    pub(crate) fn new(transform: Option<HtmlTransform>, nonce: Option<NonceProvider>) -> Self {
+        Self {
+            transform,
+            nonce,
+            #[cfg(feature = "prod-serve")]
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Transform a page that is already in memory.
+    pub(crate) fn transform(&self, req: &HttpRequest, html: String) -> String {
+        apply(req, html, self.transform.as_ref(), self.nonce.as_ref())
+    }
+
+    // The transformed page at `path`, or `None` if it can't be read as UTF-8, in which case
+    // it is served as it is.
+    #[cfg(feature = "prod-serve")]
+    pub(crate) fn render(&self, req: &HttpRequest, path: &Path) -> Option<String> {
+        let html = self.read(path)?;
+        Some(self.transform(req, html.to_string()))
+    }
+
+    #[cfg(feature = "prod-serve")]
+    fn read(&self, path: &Path) -> Option<Arc<str>> {
+        let stamp = FileStamp::of(path).ok()?;
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((cached_at, html)) = cache.get(path)
+            && *cached_at == stamp
+        {
+            return Some(html.clone());
+        }
+        let (stamp, html) = match read_stable(path) {
+            Ok((stamp, html)) => (stamp, Arc::<str>::from(html)),
+            Err(e) => {
+                warn!("Not transforming {}: {}", path.display(), e);
+                return None;
+            }
+        };
+        cache.insert(path.to_path_buf(), (stamp, html.clone()));
+        Some(html)
+    }
+}
@@ -0,0 +1,71 @@
+//! Subresource integrity for the files of a production build, see
+//! [`ProxyViteOptions::subresource_integrity`](crate::proxy_vite_options::ProxyViteOptions::subresource_integrity).
+
+use crate::manifest::{FileStamp, ViteManifest};
+use anyhow::Context;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use sha2::{Digest, Sha384};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The `integrity` values of the files of a build, by their path in the dist directory.
+pub(crate) type Integrity = HashMap<String, String>;
+
+// Hashes per manifest, with the stamp of the manifest they were made for.
+type IntegrityCache = Mutex<HashMap<PathBuf, (FileStamp, Arc<Integrity>)>>;
+static CACHE: OnceLock<IntegrityCache> = OnceLock::new();
+
+/// The `integrity` values of every script and stylesheet the manifest in `dist_dir` lists,
+/// hashed once per version of the manifest. Values the manifest has, from an SRI plugin,
+/// are taken as they are; files it lists that are missing are an error.
+pub(crate) fn cached(dist_dir: &Path) -> anyhow::Result<Arc<Integrity>> {
+    let manifest_path = ViteManifest::find(dist_dir).with_context(|| {
+        format!(
+            "No manifest.json in {}, set build.manifest to true in vite.config",
+            dist_dir.display()
+        )
+    })?;
+    let stamp = FileStamp::of(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((hashed_at, integrity)) = cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&manifest_path)
+        && *hashed_at == stamp
+    {
+        return Ok(integrity.clone());
+    }
+
+    let manifest = ViteManifest::cached(&manifest_path)?;
+    let mut integrity = Integrity::new();
+    for chunk in manifest.chunks().values() {
+        if let Some(value) = &chunk.integrity {
+            integrity.insert(chunk.file.clone(), value.clone());
+        }
+        for file in std::iter::once(&chunk.file).chain(&chunk.css) {
+            if integrity.contains_key(file) || !(file.ends_with(".js") || file.ends_with(".css")) {
+                continue;
+            }
+            let path = dist_dir.join(file);
+            let contents = std::fs::read(&path).with_context(|| {
+                format!("Failed to hash {}, which the manifest lists", path.display())
+            })?;
+            integrity.insert(file.clone(), hash(&contents));
+        }
+    }
+    let integrity = Arc::new(integrity);
+    cache
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(manifest_path, (stamp, integrity.clone()));
+    Ok(integrity)
+}
+
+// The `integrity` value of `contents`.
+fn hash(contents: &[u8]) -> String {
+    format!("sha384-{}", STANDARD.encode(Sha384::digest(contents)))
+}
@@ -0,0 +1,93 @@
+use crate::error::StartViteError;
+use crate::package_script::{PackageManager, find_workspace_root};
+use log::debug;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Files Yarn Plug'n'Play writes to the project root in place of a node_modules folder.
+const PNP_FILES: [&str; 2] = [".pnp.cjs", ".pnp.loader.mjs"];
+
+// How the `vite` of a project is started: a program and the arguments that go before the
+// ones for Vite itself.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct ViteLauncher {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl ViteLauncher {
+    /// Find the `vite` to run for the project in `working_directory`.
+    ///
+    /// Yarn Plug'n'Play installs have no `node_modules/.bin`, so their vite is run through
+    /// `yarn vite`. Otherwise the project's own `node_modules/.bin/vite` is preferred over the
+    /// one on the PATH, followed by that of the pnpm workspace root, where vite is often only
+    /// installed once for all packages.
+    pub(crate) fn resolve(working_directory: &Path) -> Result<Self, StartViteError> {
+        if let Some(pnp_root) = find_pnp_root(working_directory) {
+            return Self::yarn_pnp(pnp_root);
+        }
+        if let Some(local) = local_vite(working_directory) {
+            debug!("found vite at: {:?}", local);
+            return Ok(Self {
+                program: local.to_string_lossy().into_owned(),
+                args: Vec::new(),
+            });
+        }
+        Ok(Self {
+            program: crate::spawn::find_vite_executable()?,
+            args: Vec::new(),
+        })
+    }
+
+    // `yarn vite`, which runs the vite of a Plug'n'Play install in `pnp_root`.
+    fn yarn_pnp(pnp_root: PathBuf) -> Result<Self, StartViteError> {
+        // `yarn.cmd` on Windows, where batch files are not found without their extension.
+        let yarn = PackageManager::Yarn.program();
+        let Some(program) = crate::which(yarn)? else {
+            return Err(StartViteError::YarnNotFound { pnp_root });
+        };
+        debug!(
+            "Yarn Plug'n'Play install found in {}, starting vite through {}",
+            pnp_root.display(),
+            program
+        );
+        Ok(Self {
+            program,
+            args: vec!["vite".to_string()],
+        })
+    }
+
+    // A command running this launcher, ready for Vite's own arguments.
+    pub(crate) fn command(&self) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        command
+    }
+}
+
+// The directory with the `.pnp.cjs` or `.pnp.loader.mjs` of a Yarn Plug'n'Play install
+// that `working_directory` belongs to, if any.
+pub(crate) fn find_pnp_root(working_directory: &Path) -> Option<PathBuf> {
+    let working_directory = working_directory
+        .canonicalize()
+        .unwrap_or_else(|_| working_directory.to_path_buf());
+    working_directory
+        .ancestors()
+        .find(|dir| PNP_FILES.iter().any(|file| dir.join(file).is_file()))
+        .map(Path::to_path_buf)
+}
+
+// The project's own `node_modules/.bin/vite` or that of its pnpm workspace root, if one of
+// them is installed.
+fn local_vite(working_directory: &Path) -> Option<PathBuf> {
+    bin_vite(working_directory).or_else(|| bin_vite(&find_workspace_root(working_directory)?))
+}
+
+// `<directory>/node_modules/.bin/vite`, if it exists.
+fn bin_vite(directory: &Path) -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let local = directory.join("node_modules/.bin/vite.cmd");
+    #[cfg(not(target_os = "windows"))]
+    let local = directory.join("node_modules/.bin/vite");
+    local.is_file().then_some(local)
+}
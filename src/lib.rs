@@ -1,16 +1,173 @@
-#![doc = include_str!("../README.md")]
-
+// The README starts the Vite process, so its examples need the `spawn` feature.
+#![cfg_attr(feature = "spawn", doc = include_str!("../README.md"))]
+
+#[cfg(feature = "proxy")]
+pub mod admin_routes;
+pub mod assets;
+#[cfg(any(feature = "prod-serve", feature = "embed"))]
+mod built_dist;
+#[cfg(feature = "build-helper")]
+pub mod build;
+#[cfg(feature = "proxy")]
+mod chunked_body;
+#[cfg(feature = "spawn")]
+pub mod compile_error;
+#[cfg(feature = "compress")]
+pub mod compression;
+#[cfg(feature = "proxy")]
+pub mod dev_toolbar;
+#[cfg(all(feature = "proxy", feature = "prod-serve"))]
+pub mod dist_fallback;
+#[cfg(feature = "spawn")]
+pub mod events;
+#[cfg(feature = "embed")]
+pub mod embedded;
+#[cfg(feature = "watch")]
+pub mod config_watch;
+pub mod error;
+pub mod fault_injection;
+pub mod health;
+#[cfg(feature = "proxy")]
+mod hops;
+pub mod html;
+pub mod html_entries;
+pub mod html_transform;
+mod integrity;
+pub mod manifest;
+pub mod navigation_fallback;
+pub mod network_urls;
+#[cfg(feature = "otel")]
+pub mod otel;
+#[cfg(feature = "spawn")]
+pub mod package_script;
+#[cfg(feature = "spawn")]
+pub mod pid_file;
+#[cfg(feature = "spawn")]
+pub mod port_conflict;
+pub mod proxy;
+#[cfg(feature = "spawn")]
+mod launcher;
+#[cfg(feature = "spawn")]
+pub mod log_format;
+#[cfg(feature = "spawn")]
+mod output_log;
+#[cfg(feature = "spawn")]
+mod process_lifetime;
+#[cfg(feature = "spawn")]
+mod process_priority;
+#[cfg(feature = "proxy")]
+mod proxy_filter;
+#[cfg(feature = "proxy")]
+mod scope_prefix;
 pub mod proxy_vite_options;
+pub mod rate_limit;
+#[cfg(feature = "integration-tests")]
+mod real_vite;
+#[cfg(feature = "proxy")]
+pub mod request_id;
+#[cfg(feature = "spawn")]
+pub mod skip_spawn;
+#[cfg(feature = "spawn")]
+mod spawn;
+#[cfg(feature = "ssr")]
+pub mod ssr;
+#[cfg(feature = "proxy")]
+pub mod starting_page;
+#[cfg(feature = "spawn")]
+pub mod startup_banner;
+#[cfg(feature = "spawn")]
+pub mod startup_build;
+#[cfg(feature = "tera")]
+pub mod tera;
+#[cfg(feature = "askama")]
+pub mod askama;
+#[cfg(feature = "maud")]
+pub mod maud;
+#[cfg(feature = "prod-serve")]
+mod static_files;
+mod well_known;
+#[cfg(feature = "proxy")]
+mod ws_proxy;
+#[cfg(feature = "spawn")]
+pub mod supervisor;
+#[cfg(feature = "test-util")]
+pub mod test;
+pub mod trailing_slash;
 pub mod vite_app_factory;
-
-use std::time::Duration;
+pub mod vite_command;
+#[cfg(feature = "proxy")]
+pub mod upgrade;
+#[cfg(feature = "proxy")]
+pub mod upstream_encoding;
+#[cfg(feature = "spawn")]
+pub mod vite_instances;
+#[cfg(feature = "proxy")]
+pub mod vite_ping;
+#[cfg(feature = "proxy")]
+pub mod vite_proxy_middleware;
+#[cfg(feature = "spawn")]
+pub mod vite_server_handle;
+pub mod vite_state;
+#[cfg(feature = "spawn")]
+pub mod vite_stdio;
+pub mod vite_version;
+
+#[cfg(feature = "spawn")]
+pub use crate::spawn::{
+    lan_urls, report_server_addr, serve, serve_with, serve_with_vite, start_vite_server,
+    start_vite_server_managed, start_vite_server_with, wait_for_vite_port,
+    wait_for_vite_port_async,
+};
+#[cfg(feature = "proxy")]
+pub use crate::vite_proxy_middleware::ViteProxyMiddleware;
+
+#[cfg(any(feature = "spawn", feature = "ssr"))]
+use crate::error::StartViteError;
+#[cfg(feature = "dev-proxy")]
+use crate::proxy::ProxyTarget;
+#[cfg(feature = "proxy")]
+use crate::hops::Loop;
+#[cfg(feature = "proxy")]
+use crate::chunked_body::ChunkedBody;
+#[cfg(feature = "proxy")]
 use crate::proxy_vite_options::ProxyViteOptions;
-use actix_web::error::ErrorInternalServerError;
+#[cfg(feature = "proxy")]
+use crate::fault_injection::FAULT_HEADER;
+#[cfg(feature = "proxy")]
+use crate::health::HEALTH_PATH;
+#[cfg(feature = "proxy")]
+use crate::request_id::{REQUEST_ID_HEADER, RequestId};
+#[cfg(feature = "proxy")]
+use crate::scope_prefix::ScopedRequest;
+#[cfg(feature = "proxy")]
+use crate::upgrade::Upgrade;
+#[cfg(feature = "proxy")]
+use crate::vite_command::ViteCommand;
+#[cfg(feature = "proxy")]
+use crate::vite_ping::Probe;
+#[cfg(feature = "proxy")]
+use crate::vite_state::{ViteProcess, ViteState};
+#[cfg(feature = "proxy")]
+use actix_web::body::SizedStream;
+#[cfg(feature = "proxy")]
+use actix_web::error::{ErrorInternalServerError, PayloadError};
+#[cfg(feature = "proxy")]
+use actix_web::http::Method;
+#[cfg(feature = "proxy")]
+use actix_web::http::header::{
+    ACCEPT, ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE,
+    RETRY_AFTER,
+};
+#[cfg(feature = "proxy")]
 use actix_web::{web, Error, HttpRequest, HttpResponse};
+#[cfg(feature = "proxy")]
 use awc::Client;
-use futures_util::StreamExt;
-use log::{debug, error, info, trace, warn};
-use regex::Regex;
+#[cfg(feature = "proxy")]
+use futures_util::{Stream, StreamExt};
+#[cfg(feature = "proxy")]
+use log::{debug, trace, warn};
+#[cfg(feature = "proxy")]
+use std::time::{Duration, Instant};
 
 // The maximum payload size allowed for forwarding requests and responses.
 //
@@ -18,82 +175,373 @@ use regex::Regex;
 // when proxying. Any payload exceeding this size will result in an error.
 //
 // Currently, it is set to 1 GB.
+#[cfg(feature = "proxy")]
 const MAX_PAYLOAD_SIZE: usize = 1024 * 1024 * 1024; // 1 GB
 
-// Proxy requests to the Vite development server.
-//
-// This function forwards incoming requests to a local Vite server running on port 3000.
-// It buffers the entire request payload and response payload to avoid partial transfers.
-// Requests and responses larger than the maximum payload size will result in an error.
+// Relay Vite's HMR socket, which `configure_vite` registers under the HMR path with a guard
+// for the upgrade, so plain requests to that path still go to `proxy::handler`. Vite's HMR
+// server may listen on a port of its own, `hmr_port`.
 //
-// # Arguments
+// In a scope, the path is forwarded without the scope, under Vite's base.
+#[cfg(feature = "dev-proxy")]
+pub(crate) fn hmr_route(target: ProxyTarget, in_scope: bool) -> actix_web::Route {
+    let process = target.process();
+    web::get().to(move |req: HttpRequest, payload: web::Payload| {
+        let options = target.options();
+        let down = vite_ping::is_down(&process);
+        async move {
+            let Some(port) = options.hmr_port.or(options.port) else {
+                return Err(ErrorInternalServerError(
+                    "Unable to get port, you may have to set the port manually",
+                ));
+            };
+            let path = if in_scope {
+                let base = scope_prefix::base_path(&options.asset_base);
+                let path = options.hmr_path_for(true);
+                match req.uri().query() {
+                    Some(query) => format!("{}{}?{}", base, path, query),
+                    None => format!("{}{}", base, path),
+                }
+            } else {
+                req.uri().to_string()
+            };
+            if down && vite_ping::probe(req.method(), &path, req.headers()).is_some() {
+                return Ok(vite_ping::unavailable());
+            }
+            let hop_header = options.hop_header_name();
+            let hops = match hops::stamp(req.headers(), &hop_header, options.max_hops) {
+                Ok(hops) => hops,
+                Err(found) => {
+                    log::error!("The HMR socket {} came back from the proxy", path);
+                    return Ok(found.response());
+                }
+            };
+            let url = format!("ws://localhost:{}{}", port, path);
+            ws_proxy::forward(req, payload, url, (hop_header, hops)).await
+        }
+    })
+}
+
+// Forward `req` to Vite, to the path of `scoped` if the proxy is mounted in a scope.
 //
-// * `req` - The HTTP request object.
-// * `payload` - The request payload.
+// The entire request payload and response payload are buffered to avoid partial transfers.
+// Requests and responses larger than the maximum payload size will result in an error.
 //
-// # Returns
+// Vite knows nothing about scopes, so for a proxy mounted in one the path of the scope is
+// stripped from the forwarded request, sent along as `X-Forwarded-Prefix`, and put back
+// into redirects. A Vite `base` set as the `asset_base` is put in its place.
 //
-// An `HttpResponse` which contains the response from the Vite server,
-// or an error response in case of failure.
-async fn proxy_to_vite(
+// WebSocket upgrades, like the one for Vite's HMR socket, are relayed the same way, so a
+// frontend in a scope gets its updates from its own Vite server.
+#[cfg(feature = "proxy")]
+pub(crate) async fn forward_to_vite(
     req: HttpRequest,
-    mut payload: web::Payload,
+    mut payload: impl Stream<Item = Result<web::Bytes, PayloadError>> + Unpin + 'static,
+    scoped: Option<ScopedRequest>,
+    options: ProxyViteOptions,
+    process: ViteProcess,
 ) -> anyhow::Result<HttpResponse, Error> {
-    // Create a new HTTP client instance for making requests to the Vite server.
-    let client = Client::builder().timeout(Duration::from_secs(60)).finish();
+    // Create a new HTTP client instance for making requests to the Vite server. Its redirects
+    // go back to the browser, with the `Location` put back into the scope, instead of being
+    // followed here.
+    let client = Client::builder()
+        .timeout(Duration::from_secs(60))
+        .disable_redirects()
+        .finish();
+    let request_id = RequestId::of(&req);
+
+    // A request forwarded by the proxy came back to it, and would be forwarded forever.
+    let hop_header = options.hop_header_name();
+    let hops = match hops::stamp(req.headers(), &hop_header, options.max_hops) {
+        Ok(hops) => hops,
+        Err(found) => {
+            match found {
+                Loop::Own => log::error!(
+                    "[{}] {} came back from the proxy, is Vite's port {:?} the Actix server's?",
+                    request_id,
+                    req.path(),
+                    options.port
+                ),
+                Loop::TooManyHops(count) => log::error!(
+                    "[{}] {} went through {} proxies, the most max_hops allows",
+                    request_id,
+                    req.path(),
+                    count
+                ),
+            }
+            return Ok(found.response());
+        }
+    };
+
+    // Backend paths that are not registered are a plain 404, not Vite's index.html.
+    if options.is_excluded(req.path()) {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let vite_path = match &scoped {
+        Some(scoped) => scoped.forwarded.split('?').next().unwrap_or_default(),
+        None => req.path(),
+    };
+    // The client probes every second while Vite is away, see `vite_ping`.
+    if let Some(probe) = vite_ping::probe(req.method(), vite_path, req.headers()) {
+        if vite_ping::is_down(&process) || options.port.is_none() {
+            if options.log_vite_pings {
+                debug!(
+                    "[{}] Vite is down, answering the ping {}",
+                    request_id, vite_path
+                );
+            }
+            return Ok(vite_ping::unavailable());
+        }
+        if let (Probe::Http, Some(port)) = (probe, options.port) {
+            let url = match &scoped {
+                Some(scoped) => format!("http://localhost:{}{}", port, scoped.upstream_path()),
+                None => format!("http://localhost:{}{}", port, req.uri()),
+            };
+            let hop = (hop_header, hops);
+            return Ok(vite_ping::forward(&req, &url, hop, &request_id, &options).await);
+        }
+    }
+
+    if !options.expose_node_modules
+        && proxy_filter::is_hidden_node_module(vite_path, options.expose_vite_deps)
+    {
+        debug!("[{}] Not proxying {}, node_modules is not exposed", request_id, vite_path);
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    if let Some(extensions) = &options.proxy_extensions
+        && !proxy_filter::is_proxied_path(vite_path, extensions)
+    {
+        debug!("[{}] Not proxying {}, which is not a frontend asset", request_id, vite_path);
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    if options.enable_admin_routes && admin_routes::is_admin_path(vite_path) {
+        return Ok(admin_routes::handle(&req, vite_path, &options, &process, &mut payload).await);
+    }
+    // Answered here too, for the page shown while Vite is starting.
+    if vite_path == HEALTH_PATH && req.method() == Method::GET {
+        return Ok(health::vite_health(options, &process).await);
+    }
+    if options.dev_toolbar && vite_path == dev_toolbar::STATUS_PATH && req.method() == Method::GET
+    {
+        return Ok(dev_toolbar::status(&options, &process));
+    }
+
+    if let Some(limiter) = &options.rate_limit
+        && let Err(wait) = limiter.check(req.peer_addr().map(|peer| peer.ip()), Instant::now())
+    {
+        let peer = req
+            .peer_addr()
+            .map_or_else(|| String::from("an unknown peer"), |peer| peer.to_string());
+        warn!("[{}] Rate limited {} from {}", request_id, vite_path, peer);
+        // Whole seconds, rounded up.
+        let retry_after = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+        return Ok(HttpResponse::TooManyRequests()
+            .insert_header((RETRY_AFTER, retry_after.max(1)))
+            .body("Too many requests for the Vite dev server"));
+    }
+
+    if let Some(redirect) = trailing_slash::redirect(
+        &req,
+        vite_path,
+        options.trailing_slash,
+        &options.navigation_fallback_prefixes,
+    ) {
+        return Ok(redirect);
+    }
+    let accept = req.headers().get(ACCEPT).and_then(|value| value.to_str().ok());
+    if options.navigation_fallback_only
+        && !ws_proxy::is_websocket(&req)
+        && !navigation_fallback::is_proxied(
+            req.method(),
+            accept,
+            vite_path,
+            &options.navigation_fallback_prefixes,
+        )
+    {
+        debug!(
+            "[{}] Not proxying {}, which is neither a page nor a Vite path",
+            request_id, vite_path
+        );
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let state = process.state();
+    if state == Some(ViteState::Starting) {
+        let prefix = scoped.as_ref().map_or("", |scoped| scoped.prefix.as_str());
+        return Ok(starting_page::respond(&req, prefix, &options));
+    }
+    // The build stands in for a Vite that is down, until it is running again.
+    #[cfg(feature = "prod-serve")]
+    if let Some(state) = &state
+        && options.fallback_to_dist
+    {
+        match state {
+            ViteState::Exited(_) | ViteState::Failed { .. } => {
+                if let Some(res) = dist_fallback::serve(req.clone(), &options).await {
+                    return res;
+                }
+            }
+            _ => dist_fallback::resume(),
+        }
+    }
+    if let Some(ViteState::Failed { status, output }) = &state {
+        return Err(vite_failed_error(*status, output, &process));
+    }
 
-    // Get a copy of the current global options
-    let options = ProxyViteOptions::global();
-    
     let port = if let Some(port) = options.port {
         port
+    } else if let Some(ViteState::Exited(status)) = state {
+        return Err(vite_exited_error(status, &process));
     } else {
         return Err(ErrorInternalServerError(
             "Unable to get port, you may have to set the port manually",
         ));
     };
 
-    // Construct the URL of the Vite server by reading the VITE_PORT environment variable,
-    // defaulting to 5173 if the variable is not set.
-    // The constructed URL uses the same URI as the incoming request.
-    let forward_url = format!("http://localhost:{}{}", port, req.uri());
+    // The URL at Vite has the port of the options and the URI of the incoming request, or
+    // in a scope the path without it, under Vite's base.
+    // A page of a multi-page app is asked for under the path of its HTML entry, the only one
+    // Vite serves it under.
+    let entry = html_entries::rewrite(
+        &req,
+        vite_path,
+        &options.html_entries,
+        &options.navigation_fallback_prefixes,
+    );
+    let forward_url = match (&scoped, entry) {
+        (Some(scoped), Some(entry)) => format!("http://localhost:{}{}{}", port, scoped.base, entry),
+        (Some(scoped), None) => format!("http://localhost:{}{}", port, scoped.upstream_path()),
+        (None, Some(entry)) => format!("http://localhost:{}{}", port, entry),
+        (None, None) => format!("http://localhost:{}{}", port, req.uri()),
+    };
 
-    // Buffer the entire payload from the incoming request into body_bytes.
-    // This accumulates all chunks of the request body until no more are received or
-    // until the maximum allowed payload size is exceeded.
-    let mut body_bytes = web::BytesMut::new();
-    while let Some(chunk) = payload.next().await {
-        let chunk = chunk?;
-        // Check if the payload exceeds the maximum size defined by MAX_PAYLOAD_SIZE.
-        if (body_bytes.len() + chunk.len()) > MAX_PAYLOAD_SIZE {
-            return Err(actix_web::error::ErrorPayloadTooLarge("Payload overflow"));
+    // Vite only speaks WebSockets, other upgrades are dropped, see `upgrade`.
+    let downgrade = match upgrade::negotiate(req.method(), req.headers()) {
+        Upgrade::None => false,
+        Upgrade::WebSocket => {
+            let ws_url = forward_url.replacen("http://", "ws://", 1);
+            return ws_proxy::forward(req, payload, ws_url, (hop_header, hops)).await;
+        }
+        Upgrade::Downgrade(protocols) => {
+            debug!(
+                "[{}] Forwarding {} without its upgrade to {}",
+                request_id,
+                vite_path,
+                protocols.join(", ")
+            );
+            true
+        }
+        Upgrade::Unsupported(protocols) => {
+            debug!(
+                "[{}] Not proxying {}, a {} for {}",
+                request_id,
+                vite_path,
+                req.method(),
+                protocols.join(", ")
+            );
+            return Ok(HttpResponse::NotImplemented().finish());
+        }
+    };
+
+    if let Some(fault) = options.fault_injection.sample(vite_path) {
+        actix_web::rt::time::sleep(fault.delay).await;
+        if let Some(status) = fault.status {
+            debug!("[{}] Injecting a {} into {}", request_id, status, vite_path);
+            return Ok(HttpResponse::build(status)
+                .insert_header((FAULT_HEADER, "injected"))
+                .body(format!("Injected by vite-actix: {}", status)));
         }
-        // Append the current chunk to the body buffer.
-        body_bytes.extend_from_slice(&chunk);
     }
 
+    let body = read_payload(&mut payload).await?;
+
     // Forward the request to the Vite server along with the buffered request body.
-    let mut forwarded_resp = client
-        .request_from(forward_url.as_str(), req.head()) // Clone headers and method from the original request.
-        .no_decompress() // Disable automatic decompression of the response.
-        .send_body(body_bytes) // Send the accumulated request payload to the Vite server.
-        .await
-        .map_err(|err| ErrorInternalServerError(format!("Failed to forward request: {}", err)))?;
-
-    // Buffer the entire response body from the Vite server into resp_body_bytes.
-    // This accumulates all chunks of the response body until no more are received or
-    // until the maximum allowed payload size is exceeded.
-    let mut resp_body_bytes = web::BytesMut::new();
-    while let Some(chunk) = forwarded_resp.next().await {
-        let chunk = chunk?;
-        // Check if the response payload exceeds the maximum size defined by MAX_PAYLOAD_SIZE.
-        if (resp_body_bytes.len() + chunk.len()) > MAX_PAYLOAD_SIZE {
-            return Err(actix_web::error::ErrorPayloadTooLarge(
-                "Response payload overflow",
-            ));
+    let mut forwarded_req = client.request_from(forward_url.as_str(), req.head()); // Clone headers and method from the original request.
+    if !options.decompress_upstream {
+        forwarded_req = forwarded_req.no_decompress(); // Disable automatic decompression of the response.
+    }
+    if downgrade {
+        upgrade::strip(forwarded_req.headers_mut());
+    }
+    // Pages that are changed on the way are asked for uncompressed, everything else keeps
+    // the client's encodings.
+    if upstream_encoding::needs_identity(&options, req.method(), accept, vite_path) {
+        forwarded_req = forwarded_req.insert_header((ACCEPT_ENCODING, "identity"));
+    }
+    if let Some(scoped) = &scoped
+        && !scoped.prefix.is_empty()
+    {
+        forwarded_req =
+            forwarded_req.insert_header(("X-Forwarded-Prefix", scoped.prefix.as_str()));
+    }
+    forwarded_req = forwarded_req.insert_header((REQUEST_ID_HEADER, request_id.as_str()));
+    forwarded_req = forwarded_req.insert_header((hop_header.clone(), hops));
+
+    // Bracket the call, so the lines Vite prints in between can be told apart.
+    let started = Instant::now();
+    if options.log_proxy_spans {
+        trace!("[{}] proxy start {} {}", request_id, req.method(), vite_path);
+    }
+    #[cfg(feature = "otel")]
+    let span = otel::client_span(req.method(), &forward_url, port);
+    #[cfg(feature = "otel")]
+    otel::inject(&span, forwarded_req.headers_mut());
+    let forwarded = forwarded_req.send_body(body).await; // Send the request payload to the Vite server in the chunks it came in.
+    #[cfg(feature = "otel")]
+    otel::record_outcome(&span, forwarded.as_ref().map(|res| res.status()));
+    if options.log_proxy_spans {
+        let outcome = match &forwarded {
+            Ok(res) => res.status().to_string(),
+            Err(_) => String::from("failed"),
+        };
+        trace!(
+            "[{}] proxy end {} {}: {} in {:?}",
+            request_id,
+            req.method(),
+            vite_path,
+            outcome,
+            started.elapsed()
+        );
+    }
+    let mut forwarded_resp = forwarded.map_err(|err| {
+        warn!("[{}] Failed to forward {} to Vite: {}", request_id, vite_path, err);
+        report_proxy_error(&process, &request_id, &req, vite_path, &err.to_string());
+        match process.state() {
+            Some(ViteState::Exited(status)) => vite_exited_error(status, &process),
+            _ => ErrorInternalServerError(format!("Failed to forward request: {}", err)),
         }
-        // Append the current chunk to the response buffer.
-        resp_body_bytes.extend_from_slice(&chunk);
+    })?;
+
+    let content_type = forwarded_resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok());
+
+    // A missing `public/` file is answered with index.html, which browsers would cache as
+    // the favicon and crawlers would read as robots.txt.
+    if options.well_known_paths.iter().any(|path| path == vite_path)
+        && well_known::is_html_fallback(vite_path, content_type)
+    {
+        debug!("[{}] Vite has no {}, answering with a 404", request_id, vite_path);
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    // Rewrite pages with the CSP nonce, the html_transform and the toolbar, which can only
+    // read them uncompressed.
+    let page = html_transform::is_html(content_type);
+    let mut transform = page && options.changes_pages();
+    let encoded = forwarded_resp
+        .headers()
+        .get(CONTENT_ENCODING)
+        .is_some_and(|encoding| encoding != "identity");
+    if transform && encoded && !options.decompress_upstream {
+        warn!(
+            "[{}] Not transforming {}, which Vite sent compressed; set decompress_upstream(true)",
+            request_id, vite_path
+        );
+        transform = false;
     }
 
     // Build the HTTP response to send back to the client.
@@ -102,208 +550,182 @@ async fn proxy_to_vite(
     // Copy all headers from the response received from the Vite server
     // and include them in the response to the client.
     for (header_name, header_value) in forwarded_resp.headers().iter() {
-        res.insert_header((header_name.clone(), header_value.clone()));
+        // The body no longer has the length, or the encoding, that Vite sent.
+        let changed_body = transform || options.decompress_upstream;
+        if changed_body && (header_name == CONTENT_LENGTH || header_name == CONTENT_ENCODING) {
+            continue;
+        }
+        // A server echoing the request's headers would hand the client the proxies' tokens.
+        if header_name == hop_header {
+            continue;
+        }
+        let header_value = match &scoped {
+            Some(scoped) => scoped.rewrite_header(header_name, header_value),
+            None => header_value.clone(),
+        };
+        res.insert_header((header_name.clone(), header_value));
+    }
+    if !forwarded_resp.headers().contains_key(REQUEST_ID_HEADER) {
+        res.insert_header((REQUEST_ID_HEADER, request_id.as_str()));
+    }
+    // The dev server's pages change with every edit, so the browser asks for them again every
+    // time. `vite preview` serves the build, whose caching is left to it.
+    if page && options.command == ViteCommand::Dev {
+        res.insert_header((CACHE_CONTROL, "no-store"));
+    }
+
+    // Pass the body on as Vite sends it, with the length it announced unless it is
+    // decompressed on the way.
+    if options.stream_responses && !transform {
+        let length = forwarded_resp
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .filter(|_| !options.decompress_upstream);
+        return Ok(match length {
+            Some(length) => res.body(SizedStream::new(length, forwarded_resp)),
+            None => res.streaming(forwarded_resp),
+        });
+    }
+
+    // Buffer the entire response body from the Vite server, in the chunks it arrives in,
+    // until no more are received or until the maximum allowed payload size is exceeded.
+    let mut resp_body = ChunkedBody::default();
+    while let Some(chunk) = forwarded_resp.next().await {
+        let chunk = chunk?;
+        // Check if the response payload exceeds the maximum size defined by MAX_PAYLOAD_SIZE.
+        if (resp_body.len() + chunk.len()) > MAX_PAYLOAD_SIZE {
+            return Err(actix_web::error::ErrorPayloadTooLarge(
+                "Response payload overflow",
+            ));
+        }
+        resp_body.push(chunk);
+    }
+
+    if transform {
+        let html = String::from_utf8_lossy(&resp_body.into_bytes()).into_owned();
+        let transform = options.html_transform.as_ref();
+        let mut html = html_transform::apply(&req, html, transform, options.csp_nonce.as_ref());
+        if options.dev_toolbar {
+            let prefix = scoped.as_ref().map_or("", |scoped| scoped.prefix.as_str());
+            let nonce = html_transform::request_nonce(&req);
+            html = dev_toolbar::inject(&html, prefix, nonce.as_deref());
+        }
+        return Ok(res.body(html));
     }
 
     // Return the response with the buffered body to the client.
-    Ok(res.body(resp_body_bytes))
+    Ok(res.body(resp_body))
+}
+
+// Tell the event subscribers of `process` that `req`, for `path` at Vite, could not be
+// forwarded.
+#[cfg(all(feature = "proxy", feature = "spawn"))]
+fn report_proxy_error(
+    process: &ViteProcess,
+    request_id: &RequestId,
+    req: &HttpRequest,
+    path: &str,
+    message: &str,
+) {
+    let Some(shared) = process.shared() else {
+        return;
+    };
+    shared.publish(events::ViteEvent::ProxyError(events::ViteProxyErrorInfo {
+        request_id: request_id.to_string(),
+        method: req.method().to_string(),
+        path: path.to_string(),
+        message: message.to_string(),
+    }));
+}
+
+// Without the `spawn` feature, there is no process to subscribe to the events of.
+#[cfg(all(feature = "proxy", not(feature = "spawn")))]
+fn report_proxy_error(_: &ViteProcess, _: &RequestId, _: &HttpRequest, _: &str, _: &str) {}
+
+// Buffer the entire payload of an incoming request, in the chunks it arrives in.
+//
+// This accumulates all chunks of the request body until no more are received or until the
+// maximum allowed payload size is exceeded.
+#[cfg(feature = "proxy")]
+pub(crate) async fn read_payload(
+    payload: &mut (impl Stream<Item = Result<web::Bytes, PayloadError>> + Unpin),
+) -> Result<ChunkedBody, Error> {
+    let mut body = ChunkedBody::default();
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk?;
+        // Check if the payload exceeds the maximum size defined by MAX_PAYLOAD_SIZE.
+        if (body.len() + chunk.len()) > MAX_PAYLOAD_SIZE {
+            return Err(actix_web::error::ErrorPayloadTooLarge("Payload overflow"));
+        }
+        body.push(chunk);
+    }
+    Ok(body)
+}
+
+// Build the error returned to clients when the managed Vite `process` is no longer running.
+#[cfg(feature = "proxy")]
+fn vite_exited_error(status: std::process::ExitStatus, process: &ViteProcess) -> Error {
+    let mut message = format!("The Vite process exited with {}", status);
+    let output = process.recent_output();
+    if !output.is_empty() {
+        message.push_str("\n\nLast output:\n");
+        message.push_str(&output.join("\n"));
+    }
+    push_last_compile_error(&mut message, process);
+    actix_web::error::ErrorBadGateway(message)
+}
+
+// Append the last compile error of the Vite `process` to an error page, if there is one.
+#[cfg(feature = "proxy")]
+fn push_last_compile_error(message: &mut String, process: &ViteProcess) {
+    if let Some(error) = process.last_error() {
+        message.push_str("\n\nLast compile error:\n");
+        message.push_str(&error);
+    }
+}
+
+// Build the error returned to clients once the supervisor gave up restarting Vite.
+#[cfg(feature = "proxy")]
+fn vite_failed_error(
+    status: std::process::ExitStatus,
+    output: &[String],
+    process: &ViteProcess,
+) -> Error {
+    let mut message = format!(
+        "The Vite process keeps crashing and is no longer restarted, it last exited with {}",
+        status
+    );
+    if !output.is_empty() {
+        message.push_str("\n\nLast output:\n");
+        message.push_str(&output.join("\n"));
+    }
+    push_last_compile_error(&mut message, process);
+    actix_web::error::ErrorServiceUnavailable(message)
 }
 
-/// Starts a Vite server by locating the installation of the Vite command using the system's
-/// `where` or `which` command (based on OS) and spawning the server in the configured working
-/// directory.
-///
-/// # Returns
-///
-/// Returns a result containing the spawned process's [`std::process::Child`] handle if successful,
-/// or an [`anyhow::Error`] if an error occurs.
-///
-/// # Errors
-///
-/// - Returns an error if the `vite` command cannot be found (`NotFound` error).
-/// - Returns an error if the `vite` command fails to execute or produce valid output.
-/// - Returns an error if the working directory environment variable or directory retrieval fails.
-///
-/// # Notes
-///
-/// - The working directory for Vite is set with the `VITE_WORKING_DIR` environment variable,
-///   falling back to the result of `try_find_vite_dir` or the current directory (".").
-///
-/// # Example
-/// ```no-rust
-/// let server = start_vite_server().expect("Failed to start Vite server");
-/// println!("Vite server started with PID: {}", server.id());
-/// ```
-///
-/// # Platform-Specific
-/// - On Windows, it uses `where` to find the `vite` executable.
-/// - On other platforms, it uses `which`.
-///
-/// # Clippy:
-/// You may want to allow zombie processes in your code.   
-/// `#[allow(clippy::zombie_processes)]`
-pub fn start_vite_server() -> anyhow::Result<std::process::Child> {
+// Look `name` up on the PATH with the system's `where` or `which` command.
+#[cfg(any(feature = "spawn", feature = "ssr"))]
+pub(crate) fn which(name: &str) -> Result<Option<String>, StartViteError> {
     #[cfg(target_os = "windows")]
     let find_cmd = "where"; // Use `where` on Windows to find the executable location.
     #[cfg(not(target_os = "windows"))]
     let find_cmd = "which"; // Use `which` on Unix-based systems to find the executable location.
 
-    // Locate the `vite` executable by invoking the system command and checking its output.
-    let vite = std::process::Command::new(find_cmd)
-        .arg("vite")
+    // Locate the executable by invoking the system command and checking its output.
+    let found = std::process::Command::new(find_cmd)
+        .arg(name)
         .stdout(std::process::Stdio::piped()) // Capture the command's stdout.
-        .output()? // Execute the command and handle potential IO errors.
+        .output() // Execute the command.
+        .map_err(StartViteError::LookupFailed)?
         .stdout;
 
-    // Convert the command output from bytes to a UTF-8 string.
-    let vite = String::from_utf8(vite)?;
-    let vite = vite.as_str().trim(); // Trim whitespace around the command output.
-
-    // If the `vite` command output is empty, the executable was not found.
-    if vite.is_empty() {
-        // Log an error message and return a `NotFound` error.
-        error!("vite not found, make sure it's installed with npm install -g vite");
-        Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "vite not found",
-        ))?;
-    }
-
-    // Vite installation could have multiple paths; using the last occurrence is a safeguard.
-    let vite = vite
-        .split("\n") // Split the result line by line.
-        .collect::<Vec<_>>() // Collect lines into a vector of strings.
-        .last() // Take the last entry in the result list.
-        .expect("Failed to get vite executable") // Panic if the vector for some reason is empty.
-        .trim(); // Trim any extra whitespace around the final path.
-
-    debug!("found vite at: {:?}", vite); // Log the found Vite path for debugging.
-
-    let options = ProxyViteOptions::global();
-
-    let mut vite_process = std::process::Command::new(vite);
-    vite_process.current_dir(&options.working_directory);
-    vite_process.stdout(std::process::Stdio::piped());
-
-    if let Some(port) = options.port {
-        vite_process.arg("--port").arg(port.to_string());
-        //        vite_process.arg("--strictPort");
-    }
-
-    let mut vite_process = vite_process.spawn()?;
-
-    // Create a buffered reader to capture the output from the Vite process.
-    let vite_stdout = vite_process
-        .stdout
-        .take()
-        .ok_or_else(|| anyhow::Error::msg("Failed to capture Vite process stdout"))?;
-
-    // Clone options for the thread
-    let options_clone = options.clone();
-
-    // Create a channel to signal when Vite is ready
-    let (tx, rx) = tokio::sync::mpsc::channel::<String>(100);
-
-    // Spawn a thread to handle stdout reading
-    std::thread::spawn(move || {
-        use std::io::BufRead;
-        let mut reader = std::io::BufReader::new(vite_stdout);
-        let mut line = String::new();
-
-        // Create a Tokio runtime for this thread to handle async operations
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .expect("Failed to create Tokio runtime");
-
-        let regex = Regex::new(r"(?P<url>http://localhost:\d+).*").unwrap();
-        loop {
-            line.clear();
-            match reader.read_line(&mut line) {
-                Ok(0) => {
-                    // End of file reached, the process has likely terminated
-                    debug!("End of output stream from Vite process, exiting reader loop");
-                    break;
-                }
-                Ok(_) => {
-                    let trimmed_line = line.trim().to_string();
-
-                    // Send the line through the channel
-                    // This will block until the message is sent,
-                    // but that's okay because we're in a dedicated thread
-                    if rt.block_on(tx.send(trimmed_line.clone())).is_err() {
-                        debug!("Failed to send log line, receiver was dropped");
-                        break;
-                    }
-                    let decolored_text =
-                        String::from_utf8(strip_ansi_escapes::strip(trimmed_line.as_str()))
-                            .unwrap();
-                    if decolored_text.contains("Local")
-                        && decolored_text.contains("http://localhost:")
-                    {
-                        let caps = regex.captures(&decolored_text).unwrap();
-                        let url = caps.name("url").unwrap().as_str();
-                        let port = url.split(":").last().unwrap();
-                        let port: u16 = port.parse().unwrap();
-                        
-                        if let Err(e) = ProxyViteOptions::update_port(port) {
-                            debug!("Failed to update Vite port to {}: {}", port, e);
-                        } else {
-                            debug!("Successfully updated Vite port to {}", port);
-                        }
-                    }
-                }
-                Err(err) => {
-                    error!("Failed to read line from Vite process: {}", err);
-                    break;
-                }
-            }
-        }
-        debug!("Exiting Vite stdout reader thread");
-    });
-
-    // Spawn a task to receive messages and log them
-    // This will work if we're in an async context with a Tokio runtime
-    if let Ok(handle) = tokio::runtime::Handle::try_current() {
-        let options = options_clone.clone();
-        handle.spawn(async move {
-            let mut rx = rx;
-            while let Some(line) = rx.recv().await {
-                match options.log_level {
-                    None => {}
-                    Some(log::Level::Trace) => trace!("{}", line),
-                    Some(log::Level::Debug) => debug!("{}", line),
-                    Some(log::Level::Info) => info!("{}", line),
-                    Some(log::Level::Warn) => warn!("{}", line),
-                    Some(log::Level::Error) => error!("{}", line),
-                }
-            }
-        });
-    } else {
-        // If we're not in a Tokio runtime context, we can create a thread to handle it
-        std::thread::spawn(move || {
-            // Create a runtime for this thread
-            let rt = tokio::runtime::Builder::new_current_thread()
-                .enable_all()
-                .build()
-                .expect("Failed to create Tokio runtime");
-
-            rt.block_on(async move {
-                let mut rx = rx;
-                while let Some(line) = rx.recv().await {
-                    match options_clone.log_level {
-                        None => {}
-                        Some(log::Level::Trace) => trace!("{}", line),
-                        Some(log::Level::Debug) => debug!("{}", line),
-                        Some(log::Level::Info) => info!("{}", line),
-                        Some(log::Level::Warn) => warn!("{}", line),
-                        Some(log::Level::Error) => error!("{}", line),
-                    }
-                }
-            });
-        });
-    }
-
-    // Return the process, which will continue running and logging output
-    Ok(vite_process)
+    // Convert the command output from bytes to a string and take the last non-empty line;
+    // an installation could have multiple paths, using the last occurrence is a safeguard.
+    let found = String::from_utf8_lossy(&found);
+    Ok(found
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(str::to_string))
 }
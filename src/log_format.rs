@@ -0,0 +1,258 @@
+//! Vite's output lines as JSON, for log pipelines that want structure instead of free text,
+//! see [`ProxyViteOptions::vite_log_format`].
+//!
+//! A [`ViteLogRecord`] holds what could be read from a line: the level, from a prefix like
+//! `error:` or `[WARNING]` or the color of the `[vite]` tag, the environment of Vite 6, the
+//! modules of an `hmr update`, and for error blocks the file, line and column. Lines with
+//! none of that become a record with just the message.
+//!
+//! # Example
+//! ```rust
+//! use vite_actix::compile_error::ViteCompileError;
+//! use vite_actix::log_format::ViteLogRecord;
+//!
+//! let hmr = "\x1b[2m10:21:33 AM\x1b[22m \x1b[36m\x1b[1m[vite]\x1b[22m\x1b[39m \
+//!            \x1b[32m(client)\x1b[39m \x1b[32mhmr update \x1b[39m\x1b[2m/src/App.tsx, /src/index.css\x1b[22m";
+//! assert_eq!(
+//!     ViteLogRecord::parse(hmr).to_json(),
+//!     r#"{"source":"vite","level":"info","message":"hmr update /src/App.tsx, /src/index.css","environment":"client","hmr_update":["/src/App.tsx","/src/index.css"]}"#
+//! );
+//!
+//! let warning = "\x1b[33m\x1b[1m[vite]\x1b[22m\x1b[39m warning: `optimizeDeps.esbuildOptions.loader` is deprecated";
+//! assert_eq!(ViteLogRecord::parse(warning).level, Some(log::Level::Warn));
+//!
+//! let error = ViteCompileError {
+//!     file: Some("/app/src/main.ts:3:10".to_string()),
+//!     message: "Failed to resolve import \"./missing\" from \"src/main.ts\"".to_string(),
+//!     frame: None,
+//! };
+//! assert_eq!(
+//!     ViteLogRecord::from_compile_error(&error).to_json(),
+//!     r#"{"source":"vite","level":"error","message":"Failed to resolve import \"./missing\" from \"src/main.ts\"","file":"/app/src/main.ts","line":3,"column":10}"#
+//! );
+//!
+//! // Anything else is kept as it is.
+//! assert_eq!(
+//!     ViteLogRecord::parse("  ➜  press h + enter to show help").to_json(),
+//!     r#"{"source":"vite","message":"➜  press h + enter to show help"}"#
+//! );
+//! for line in ["", "\x1b[", "[vite]", "12:00:00 [vite] hmr update ", "\u{0}\u{feff}ß💥", "\x1b[31m[vite"] {
+//!     let json: serde_json::Value = serde_json::from_str(&ViteLogRecord::parse(line).to_json()).unwrap();
+//!     assert_eq!(json["source"], "vite");
+//! }
+//! ```
+//!
+//! [`ProxyViteOptions::vite_log_format`]: crate::proxy_vite_options::ProxyViteOptions::vite_log_format
+
+use crate::compile_error::ViteCompileError;
+use regex::Regex;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// How the lines of Vite's output are forwarded to the `log` crate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Every line as Vite printed it.
+    #[default]
+    Text,
+    /// Every line as a single-line JSON object, see [`ViteLogRecord`].
+    ///
+    /// The lines of an error block are held back until the block ends and logged as one
+    /// object with the file, line and column of the error and its code frame.
+    Json,
+}
+
+/// What could be read from a line of Vite's output, or from an error block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ViteLogRecord {
+    /// The level Vite printed the line at, if it said.
+    pub level: Option<log::Level>,
+    /// The line without its timestamp, `[vite]` tag and environment.
+    pub message: String,
+    /// The environment of Vite 6, e.g. `client` for `[vite] (client) ...`.
+    pub environment: Option<String>,
+    /// The modules of an `hmr update` line.
+    pub hmr_update: Vec<String>,
+    /// The file of an error.
+    pub file: Option<String>,
+    /// The line in `file`, starting at 1.
+    pub line: Option<u32>,
+    /// The column in `line`.
+    pub column: Option<u32>,
+    /// The code frame pointing at the error.
+    pub frame: Option<String>,
+}
+
+impl ViteLogRecord {
+    /// Reads what it can from `line`, as Vite printed it, ANSI escape codes included.
+    pub fn parse(line: &str) -> Self {
+        static TIMESTAMP: OnceLock<Regex> = OnceLock::new();
+        static TAG: OnceLock<Regex> = OnceLock::new();
+        static HMR_UPDATE: OnceLock<Regex> = OnceLock::new();
+        let timestamp = TIMESTAMP
+            .get_or_init(|| Regex::new(r"^\d{1,2}:\d{2}:\d{2}(?:\s*[AP]M)?\s+").unwrap());
+        let tag = TAG.get_or_init(|| {
+            Regex::new(r"^\[vite\](?:\s+\((?P<environment>[\w-]+)\))?\s*").unwrap()
+        });
+        let hmr_update = HMR_UPDATE.get_or_init(|| {
+            Regex::new(r"^hmr update\s+(?P<modules>.+?)(?:\s+\(x\d+\))?$").unwrap()
+        });
+
+        let plain = String::from_utf8_lossy(&strip_ansi_escapes::strip(line)).into_owned();
+        let mut message = plain.trim();
+        if let Some(found) = timestamp.find(message) {
+            message = &message[found.end()..];
+        }
+        let mut record = Self::default();
+        let tagged = if let Some(caps) = tag.captures(message) {
+            record.environment = caps.name("environment").map(|m| m.as_str().to_string());
+            message = &message[caps.get(0).map_or(0, |m| m.end())..];
+            true
+        } else {
+            false
+        };
+        let message = message.trim();
+
+        if let Some(caps) = hmr_update.captures(message) {
+            record.hmr_update = caps["modules"]
+                .split(',')
+                .map(str::trim)
+                .filter(|module| !module.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        record.level = prefix_level(message)
+            .or_else(|| tagged.then(|| tag_color_level(line)).flatten())
+            .or(tagged.then_some(log::Level::Info));
+        record.message = message.to_string();
+        record
+    }
+
+    /// The record of an error block, at the `error` level, with its file split into file,
+    /// line and column.
+    pub fn from_compile_error(error: &ViteCompileError) -> Self {
+        let (file, line, column) = match &error.file {
+            Some(file) => {
+                let (file, line, column) = split_location(file);
+                (Some(file.to_string()), line, column)
+            }
+            None => (None, None, None),
+        };
+        Self {
+            level: Some(log::Level::Error),
+            message: error.message.clone(),
+            file,
+            line,
+            column,
+            frame: error.frame.clone(),
+            ..Self::default()
+        }
+    }
+
+    /// The record as a single-line JSON object, leaving out what it does not have.
+    pub fn to_json(&self) -> String {
+        let level = self.level.map(|level| level.as_str().to_ascii_lowercase());
+        let json = RecordJson {
+            source: "vite",
+            level: level.as_deref(),
+            message: &self.message,
+            environment: self.environment.as_deref(),
+            hmr_update: &self.hmr_update,
+            file: self.file.as_deref(),
+            line: self.line,
+            column: self.column,
+            frame: self.frame.as_deref(),
+        };
+        // Strings are escaped, newlines included, so the object stays on one line.
+        serde_json::to_string(&json).unwrap_or_default()
+    }
+}
+
+// The fields of a record in the order they are written, `source` first.
+#[derive(Serialize)]
+struct RecordJson<'a> {
+    source: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    level: Option<&'a str>,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    environment: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    hmr_update: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<&'a str>,
+}
+
+// The level a message names at its start, e.g. esbuild's `✘ [ERROR]` or Rollup's `(!)`.
+fn prefix_level(message: &str) -> Option<log::Level> {
+    static ERROR: OnceLock<Regex> = OnceLock::new();
+    static WARN: OnceLock<Regex> = OnceLock::new();
+    let error = ERROR.get_or_init(|| {
+        Regex::new(
+            r"(?i)^(?:✘\s*)?(?:\[error\]|error\b|internal server error\b|pre-transform error\b)",
+        )
+        .unwrap()
+    });
+    let warn = WARN.get_or_init(|| {
+        Regex::new(r"(?i)^(?:▲\s*)?(?:\[warning\]|warn(?:ing)?\b|\(!\))").unwrap()
+    });
+    if error.is_match(message) {
+        Some(log::Level::Error)
+    } else if warn.is_match(message) {
+        Some(log::Level::Warn)
+    } else {
+        None
+    }
+}
+
+// The level of the color Vite's logger gives the `[vite]` tag: red for errors, yellow for
+// warnings and cyan otherwise.
+fn tag_color_level(line: &str) -> Option<log::Level> {
+    static COLORED_TAG: OnceLock<Regex> = OnceLock::new();
+    let regex = COLORED_TAG
+        .get_or_init(|| Regex::new(r"(?P<codes>(?:\x1b\[[\d;]*m)+)\[vite\]").unwrap());
+    let codes = regex.captures(line)?.name("codes")?.as_str();
+    let codes = codes
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|code| !code.is_empty());
+    let mut level = None;
+    for code in codes {
+        match code {
+            "31" | "91" => return Some(log::Level::Error),
+            "33" | "93" => level = Some(log::Level::Warn),
+            _ => {}
+        }
+    }
+    level
+}
+
+// `/app/src/main.ts:3:10` as the file, line and column, each number only if it is one.
+fn split_location(location: &str) -> (&str, Option<u32>, Option<u32>) {
+    let mut parts = location.rsplitn(3, ':');
+    let last = parts.next().unwrap_or_default();
+    let middle = parts.next();
+    let rest = parts.next();
+    match (middle, rest) {
+        (Some(middle), Some(rest)) => match (middle.parse(), last.parse()) {
+            (Ok(line), Ok(column)) => return (rest, Some(line), Some(column)),
+            (_, Ok(line)) => {
+                let end = location.len() - last.len() - 1;
+                return (&location[..end], Some(line), None);
+            }
+            _ => {}
+        },
+        (Some(middle), None) => {
+            if let Ok(line) = last.parse() {
+                return (middle, Some(line), None);
+            }
+        }
+        _ => {}
+    }
+    (location, None, None)
+}
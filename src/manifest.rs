@@ -0,0 +1,649 @@
+//! Reading the `manifest.json` that `vite build` writes with `build.manifest` turned on.
+//!
+//! The manifest maps the source files of the entry points, like `src/main.ts`, to the
+//! hashed files they were built into, along with their CSS and the chunks they import. See
+//! <https://vite.dev/guide/backend-integration>.
+//!
+//! # Example
+//! ```rust
+//! use vite_actix::manifest::ViteManifest;
+//!
+//! // The dist directory of a build in the shape `vite build` 5 writes, see `examples/manifests`.
+//! let manifest = ViteManifest::load("examples/manifests/vite5".as_ref())?;
+//!
+//! let main = manifest.entry("src/main.tsx").unwrap();
+//! assert_eq!(main.file, "assets/main-CuXyZ8bL.js");
+//! assert!(main.is_entry);
+//!
+//! // The CSS of the entry and of every chunk it imports, directly or not, but not that of
+//! // the lazily loaded settings page.
+//! assert_eq!(
+//!     manifest.css_for("src/main.tsx"),
+//!     ["assets/main-DiwrgTda.css", "assets/shared-ChJ_j-JJ.css", "assets/vendor-BmQ1Xyca.css"]
+//! );
+//! let settings = manifest.entry("src/pages/Settings.tsx").unwrap();
+//! assert!(settings.is_dynamic_entry);
+//! assert_eq!(
+//!     manifest.css_for("src/pages/Settings.tsx"),
+//!     ["assets/Settings-C0pL8fVe.css", "assets/shared-ChJ_j-JJ.css", "assets/vendor-BmQ1Xyca.css"]
+//! );
+//!
+//! // Vite 6 names the entry of a single page app after its index.html.
+//! let manifest = ViteManifest::load("examples/manifests/vite6".as_ref())?;
+//! let index = manifest.entry("index.html").unwrap();
+//! assert_eq!(index.src.as_deref(), Some("index.html"));
+//! assert_eq!(manifest.css_for("index.html"), ["assets/index-D8b4DwFg.css"]);
+//! assert_eq!(manifest.entry("src/assets/react.svg").unwrap().names, ["react.svg"]);
+//!
+//! // Entries without a file are skipped instead of failing the whole manifest.
+//! let partial = ViteManifest::parse(r#"{ "a.ts": { "file": "a.js" }, "b.ts": {} }"#)?;
+//! assert_eq!(partial.chunks().len(), 1);
+//! assert!(ViteManifest::parse("[]").is_err());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use anyhow::Context;
+use log::{debug, error, warn};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime};
+
+/// Where Vite writes the manifest in the dist directory: `.vite/manifest.json` since Vite 5,
+/// `manifest.json` before.
+pub const MANIFEST_PATHS: [&str; 2] = [".vite/manifest.json", "manifest.json"];
+
+// The ids of the polyfill chunks of `@vitejs/plugin-legacy`.
+const LEGACY_POLYFILLS: &str = "vite/legacy-polyfills-legacy";
+const MODERN_POLYFILLS: &str = "vite/legacy-polyfills";
+
+// Manifests read by `ViteManifest::cached`, with the stamp of the file they were read from.
+type ManifestCache = Mutex<HashMap<PathBuf, (FileStamp, Arc<ViteManifest>)>>;
+static CACHE: OnceLock<ManifestCache> = OnceLock::new();
+
+// Bumped by `invalidate`, so that every file cached by its `FileStamp` is read again.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// How often a file caught while a deployment writes it is read, and how long to wait before
+// reading it again.
+const READ_ATTEMPTS: u32 = 3;
+const READ_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// A built chunk or asset of the manifest.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestChunk {
+    /// The built file, relative to the dist directory, e.g. `assets/main-BxQw2a1z.js`.
+    pub file: String,
+    /// The name of the chunk, e.g. `main`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// The original file names of an asset, written by Vite 6.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    /// The source file, e.g. `src/main.ts`, for chunks that have one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src: Option<String>,
+    /// Whether the chunk is an entry point.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_entry: bool,
+    /// Whether the chunk is loaded with `import()`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_dynamic_entry: bool,
+    /// Manifest keys of the chunks it imports statically.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imports: Vec<String>,
+    /// Manifest keys of the chunks it imports with `import()`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dynamic_imports: Vec<String>,
+    /// The CSS files of the chunk, relative to the dist directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub css: Vec<String>,
+    /// Other files the chunk references, like images and fonts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<String>,
+    /// The subresource integrity of `file`, e.g. `sha384-…`, written by plugins like
+    /// `vite-plugin-manifest-sri`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+}
+
+/// What [`ProxyViteOptions::build`] does when files the build refers to are missing from
+/// the dist directory, see [`ViteManifest::verify`].
+///
+/// A partial deployment can leave an `index.html` or a manifest behind whose scripts are
+/// not on disk, which only shows as blank pages. Outside of dev mode, `build()` checks the
+/// dist directory it serves and, by default, logs every missing file as an error.
+///
+/// # Example
+/// ```rust
+/// # #[cfg(feature = "prod-serve")]
+/// # {
+/// use vite_actix::manifest::AssetCheck;
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+///
+/// // Only the manifest of this fixture is there, not the files it lists.
+/// let options = ProxyViteOptions::new().dev_mode(false).dist_directory("examples/manifests/vite5");
+/// let error = options.asset_check(AssetCheck::Fail).build().unwrap_err().to_string();
+/// assert!(error.contains("assets/main-CuXyZ8bL.js (referenced by src/main.tsx)"), "{}", error);
+/// # }
+/// ```
+///
+/// [`ProxyViteOptions::build`]: crate::proxy_vite_options::ProxyViteOptions::build
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AssetCheck {
+    /// The dist directory is not checked.
+    Off,
+    /// Missing files are logged as an error, and the server starts anyway.
+    #[default]
+    Log,
+    /// Missing files fail `build()`, so the server does not start.
+    Fail,
+}
+
+/// A file the manifest or the `index.html` of a build refers to that is not in the build.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingAsset {
+    /// The missing file, relative to the dist directory, e.g. `assets/main-BxQw2a1z.js`.
+    pub file: String,
+    /// What refers to it: the manifest key of a chunk, e.g. `src/main.ts`, or `index.html`.
+    pub referenced_by: String,
+}
+
+impl Display for MissingAsset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (referenced by {})", self.file, self.referenced_by)
+    }
+}
+
+/// A parsed Vite manifest.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ViteManifest {
+    chunks: HashMap<String, ManifestChunk>,
+}
+
+impl ViteManifest {
+    /// Parses the contents of a manifest.
+    ///
+    /// Entries that are not a chunk, e.g. because they have no `file`, are skipped with a
+    /// warning, so one odd entry does not take the others with it.
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let value: Value = serde_json::from_str(json).context("The manifest is not valid JSON")?;
+        let Value::Object(entries) = value else {
+            return Err(anyhow::anyhow!("The manifest is not a JSON object"));
+        };
+        let mut chunks = HashMap::with_capacity(entries.len());
+        for (key, chunk) in entries {
+            match serde_json::from_value(chunk) {
+                Ok(chunk) => {
+                    chunks.insert(key, chunk);
+                }
+                Err(e) => warn!("Skipping the manifest entry {:?}: {}", key, e),
+            }
+        }
+        Ok(Self { chunks })
+    }
+
+    /// Reads a manifest, from the file at `path` or, for a dist directory, from one of the
+    /// [`MANIFEST_PATHS`] in it.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let path = Self::resolve(path)?;
+        let json = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Like [`ViteManifest::load`], but the manifest is only read again once the file
+    /// changes, e.g. after a rebuild or a deployment that replaces the dist directory, or
+    /// after [`invalidate`].
+    ///
+    /// A manifest that fails to parse is read again a few times, as a deployment may be
+    /// halfway through writing it. If it still does not parse, the manifest read before is
+    /// kept until the file changes again, so pages keep working with the previous build.
+    pub fn cached(path: &Path) -> anyhow::Result<Arc<Self>> {
+        let path = Self::resolve(path)?;
+        let stamp =
+            FileStamp::of(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+        let previous = cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&path)
+            .cloned();
+        if let Some((cached_at, manifest)) = &previous
+            && *cached_at == stamp
+        {
+            return Ok(manifest.clone());
+        }
+        let (stamp, manifest) = match Self::reload(&path) {
+            Ok((stamp, manifest)) => (stamp, Arc::new(manifest)),
+            Err(e) => {
+                let Some((_, manifest)) = previous else {
+                    return Err(e);
+                };
+                warn!("Keeping the manifest read before: {:#}", e);
+                (FileStamp::of(&path).unwrap_or(stamp), manifest)
+            }
+        };
+        cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path, (stamp, manifest.clone()));
+        Ok(manifest)
+    }
+
+    // Read the manifest at `path` for `cached`, again while it does not parse.
+    fn reload(path: &Path) -> anyhow::Result<(FileStamp, Self)> {
+        let mut attempt = 1;
+        loop {
+            let (stamp, json) =
+                read_stable(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            match Self::parse(&json) {
+                Ok(manifest) => return Ok((stamp, manifest)),
+                Err(e) if attempt < READ_ATTEMPTS => {
+                    debug!("Reading {} again: {:#}", path.display(), e);
+                    std::thread::sleep(READ_RETRY_DELAY);
+                    attempt += 1;
+                }
+                Err(e) => return Err(e.context(format!("Failed to parse {}", path.display()))),
+            }
+        }
+    }
+
+    /// Checks that every file the build in `dist_dir` refers to is there: the file, CSS and
+    /// assets of every chunk of its manifest, and the local scripts and stylesheets its
+    /// `index.html` loads. Useful in a smoke test after a deployment.
+    ///
+    /// A build without a manifest or an `index.html` is only checked for the other. The
+    /// missing files are sorted, and each is listed once.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::manifest::{MissingAsset, ViteManifest};
+    ///
+    /// assert_eq!(ViteManifest::verify("examples/embedded_dist".as_ref()), Ok(()));
+    ///
+    /// // A deployment that only copied part of the build.
+    /// let dist = std::env::temp_dir().join(format!("vite-actix-verify-{}", std::process::id()));
+    /// std::fs::create_dir_all(dist.join(".vite")).unwrap();
+    /// std::fs::create_dir_all(dist.join("assets")).unwrap();
+    /// for file in [".vite/manifest.json", "assets/index-Cq3Zx9Lm.css"] {
+    ///     std::fs::copy(format!("examples/embedded_dist/{}", file), dist.join(file)).unwrap();
+    /// }
+    /// let index = r#"<link rel="icon" href="/favicon.svg?v=2">
+    ///     <link rel="stylesheet" href="https://fonts.example.com/inter.css">
+    ///     <script type="module" src="/assets/index-BxQw2a1z.js"></script>"#;
+    /// std::fs::write(dist.join("index.html"), index).unwrap();
+    /// let missing = |file: &str, referenced_by: &str| MissingAsset {
+    ///     file: file.to_string(),
+    ///     referenced_by: referenced_by.to_string(),
+    /// };
+    /// assert_eq!(
+    ///     ViteManifest::verify(&dist),
+    ///     Err(vec![
+    ///         missing("assets/index-BxQw2a1z.js", "src/main.ts"),
+    ///         missing("assets/vendor-D4e5F6g7.js", "_vendor-D4e5F6g7.js"),
+    ///         missing("favicon.svg", "index.html"),
+    ///     ])
+    /// );
+    /// std::fs::remove_dir_all(&dist).unwrap();
+    /// ```
+    pub fn verify(dist_dir: &Path) -> Result<(), Vec<MissingAsset>> {
+        verify_build(dist_dir, "/")
+    }
+
+    /// The path of the manifest in `dist_dir`, if there is one.
+    pub fn find(dist_dir: &Path) -> Option<PathBuf> {
+        MANIFEST_PATHS
+            .iter()
+            .map(|path| dist_dir.join(path))
+            .find(|path| path.is_file())
+    }
+
+    // The manifest file for a path given to `load`.
+    fn resolve(path: &Path) -> anyhow::Result<PathBuf> {
+        if !path.is_dir() {
+            return Ok(path.to_path_buf());
+        }
+        Self::find(path).with_context(|| {
+            format!(
+                "No manifest.json in {}, set build.manifest to true in vite.config",
+                path.display()
+            )
+        })
+    }
+
+    /// The chunk under the manifest key `key`; a leading `/` is ignored.
+    pub fn chunk(&self, key: &str) -> Option<&ManifestChunk> {
+        self.chunks.get(key.trim_start_matches('/'))
+    }
+
+    /// The chunk built from the source file `src`, e.g. `src/main.tsx`; a leading `/` is
+    /// ignored.
+    pub fn entry(&self, src: &str) -> Option<&ManifestChunk> {
+        let src = src.trim_start_matches('/');
+        self.chunk(src).or_else(|| {
+            self.chunks
+                .values()
+                .find(|chunk| chunk.src.as_deref() == Some(src))
+        })
+    }
+
+    /// Every chunk, by its manifest key.
+    pub fn chunks(&self) -> &HashMap<String, ManifestChunk> {
+        &self.chunks
+    }
+
+    /// The chunk `@vitejs/plugin-legacy` built for browsers without ES modules from the entry
+    /// `src`, which it names with `-legacy` before the extension, e.g. `src/main-legacy.ts`
+    /// for `src/main.ts`.
+    pub fn legacy_entry(&self, src: &str) -> Option<&ManifestChunk> {
+        let src = src.trim_start_matches('/');
+        let name = src.rfind('/').map_or(0, |slash| slash + 1);
+        let legacy = match src[name..].rfind('.') {
+            Some(dot) if dot > 0 => {
+                let (stem, extension) = src.split_at(name + dot);
+                format!("{}-legacy{}", stem, extension)
+            }
+            _ => format!("{}-legacy", src),
+        };
+        self.entry(&legacy)
+    }
+
+    /// The polyfills `@vitejs/plugin-legacy` built for browsers without ES modules, if the
+    /// build used it.
+    pub fn legacy_polyfills(&self) -> Option<&ManifestChunk> {
+        self.polyfills(LEGACY_POLYFILLS)
+    }
+
+    /// The polyfills `@vitejs/plugin-legacy` built for modern browsers, if the build used it
+    /// with `modernPolyfills`.
+    pub fn modern_polyfills(&self) -> Option<&ManifestChunk> {
+        self.polyfills(MODERN_POLYFILLS)
+    }
+
+    // The polyfills chunk with the id `id`, which the manifest keys relative to the project,
+    // like `../../vite/legacy-polyfills`.
+    fn polyfills(&self, id: &str) -> Option<&ManifestChunk> {
+        self.chunks
+            .iter()
+            .find(|(key, _)| *key == id || key.ends_with(&format!("/{}", id)))
+            .map(|(_, chunk)| chunk)
+    }
+
+    /// Every chunk the entry `src` imports statically, directly or through others, each
+    /// once and in the order they are first imported. Empty if there is no such entry.
+    pub fn imports_for(&self, src: &str) -> Vec<&ManifestChunk> {
+        let mut imports = Vec::new();
+        if let Some(chunk) = self.entry(src) {
+            self.collect_imports(chunk, &mut imports);
+        }
+        imports
+    }
+
+    /// The chunks the entry `src` or the chunks it imports statically load with `import()`,
+    /// followed by the chunks those import statically, each once and without the entry's
+    /// own static imports. Chunks that are only reached through another `import()` are left
+    /// out. Empty if there is no such entry.
+    pub fn dynamic_imports_for(&self, src: &str) -> Vec<&ManifestChunk> {
+        let Some(chunk) = self.entry(src) else {
+            return Vec::new();
+        };
+        let static_imports = self.imports_for(src);
+        let mut dynamic: Vec<&ManifestChunk> = Vec::new();
+        for key in std::iter::once(chunk)
+            .chain(static_imports.iter().copied())
+            .flat_map(|chunk| &chunk.dynamic_imports)
+        {
+            let Some(import) = self.chunks.get(key) else {
+                continue;
+            };
+            let mut found = vec![import];
+            self.collect_imports(import, &mut found);
+            for import in found {
+                let known = std::ptr::eq(import, chunk)
+                    || static_imports.iter().chain(&dynamic).any(|seen| std::ptr::eq(*seen, import));
+                if !known {
+                    dynamic.push(import);
+                }
+            }
+        }
+        dynamic
+    }
+
+    /// The CSS files a page loading the entry `src` needs: those of the entry, followed by
+    /// those of every chunk it imports statically, directly or not, each once. Chunks it
+    /// imports with `import()` load their own CSS. Empty if there is no such entry.
+    pub fn css_for(&self, src: &str) -> Vec<&str> {
+        let Some(chunk) = self.entry(src) else {
+            return Vec::new();
+        };
+        let mut css: Vec<&str> = Vec::new();
+        for file in std::iter::once(chunk)
+            .chain(self.imports_for(src))
+            .flat_map(|chunk| &chunk.css)
+        {
+            if !css.contains(&file.as_str()) {
+                css.push(file);
+            }
+        }
+        css
+    }
+
+    // Every chunk `chunk` imports statically, directly or through others, each once.
+    fn collect_imports<'a>(&'a self, chunk: &'a ManifestChunk, found: &mut Vec<&'a ManifestChunk>) {
+        for key in &chunk.imports {
+            let Some(import) = self.chunks.get(key) else {
+                continue;
+            };
+            if !found.iter().any(|seen| std::ptr::eq(*seen, import)) {
+                found.push(import);
+                self.collect_imports(import, found);
+            }
+        }
+    }
+}
+
+// `ViteManifest::verify` for a build whose `index.html` loads its files under `base`, e.g.
+// `/static/`.
+pub(crate) fn verify_build(dist_dir: &Path, base: &str) -> Result<(), Vec<MissingAsset>> {
+    let manifest = match ViteManifest::find(dist_dir).map(|path| ViteManifest::load(&path)) {
+        Some(Ok(manifest)) => Some(manifest),
+        Some(Err(e)) => {
+            error!("Not checking the files of the manifest: {:#}", e);
+            None
+        }
+        None => None,
+    };
+    let index_html = std::fs::read_to_string(dist_dir.join("index.html")).ok();
+    let missing = missing_assets(manifest.as_ref(), index_html.as_deref(), base, |file| {
+        dist_dir.join(file).is_file()
+    });
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(missing),
+    }
+}
+
+// `missing` for a log message, one file per line.
+pub(crate) fn list(missing: &[MissingAsset]) -> String {
+    missing.iter().map(|file| format!("\n  {}", file)).collect()
+}
+
+// The files `manifest` and `index_html` refer to for which `exists` is false.
+pub(crate) fn missing_assets(
+    manifest: Option<&ViteManifest>,
+    index_html: Option<&str>,
+    base: &str,
+    exists: impl Fn(&str) -> bool,
+) -> Vec<MissingAsset> {
+    let mut referenced: BTreeMap<String, String> = BTreeMap::new();
+    if let Some(manifest) = manifest {
+        let mut chunks: Vec<_> = manifest.chunks().iter().collect();
+        chunks.sort_by_key(|(key, _)| key.as_str());
+        for (key, chunk) in chunks {
+            for file in std::iter::once(&chunk.file)
+                .chain(&chunk.css)
+                .chain(&chunk.assets)
+            {
+                referenced
+                    .entry(file.clone())
+                    .or_insert_with(|| key.clone());
+            }
+        }
+    }
+    if let Some(html) = index_html {
+        for file in loaded_files(html, base) {
+            referenced
+                .entry(file)
+                .or_insert_with(|| "index.html".to_string());
+        }
+    }
+    referenced
+        .into_iter()
+        .filter(|(file, _)| !exists(file))
+        .map(|(file, referenced_by)| MissingAsset {
+            file,
+            referenced_by,
+        })
+        .collect()
+}
+
+// The files under `base` that the script and link tags of `html` load, relative to the
+// dist directory.
+fn loaded_files(html: &str, base: &str) -> Vec<String> {
+    loaded_urls(html)
+        .filter_map(|path| {
+            let file = path.strip_prefix(base)?;
+            (!path.starts_with("//") && !file.is_empty()).then(|| file.to_string())
+        })
+        .collect()
+}
+
+// The URLs the script and link tags of `html` load, without their query or fragment.
+fn loaded_urls(html: &str) -> impl Iterator<Item = &str> {
+    static TAG_URL: OnceLock<Regex> = OnceLock::new();
+    let regex = TAG_URL.get_or_init(|| {
+        Regex::new(r#"(?i)<(?:script|link)\b[^>]*?\s(?:src|href)\s*=\s*["']([^"']+)["']"#).unwrap()
+    });
+    regex
+        .captures_iter(html)
+        .filter_map(|caps| caps.get(1)?.as_str().split(['?', '#']).next())
+}
+
+// The `base` the build in `dist_dir` was made with, e.g. `/app/`, as its `index.html`
+// loads a file of its manifest under it, or `None` if that can't be told.
+pub(crate) fn built_base(dist_dir: &Path) -> Option<String> {
+    let manifest = ViteManifest::load(&ViteManifest::find(dist_dir)?).ok()?;
+    let html = std::fs::read_to_string(dist_dir.join("index.html")).ok()?;
+    loaded_urls(&html).find_map(|url| {
+        manifest
+            .chunks()
+            .values()
+            .flat_map(|chunk| std::iter::once(&chunk.file).chain(&chunk.css))
+            .find_map(|file| url.strip_suffix(file.as_str()))
+            .filter(|base| base.ends_with('/'))
+            .map(str::to_string)
+    })
+}
+
+/// Forgets every manifest, and the subresource integrity values and HTML pages read from a
+/// dist directory, so they are read again the next time they are needed.
+///
+/// Files are already read again when their modification time or size changes, or when
+/// they are replaced by another file. This is for deployment hooks that want to be sure,
+/// e.g. after copying a build that kept the modification times of the previous one.
+///
+/// # Example
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+/// use vite_actix::assets::tags_for_with;
+/// use vite_actix::manifest::invalidate;
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+///
+/// let dist = std::env::temp_dir().join(format!("vite-actix-invalidate-{}", std::process::id()));
+/// let manifest = dist.join(".vite/manifest.json");
+/// std::fs::create_dir_all(manifest.parent().unwrap()).unwrap();
+/// let deploy = |hash: &str| {
+///     let json = format!(r#"{{"src/main.ts": {{"file": "assets/main-{}.js", "isEntry": true}}}}"#, hash);
+///     std::fs::write(&manifest, json).unwrap();
+///     let file = std::fs::File::options().write(true).open(&manifest).unwrap();
+///     file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)).unwrap();
+/// };
+/// let options = ProxyViteOptions::new().dev_mode(false).dist_directory(dist.to_str().unwrap());
+/// let script = || tags_for_with(&options, &["src/main.ts"]).scripts().to_vec();
+///
+/// deploy("AAAAAAAA");
+/// assert_eq!(script(), ["/assets/main-AAAAAAAA.js"]);
+///
+/// // A new manifest is picked up once it is written.
+/// std::fs::write(&manifest, r#"{"src/main.ts": {"file": "assets/main-CCCC.js", "isEntry": true}}"#)
+///     .unwrap();
+/// assert_eq!(script(), ["/assets/main-CCCC.js"]);
+///
+/// // One caught halfway through being written is not, the previous one is kept.
+/// std::fs::write(&manifest, r#"{"src/main.ts": {"file": "assets/ma"#).unwrap();
+/// assert_eq!(script(), ["/assets/main-CCCC.js"]);
+///
+/// // A copy with the same size and modification time as the one read before is only
+/// // picked up after `invalidate`.
+/// deploy("AAAAAAAA");
+/// assert_eq!(script(), ["/assets/main-AAAAAAAA.js"]);
+/// deploy("BBBBBBBB");
+/// assert_eq!(script(), ["/assets/main-AAAAAAAA.js"]);
+/// invalidate();
+/// assert_eq!(script(), ["/assets/main-BBBBBBBB.js"]);
+/// std::fs::remove_dir_all(&dist).unwrap();
+/// ```
+pub fn invalidate() {
+    GENERATION.fetch_add(1, Ordering::Relaxed);
+    if let Some(cache) = CACHE.get() {
+        cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+// What a cached file was read at: its modification time, size and, on Unix, inode, one of
+// which changes when a deployment writes or replaces the file, and the generation of
+// `invalidate`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct FileStamp {
+    modified: SystemTime,
+    len: u64,
+    #[cfg(unix)]
+    inode: u64,
+    generation: u64,
+}
+
+impl FileStamp {
+    pub(crate) fn of(path: &Path) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            modified: metadata.modified()?,
+            len: metadata.len(),
+            #[cfg(unix)]
+            inode: std::os::unix::fs::MetadataExt::ino(&metadata),
+            generation: GENERATION.load(Ordering::Relaxed),
+        })
+    }
+}
+
+// Read the file at `path` along with its stamp, again if it changes while being read.
+pub(crate) fn read_stable(path: &Path) -> std::io::Result<(FileStamp, String)> {
+    let mut attempt = 1;
+    loop {
+        let stamp = FileStamp::of(path)?;
+        let contents = std::fs::read_to_string(path)?;
+        if FileStamp::of(path)? == stamp || attempt == READ_ATTEMPTS {
+            return Ok((stamp, contents));
+        }
+        std::thread::sleep(READ_RETRY_DELAY);
+        attempt += 1;
+    }
+}
@@ -0,0 +1,138 @@
+//! Vite's asset tags as [Maud](https://maud.lambda.xyz) markup, enabled with the `maud`
+//! feature.
+//!
+//! The functions here render what [`crate::assets`] and [`crate::html`] do for the same
+//! options, as [`Markup`] that goes straight into `html!`. The tags are built with Maud
+//! itself, so URLs, `integrity` values and the nonce of [`AssetTags::with_nonce`] are
+//! escaped like any other attribute.
+//!
+//! # Example
+//! ```rust
+//! use maud::{DOCTYPE, html};
+//! use vite_actix::maud::{asset_url_with, vite_head_with};
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! let page = |options: &ProxyViteOptions| {
+//!     html! {
+//!         (DOCTYPE)
+//!         html {
+//!             head { (vite_head_with(options, "src/main.tsx")) }
+//!             body { img src=(asset_url_with(options, "src/assets/react.svg")); }
+//!         }
+//!     }
+//!     .into_string()
+//! };
+//!
+//! // In dev mode, everything comes from the dev server.
+//! assert_eq!(
+//!     page(&ProxyViteOptions::new().dev_mode(true)),
+//!     "<!DOCTYPE html><html><head>\
+//!      <script type=\"module\" src=\"/@vite/client\"></script>\
+//!      <script type=\"module\" src=\"/src/main.tsx\"></script>\
+//!      </head><body><img src=\"/src/assets/react.svg\"></body></html>"
+//! );
+//!
+//! // Otherwise from the manifest of the build.
+//! let prod = ProxyViteOptions::new()
+//!     .dev_mode(false)
+//!     .dist_directory("examples/manifests/vite5");
+//! assert_eq!(
+//!     page(&prod),
+//!     "<!DOCTYPE html><html><head>\
+//!      <link rel=\"stylesheet\" href=\"/assets/main-DiwrgTda.css\">\
+//!      <link rel=\"stylesheet\" href=\"/assets/shared-ChJ_j-JJ.css\">\
+//!      <link rel=\"stylesheet\" href=\"/assets/vendor-BmQ1Xyca.css\">\
+//!      <script type=\"module\" src=\"/assets/main-CuXyZ8bL.js\"></script>\
+//!      <link rel=\"modulepreload\" href=\"/assets/shared-B7PI925R.js\">\
+//!      <link rel=\"modulepreload\" href=\"/assets/vendor-BTzA1Yrx.js\">\
+//!      </head><body><img src=\"/assets/react-CHdo91hT.svg\"></body></html>"
+//! );
+//!
+//! // With subresource integrity, and a base that has to be escaped.
+//! let sri = ProxyViteOptions::new()
+//!     .dev_mode(false)
+//!     .dist_directory("examples/embedded_dist")
+//!     .asset_base("/a&b/")
+//!     .subresource_integrity(true);
+//! assert_eq!(
+//!     vite_head_with(&sri, "src/main.ts").into_string(),
+//!     "<link rel=\"stylesheet\" href=\"/a&amp;b/assets/index-Cq3Zx9Lm.css\" \
+//!      integrity=\"sha384-Un1VVPWRhFwdptqCbp6C11q09OsaPAuBIihjcY2/Ac6wrpxem4XVrPRAqT2Q2t3y\" \
+//!      crossorigin=\"anonymous\">\
+//!      <script type=\"module\" src=\"/a&amp;b/assets/index-BxQw2a1z.js\" \
+//!      integrity=\"sha384-m5sq0YjLvn/6cLCPKTD1hD9jeLUTJBRQDrmMKnnXu8Nn6qx7fpx9kZp/ON5rLbi9\" \
+//!      crossorigin=\"anonymous\"></script>\
+//!      <link rel=\"modulepreload\" href=\"/a&amp;b/assets/vendor-D4e5F6g7.js\" \
+//!      integrity=\"sha384-RIgLKHVgit/h+BUuaoo7Hj0vC8XGFRvEZJi8CL35qQwkXnEBr2LwvGv9WPF4oOYR\" \
+//!      crossorigin=\"anonymous\">"
+//! );
+//!
+//! // With a CSP nonce on every tag.
+//! let tags = vite_actix::assets::tags_for_with(&prod, &["src/main.tsx"]).with_nonce("a\"b");
+//! let markup = vite_actix::maud::asset_tags(&tags).into_string();
+//! assert_eq!(markup.matches(" nonce=\"a&quot;b\">").count(), 6);
+//! ```
+
+use crate::assets::{AssetTags, tags_for_with};
+use crate::html::{react_refresh_preamble_with, react_refresh_preamble_with_nonce};
+use crate::proxy_vite_options::ProxyViteOptions;
+use maud::{Markup, PreEscaped, html};
+
+/// The tags that load `entry`, e.g. `src/main.tsx`, into the `head` of a page, for the
+/// global options, see [`crate::html::vite_head_tags`].
+pub fn vite_head(entry: &str) -> Markup {
+    vite_head_with(&ProxyViteOptions::global(), entry)
+}
+
+/// Like [`vite_head`], but for `options` instead of the global options.
+pub fn vite_head_with(options: &ProxyViteOptions, entry: &str) -> Markup {
+    asset_tags(&tags_for_with(options, &[entry]))
+}
+
+/// `tags` as markup: stylesheets, then scripts, then module preloads, then the tags of
+/// `@vitejs/plugin-legacy` after [`AssetTags::with_legacy`]. For several entries, or the
+/// other options of [`AssetTags`].
+pub fn asset_tags(tags: &AssetTags) -> Markup {
+    let crossorigin = |url: &str| tags.integrity(url).map(|_| "anonymous");
+    html! {
+        @for url in tags.stylesheets() {
+            link rel="stylesheet" href=(url) integrity=[tags.integrity(url)] crossorigin=[crossorigin(url)] nonce=[tags.nonce()];
+        }
+        @for url in tags.scripts() {
+            script type="module" src=(url) integrity=[tags.integrity(url)] crossorigin=[crossorigin(url)] nonce=[tags.nonce()] {}
+        }
+        @for url in tags.preloads() {
+            link rel="modulepreload" href=(url) integrity=[tags.integrity(url)] crossorigin=[crossorigin(url)] nonce=[tags.nonce()];
+        }
+        // Inline scripts, with their URLs escaped already.
+        (PreEscaped(tags.legacy_tags()))
+    }
+}
+
+/// The preamble `@vitejs/plugin-react` needs, for the global options, see
+/// [`crate::html::react_refresh_preamble`]. Empty outside of dev mode.
+pub fn react_refresh() -> Markup {
+    react_refresh_with(&ProxyViteOptions::global())
+}
+
+/// Like [`react_refresh`], but for `options` instead of the global options.
+pub fn react_refresh_with(options: &ProxyViteOptions) -> Markup {
+    PreEscaped(react_refresh_preamble_with(options))
+}
+
+/// Like [`react_refresh_with`], with `nonce` on the script, see
+/// [`crate::html::react_refresh_preamble_with_nonce`].
+pub fn react_refresh_with_nonce(options: &ProxyViteOptions, nonce: Option<&str>) -> Markup {
+    PreEscaped(react_refresh_preamble_with_nonce(options, nonce))
+}
+
+/// The URL of a file of the app, e.g. for the `src` of an image, for the global options, see
+/// [`crate::assets::asset_url`].
+pub fn asset_url(path: &str) -> Markup {
+    asset_url_with(&ProxyViteOptions::global(), path)
+}
+
+/// Like [`asset_url`], but for `options` instead of the global options.
+pub fn asset_url_with(options: &ProxyViteOptions, path: &str) -> Markup {
+    html! { (crate::assets::asset_url_with(options, path)) }
+}
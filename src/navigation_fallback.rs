@@ -0,0 +1,168 @@
+//! The rules of [`ProxyViteOptions::navigation_fallback_only`], which mirror those of
+//! `connect-history-api-fallback` for the dev proxy: only page navigations, and Vite's own
+//! module and asset paths, are forwarded to Vite; every other unmatched request is a 404.
+//!
+//! Which paths name files, see [`path_extension`] and [`is_asset_path`], is decided the same
+//! way for [`ProxyViteOptions::proxy_extensions`] and the SPA fallback of the build.
+//!
+//! [`ProxyViteOptions::navigation_fallback_only`]: crate::proxy_vite_options::ProxyViteOptions::navigation_fallback_only
+//! [`ProxyViteOptions::proxy_extensions`]: crate::proxy_vite_options::ProxyViteOptions::proxy_extensions
+
+use crate::proxy_vite_options::has_path_prefix;
+use actix_web::http::Method;
+
+/// The path prefixes forwarded whatever the request accepts: Vite's own modules, the sources
+/// and dependencies it transforms, and the `assets` directory of the project.
+pub const DEFAULT_VITE_PREFIXES: [&str; 7] = [
+    "/@vite",
+    "/@id",
+    "/@fs",
+    "/@react-refresh",
+    "/src",
+    "/node_modules",
+    "/assets",
+];
+
+/// Whether an unmatched request for `path`, as Vite sees it, is forwarded: either it is under
+/// one of `vite_prefixes`, or it is a page navigation, see [`is_navigation`].
+///
+/// # Example
+/// ```rust
+/// use actix_web::http::Method;
+/// use vite_actix::navigation_fallback::{DEFAULT_VITE_PREFIXES, is_proxied};
+///
+/// let prefixes: Vec<String> = DEFAULT_VITE_PREFIXES.map(String::from).to_vec();
+/// let page = Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8");
+///
+/// assert!(is_proxied(&Method::GET, page, "/users/42", &prefixes));
+/// assert!(is_proxied(&Method::GET, Some("*/*"), "/src/main.ts", &prefixes));
+/// assert!(is_proxied(&Method::GET, None, "/@vite/client", &prefixes));
+/// // A typo in a backend route, fetched by a script.
+/// assert!(!is_proxied(&Method::GET, Some("application/json"), "/api/user", &prefixes));
+/// assert!(!is_proxied(&Method::POST, page, "/users/42", &prefixes));
+/// // `/srcmap` is not under `/src`.
+/// assert!(!is_proxied(&Method::GET, Some("*/*"), "/srcmap", &prefixes));
+/// ```
+pub fn is_proxied(
+    method: &Method,
+    accept: Option<&str>,
+    path: &str,
+    vite_prefixes: &[String],
+) -> bool {
+    let under_prefix = vite_prefixes.iter().any(|prefix| {
+        path == prefix
+            || path
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+    });
+    under_prefix || is_navigation(method, accept, path)
+}
+
+/// Whether a request is a top-level page navigation, by the rules of
+/// `connect-history-api-fallback`: a `GET` or `HEAD` whose `Accept` header prefers
+/// `text/html`, see [`prefers_html`], for a path whose last segment has no dot, as a file
+/// like `/logo.png` would.
+///
+/// # Example
+/// ```rust
+/// use actix_web::http::Method;
+/// use vite_actix::navigation_fallback::is_navigation;
+///
+/// assert!(is_navigation(&Method::GET, Some("text/html"), "/settings"));
+/// assert!(is_navigation(&Method::HEAD, Some("text/html"), "/"));
+/// assert!(!is_navigation(&Method::GET, Some("text/html"), "/logo.png"));
+/// assert!(!is_navigation(&Method::GET, None, "/settings"));
+/// assert!(!is_navigation(&Method::DELETE, Some("text/html"), "/settings"));
+/// ```
+pub fn is_navigation(method: &Method, accept: Option<&str>, path: &str) -> bool {
+    (method == Method::GET || method == Method::HEAD)
+        && accept.is_some_and(prefers_html)
+        && path_extension(path).is_none()
+}
+
+/// The extension of the file a request for `path` names, the part of its last segment after
+/// the last dot, or `None` for a path like a page route, e.g. `/settings` or `/docs/`.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::navigation_fallback::path_extension;
+///
+/// assert_eq!(path_extension("/assets/index-BxQw2a1z.js"), Some("js"));
+/// assert_eq!(path_extension("/archive.tar.gz"), Some("gz"));
+/// assert_eq!(path_extension("/.env"), Some("env"));
+/// assert_eq!(path_extension("/settings"), None);
+/// assert_eq!(path_extension("/v1.2/docs/"), None);
+/// assert_eq!(path_extension("/"), None);
+/// ```
+pub fn path_extension(path: &str) -> Option<&str> {
+    let name = path.rsplit('/').next().unwrap_or_default();
+    name.rsplit_once('.').map(|(_, extension)| extension)
+}
+
+/// Whether a request for `path` is for a file rather than a page: it has an extension, see
+/// [`path_extension`], or it is under `assets_prefix`, where Vite puts the built files, e.g.
+/// `/assets`. The SPA fallback of the build answers these with a 404 instead of
+/// `index.html`, which a browser would try to run as the missing script.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::navigation_fallback::is_asset_path;
+///
+/// assert!(is_asset_path("/assets/index-0ld0ne5x.js", "/assets"));
+/// assert!(is_asset_path("/assets/chunks/vendor", "/assets"));
+/// assert!(is_asset_path("/favicon.ico", "/assets"));
+/// assert!(is_asset_path("/static/assets/logo", "/static/assets"));
+/// assert!(!is_asset_path("/settings/profile", "/assets"));
+/// assert!(!is_asset_path("/assets-overview", "/assets"));
+/// assert!(!is_asset_path("/", "/assets"));
+/// ```
+///
+/// Serving the build, a stale page asking for a script of an older deployment gets a 404,
+/// and JSON if it asks for that.
+pub fn is_asset_path(path: &str, assets_prefix: &str) -> bool {
+    path_extension(path).is_some() || has_path_prefix(path, assets_prefix)
+}
+
+/// Whether an `Accept` header prefers `text/html`: it names `text/html` with a quality above
+/// zero, and no other type with a higher one. Wildcards like `*/*` do not count either way,
+/// so the default `*/*` of `fetch` is not a navigation.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::navigation_fallback::prefers_html;
+///
+/// // What browsers send when following a link.
+/// assert!(prefers_html("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"));
+/// assert!(prefers_html("application/json;q=0.5, text/html"));
+/// assert!(!prefers_html("application/json, text/html;q=0.9"));
+/// assert!(!prefers_html("text/html;q=0"));
+/// assert!(!prefers_html("*/*"));
+/// ```
+pub fn prefers_html(accept: &str) -> bool {
+    prefers(accept, "text/html")
+}
+
+// Whether an `Accept` header prefers `preferred`, a media type without wildcards, by the
+// rules of `prefers_html`.
+pub(crate) fn prefers(accept: &str, preferred: &str) -> bool {
+    let mut wanted: Option<f32> = None;
+    let mut other: f32 = 0.0;
+    for range in accept.split(',') {
+        let mut params = range.split(';');
+        let media_type = params.next().unwrap_or_default().trim();
+        let quality = params
+            .find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                name.trim()
+                    .eq_ignore_ascii_case("q")
+                    .then(|| value.trim().parse::<f32>().unwrap_or(0.0))
+            })
+            .unwrap_or(1.0);
+        if media_type.eq_ignore_ascii_case(preferred) {
+            wanted = Some(wanted.map_or(quality, |wanted| wanted.max(quality)));
+        } else if !media_type.contains('*') {
+            other = other.max(quality);
+        }
+    }
+    wanted.is_some_and(|wanted| wanted > 0.0 && wanted >= other)
+}
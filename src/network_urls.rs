@@ -0,0 +1,52 @@
+use regex::Regex;
+use std::sync::OnceLock;
+use url::Url;
+
+/// Parses a `Network: http://192.168.1.5:5173/` line of Vite's banner, printed once per
+/// interface when Vite is started with `--host`.
+///
+/// Returns `None` for other lines, including `Network: use --host to expose`. ANSI color
+/// codes must be stripped before calling this.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::network_urls::detect_network_url;
+///
+/// let url = detect_network_url("  ➜  Network: http://192.168.1.5:5173/").unwrap();
+/// assert_eq!(url.as_str(), "http://192.168.1.5:5173/");
+/// let url = detect_network_url("  ➜  Network: https://[fd00::5]:5173/app/").unwrap();
+/// assert_eq!((url.host_str(), url.port(), url.path()), (Some("[fd00::5]"), Some(5173), "/app/"));
+/// assert_eq!(detect_network_url("  ➜  Network: use --host to expose"), None);
+/// assert_eq!(detect_network_url("  ➜  Local:   http://localhost:5173/"), None);
+/// ```
+pub fn detect_network_url(line: &str) -> Option<Url> {
+    static NETWORK_URL: OnceLock<Regex> = OnceLock::new();
+    let regex = NETWORK_URL
+        .get_or_init(|| Regex::new(r"\bNetwork:\s+(?P<url>https?://\S+)").unwrap());
+    Url::parse(regex.captures(line)?.name("url")?.as_str()).ok()
+}
+
+/// `urls` with their port replaced by `port`, skipping URLs whose port cannot be changed.
+///
+/// # Example
+/// ```rust
+/// use url::Url;
+/// use vite_actix::network_urls::with_port;
+///
+/// let urls = [
+///     Url::parse("http://192.168.1.5:5173/").unwrap(),
+///     Url::parse("https://[fd00::5]:5173/app/").unwrap(),
+///     Url::parse("file:///tmp/index.html").unwrap(),
+/// ];
+/// let urls: Vec<String> = with_port(&urls, 8080).iter().map(Url::to_string).collect();
+/// assert_eq!(urls, ["http://192.168.1.5:8080/", "https://[fd00::5]:8080/app/"]);
+/// ```
+pub fn with_port(urls: &[Url], port: u16) -> Vec<Url> {
+    urls.iter()
+        .filter_map(|url| {
+            let mut url = url.clone();
+            url.set_port(Some(port)).ok()?;
+            Some(url)
+        })
+        .collect()
+}
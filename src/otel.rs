@@ -0,0 +1,88 @@
+//! OpenTelemetry spans for forwarded requests, with the `otel` feature.
+//!
+//! Each request the proxy forwards to Vite gets a `client` span named after its method, with
+//! the attributes of the HTTP semantic conventions: `http.request.method`, `server.address`,
+//! `server.port`, `url.full` and, once Vite answers, `http.response.status_code`. A `4xx` or
+//! `5xx` answer or a failure to reach Vite marks the span as an error, with an `error.type`.
+//!
+//! The spans are [`tracing`] spans, exported by the [`tracing-opentelemetry`] layer of the
+//! application, under the span of the request being handled, e.g. that of
+//! [`tracing-actix-web`]. The trace context is sent along to Vite in `traceparent` and
+//! `tracestate` headers with the global text map propagator, which is a no-op until the
+//! application sets one, e.g. `TraceContextPropagator` of `opentelemetry_sdk`.
+//!
+//! [`tracing`]: https://docs.rs/tracing
+//! [`tracing-opentelemetry`]: https://docs.rs/tracing-opentelemetry
+//! [`tracing-actix-web`]: https://docs.rs/tracing-actix-web
+
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use actix_web::http::{Method, StatusCode};
+use awc::error::SendRequestError;
+use opentelemetry::global;
+use opentelemetry::propagation::Injector;
+use tracing::Span;
+use tracing::field::Empty;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+// The client span of a request forwarded to `port` on localhost at `url`, under the current
+// span.
+pub(crate) fn client_span(method: &Method, url: &str, port: u16) -> Span {
+    tracing::info_span!(
+        "vite proxy",
+        otel.name = %method,
+        otel.kind = "client",
+        otel.status_code = Empty,
+        http.request.method = %method,
+        server.address = "localhost",
+        server.port = i64::from(port),
+        url.full = url,
+        http.response.status_code = Empty,
+        error.type = Empty,
+    )
+}
+
+// Put the trace context of `span` in `headers`, with the global propagator.
+pub(crate) fn inject(span: &Span, headers: &mut HeaderMap) {
+    let context = span.context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers))
+    });
+}
+
+// Record what became of the request of `span`: the status Vite answered with, or why it
+// could not be reached.
+pub(crate) fn record_outcome(span: &Span, outcome: Result<StatusCode, &SendRequestError>) {
+    match outcome {
+        Ok(status) => {
+            span.record("http.response.status_code", i64::from(status.as_u16()));
+            // Client spans are errors for every 4xx and 5xx.
+            if status.is_client_error() || status.is_server_error() {
+                span.record("error.type", status.as_str());
+                span.record("otel.status_code", "ERROR");
+            }
+        }
+        Err(err) => {
+            let error_type = match err {
+                SendRequestError::Connect(_) => "connect",
+                SendRequestError::Timeout => "timeout",
+                _ => "_OTHER",
+            };
+            span.record("error.type", error_type);
+            span.record("otel.status_code", "ERROR");
+        }
+    }
+}
+
+// Sets the headers of a forwarded request for a propagator.
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::vite_state::ViteShared;
+use crate::vite_stdio::{ViteOutputLine, ViteStream};
+use log::{debug, warn};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::error::RecvError;
+
+// Where Vite's output is written and when the file is rotated.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct LogFile {
+    pub path: PathBuf,
+    pub max_size: u64,
+    pub max_files: usize,
+}
+
+/// A log file that is moved to `<path>.1` once it would grow past `max_size`, shifting older
+/// files to `.2`, `.3` and so on and dropping those beyond `max_files`.
+pub(crate) struct RotatingLogFile {
+    config: LogFile,
+    file: File,
+    size: u64,
+}
+
+impl RotatingLogFile {
+    pub(crate) fn open(config: LogFile) -> std::io::Result<Self> {
+        if let Some(parent) = config.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { config, file, size })
+    }
+
+    // Append `line` as `<timestamp> [<stream>] <line>`, rotating first if it does not fit.
+    pub(crate) fn write_line(&mut self, line: &ViteOutputLine) -> std::io::Result<()> {
+        let stream = match line.stream {
+            ViteStream::Stdout => "stdout",
+            ViteStream::Stderr => "stderr",
+        };
+        self.write(&format!(
+            "{} [{}] {}\n",
+            format_timestamp(line.at),
+            stream,
+            line.line
+        ))
+    }
+
+    fn write(&mut self, text: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + text.len() as u64 > self.config.max_size {
+            self.rotate()?;
+        }
+        // One write per line, so the file is never behind by more than the current line.
+        self.file.write_all(text.as_bytes())?;
+        self.size += text.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        let path = &self.config.path;
+        if self.config.max_files == 0 {
+            std::fs::remove_file(path)?;
+        } else {
+            let oldest = rotated_path(path, self.config.max_files);
+            if oldest.exists() {
+                std::fs::remove_file(&oldest)?;
+            }
+            for index in (1..self.config.max_files).rev() {
+                let from = rotated_path(path, index);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(path, index + 1))?;
+                }
+            }
+            std::fs::rename(path, rotated_path(path, 1))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+// `<path>.<index>`, e.g. `vite.log.1`.
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
+
+// Format `at` as an RFC 3339 timestamp in UTC with milliseconds.
+fn format_timestamp(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (days, time) = ((seconds / 86_400) as i64, seconds % 86_400);
+
+    // Days since the epoch to a civil date, after Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time % 3_600 / 60,
+        time % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+// Write every output line of `shared` to `config.path` on a dedicated thread, unless that
+// is already being done, e.g. for a process respawned in place of an earlier one.
+pub(crate) fn start(shared: &ViteShared, config: &LogFile) {
+    if !shared.claim_log_file(&config.path) {
+        return;
+    }
+    let mut output = shared.subscribe_output();
+    let mut file = match RotatingLogFile::open(config.clone()) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!(
+                "Failed to open the Vite log file {}: {}",
+                config.path.display(),
+                e
+            );
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        loop {
+            let written = match output.blocking_recv() {
+                Ok(line) => file.write_line(&line),
+                Err(RecvError::Lagged(skipped)) => {
+                    file.write(&format!("... {} lines of Vite output were dropped\n", skipped))
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if let Err(e) = written {
+                warn!(
+                    "Failed to write to the Vite log file {}: {}",
+                    file.config.path.display(),
+                    e
+                );
+                break;
+            }
+        }
+        debug!("Stopped writing the Vite log file {}", file.config.path.display());
+    });
+}
@@ -0,0 +1,300 @@
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+// Scripts that usually start the Vite dev server, in order of preference.
+const DEV_SCRIPTS: [&str; 2] = ["dev", "start"];
+
+// The lock files of the package managers.
+#[cfg(feature = "build-helper")]
+pub(crate) const LOCK_FILES: [&str; 5] = [
+    "package-lock.json",
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lockb",
+    "bun.lock",
+];
+
+/// The package manager used to run a `package.json` script.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PackageManager {
+    Npm,
+    Pnpm,
+    Yarn,
+    Bun,
+}
+
+impl PackageManager {
+    /// Detects the package manager of the project in `working_directory` from the
+    /// `packageManager` field of its `package.json` or its lock file, defaulting to npm.
+    ///
+    /// A package of a pnpm workspace has no lock file of its own, so the lock file of the
+    /// workspace root is used for it, and the workspace itself means pnpm if there is none.
+    pub fn detect(working_directory: impl AsRef<Path>, package_json: &str) -> Self {
+        let working_directory = working_directory.as_ref();
+        let declared = serde_json::from_str::<serde_json::Value>(package_json)
+            .ok()
+            .and_then(|json| json.get("packageManager")?.as_str().map(str::to_string));
+        if let Some(declared) = declared {
+            let name = declared.split('@').next().unwrap_or_default();
+            if let Some(manager) = Self::from_name(name) {
+                return manager;
+            }
+        }
+
+        if let Some(manager) = Self::from_lock_file(working_directory) {
+            return manager;
+        }
+        match find_workspace_root(working_directory) {
+            Some(root) => Self::from_lock_file(&root).unwrap_or(PackageManager::Pnpm),
+            None => PackageManager::Npm,
+        }
+    }
+
+    // The package manager whose lock file is in `directory`, if any.
+    fn from_lock_file(directory: &Path) -> Option<Self> {
+        if directory.join("pnpm-lock.yaml").exists() {
+            Some(PackageManager::Pnpm)
+        } else if directory.join("yarn.lock").exists() {
+            Some(PackageManager::Yarn)
+        } else if directory.join("bun.lockb").exists() || directory.join("bun.lock").exists() {
+            Some(PackageManager::Bun)
+        } else if directory.join("package-lock.json").exists() {
+            Some(PackageManager::Npm)
+        } else {
+            None
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "npm" => Some(PackageManager::Npm),
+            "pnpm" => Some(PackageManager::Pnpm),
+            "yarn" => Some(PackageManager::Yarn),
+            "bun" => Some(PackageManager::Bun),
+            _ => None,
+        }
+    }
+
+    /// The executable to launch for this package manager.
+    pub fn program(&self) -> &'static str {
+        #[cfg(target_os = "windows")]
+        return match self {
+            PackageManager::Npm => "npm.cmd",
+            PackageManager::Pnpm => "pnpm.cmd",
+            PackageManager::Yarn => "yarn.cmd",
+            PackageManager::Bun => "bun.exe",
+        };
+        #[cfg(not(target_os = "windows"))]
+        match self {
+            PackageManager::Npm => "npm",
+            PackageManager::Pnpm => "pnpm",
+            PackageManager::Yarn => "yarn",
+            PackageManager::Bun => "bun",
+        }
+    }
+
+    /// The arguments that run `script` with `forwarded` passed on to its command.
+    ///
+    /// npm needs a `--` separator to forward arguments, the other managers pass them on as is.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::package_script::PackageManager;
+    ///
+    /// let flags = ["--port".to_string(), "5173".to_string()];
+    /// let npm = PackageManager::Npm.run_args("dev", &flags);
+    /// assert_eq!(npm, ["run", "dev", "--", "--port", "5173"]);
+    /// let pnpm = PackageManager::Pnpm.run_args("dev", &flags);
+    /// assert_eq!(pnpm, ["run", "dev", "--port", "5173"]);
+    /// assert_eq!(PackageManager::Npm.run_args("start", &[]), ["run", "start"]);
+    /// ```
+    pub fn run_args(&self, script: &str, forwarded: &[String]) -> Vec<String> {
+        let mut args = vec!["run".to_string(), script.to_string()];
+        if !forwarded.is_empty() {
+            if *self == PackageManager::Npm {
+                args.push("--".to_string());
+            }
+            args.extend(forwarded.iter().cloned());
+        }
+        args
+    }
+}
+
+/// A `package.json` script that starts the Vite dev server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageScript {
+    /// The name of the script, e.g. `dev`.
+    pub name: String,
+    /// The command line of the script, e.g. `vite --host`.
+    pub command: String,
+}
+
+impl PackageScript {
+    /// Reads `<working_directory>/package.json` and finds its Vite dev script, if any.
+    ///
+    /// Vite is started through this script unless [`ProxyViteOptions::package_script`] is
+    /// off.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use vite_actix::package_script::PackageScript;
+    ///
+    /// if let Some((manager, script)) = PackageScript::find("./frontend") {
+    ///     println!("{} runs `{}` for Vite", manager.program(), script.command);
+    /// }
+    /// ```
+    ///
+    /// [`ProxyViteOptions::package_script`]: crate::proxy_vite_options::ProxyViteOptions::package_script
+    pub fn find(working_directory: impl AsRef<Path>) -> Option<(PackageManager, Self)> {
+        let working_directory = working_directory.as_ref();
+        let package_json = std::fs::read_to_string(working_directory.join("package.json")).ok()?;
+        let script = Self::from_package_json(&package_json)?;
+        Some((
+            PackageManager::detect(working_directory, &package_json),
+            script,
+        ))
+    }
+
+    /// Finds the `dev` or `start` script in the contents of a `package.json` whose command
+    /// runs the Vite dev server.
+    ///
+    /// Scripts running `vite build` or `vite preview` are not dev servers and are skipped.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::package_script::PackageScript;
+    ///
+    /// let script = |package_json: &str| {
+    ///     PackageScript::from_package_json(package_json).map(|script| script.name)
+    /// };
+    /// let both = r#"{"scripts": {"dev": "vite --host", "start": "vite"}}"#;
+    /// assert_eq!(script(both).as_deref(), Some("dev"));
+    /// let build = r#"{"scripts": {"dev": "vite build", "start": "vite"}}"#;
+    /// assert_eq!(script(build).as_deref(), Some("start"));
+    /// for command in [
+    ///     "vite",
+    ///     "vite dev --open",
+    ///     "cross-env NODE_ENV=development vite --host",
+    ///     "npm run codegen && vite",
+    ///     "./node_modules/.bin/vite",
+    /// ] {
+    ///     let package_json = serde_json::json!({ "scripts": { "dev": command } }).to_string();
+    ///     assert_eq!(script(&package_json).as_deref(), Some("dev"), "{}", command);
+    /// }
+    /// for command in ["vite build", "vite preview --port 4173", "vitest", "webpack serve"] {
+    ///     let package_json = serde_json::json!({ "scripts": { "dev": command } }).to_string();
+    ///     assert_eq!(script(&package_json), None, "{}", command);
+    /// }
+    /// // Only `dev` and `start` are dev scripts, and the file has to be valid JSON.
+    /// assert_eq!(script(r#"{"scripts": {"serve": "vite"}}"#), None);
+    /// assert_eq!(script(r#"{"name": "app"}"#), None);
+    /// assert_eq!(script(r#"{"scripts": {"dev": "vite",}}"#), None);
+    /// ```
+    pub fn from_package_json(package_json: &str) -> Option<Self> {
+        let json: serde_json::Value = serde_json::from_str(package_json).ok()?;
+        let scripts = json.get("scripts")?.as_object()?;
+        DEV_SCRIPTS.iter().find_map(|name| {
+            let command = scripts.get(*name)?.as_str()?;
+            runs_vite_dev_server(command).then(|| Self {
+                name: name.to_string(),
+                command: command.to_string(),
+            })
+        })
+    }
+
+    /// Removes the flags from `flags` that the script's command line already sets.
+    ///
+    /// Vite rejects an option given twice, so the value in the script wins; a flag's value
+    /// is removed along with it.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::package_script::PackageScript;
+    ///
+    /// let script = PackageScript {
+    ///     name: "dev".to_string(),
+    ///     command: "vite --port=3000 --host".to_string(),
+    /// };
+    /// let flags: Vec<String> = ["--port", "5173", "--strictPort", "--host=0.0.0.0", "--open"]
+    ///     .map(String::from)
+    ///     .into();
+    /// assert_eq!(script.merge_flags(&flags), ["--strictPort", "--open"]);
+    /// ```
+    pub fn merge_flags(&self, flags: &[String]) -> Vec<String> {
+        let existing: Vec<&str> = self
+            .command
+            .split_whitespace()
+            .filter_map(|word| word.split('=').next())
+            .filter(|word| word.starts_with("--"))
+            .collect();
+
+        let mut merged = Vec::new();
+        let mut skip_value = false;
+        for flag in flags {
+            if skip_value && !flag.starts_with('-') {
+                skip_value = false;
+                continue;
+            }
+            skip_value = false;
+            let name = flag.split('=').next().unwrap_or(flag);
+            if name.starts_with("--") && existing.contains(&name) {
+                log::debug!("Not forwarding {} to the dev script, which already sets it", name);
+                skip_value = !flag.contains('=');
+                continue;
+            }
+            merged.push(flag.clone());
+        }
+        merged
+    }
+}
+
+/// The root of the pnpm workspace `working_directory` belongs to: the closest directory at or
+/// above it with a `pnpm-workspace.yaml`.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::package_script::{PackageManager, find_workspace_root};
+///
+/// let root = std::env::temp_dir().join(format!("vite-actix-workspace-{}", std::process::id()));
+/// let package = root.join("packages/web");
+/// std::fs::create_dir_all(package.join("src")).unwrap();
+/// assert_eq!(find_workspace_root(&package), None);
+/// assert_eq!(PackageManager::detect(&package, "{}"), PackageManager::Npm);
+///
+/// std::fs::write(root.join("pnpm-workspace.yaml"), "packages:\n  - packages/*\n").unwrap();
+/// let root = root.canonicalize().unwrap();
+/// assert_eq!(find_workspace_root(&package), Some(root.clone()));
+/// assert_eq!(find_workspace_root(package.join("src")), Some(root.clone()));
+/// assert_eq!(find_workspace_root(&root), Some(root.clone()));
+/// // The package has no lock file, the workspace root has that of pnpm, if any.
+/// assert_eq!(PackageManager::detect(&package, "{}"), PackageManager::Pnpm);
+/// std::fs::write(root.join("pnpm-lock.yaml"), "").unwrap();
+/// assert_eq!(PackageManager::detect(&package, "{}"), PackageManager::Pnpm);
+/// std::fs::remove_dir_all(&root).unwrap();
+/// ```
+pub fn find_workspace_root(working_directory: impl AsRef<Path>) -> Option<PathBuf> {
+    let working_directory = working_directory.as_ref();
+    let working_directory = working_directory
+        .canonicalize()
+        .unwrap_or_else(|_| working_directory.to_path_buf());
+    working_directory
+        .ancestors()
+        .find(|dir| dir.join("pnpm-workspace.yaml").is_file())
+        .map(Path::to_path_buf)
+}
+
+// Whether a script command line starts the Vite dev server, e.g. `vite`, `vite dev --host`
+// or `cross-env FOO=1 vite`, but not `vite build` or `vite preview`.
+fn runs_vite_dev_server(command: &str) -> bool {
+    static VITE_COMMAND: OnceLock<Regex> = OnceLock::new();
+    let regex = VITE_COMMAND.get_or_init(|| {
+        Regex::new(r"(?:^|[\s&;|/])vite(?:\s+(?P<sub>[a-z]+))?(?:\s|$)").unwrap()
+    });
+    regex.captures_iter(command).any(|caps| {
+        !matches!(
+            caps.name("sub").map(|sub| sub.as_str()),
+            Some("build" | "preview" | "optimize")
+        )
+    })
+}
@@ -0,0 +1,221 @@
+use log::{debug, warn};
+use std::path::{Path, PathBuf};
+
+/// What the managed start path does with a Vite process left behind by a previous run.
+///
+/// A leftover process is detected through the PID file written to
+/// `node_modules/.vite-actix/vite.pid` in the working directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StalePolicy {
+    /// Kill the leftover process and start a fresh one.
+    #[default]
+    Kill,
+    /// Attach to the leftover process and proxy to the port it recorded.
+    Reuse,
+    /// Leave the leftover process alone and start a new one anyway.
+    Ignore,
+}
+
+/// The contents of a Vite PID file.
+///
+/// # Example
+/// ```rust,no_run
+/// use vite_actix::pid_file::{PidFile, is_process_alive};
+///
+/// if let Some(leftover) = PidFile::read("./frontend")
+///     && is_process_alive(leftover.pid)
+/// {
+///     println!("Vite {} of an earlier run is still up, on {:?}", leftover.pid, leftover.port);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PidFile {
+    pub pid: u32,
+    pub port: Option<u16>,
+}
+
+impl PidFile {
+    /// The location of the PID file for a Vite project in `working_directory`.
+    pub fn path(working_directory: impl AsRef<Path>) -> PathBuf {
+        working_directory
+            .as_ref()
+            .join("node_modules")
+            .join(".vite-actix")
+            .join("vite.pid")
+    }
+
+    /// Reads the PID file of `working_directory`, if there is a valid one.
+    pub fn read(working_directory: impl AsRef<Path>) -> Option<Self> {
+        let contents = std::fs::read_to_string(Self::path(working_directory)).ok()?;
+        let mut lines = contents.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let port = lines.next().and_then(|port| port.trim().parse().ok());
+        Some(Self { pid, port })
+    }
+
+    /// Writes this PID file into `working_directory`, creating the directory if needed.
+    pub fn write(&self, working_directory: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = Self::path(working_directory);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let port = self.port.map(|port| port.to_string()).unwrap_or_default();
+        std::fs::write(path, format!("{}\n{}\n", self.pid, port))
+    }
+
+    /// Removes the PID file of `working_directory` if it still belongs to `pid`.
+    pub fn remove(working_directory: impl AsRef<Path>, pid: u32) {
+        let working_directory = working_directory.as_ref();
+        if Self::read(working_directory).is_some_and(|file| file.pid == pid)
+            && let Err(e) = std::fs::remove_file(Self::path(working_directory))
+        {
+            debug!("Failed to remove Vite PID file: {}", e);
+        }
+    }
+}
+
+/// Checks whether a process with `pid` is running.
+pub fn is_process_alive(pid: u32) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        Path::new("/proc").join(pid.to_string()).exists()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        process_description(pid).is_some()
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        std::process::Command::new("kill")
+            .arg("-0")
+            .arg(pid.to_string())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+}
+
+/// Best-effort check whether `pid` is a Vite process, based on its command line.
+pub fn is_vite_process(pid: u32) -> bool {
+    process_description(pid).is_some_and(|description| {
+        let description = description.to_lowercase();
+        description.contains("vite") || description.contains("node")
+    })
+}
+
+/// Forcefully terminates `pid`.
+pub fn kill_process(pid: u32) -> std::io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let status = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdout(std::process::Stdio::null())
+        .status()?;
+    #[cfg(not(target_os = "windows"))]
+    let status = std::process::Command::new("kill")
+        .args(["-KILL", &pid.to_string()])
+        .status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!(
+            "Failed to kill process {}",
+            pid
+        )))
+    }
+}
+
+/// Forcefully terminates every process started by `pid`, directly or indirectly, but not
+/// `pid` itself.
+///
+/// Vite started through a package manager runs as a grandchild, which killing the package
+/// manager alone would leave behind. On Windows `kill_process` already kills the whole tree.
+pub fn kill_descendants(pid: u32) {
+    #[cfg(not(target_os = "windows"))]
+    {
+        // Collect the whole tree first, so nothing is reparented away while killing it.
+        let mut descendants = Vec::new();
+        let mut pending = child_pids(pid);
+        while let Some(child) = pending.pop() {
+            pending.extend(child_pids(child));
+            descendants.push(child);
+        }
+        for descendant in descendants {
+            if let Err(e) = kill_process(descendant) {
+                debug!("{}", e);
+            }
+        }
+    }
+    #[cfg(target_os = "windows")]
+    let _ = pid;
+}
+
+// The ids of the processes whose parent is `pid`.
+#[cfg(not(target_os = "windows"))]
+fn child_pids(pid: u32) -> Vec<u32> {
+    std::process::Command::new("pgrep")
+        .args(["-P", &pid.to_string()])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter_map(|line| line.trim().parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// The command line (or image name on Windows) of `pid`, if it is running.
+fn process_description(pid: u32) -> Option<String> {
+    #[cfg(target_os = "linux")]
+    {
+        let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+        Some(String::from_utf8_lossy(&cmdline).replace('\0', " "))
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let output = std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+            .output()
+            .ok()?;
+        let output = String::from_utf8_lossy(&output.stdout).to_string();
+        output.contains(&format!("\"{}\"", pid)).then_some(output)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        let output = std::process::Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", "command="])
+            .output()
+            .ok()?;
+        let output = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!output.is_empty()).then_some(output)
+    }
+}
+
+/// A Vite process recorded by a previous run that is still alive.
+pub(crate) fn find_stale_process(working_directory: &str) -> Option<PidFile> {
+    let file = PidFile::read(working_directory)?;
+    if is_process_alive(file.pid) && is_vite_process(file.pid) {
+        return Some(file);
+    }
+
+    debug!(
+        "Removing stale Vite PID file for process {}, which is no longer running",
+        file.pid
+    );
+    PidFile::remove(working_directory, file.pid);
+    None
+}
+
+/// Kills a leftover Vite process and removes its PID file.
+pub(crate) fn kill_stale_process(working_directory: &str, file: PidFile) {
+    warn!(
+        "Killing Vite process {} left behind by a previous run",
+        file.pid
+    );
+    kill_descendants(file.pid);
+    if let Err(e) = kill_process(file.pid) {
+        warn!("{}", e);
+    }
+    PidFile::remove(working_directory, file.pid);
+}
@@ -0,0 +1,103 @@
+use crate::error::StartViteError;
+use regex::Regex;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, TcpListener};
+use std::sync::OnceLock;
+
+/// What to do when Vite reports that its port is already in use.
+///
+/// Vite only gives up on a busy port when `strict_port` is enabled; otherwise it silently
+/// picks the next port itself and the proxy follows the `Local:` banner line.
+///
+/// # Example
+/// ```rust,no_run
+/// # fn run() -> anyhow::Result<()> {
+/// use vite_actix::port_conflict::PortConflictStrategy;
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+///
+/// ProxyViteOptions::new()
+///     .port(5173)
+///     .strict_port(true)
+///     .port_conflict(PortConflictStrategy::NextFreePort)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PortConflictStrategy {
+    /// Fail with an error naming the conflicting port and, where possible, the PID owning it.
+    #[default]
+    FailFast,
+    /// Respawn Vite on the next free port and point the proxy at it.
+    NextFreePort,
+}
+
+/// Parses a `Port 5173 is in use` / `Port 5173 is already in use` line from Vite's output.
+///
+/// Returns the conflicting port, or `None` if the line is not a port conflict message.
+/// ANSI color codes must be stripped before calling this.
+pub fn detect_port_conflict(line: &str) -> Option<u16> {
+    static PORT_IN_USE: OnceLock<Regex> = OnceLock::new();
+    let regex = PORT_IN_USE
+        .get_or_init(|| Regex::new(r"Port (?P<port>\d{1,5}) is (?:already )?in use").unwrap());
+    regex.captures(line)?.name("port")?.as_str().parse().ok()
+}
+
+/// Checks whether `port` is free wherever Vite may listen: on the loopback interfaces, as
+/// `localhost` resolves to `::1` first on newer Node.js versions, and on every IPv4
+/// interface, which `--host` binds. A machine without IPv6 only needs IPv4 to be free.
+pub fn is_port_free(port: u16) -> bool {
+    let in_use = |address: IpAddr| {
+        TcpListener::bind((address, port)).is_err_and(|e| e.kind() == ErrorKind::AddrInUse)
+    };
+    TcpListener::bind((Ipv4Addr::LOCALHOST, port)).is_ok()
+        && !in_use(Ipv4Addr::UNSPECIFIED.into())
+        && !in_use(Ipv6Addr::LOCALHOST.into())
+}
+
+/// Finds the first port at or above `start` that is free, see [`is_port_free`].
+pub fn find_free_port(start: u16) -> Option<u16> {
+    (start..=u16::MAX).find(|port| is_port_free(*port))
+}
+
+/// Best-effort lookup of the PID listening on `port`.
+///
+/// Uses `lsof` on unix and `netstat -ano` on Windows; returns `None` if the tool is missing
+/// or the owner cannot be determined.
+pub fn port_owner_pid(port: u16) -> Option<u32> {
+    #[cfg(target_os = "windows")]
+    {
+        let output = std::process::Command::new("netstat")
+            .args(["-ano", "-p", "TCP"])
+            .output()
+            .ok()?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        let suffix = format!(":{}", port);
+        output.lines().find_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            match columns.as_slice() {
+                [_, local, _, "LISTENING", pid] if local.ends_with(&suffix) => pid.parse().ok(),
+                _ => None,
+            }
+        })
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let output = std::process::Command::new("lsof")
+            .args(["-t", "-sTCP:LISTEN", "-i"])
+            .arg(format!("tcp:{}", port))
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.trim().parse().ok())
+    }
+}
+
+/// Builds the error reported when Vite cannot bind `port`, naming the owning process if known.
+pub fn port_conflict_error(port: u16) -> StartViteError {
+    StartViteError::PortInUse {
+        port,
+        pid: port_owner_pid(port),
+    }
+}
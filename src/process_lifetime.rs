@@ -0,0 +1,194 @@
+use std::process::{Child, Command, ExitStatus};
+use std::time::Duration;
+
+// How long a Vite process group gets to exit after SIGTERM before it is killed.
+#[cfg(unix)]
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(3);
+
+/// Prepares `command` so the process it spawns dies with this process.
+///
+/// On unix the child starts its own session, which makes it the leader of a new process
+/// group that [`stop_group`] can signal as a whole. On Linux it also asks the kernel to send
+/// it SIGTERM when its parent dies, so a SIGKILLed parent does not leave Vite running; spawn
+/// such a command with [`spawn`]. On other platforms this does nothing.
+pub(crate) fn configure(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        #[cfg(target_os = "linux")]
+        let parent = std::process::id() as libc::pid_t;
+        // Only async-signal-safe calls are allowed between fork and exec.
+        unsafe {
+            command.pre_exec(move || {
+                if libc::setsid() == -1 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                #[cfg(target_os = "linux")]
+                {
+                    if libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGTERM) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    // The parent may have died before the death signal was set up.
+                    if libc::getppid() != parent {
+                        return Err(std::io::Error::from_raw_os_error(libc::ESRCH));
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+/// Spawns a command prepared with [`configure`].
+///
+/// The Linux parent-death signal fires when the *thread* that spawned the child exits, not
+/// the process. Respawns happen on short-lived threads, so every spawn is handed to a
+/// dedicated thread that lives as long as the process.
+pub(crate) fn spawn(command: Command) -> std::io::Result<Child> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::sync::mpsc;
+        use std::sync::{Mutex, OnceLock};
+
+        type SpawnJob = (Command, mpsc::Sender<std::io::Result<Child>>);
+        static SPAWNER: OnceLock<Mutex<mpsc::Sender<SpawnJob>>> = OnceLock::new();
+
+        let spawner = SPAWNER.get_or_init(|| {
+            let (jobs, receiver) = mpsc::channel::<SpawnJob>();
+            std::thread::spawn(move || {
+                for (mut command, result) in receiver {
+                    let _ = result.send(command.spawn());
+                }
+            });
+            Mutex::new(jobs)
+        });
+
+        let (result, receiver) = mpsc::channel();
+        spawner
+            .lock()
+            .map_err(|_| std::io::Error::other("Vite spawner thread is unavailable"))?
+            .send((command, result))
+            .map_err(|_| std::io::Error::other("Vite spawner thread is unavailable"))?;
+        receiver
+            .recv()
+            .map_err(|_| std::io::Error::other("Vite spawner thread is unavailable"))?
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let mut command = command;
+        command.spawn()
+    }
+}
+
+/// Stops a process started with [`configure`] together with everything it started.
+///
+/// On unix the process group is sent SIGTERM so Vite can clean up, and SIGKILL if it is
+/// still running after a grace period. Elsewhere the process is killed right away.
+pub(crate) fn stop_group(child: &mut Child) -> std::io::Result<ExitStatus> {
+    #[cfg(unix)]
+    {
+        let group = child.id() as libc::pid_t;
+        if unsafe { libc::killpg(group, libc::SIGTERM) } == 0 {
+            let deadline = std::time::Instant::now() + TERMINATE_GRACE_PERIOD;
+            while std::time::Instant::now() < deadline {
+                if let Some(status) = child.try_wait()? {
+                    // The leader is gone, make sure nothing else of the group lingers.
+                    unsafe { libc::killpg(group, libc::SIGKILL) };
+                    return Ok(status);
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            log::debug!("Vite did not exit after SIGTERM, killing process group {}", group);
+            if unsafe { libc::killpg(group, libc::SIGKILL) } == 0 {
+                return child.wait();
+            }
+        }
+    }
+    // `taskkill /T` takes the whole tree down on Windows.
+    #[cfg(windows)]
+    if crate::pid_file::kill_process(child.id()).is_ok() {
+        return child.wait();
+    }
+    child.kill()?;
+    child.wait()
+}
+
+/// Ties the lifetime of `child` and every process it starts to this process, so the OS
+/// reaps the whole Vite tree if this process dies without shutting Vite down.
+///
+/// On Windows the child is assigned to a Job Object created with
+/// `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`; the job handle is kept open for the lifetime of this
+/// process and closed by the OS when it exits, for whatever reason. On unix this is a no-op,
+/// the same is done by [`configure`] before the child starts.
+pub(crate) fn kill_with_parent(child: &Child) {
+    #[cfg(windows)]
+    windows::assign_to_job(child);
+    #[cfg(not(windows))]
+    let _ = child;
+}
+
+#[cfg(windows)]
+mod windows {
+    use log::debug;
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JobObjectExtendedLimitInformation,
+        SetInformationJobObject,
+    };
+
+    // The job every Vite process is assigned to, stored as an address so it can be shared.
+    // It is intentionally never closed: the OS closing it on exit is what kills the tree.
+    static VITE_JOB: OnceLock<Option<usize>> = OnceLock::new();
+
+    pub(super) fn assign_to_job(child: &Child) {
+        let Some(job) = *VITE_JOB.get_or_init(create_job) else {
+            return;
+        };
+        let assigned =
+            unsafe { AssignProcessToJobObject(job as HANDLE, child.as_raw_handle() as HANDLE) };
+        if assigned == 0 {
+            debug!(
+                "Failed to assign Vite process {} to a job object: {}",
+                child.id(),
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+
+    fn create_job() -> Option<usize> {
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                debug!(
+                    "Failed to create a job object for Vite: {}",
+                    std::io::Error::last_os_error()
+                );
+                return None;
+            }
+
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+            let configured = SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &info as *const JOBOBJECT_EXTENDED_LIMIT_INFORMATION as *const _,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            );
+            if configured == 0 {
+                debug!(
+                    "Failed to configure the job object for Vite: {}",
+                    std::io::Error::last_os_error()
+                );
+                CloseHandle(job);
+                return None;
+            }
+            Some(job as usize)
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use std::process::Command;
+
+// How much the niceness of a low priority Vite process is raised on unix.
+#[cfg(unix)]
+const LOW_PRIORITY_NICENESS: libc::c_int = 10;
+
+/// Makes the process `command` spawns run at a lower CPU priority than this one.
+///
+/// On unix the child raises its niceness before it starts Vite, on Windows it is created in
+/// the below-normal priority class. Either way the processes Vite starts itself, like the
+/// esbuild service, inherit the lower priority.
+pub(crate) fn lower_priority(command: &mut Command) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        // Only async-signal-safe calls are allowed between fork and exec.
+        unsafe {
+            command.pre_exec(|| {
+                // A lower priority is nice to have, so a failure does not stop Vite starting.
+                libc::nice(LOW_PRIORITY_NICENESS);
+                Ok(())
+            });
+        }
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows_sys::Win32::System::Threading::BELOW_NORMAL_PRIORITY_CLASS;
+        command.creation_flags(BELOW_NORMAL_PRIORITY_CLASS);
+    }
+    #[cfg(not(any(unix, windows)))]
+    let _ = command;
+}
@@ -0,0 +1,212 @@
+//! The proxy of [`configure_vite`] as a handler, to mount on routes of the application's
+//! own choosing, with its own guards and middleware in front. The handler needs the `proxy`
+//! feature.
+//!
+//! [`handler`] forwards every request it gets to Vite, like the default service that
+//! `configure_vite` registers, WebSocket upgrades included. The [`ProxyTarget`] says which
+//! options it reads, so mounts can go to different dev servers.
+//!
+//! The request is forwarded with the path it has, so a handler mounted at `/frontend/**`
+//! suits a Vite with `base: '/frontend/'`. As the default service of a scope,
+//! [`ProxyHandler::strip_scope`] forwards the path without the scope instead, like
+//! `configure_vite` in a scope.
+//!
+//! The body is read from the `web::Payload`, which no `web::PayloadConfig` limits, and
+//! forwarded up to 1 GB. Middleware in front of the handler that reads the body has to put
+//! it back into the request for it to reach Vite.
+//!
+//! Bodies are kept in the chunks they arrive in rather than copied into one buffer, and sent
+//! on that way, with their length. With [`ProxyViteOptions::stream_responses`], Vite's
+//! responses are not read whole first either, except pages for the `html_transform`.
+//!
+//! # Example
+//! ```rust,no_run
+//! # #[cfg(feature = "proxy")]
+//! # {
+//! use actix_web::{App, guard, web};
+//! use vite_actix::proxy;
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! // Only requests with the token reach Vite, under `/frontend`.
+//! let options = ProxyViteOptions::new().port(5173);
+//! let app = App::new().service(
+//!     web::scope("/frontend")
+//!         .guard(guard::Header("authorization", "Bearer secret"))
+//!         .route("/{tail:.*}", web::route().to(proxy::handler(options))),
+//! );
+//! # }
+//! ```
+//!
+//! With [`ProxyViteOptions::expose_node_modules`] turned off, requests under `/node_modules`
+//! are a 404 instead of reaching Vite, however the path is spelled, except Vite's prebundled
+//! dependencies under `/node_modules/.vite/deps`, which the app does not load without.
+//! [`ProxyViteOptions::expose_vite_deps`] turns those off too; it does nothing while
+//! `/node_modules` is exposed.
+//!
+//! A Vite on the port of the Actix server would have the proxy forward every request to
+//! itself. With [`ProxyViteOptions::actix_port`], which `serve` sets from the address it binds,
+//! `build()` and `try_configure_vite` refuse such options. Whatever the options say, the
+//! proxy stamps the requests it forwards with a token of its process in the [`HOP_HEADER`],
+//! and answers one that comes back with it with `508 Loop Detected`, also through other
+//! reverse proxies.
+//!
+//! Instances of the proxy in front of each other each add their token, and a chain of
+//! [`ProxyViteOptions::max_hops`] or more is taken for a loop through several of them. The
+//! header is never passed on to the client.
+//!
+//! [`configure_vite`]: crate::vite_app_factory::ViteAppFactory::configure_vite
+//! [`ProxyViteOptions::stream_responses`]: crate::proxy_vite_options::ProxyViteOptions::stream_responses
+//! [`ProxyViteOptions::expose_node_modules`]: crate::proxy_vite_options::ProxyViteOptions::expose_node_modules
+//! [`ProxyViteOptions::expose_vite_deps`]: crate::proxy_vite_options::ProxyViteOptions::expose_vite_deps
+//! [`ProxyViteOptions::actix_port`]: crate::proxy_vite_options::ProxyViteOptions::actix_port
+//! [`ProxyViteOptions::max_hops`]: crate::proxy_vite_options::ProxyViteOptions::max_hops
+
+#[cfg(feature = "proxy")]
+use crate::forward_to_vite;
+use crate::proxy_vite_options::ProxyViteOptions;
+#[cfg(feature = "proxy")]
+use crate::scope_prefix::ScopedRequest;
+#[cfg(feature = "proxy")]
+use crate::vite_state::ViteProcess;
+#[cfg(feature = "proxy")]
+use actix_web::{Error, Handler, HttpRequest, HttpResponse, web};
+#[cfg(feature = "proxy")]
+use futures_util::future::LocalBoxFuture;
+use std::sync::Arc;
+
+/// The default name of the header the proxy stamps the requests it forwards with, set with
+/// [`ProxyViteOptions::hop_header`]. Every proxy adds a token of its process to the list the
+/// header holds. A request that arrives with the token of the process came back from the
+/// proxy itself, e.g. because Vite's port is the Actix server's, and is answered with
+/// `508 Loop Detected` instead of being forwarded again, and so is one that went through
+/// [`ProxyViteOptions::max_hops`] proxies already.
+pub const HOP_HEADER: &str = "x-vite-actix-hop";
+
+/// The default of [`ProxyViteOptions::max_hops`].
+pub const DEFAULT_MAX_HOPS: usize = 10;
+
+/// The options a [`ProxyHandler`] forwards with.
+///
+/// The state of the Vite process the crate started with the global options, for the
+/// starting page, error pages, the health route and the toolbar, only applies to
+/// [`ProxyTarget::Global`]; options of their own go by the server on their port alone, even
+/// for the same project.
+///
+/// # Example
+/// ```rust,no_run
+/// # #[cfg(feature = "proxy")]
+/// # {
+/// use actix_web::{App, web};
+/// use vite_actix::proxy::{self, ProxyTarget};
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+///
+/// // The managed Vite, wherever it ends up, and a second project on a port of its own.
+/// let app = App::new()
+///     .route("/app/{tail:.*}", web::route().to(proxy::handler(ProxyTarget::Global)))
+///     .route(
+///         "/admin/{tail:.*}",
+///         web::route().to(proxy::handler(ProxyViteOptions::new().port(5174))),
+///     );
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub enum ProxyTarget {
+    /// The global options, read again for every request, so the proxy follows the port Vite
+    /// reports once it is ready.
+    #[default]
+    Global,
+    /// Options of its own, which the proxy neither reads from nor writes to the global ones.
+    /// Needs a port in dev mode, since no Vite process reports one to them.
+    Options(Arc<ProxyViteOptions>),
+}
+
+impl ProxyTarget {
+    // The options to handle a request with.
+    #[cfg(feature = "proxy")]
+    pub(crate) fn options(&self) -> ProxyViteOptions {
+        match self {
+            Self::Global => ProxyViteOptions::global(),
+            Self::Options(options) => options.as_ref().clone(),
+        }
+    }
+
+    // The Vite process the proxy reports on: the one started with the global options for
+    // `Global`, none for options of its own.
+    #[cfg(feature = "proxy")]
+    pub(crate) fn process(&self) -> ViteProcess {
+        match self {
+            Self::Global => ViteProcess::global(),
+            Self::Options(_) => ViteProcess::default(),
+        }
+    }
+}
+
+impl From<ProxyViteOptions> for ProxyTarget {
+    fn from(options: ProxyViteOptions) -> Self {
+        Self::Options(Arc::new(options))
+    }
+}
+
+/// Forwards requests to Vite, created with [`handler`], to mount with
+/// `web::route().to(handler)`.
+#[cfg(feature = "proxy")]
+#[derive(Clone)]
+pub struct ProxyHandler {
+    target: ProxyTarget,
+    process: ViteProcess,
+    strip_scope: bool,
+    // The pattern of the resource in a scope the handler is mounted on, for the part of the
+    // path the scopes matched; see `proxy_routes`.
+    route: Option<Arc<str>>,
+}
+
+#[cfg(feature = "proxy")]
+impl ProxyHandler {
+    /// Forward the path without the part the scopes around the handler matched, sent along
+    /// as `X-Forwarded-Prefix`, and put it back into the `Location` Vite answers with.
+    /// Vite's `base`, the [`ProxyViteOptions::asset_base`], takes the place of the scope.
+    ///
+    /// Only for the default service of a scope, since a route takes the rest of the path
+    /// for itself.
+    pub fn strip_scope(mut self) -> Self {
+        self.strip_scope = true;
+        self
+    }
+
+    // Like `strip_scope`, for a resource with `pattern` in a scope.
+    #[cfg(feature = "dev-proxy")]
+    pub(crate) fn strip_scope_of_route(mut self, pattern: &str) -> Self {
+        self.strip_scope = true;
+        self.route = Some(Arc::from(pattern));
+        self
+    }
+}
+
+#[cfg(feature = "proxy")]
+impl Handler<(HttpRequest, web::Payload)> for ProxyHandler {
+    type Output = Result<HttpResponse, Error>;
+    type Future = LocalBoxFuture<'static, Self::Output>;
+
+    fn call(&self, (req, payload): (HttpRequest, web::Payload)) -> Self::Future {
+        let options = self.target.options();
+        let scoped = self.strip_scope.then(|| match &self.route {
+            Some(pattern) => ScopedRequest::of_route(&req, pattern, &options.asset_base),
+            None => ScopedRequest::new(&req, &options.asset_base),
+        });
+        let process = self.process.clone();
+        Box::pin(forward_to_vite(req, payload, scoped, options, process))
+    }
+}
+
+/// The proxy to Vite for `target`, e.g. [`ProxyTarget::Global`] or options, see
+/// [`crate::proxy`].
+#[cfg(feature = "proxy")]
+pub fn handler(target: impl Into<ProxyTarget>) -> ProxyHandler {
+    let target = target.into();
+    ProxyHandler {
+        process: target.process(),
+        target,
+        strip_scope: false,
+        route: None,
+    }
+}
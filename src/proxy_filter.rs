@@ -0,0 +1,131 @@
+//! Requests the proxy keeps from Vite and answers with a 404 itself: paths under a prefix
+//! of [`ProxyViteOptions::exclude_prefix`], `/node_modules` unless it is exposed, and, with
+//! [`ProxyViteOptions::proxy_extensions`], files of other types.
+//!
+//! [`ProxyViteOptions::exclude_prefix`]: crate::proxy_vite_options::ProxyViteOptions::exclude_prefix
+//! [`ProxyViteOptions::proxy_extensions`]: crate::proxy_vite_options::ProxyViteOptions::proxy_extensions
+
+use crate::navigation_fallback::path_extension;
+
+// Path prefixes of the modules Vite serves itself, e.g. `/@vite/client`, whatever their
+// extension.
+const VITE_PREFIXES: [&str; 4] = ["/@vite", "/@id", "/@fs", "/@react-refresh"];
+
+/// Whether a request for `path`, as Vite sees it, is one the proxy should forward when only
+/// `extensions` are allowed: Vite's own modules, page navigations (no extension), and files
+/// with one of `extensions`, given in lowercase without the dot. A query string is ignored.
+pub(crate) fn is_proxied_path(path: &str, extensions: &[String]) -> bool {
+    let path = path.split('?').next().unwrap_or_default();
+    if VITE_PREFIXES
+        .iter()
+        .any(|prefix| path == *prefix || path.starts_with(&format!("{}/", prefix)))
+    {
+        return true;
+    }
+    match path_extension(path) {
+        Some(extension) => extensions
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(extension)),
+        None => true,
+    }
+}
+
+// Whether a request for `path`, as Vite sees it, is under `/node_modules` and kept from Vite
+// because the options do not expose it. Vite's prebundled dependencies, under
+// `/node_modules/.vite/deps`, are let through if `vite_deps` is set. The path is compared as
+// Vite would resolve it: percent-decoded, with `.` and `..` segments applied, and ignoring
+// case, for file systems that do too.
+pub(crate) fn is_hidden_node_module(path: &str, vite_deps: bool) -> bool {
+    let decoded = percent_encoding::percent_decode_str(path).decode_utf8_lossy();
+    let mut segments = Vec::new();
+    for segment in decoded.split(['/', '\\']) {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let is = |index: usize, name: &str| {
+        segments
+            .get(index)
+            .is_some_and(|segment| segment.eq_ignore_ascii_case(name))
+    };
+    if !is(0, "node_modules") {
+        return false;
+    }
+    !(vite_deps && is(1, ".vite") && is(2, "deps"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxied(path: &str) -> bool {
+        is_proxied_path(path, &["ts".to_string(), "css".to_string()])
+    }
+
+    #[test]
+    fn only_files_of_the_extensions_are_proxied() {
+        assert!(proxied("/src/main.ts"));
+        assert!(proxied("/style.css"));
+        assert!(proxied("/src/App.vue.ts"));
+        assert!(!proxied("/src/main.tsx"));
+        assert!(!proxied("/wp-login.php"));
+        assert!(!proxied("/.env"));
+        assert!(!proxied("/backup.tar.gz"));
+        assert!(!is_proxied_path("/src/main.ts", &[]));
+    }
+
+    #[test]
+    fn extensions_match_in_any_case() {
+        assert!(proxied("/src/Main.TS"));
+        assert!(proxied("/Style.Css"));
+        assert!(!proxied("/INDEX.PHP"));
+    }
+
+    #[test]
+    fn query_strings_are_ignored() {
+        assert!(proxied("/src/App.vue.ts?t=1"));
+        assert!(proxied("/src/main.ts?import&v=1.2.3"));
+        assert!(proxied("/about?tab=team.php"));
+        assert!(!proxied("/wp-login.php?action=login"));
+    }
+
+    #[test]
+    fn vite_modules_and_pages_are_proxied() {
+        for path in ["/@vite/client", "/@vite", "/@id/virtual:pwa", "/@fs/app/src/x.vue"] {
+            assert!(proxied(path), "{}", path);
+        }
+        assert!(proxied("/@react-refresh"));
+        assert!(proxied("/"));
+        assert!(proxied("/settings/profile"));
+        // Only under the prefixes themselves.
+        assert!(!proxied("/@vitest/ui.js"));
+    }
+
+    #[test]
+    fn node_modules_go_by_their_extension() {
+        assert!(proxied("/node_modules/.vite/deps/vue.ts?v=1"));
+        assert!(proxied("/node_modules/vue"));
+        assert!(!proxied("/node_modules/.vite/deps/vue.js?v=1"));
+        assert!(!proxied("/node_modules/.bin/vite.cmd"));
+    }
+
+    #[test]
+    fn node_modules_are_hidden_however_spelled() {
+        for path in [
+            "/node_modules/vue/package.json",
+            "/Node_Modules/vue",
+            "/src/../node_modules/.env",
+            "/%6eode_modules/vue",
+            "//node_modules\\vue",
+        ] {
+            assert!(is_hidden_node_module(path, true), "{}", path);
+        }
+        assert!(is_hidden_node_module("/node_modules/.vite/deps/vue.js", false));
+        assert!(!is_hidden_node_module("/node_modules/.vite/deps/vue.js", true));
+        assert!(!is_hidden_node_module("/src/node_modules.ts", true));
+    }
+}
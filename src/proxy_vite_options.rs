@@ -1,15 +1,171 @@
+#[cfg(feature = "compress")]
+use crate::compression::{Compression, Compressor};
+use crate::error::ConfigProblem;
+use crate::fault_injection::{FaultInjector, Faults, Latency};
+use crate::html_entries::HtmlEntries;
+#[cfg(any(feature = "prod-serve", feature = "embed"))]
+use crate::html_transform::HtmlFiles;
+use crate::html_transform::{HtmlTransform, NonceProvider};
+#[cfg(feature = "spawn")]
+use crate::log_format::LogFormat;
+use crate::manifest::{AssetCheck, built_base, list, verify_build};
+#[cfg(feature = "spawn")]
+use crate::pid_file::StalePolicy;
+#[cfg(feature = "spawn")]
+use crate::port_conflict::PortConflictStrategy;
+use crate::proxy::{DEFAULT_MAX_HOPS, HOP_HEADER};
+use crate::rate_limit::{RateLimitKey, RateLimiter};
+#[cfg(feature = "spawn")]
+use crate::skip_spawn::SkipSpawnDetector;
+#[cfg(feature = "spawn")]
+use crate::startup_build::DEFAULT_BUILD_TIMEOUT;
+#[cfg(any(feature = "prod-serve", feature = "embed"))]
+use crate::built_dist::BuiltDist;
+#[cfg(feature = "prod-serve")]
+use crate::built_dist::shadowed_files;
+use crate::well_known::DEFAULT_WELL_KNOWN_PATHS;
+use crate::navigation_fallback::DEFAULT_VITE_PREFIXES;
+#[cfg(feature = "spawn")]
+use crate::supervisor::RestartPolicy;
+#[cfg(feature = "spawn")]
+use crate::vite_state::DEFAULT_OUTPUT_BUFFER_LINES;
+#[cfg(feature = "spawn")]
+use crate::vite_state::ReadyPhase;
+use crate::trailing_slash::TrailingSlash;
+use crate::vite_command::ViteCommand;
+#[cfg(feature = "spawn")]
+use crate::vite_instances::ViteInstanceOptions;
+#[cfg(feature = "spawn")]
+use crate::vite_server_handle::{OnExitCallback, OnReadyCallback, OnRebuildCallback};
+#[cfg(feature = "spawn")]
+use crate::vite_stdio::{self, ViteStdio};
+use actix_web::http::header::{HeaderName, HeaderValue};
+#[cfg(feature = "spawn")]
 use log::Level::Debug;
+use regex::Regex;
+#[cfg(feature = "spawn")]
+use std::collections::HashMap;
 use std::env::current_dir;
-use std::sync::{Mutex, OnceLock};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+#[cfg(feature = "spawn")]
+use std::time::Duration;
 
 // Use OnceLock to ensure the Mutex is initialized only once
 static PROXY_VITE_OPTIONS: OnceLock<Mutex<ProxyViteOptions>> = OnceLock::new();
 
+// Whether `build()` ever stored options, as opposed to the defaults being used.
+static BUILT: AtomicBool = AtomicBool::new(false);
+
+// The default of `hashed_asset_pattern`, matching the 8 character content hash Vite puts in
+// the names of built files, e.g. `index-BxQw2a1z.js`.
+const DEFAULT_HASHED_ASSET_PATTERN: &str = r"-[A-Za-z0-9_-]{8}\.";
+
+// The fields and setters for the Vite process, from `args` to `ready_phase`, only exist with
+// the `spawn` feature; without it the options describe a Vite server started elsewhere.
 #[derive(Clone)]
 pub struct ProxyViteOptions {
     pub port: Option<u16>,
     pub working_directory: String,
+    #[cfg(feature = "spawn")]
+    pub args: Vec<String>,
+    #[cfg(feature = "spawn")]
     pub log_level: Option<log::Level>,
+    #[cfg(feature = "spawn")]
+    pub vite_log_format: LogFormat,
+    #[cfg(feature = "spawn")]
+    pub instances: HashMap<String, ViteInstanceOptions>,
+    #[cfg(feature = "spawn")]
+    pub on_exit: Option<OnExitCallback>,
+    #[cfg(feature = "spawn")]
+    pub strict_port: bool,
+    #[cfg(feature = "spawn")]
+    pub port_conflict: PortConflictStrategy,
+    #[cfg(feature = "spawn")]
+    pub pid_file: bool,
+    #[cfg(feature = "spawn")]
+    pub stale_policy: StalePolicy,
+    #[cfg(feature = "spawn")]
+    pub stdio: ViteStdio,
+    pub command: ViteCommand,
+    pub dev_mode: bool,
+    pub dist_directory: Option<String>,
+    pub serve_dist: bool,
+    pub spa_fallback: bool,
+    pub spa_fallback_excludes: Vec<String>,
+    pub hashed_asset_pattern: String,
+    pub cache_overrides: Vec<(String, String)>,
+    pub extra_static_dirs: Vec<(String, String)>,
+    pub subresource_integrity: bool,
+    pub asset_check: AssetCheck,
+    pub proxy_excludes: Vec<String>,
+    pub proxy_extensions: Option<Vec<String>>,
+    pub proxy_routes: Vec<String>,
+    pub register_default_service: bool,
+    pub expose_node_modules: bool,
+    pub expose_vite_deps: bool,
+    pub navigation_fallback_only: bool,
+    pub navigation_fallback_prefixes: Vec<String>,
+    pub trailing_slash: TrailingSlash,
+    pub html_entries: HtmlEntries,
+    #[cfg(feature = "compress")]
+    pub compression: Option<Compression>,
+    pub html_transform: Option<HtmlTransform>,
+    pub csp_nonce: Option<NonceProvider>,
+    pub asset_base: String,
+    pub serve_prefix: String,
+    pub decompress_upstream: bool,
+    pub stream_responses: bool,
+    pub well_known_paths: Vec<String>,
+    #[cfg(feature = "spawn")]
+    pub on_ready: Option<OnReadyCallback>,
+    #[cfg(feature = "spawn")]
+    pub on_rebuild: Option<OnRebuildCallback>,
+    #[cfg(feature = "spawn")]
+    pub print_startup_banner: bool,
+    #[cfg(feature = "spawn")]
+    pub skip_spawn: Option<SkipSpawnDetector>,
+    #[cfg(feature = "spawn")]
+    pub package_script: bool,
+    #[cfg(feature = "spawn")]
+    pub kill_on_parent_exit: bool,
+    #[cfg(feature = "spawn")]
+    pub low_priority: bool,
+    #[cfg(feature = "spawn")]
+    pub restart_policy: Option<RestartPolicy>,
+    #[cfg(feature = "spawn")]
+    pub log_file: Option<PathBuf>,
+    #[cfg(feature = "spawn")]
+    pub log_file_max_size: u64,
+    #[cfg(feature = "spawn")]
+    pub log_file_max_files: usize,
+    #[cfg(feature = "spawn")]
+    pub output_buffer_lines: usize,
+    #[cfg(feature = "spawn")]
+    pub ready_phase: ReadyPhase,
+    #[cfg(feature = "spawn")]
+    pub build_if_missing: bool,
+    #[cfg(feature = "spawn")]
+    pub build_if_stale: bool,
+    #[cfg(feature = "spawn")]
+    pub build_timeout: Duration,
+    pub hmr_path: Option<String>,
+    pub hmr_port: Option<u16>,
+    pub enable_admin_routes: bool,
+    pub admin_secret: Option<String>,
+    pub enable_health_route: bool,
+    pub starting_page: Option<String>,
+    pub log_proxy_spans: bool,
+    pub log_vite_pings: bool,
+    pub fault_injection: Arc<FaultInjector>,
+    pub rate_limit: Option<Arc<RateLimiter>>,
+    pub rate_limit_key: RateLimitKey,
+    pub dev_toolbar: bool,
+    pub fallback_to_dist: bool,
+    pub actix_port: Option<u16>,
+    pub hop_header: String,
+    pub max_hops: usize,
 }
 
 impl Default for ProxyViteOptions {
@@ -17,7 +173,104 @@ impl Default for ProxyViteOptions {
         Self {
             port: None,
             working_directory: try_find_vite_dir().unwrap_or(String::from("./")),
+            #[cfg(feature = "spawn")]
+            args: Vec::new(),
+            #[cfg(feature = "spawn")]
             log_level: Some(Debug),
+            #[cfg(feature = "spawn")]
+            vite_log_format: LogFormat::default(),
+            #[cfg(feature = "spawn")]
+            instances: HashMap::new(),
+            #[cfg(feature = "spawn")]
+            on_exit: None,
+            #[cfg(feature = "spawn")]
+            strict_port: false,
+            #[cfg(feature = "spawn")]
+            port_conflict: PortConflictStrategy::default(),
+            #[cfg(feature = "spawn")]
+            pid_file: true,
+            #[cfg(feature = "spawn")]
+            stale_policy: StalePolicy::default(),
+            #[cfg(feature = "spawn")]
+            stdio: ViteStdio::default(),
+            command: ViteCommand::default(),
+            dev_mode: cfg!(debug_assertions),
+            dist_directory: None,
+            serve_dist: true,
+            spa_fallback: true,
+            spa_fallback_excludes: Vec::new(),
+            hashed_asset_pattern: DEFAULT_HASHED_ASSET_PATTERN.to_string(),
+            cache_overrides: Vec::new(),
+            extra_static_dirs: Vec::new(),
+            subresource_integrity: false,
+            asset_check: AssetCheck::Log,
+            proxy_excludes: Vec::new(),
+            proxy_extensions: None,
+            proxy_routes: Vec::new(),
+            register_default_service: true,
+            expose_node_modules: true,
+            expose_vite_deps: true,
+            navigation_fallback_only: false,
+            navigation_fallback_prefixes: DEFAULT_VITE_PREFIXES.map(String::from).to_vec(),
+            trailing_slash: TrailingSlash::None,
+            html_entries: HtmlEntries::default(),
+            #[cfg(feature = "compress")]
+            compression: None,
+            html_transform: None,
+            csp_nonce: None,
+            asset_base: String::from("/"),
+            serve_prefix: String::new(),
+            decompress_upstream: false,
+            stream_responses: false,
+            well_known_paths: DEFAULT_WELL_KNOWN_PATHS.map(String::from).to_vec(),
+            #[cfg(feature = "spawn")]
+            on_ready: None,
+            #[cfg(feature = "spawn")]
+            on_rebuild: None,
+            #[cfg(feature = "spawn")]
+            print_startup_banner: false,
+            #[cfg(feature = "spawn")]
+            skip_spawn: None,
+            #[cfg(feature = "spawn")]
+            package_script: true,
+            #[cfg(feature = "spawn")]
+            kill_on_parent_exit: true,
+            #[cfg(feature = "spawn")]
+            low_priority: false,
+            #[cfg(feature = "spawn")]
+            restart_policy: None,
+            #[cfg(feature = "spawn")]
+            log_file: None,
+            #[cfg(feature = "spawn")]
+            log_file_max_size: 10 * 1024 * 1024,
+            #[cfg(feature = "spawn")]
+            log_file_max_files: 5,
+            #[cfg(feature = "spawn")]
+            output_buffer_lines: DEFAULT_OUTPUT_BUFFER_LINES,
+            #[cfg(feature = "spawn")]
+            ready_phase: ReadyPhase::default(),
+            #[cfg(feature = "spawn")]
+            build_if_missing: false,
+            #[cfg(feature = "spawn")]
+            build_if_stale: false,
+            #[cfg(feature = "spawn")]
+            build_timeout: DEFAULT_BUILD_TIMEOUT,
+            hmr_path: None,
+            hmr_port: None,
+            enable_admin_routes: false,
+            admin_secret: None,
+            enable_health_route: false,
+            starting_page: None,
+            log_proxy_spans: false,
+            log_vite_pings: false,
+            fault_injection: Arc::default(),
+            rate_limit: None,
+            rate_limit_key: RateLimitKey::default(),
+            dev_toolbar: false,
+            fallback_to_dist: false,
+            actix_port: None,
+            hop_header: String::from(HOP_HEADER),
+            max_hops: DEFAULT_MAX_HOPS,
         }
     }
 }
@@ -27,27 +280,721 @@ impl ProxyViteOptions {
         Self::default()
     }
 
+    // The port Vite listens on; like every other option, it becomes the global one with
+    // `build()`
     pub fn port(mut self, port: u16) -> Self {
         self.port = Some(port);
-        Self::update_port(port).ok();
         self
     }
 
+    // The port the Actix server binds, which `serve` sets; `build()` fails in dev mode if
+    // Vite's port is the same, as the proxy would forward to itself
+    pub fn actix_port(mut self, port: u16) -> Self {
+        self.actix_port = Some(port);
+        self
+    }
+
+    // The header the proxy stamps the requests it forwards with, to find loops; defaults to
+    // `HOP_HEADER`, see `proxy`
+    pub fn hop_header(mut self, hop_header: impl AsRef<str>) -> Self {
+        self.hop_header = hop_header.as_ref().to_ascii_lowercase();
+        self
+    }
+
+    // Answer requests that went through this many vite-actix proxies already with a 508,
+    // for chains of instances forwarding to each other; defaults to `DEFAULT_MAX_HOPS`
+    pub fn max_hops(mut self, max_hops: usize) -> Self {
+        self.max_hops = max_hops;
+        self
+    }
+
+    // The name of the hop header, or the default one if the option is not a header name
+    #[cfg(feature = "proxy")]
+    pub(crate) fn hop_header_name(&self) -> HeaderName {
+        HeaderName::try_from(self.hop_header.as_str())
+            .unwrap_or(HeaderName::from_static(HOP_HEADER))
+    }
+
     pub fn working_directory(mut self, working_directory: impl AsRef<str>) -> Self {
         self.working_directory = working_directory.as_ref().to_string();
         self
     }
 
+    // Extra arguments passed to Vite after the generated ones
+    #[cfg(feature = "spawn")]
+    pub fn arg(mut self, arg: impl AsRef<str>) -> Self {
+        self.args.push(arg.as_ref().to_string());
+        self
+    }
+
+    #[cfg(feature = "spawn")]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_string()));
+        self
+    }
+
+    // Pass `--strictPort` so Vite exits instead of silently moving to another port
+    #[cfg(feature = "spawn")]
+    pub fn strict_port(mut self, strict_port: bool) -> Self {
+        self.strict_port = strict_port;
+        self
+    }
+
+    // What the managed start path does when the port is already in use
+    #[cfg(feature = "spawn")]
+    pub fn port_conflict(mut self, strategy: PortConflictStrategy) -> Self {
+        self.port_conflict = strategy;
+        self
+    }
+
+    // Write `node_modules/.vite-actix/vite.pid` so leftovers of a crashed run can be found
+    #[cfg(feature = "spawn")]
+    pub fn pid_file(mut self, pid_file: bool) -> Self {
+        self.pid_file = pid_file;
+        self
+    }
+
+    // What to do with a Vite process left behind by a previous run
+    #[cfg(feature = "spawn")]
+    pub fn stale_policy(mut self, stale_policy: StalePolicy) -> Self {
+        self.stale_policy = stale_policy;
+        self
+    }
+
+    // Let Vite write to the terminal directly instead of re-logging its output
+    #[cfg(feature = "spawn")]
+    pub fn stdio(mut self, stdio: ViteStdio) -> Self {
+        self.stdio = stdio;
+        self
+    }
+
+    // Don't spawn Vite when the detector returns a reason, e.g. `skip_spawn::default_detector`
+    #[cfg(feature = "spawn")]
+    pub fn skip_spawn_when(mut self, detector: SkipSpawnDetector) -> Self {
+        self.skip_spawn = Some(detector);
+        self
+    }
+
+    // Start the dev server through the `dev` or `start` script of package.json if it runs vite
+    #[cfg(feature = "spawn")]
+    pub fn package_script(mut self, package_script: bool) -> Self {
+        self.package_script = package_script;
+        self
+    }
+
+    // Have the OS kill the managed Vite tree when this process dies, even if it is killed.
+    // On unix this also starts Vite in its own process group, stopped with SIGTERM then SIGKILL
+    #[cfg(feature = "spawn")]
+    pub fn kill_on_parent_exit(mut self, kill_on_parent_exit: bool) -> Self {
+        self.kill_on_parent_exit = kill_on_parent_exit;
+        self
+    }
+
+    // Run Vite and the esbuild workers it starts at a lower CPU priority, e.g. so they do not
+    // slow down `cargo watch` rebuilds
+    #[cfg(feature = "spawn")]
+    pub fn low_priority(mut self, low_priority: bool) -> Self {
+        self.low_priority = low_priority;
+        self
+    }
+
+    // Restart managed Vite processes that crash, backing off and giving up as the policy says
+    #[cfg(feature = "spawn")]
+    pub fn restart_on_crash(mut self, policy: RestartPolicy) -> Self {
+        self.restart_policy = Some(policy);
+        self
+    }
+
+    // Also write every captured line of Vite's output, timestamped, to this file
+    #[cfg(feature = "spawn")]
+    pub fn log_to_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.log_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    // Rotate the log file to `.1`, `.2`, ... once it reaches `max_size` bytes, keeping `max_files`
+    #[cfg(feature = "spawn")]
+    pub fn log_file_rotation(mut self, max_size: u64, max_files: usize) -> Self {
+        self.log_file_max_size = max_size;
+        self.log_file_max_files = max_files;
+        self
+    }
+
+    // How many of the last lines Vite printed are kept for `ViteServerHandle::recent_logs`,
+    // timestamped and without ANSI codes. Defaults to 500
+    #[cfg(feature = "spawn")]
+    pub fn output_buffer_lines(mut self, lines: usize) -> Self {
+        self.output_buffer_lines = lines;
+        self
+    }
+
+    // Which state `on_ready` and `ViteServerHandle::wait_ready` wait for, see `ReadyPhase`
+    #[cfg(feature = "spawn")]
+    pub fn ready_phase(mut self, ready_phase: ReadyPhase) -> Self {
+        self.ready_phase = ready_phase;
+        self
+    }
+
+    // Run `vite build` in `build()` when the dist directory to serve has no build, instead of
+    // failing; see `startup_build`. `VITE_ACTIX_SKIP_BUILD` turns this off
+    #[cfg(feature = "spawn")]
+    pub fn build_if_missing(mut self, build: bool) -> Self {
+        self.build_if_missing = build;
+        self
+    }
+
+    // Like `build_if_missing`, but also build when a file of `src/`, `public/`, `index.html`
+    // or `vite.config.*` is newer than the build
+    #[cfg(feature = "spawn")]
+    pub fn build_if_stale(mut self, build: bool) -> Self {
+        self.build_if_stale = build;
+        self
+    }
+
+    // How long `vite build` at startup may take before `build()` fails; defaults to five
+    // minutes
+    #[cfg(feature = "spawn")]
+    pub fn build_timeout(mut self, timeout: Duration) -> Self {
+        self.build_timeout = timeout;
+        self
+    }
+
+    // Run `vite preview` instead of the dev server, e.g. for release-candidate testing. Only
+    // the dev server's pages are proxied with `Cache-Control: no-store`
+    pub fn command(mut self, command: ViteCommand) -> Self {
+        self.command = command;
+        self
+    }
+
+    // Whether `configure_vite` registers the proxy; defaults to `cfg!(debug_assertions)`.
+    // Without the `dev-proxy` feature, `configure_vite` serves the build either way
+    pub fn dev_mode(mut self, dev_mode: bool) -> Self {
+        self.dev_mode = dev_mode;
+        self
+    }
+
+    #[cfg(feature = "spawn")]
     pub fn log_level(mut self, log_level: log::Level) -> Self {
         self.log_level = Some(log_level);
         self
     }
 
+    #[cfg(feature = "spawn")]
     pub fn disable_logging(mut self) -> Self {
         self.log_level = None;
         self
     }
 
+    // Log Vite's lines as single-line JSON objects with `LogFormat::Json`, e.g. for Loki;
+    // they keep the `log_level`, the object says the level Vite printed them at
+    #[cfg(feature = "spawn")]
+    pub fn vite_log_format(mut self, vite_log_format: LogFormat) -> Self {
+        self.vite_log_format = vite_log_format;
+        self
+    }
+
+    // Called once with the exit status when a managed Vite process exits
+    #[cfg(feature = "spawn")]
+    pub fn on_exit(mut self, on_exit: OnExitCallback) -> Self {
+        self.on_exit = Some(on_exit);
+        self
+    }
+
+    // Called when Vite is ready: the banner was printed, or the first watch build finished
+    #[cfg(feature = "spawn")]
+    pub fn on_ready(mut self, on_ready: OnReadyCallback) -> Self {
+        self.on_ready = Some(on_ready);
+        self
+    }
+
+    // Called with the build time after every `vite build --watch` rebuild
+    #[cfg(feature = "spawn")]
+    pub fn on_rebuild(mut self, on_rebuild: OnRebuildCallback) -> Self {
+        self.on_rebuild = Some(on_rebuild);
+        self
+    }
+
+    // Log one block with the Actix and Vite URLs once both are up, see `report_server_addr`
+    #[cfg(feature = "spawn")]
+    pub fn print_startup_banner(mut self, print_startup_banner: bool) -> Self {
+        self.print_startup_banner = print_startup_banner;
+        self
+    }
+
+    // The path of Vite's HMR socket, which `configure_vite` relays straight to Vite; defaults
+    // to the `asset_base`, or to the root of the scope the proxy is mounted in. Set it to
+    // Vite's `server.hmr.path` under its base if it has one
+    pub fn hmr_path(mut self, path: impl AsRef<str>) -> Self {
+        self.hmr_path = Some(format!("/{}", path.as_ref().trim_start_matches('/')));
+        self
+    }
+
+    // The port Vite's HMR server listens on when it is not the dev server's, like
+    // `server.hmr.port`; the HMR socket is relayed there
+    pub fn hmr_port(mut self, port: u16) -> Self {
+        self.hmr_port = Some(port);
+        self
+    }
+
+    // Answer the dev-only endpoints under `/__vite_actix`, e.g. `POST /__vite_actix/restart`,
+    // from `configure_vite` instead of proxying them, see `admin_routes`
+    pub fn enable_admin_routes(mut self, enable_admin_routes: bool) -> Self {
+        self.enable_admin_routes = enable_admin_routes;
+        self
+    }
+
+    // Also require this value in the `X-Vite-Actix-Secret` header of admin requests
+    pub fn admin_secret(mut self, secret: impl AsRef<str>) -> Self {
+        self.admin_secret = Some(secret.as_ref().to_string());
+        self
+    }
+
+    // Register `GET /__vite_actix/health` from `configure_vite`, answering whether Vite is
+    // ready in dev mode and whether the build can be served otherwise, see `health`
+    pub fn enable_health_route(mut self, enable_health_route: bool) -> Self {
+        self.enable_health_route = enable_health_route;
+        self
+    }
+
+    // The HTML page navigations get while Vite is starting, in place of the built-in one;
+    // the script that reloads it once Vite is ready is added, see `starting_page`
+    pub fn starting_page(mut self, html: impl AsRef<str>) -> Self {
+        self.starting_page = Some(html.as_ref().to_string());
+        self
+    }
+
+    // Log `proxy start` and `proxy end` at the trace level around every call to Vite, with
+    // the request ID and path, to place Vite's own output; see `request_id`
+    pub fn log_proxy_spans(mut self, log_proxy_spans: bool) -> Self {
+        self.log_proxy_spans = log_proxy_spans;
+        self
+    }
+
+    // Log the probes Vite's client sends while the HMR socket is down, which the proxy
+    // otherwise answers without a line, even with `log_proxy_spans`; see `vite_ping`
+    pub fn log_vite_pings(mut self, log_vite_pings: bool) -> Self {
+        self.log_vite_pings = log_vite_pings;
+        self
+    }
+
+    // Wait this long, or a time drawn from a range, before forwarding each request; for
+    // trying loading states in dev mode, see `fault_injection`
+    pub fn inject_latency(self, latency: impl Into<Latency>) -> Self {
+        let latency = latency.into();
+        self.with_faults(|faults| {
+            faults.min_latency_ms = latency.min.as_millis() as u64;
+            faults.max_latency_ms = latency.max.as_millis() as u64;
+        })
+    }
+
+    // Answer `rate` of the forwarded requests, from 0 to 1, with `status` instead
+    pub fn inject_errors(self, rate: f32, status: u16) -> Self {
+        self.with_faults(|faults| {
+            faults.error_rate = rate;
+            faults.error_status = status;
+        })
+    }
+
+    // Only inject latency and errors into requests under this path, e.g. `/api`; may be
+    // called more than once
+    pub fn inject_faults_under(self, prefix: impl AsRef<str>) -> Self {
+        let prefix = prefix.as_ref().to_string();
+        self.with_faults(|faults| faults.paths.push(prefix))
+    }
+
+    // Draw the same latencies and errors on every run
+    pub fn fault_seed(mut self, seed: u64) -> Self {
+        let faults = self.fault_injection.faults();
+        self.fault_injection = Arc::new(FaultInjector::new(faults, Some(seed)));
+        self
+    }
+
+    // Answer requests beyond `burst` at once and `requests_per_second` after that with a 429
+    // instead of forwarding them to Vite; see `rate_limit`
+    pub fn rate_limit(mut self, requests_per_second: u32, burst: u32) -> Self {
+        self.rate_limit = Some(Arc::new(RateLimiter::new(
+            requests_per_second,
+            burst,
+            self.rate_limit_key,
+        )));
+        self
+    }
+
+    // Count the requests of `rate_limit` together, the default, or per client IP address
+    pub fn rate_limit_key(mut self, key: RateLimitKey) -> Self {
+        self.rate_limit_key = key;
+        if let Some(limiter) = &self.rate_limit {
+            self.rate_limit = Some(Arc::new(RateLimiter::new(
+                limiter.requests_per_second(),
+                limiter.burst(),
+                key,
+            )));
+        }
+        self
+    }
+
+    // Add a badge with Vite's state, the time of the last HMR update and a restart button to
+    // the pages proxied in dev mode; see `dev_toolbar`
+    pub fn dev_toolbar(mut self, dev_toolbar: bool) -> Self {
+        self.dev_toolbar = dev_toolbar;
+        self
+    }
+
+    // Serve the built frontend from the dist directory in dev mode while the managed Vite
+    // process is down, instead of an error; see `dist_fallback`. Needs the `prod-serve`
+    // feature
+    pub fn fallback_to_dist(mut self, fallback_to_dist: bool) -> Self {
+        self.fallback_to_dist = fallback_to_dist;
+        self
+    }
+
+    // The faults are shared with the clones of the options, for the admin route to change
+    // them at runtime, so changing them here starts over with a copy.
+    fn with_faults(mut self, change: impl FnOnce(&mut Faults)) -> Self {
+        let mut faults = self.fault_injection.faults();
+        change(&mut faults);
+        self.fault_injection = Arc::new(FaultInjector::new(faults, self.fault_injection.seed()));
+        self
+    }
+
+    // Directory containing the built frontend; defaults to `<working_directory>/dist`
+    pub fn dist_directory(mut self, dist_directory: impl AsRef<str>) -> Self {
+        self.dist_directory = Some(dist_directory.as_ref().to_string());
+        self
+    }
+
+    // Serve the dist directory from `configure_vite` when not in dev mode; turn this off if
+    // the built assets are served elsewhere, e.g. by a CDN or reverse proxy
+    pub fn serve_dist(mut self, serve_dist: bool) -> Self {
+        self.serve_dist = serve_dist;
+        self
+    }
+
+    // Answer page navigations to unknown paths with dist/index.html, for client-side routing
+    pub fn spa_fallback(mut self, spa_fallback: bool) -> Self {
+        self.spa_fallback = spa_fallback;
+        self
+    }
+
+    // Never fall back to index.html under this path prefix, e.g. `/api`
+    pub fn spa_fallback_exclude(mut self, prefix: impl AsRef<str>) -> Self {
+        self.spa_fallback_excludes.push(prefix.as_ref().to_string());
+        self
+    }
+
+    // Regex for the request paths of built files with a content hash in their name, which are
+    // served with a one-year immutable cache; defaults to Vite's `-[A-Za-z0-9_-]{8}\.`
+    pub fn hashed_asset_pattern(mut self, pattern: impl AsRef<str>) -> Self {
+        self.hashed_asset_pattern = pattern.as_ref().to_string();
+        self
+    }
+
+    // Serve built files whose request path matches the regex `pattern` with this
+    // `Cache-Control` value, e.g. `("^/fonts/", "public, max-age=604800")`; the first
+    // matching override wins over the defaults
+    pub fn cache_control(mut self, pattern: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.cache_overrides
+            .push((pattern.as_ref().to_string(), value.as_ref().to_string()));
+        self
+    }
+
+    // Also serve the files of the directory `fs_path` under `url_prefix` outside of dev mode,
+    // e.g. `("/media", "/srv/app/public/media")` for public files deployed apart from the
+    // build; they are revalidated on every request, and files of the dist directory win
+    pub fn extra_static_dir(mut self, url_prefix: impl AsRef<str>, fs_path: impl AsRef<str>) -> Self {
+        let url_prefix = format!("/{}", url_prefix.as_ref().trim_matches('/'));
+        self.extra_static_dirs
+            .push((url_prefix, fs_path.as_ref().to_string()));
+        self
+    }
+
+    // Put `integrity` and `crossorigin="anonymous"` on the tags of `vite_actix::assets` outside
+    // of dev mode, with SHA-384 hashes of the built files, or those an SRI plugin wrote into
+    // the manifest; `build()` hashes the files and fails if one is missing
+    pub fn subresource_integrity(mut self, enabled: bool) -> Self {
+        self.subresource_integrity = enabled;
+        self
+    }
+
+    // Never proxy requests under this path prefix, e.g. `/api`, so unregistered backend routes
+    // are a 404 instead of Vite's index.html
+    pub fn exclude_prefix(mut self, prefix: impl AsRef<str>) -> Self {
+        self.proxy_excludes.push(prefix.as_ref().to_string());
+        self
+    }
+
+    // Answer this path with a 404 when Vite serves index.html for it because the file is not
+    // in `public/`, like it does by default for /favicon.ico, /robots.txt and a few others
+    pub fn well_known_path(mut self, path: impl AsRef<str>) -> Self {
+        self.well_known_paths.push(path.as_ref().to_string());
+        self
+    }
+
+    // Only proxy files with these extensions, e.g. `&["js", "ts", "css"]`, along with Vite's
+    // `/@vite`, `/@id`, `/@fs` and `/@react-refresh` modules and paths without an extension;
+    // everything else is a 404. Without this, every unmatched request is proxied
+    pub fn proxy_extensions(mut self, extensions: &[&str]) -> Self {
+        let extensions = extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+        self.proxy_extensions = Some(extensions);
+        self
+    }
+
+    // Proxy the requests matching these Actix route patterns, e.g. `&["/assets/{file:.*}",
+    // "/src/{file:.*}", "/@{rest:.*}"]`, with resources of their own, in dev mode; together
+    // with `register_default_service(false)`, other paths are left to the application
+    pub fn proxy_routes(mut self, patterns: &[&str]) -> Self {
+        self.proxy_routes = patterns.iter().map(|pattern| pattern.to_string()).collect();
+        self
+    }
+
+    // Proxy every unmatched request from the default service in dev mode, the default; turn
+    // this off for an application with a default service of its own, and list what Vite
+    // serves with `proxy_routes`
+    pub fn register_default_service(mut self, register_default_service: bool) -> Self {
+        self.register_default_service = register_default_service;
+        self
+    }
+
+    // Proxy requests under `/node_modules`; turned off, they are a 404, except Vite's
+    // prebundled dependencies under `/node_modules/.vite/deps` unless `expose_vite_deps` is
+    // turned off too. Defaults to `true`
+    pub fn expose_node_modules(mut self, expose: bool) -> Self {
+        self.expose_node_modules = expose;
+        self
+    }
+
+    // Proxy Vite's prebundled dependencies under `/node_modules/.vite/deps` while
+    // `expose_node_modules` is turned off. Defaults to `true`
+    pub fn expose_vite_deps(mut self, expose: bool) -> Self {
+        self.expose_vite_deps = expose;
+        self
+    }
+
+    // Like connect-history-api-fallback, only proxy page navigations, GET or HEAD requests
+    // whose Accept header prefers text/html, and paths under Vite's prefixes (`/@vite`,
+    // `/src`, `/node_modules`, `/assets`, ...); every other unmatched request is a 404
+    pub fn navigation_fallback_only(mut self, enabled: bool) -> Self {
+        self.navigation_fallback_only = enabled;
+        self
+    }
+
+    // Also proxy every request under this path prefix in `navigation_fallback_only` mode,
+    // e.g. `/images` for files of Vite's `public/` directory
+    pub fn navigation_fallback_prefix(mut self, prefix: impl AsRef<str>) -> Self {
+        let prefix = prefix.as_ref().trim_matches('/');
+        self.navigation_fallback_prefixes.push(format!("/{}", prefix));
+        self
+    }
+
+    // Redirect page navigations to `/about` to `/about/`, or the other way around, with a
+    // 308, whether Vite or the dist directory serves them; see `trailing_slash`. Defaults to
+    // `TrailingSlash::None`
+    pub fn trailing_slash(mut self, policy: TrailingSlash) -> Self {
+        self.trailing_slash = policy;
+        self
+    }
+
+    // What `build()` does when files the manifest or `index.html` of the build refer to are
+    // missing from the dist directory; see `manifest::AssetCheck`. Defaults to logging them
+    pub fn asset_check(mut self, check: AssetCheck) -> Self {
+        self.asset_check = check;
+        self
+    }
+
+    // The HTML entries of a multi-page app by the path prefix of their pages, e.g.
+    // `[("/admin", "admin/index.html"), ("/", "index.html")]`: page navigations fall back to
+    // the entry of the longest matching prefix in the build, and are forwarded to Vite under
+    // its path in dev mode; see `html_entries`
+    pub fn html_entries(mut self, entries: &[(&str, &str)]) -> Self {
+        self.html_entries = HtmlEntries::new(entries);
+        self
+    }
+
+    // Compress the text files of the build, like scripts, stylesheets and pages, on the fly
+    // for clients that accept Brotli or gzip, when they have no precompressed `.br` or `.gz`
+    // file; see `compression`. Off by default
+    #[cfg(feature = "compress")]
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    // Rewrite every HTML page before it is sent, whether Vite or the dist directory served it,
+    // e.g. to add a CSP nonce or a `window.__ENV__` script; not called for other responses
+    pub fn html_transform(mut self, transform: HtmlTransform) -> Self {
+        self.html_transform = Some(transform);
+        self
+    }
+
+    // The CSP nonce of a request, put on every script and style tag added to its page and in
+    // place of `vite_actix::html::NONCE_PLACEHOLDER` in the page
+    pub fn csp_nonce(mut self, provider: NonceProvider) -> Self {
+        self.csp_nonce = Some(provider);
+        self
+    }
+
+    // The public path the built files are served under, prepended to the URLs of
+    // `vite_actix::assets::tags_for`; match Vite's `base`, e.g. `/static/` or a CDN URL. A
+    // proxy mounted in a scope forwards requests under this path when it is one
+    pub fn asset_base(mut self, base: impl AsRef<str>) -> Self {
+        let base = base.as_ref().trim_end_matches('/');
+        let is_url = base.contains("://") || base.starts_with("//");
+        self.asset_base = if is_url || base.starts_with('/') {
+            format!("{}/", base)
+        } else if base.is_empty() {
+            String::from("/")
+        } else {
+            format!("/{}/", base)
+        };
+        self
+    }
+
+    // Serve the build under this path instead of the root, e.g. `/app` for one made with
+    // Vite's `base: '/app/'`, and leave the paths outside of it to the app's routes. Also
+    // becomes the `asset_base` unless another one is set
+    pub fn serve_prefix(mut self, prefix: impl AsRef<str>) -> Self {
+        let prefix = prefix.as_ref().trim_matches('/');
+        let uses_prefix =
+            self.asset_base == "/" || self.asset_base == format!("{}/", self.serve_prefix);
+        self.serve_prefix = match prefix {
+            "" => String::new(),
+            prefix => format!("/{}", prefix),
+        };
+        if uses_prefix {
+            self.asset_base = format!("{}/", self.serve_prefix);
+        }
+        self
+    }
+
+    // Let the proxy decompress Vite's responses instead of passing them on compressed, which
+    // `html_transform` needs if Vite, or a plugin of it, compresses pages it is asked for
+    // uncompressed; see `upstream_encoding`
+    pub fn decompress_upstream(mut self, decompress: bool) -> Self {
+        self.decompress_upstream = decompress;
+        self
+    }
+
+    // Pass Vite's responses on as they arrive instead of reading them whole first, except
+    // pages for `html_transform`. A response Vite breaks off then ends early instead of
+    // becoming a 500, and the proxy's 1 GB limit does not apply
+    pub fn stream_responses(mut self, stream: bool) -> Self {
+        self.stream_responses = stream;
+        self
+    }
+
+    // Whether pages are rewritten before they are sent, which needs them uncompressed
+    #[cfg(feature = "proxy")]
+    pub(crate) fn changes_pages(&self) -> bool {
+        self.html_transform.is_some() || self.csp_nonce.is_some() || self.dev_toolbar
+    }
+
+    // Whether requests for `path` are kept from the proxy by `exclude_prefix`
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.proxy_excludes
+            .iter()
+            .any(|prefix| has_path_prefix(path, prefix))
+    }
+
+    // Whether `configure_vite` serves the dist directory, i.e. outside of dev mode, or in any
+    // mode without the `dev-proxy` feature
+    pub fn serves_dist(&self) -> bool {
+        #[cfg(not(feature = "prod-serve"))]
+        return false;
+        #[cfg(all(feature = "prod-serve", feature = "dev-proxy"))]
+        return !self.dev_mode && self.serve_dist;
+        #[cfg(all(feature = "prod-serve", not(feature = "dev-proxy")))]
+        return self.serve_dist;
+    }
+
+    // How `configure_vite` serves the dist directory
+    #[cfg(any(feature = "prod-serve", feature = "embed"))]
+    pub(crate) fn built_dist(&self) -> BuiltDist {
+        BuiltDist {
+            dist_dir: self.dist_dir(),
+            serve_prefix: self.serve_prefix.clone(),
+            spa_fallback: self.spa_fallback,
+            fallback_excludes: self.spa_fallback_excludes.clone(),
+            assets_prefix: self.assets_prefix(),
+            hashed: Regex::new(&self.hashed_asset_pattern)
+                .inspect_err(|e| log::error!("Invalid hashed_asset_pattern: {}", e))
+                .ok(),
+            cache_overrides: self
+                .cache_overrides
+                .iter()
+                .filter_map(|(pattern, value)| match parse_cache_override(pattern, value) {
+                    Ok(cache_override) => Some(cache_override),
+                    Err(e) => {
+                        log::error!("{}", e);
+                        None
+                    }
+                })
+                .collect(),
+            html: self.html_files(),
+            trailing_slash: self.trailing_slash,
+            html_entries: self.html_entries.clone(),
+            #[cfg(feature = "compress")]
+            compressor: self
+                .compression
+                .map(|compression| Arc::new(Compressor::new(compression))),
+            #[cfg(feature = "prod-serve")]
+            extra_dirs: self
+                .extra_static_dirs
+                .iter()
+                .map(|(url_prefix, dir)| (url_prefix.clone(), PathBuf::from(dir)))
+                .collect(),
+        }
+    }
+
+    // The path the built assets are served under: Vite's `assets` directory under the
+    // `asset_base`, or at the root if that is another host
+    #[cfg(any(feature = "prod-serve", feature = "embed"))]
+    fn assets_prefix(&self) -> String {
+        format!("{}assets", self.index_asset_base())
+    }
+
+    // The path the built files are served under, `asset_base` if it is a local path and `/`
+    // if it is another host
+    pub(crate) fn index_asset_base(&self) -> &str {
+        let base = &self.asset_base;
+        let local = base.starts_with('/') && !base.starts_with("//");
+        if local { base } else { "/" }
+    }
+
+    // The `html_transform` and `csp_nonce` for pages read from disk
+    #[cfg(any(feature = "prod-serve", feature = "embed"))]
+    pub(crate) fn html_files(&self) -> Option<Arc<HtmlFiles>> {
+        if self.html_transform.is_none() && self.csp_nonce.is_none() {
+            return None;
+        }
+        Some(Arc::new(HtmlFiles::new(
+            self.html_transform.clone(),
+            self.csp_nonce.clone(),
+        )))
+    }
+
+    // The effective dist directory
+    pub fn dist_dir(&self) -> PathBuf {
+        match &self.dist_directory {
+            Some(dist_directory) => PathBuf::from(dist_directory),
+            None => Path::new(&self.working_directory).join("dist"),
+        }
+    }
+
+    // Register a named Vite instance, started later with `ViteInstances::start(name)`
+    #[cfg(feature = "spawn")]
+    pub fn instance(mut self, name: impl AsRef<str>, options: ViteInstanceOptions) -> Self {
+        self.instances.insert(name.as_ref().to_string(), options);
+        self
+    }
+
     // Update port without cloning the entire object
     pub fn update_port(port: u16) -> anyhow::Result<()> {
         let options = get_or_init_mutex();
@@ -61,15 +1008,224 @@ impl ProxyViteOptions {
         Ok(())
     }
 
+    // The path `configure_vite` registers the HMR socket under, relative to the scope for a
+    // proxy `in_scope`
+    #[cfg(feature = "dev-proxy")]
+    pub(crate) fn hmr_path_for(&self, in_scope: bool) -> String {
+        match &self.hmr_path {
+            Some(path) => path.clone(),
+            // A URL as the base, e.g. of a CDN, is not where the dev server is.
+            None if in_scope || !self.asset_base.starts_with('/') => String::from("/"),
+            None if self.asset_base.starts_with("//") => String::from("/"),
+            None => self.asset_base.clone(),
+        }
+    }
+
+    // Whether the port of a dev server without one in the options can come from elsewhere:
+    // `VITE_PORT`, or the banner of the Vite process unless the options are `pinned`
+    #[cfg(feature = "spawn")]
+    fn port_reported(&self, pinned: bool) -> bool {
+        vite_stdio::env_port().is_some() || (!pinned && self.stdio != ViteStdio::Inherit)
+    }
+
+    // Without the `spawn` feature, no Vite process reports its port
+    #[cfg(not(feature = "spawn"))]
+    fn port_reported(&self, _pinned: bool) -> bool {
+        false
+    }
+
+    // Everything wrong with these options for `configure_vite`, proxying when `dev_mode`;
+    // `pinned` options are used as they are, without a Vite process reporting its port
+    pub(crate) fn problems(&self, dev_mode: bool, pinned: bool) -> Vec<ConfigProblem> {
+        let mut problems = Vec::new();
+        if !pinned && !BUILT.load(Ordering::SeqCst) {
+            problems.push(ConfigProblem::NotBuilt);
+        }
+
+        if dev_mode
+            && self.command.is_server()
+            && self.port.is_none()
+            && !self.port_reported(pinned)
+        {
+            problems.push(ConfigProblem::NoPort);
+        }
+
+        #[cfg(feature = "prod-serve")]
+        if !dev_mode && self.serve_dist {
+            if !self.dist_dir().is_dir() {
+                problems.push(ConfigProblem::DistMissing(self.dist_dir()));
+            }
+            for (_, dir) in &self.extra_static_dirs {
+                if !Path::new(dir).is_dir() {
+                    problems.push(ConfigProblem::ExtraStaticDirMissing(PathBuf::from(dir)));
+                }
+            }
+        }
+
+        for prefix in &self.proxy_excludes {
+            let is_path = prefix.starts_with('/')
+                && !prefix.contains(['?', '#', '*', '\\'])
+                && !prefix.chars().any(char::is_whitespace);
+            if !is_path {
+                problems.push(ConfigProblem::InvalidExcludePrefix(prefix.clone()));
+            }
+        }
+
+        if let Err(e) = Regex::new(&self.hashed_asset_pattern) {
+            problems.push(ConfigProblem::InvalidPattern(format!(
+                "Invalid hashed_asset_pattern: {}",
+                e
+            )));
+        }
+        for (pattern, value) in &self.cache_overrides {
+            if let Err(e) = parse_cache_override(pattern, value) {
+                problems.push(ConfigProblem::InvalidPattern(e.to_string()));
+            }
+        }
+
+        if dev_mode {
+            for pattern in &self.proxy_routes {
+                if let Err(reason) = route_problem(pattern) {
+                    problems.push(ConfigProblem::InvalidRoute(pattern.clone(), reason));
+                }
+            }
+        }
+
+        if let Some(problem) = self.fault_injection.faults().problem() {
+            problems.push(ConfigProblem::InvalidFaults(problem));
+        }
+
+        if let Some(port) = self.proxy_loop(dev_mode) {
+            problems.push(ConfigProblem::ProxyLoop(port));
+        }
+        if HeaderName::try_from(self.hop_header.as_str()).is_err() {
+            problems.push(ConfigProblem::InvalidHopHeader(self.hop_header.clone()));
+        }
+
+        if self.proxy_extensions.is_some() && self.navigation_fallback_only {
+            problems.push(ConfigProblem::Conflict(
+                "proxy_extensions",
+                "navigation_fallback_only",
+            ));
+        }
+        problems
+    }
+
+    // Vite's port, if it is the Actix server's and the proxy would forward to itself
+    fn proxy_loop(&self, dev_mode: bool) -> Option<u16> {
+        let port = self.port.filter(|port| *port != 0)?;
+        (dev_mode && self.actix_port == Some(port)).then_some(port)
+    }
+
     // Initialize or update global options
     pub fn build(self) -> anyhow::Result<()> {
+        // Without piped output the port can't be read from the banner, so it must be known.
+        #[cfg(feature = "spawn")]
+        if self.stdio == ViteStdio::Inherit
+            && self.command.is_server()
+            && self.port.is_none()
+            && vite_stdio::env_port().is_none()
+        {
+            return Err(anyhow::Error::msg(
+                "ViteStdio::Inherit needs a port to probe, set one with port() or VITE_PORT",
+            ));
+        }
+
+        // The proxy would forward to the Actix server, which would forward to itself again.
+        if let Some(port) = self.proxy_loop(self.dev_mode) {
+            return Err(anyhow::anyhow!("{}", ConfigProblem::ProxyLoop(port)));
+        }
+
+        #[cfg(feature = "spawn")]
+        if self.serves_dist() && (self.build_if_missing || self.build_if_stale) {
+            crate::startup_build::build_if_needed(&self)?;
+        }
+
+        // Without the build there is nothing to serve, so fail now rather than on every request.
+        if self.serves_dist() && !self.dist_dir().is_dir() {
+            return Err(anyhow::anyhow!(
+                "The dist directory {} does not exist; run `vite build` first, point \
+                 dist_directory() at the build, or use serve_dist(false) if it is served \
+                 elsewhere",
+                self.dist_dir().display()
+            ));
+        }
+
+        #[cfg(feature = "prod-serve")]
+        if self.serves_dist() {
+            for (url_prefix, dir) in &self.extra_static_dirs {
+                if !Path::new(dir).is_dir() {
+                    return Err(anyhow::anyhow!("The extra static directory {} does not exist", dir));
+                }
+                let shadowed = shadowed_files(&self.dist_dir(), url_prefix, Path::new(dir));
+                if let Some(first) = shadowed.first() {
+                    log::warn!(
+                        "{} file(s) of {} are also in the dist directory, e.g. {}; the dist \
+                         directory's are served",
+                        shadowed.len(),
+                        dir,
+                        first
+                    );
+                }
+            }
+        }
+
+        // A build made for another `base` loads its files from where they are not served.
+        if self.serves_dist()
+            && let Some(base) = built_base(&self.dist_dir())
+            && base != self.asset_base
+        {
+            log::warn!(
+                "The build in {} loads its files from {}, but they are served under {}; build \
+                 with Vite's base set to {:?} or change serve_prefix or asset_base to match",
+                self.dist_dir().display(),
+                base,
+                self.asset_base,
+                self.asset_base
+            );
+        }
+
+        // A partial deployment serves pages whose scripts are missing, so tell now.
+        if self.serves_dist()
+            && self.asset_check != AssetCheck::Off
+            && let Err(missing) = verify_build(&self.dist_dir(), self.index_asset_base())
+        {
+            let message = format!(
+                "{} file(s) the build refers to are missing from {}:{}",
+                missing.len(),
+                self.dist_dir().display(),
+                list(&missing)
+            );
+            if self.asset_check == AssetCheck::Fail {
+                return Err(anyhow::Error::msg(message));
+            }
+            log::error!("{}", message);
+        }
+
+        // Hash the build now, so a broken deployment fails here instead of on every page.
+        if self.subresource_integrity && !(self.dev_mode && self.command.is_dev_server()) {
+            crate::integrity::cached(&self.dist_dir())
+                .map_err(|e| anyhow::anyhow!("Subresource integrity: {:#}", e))?;
+        }
+
+        Regex::new(&self.hashed_asset_pattern)
+            .map_err(|e| anyhow::anyhow!("Invalid hashed_asset_pattern: {}", e))?;
+        for (pattern, value) in &self.cache_overrides {
+            parse_cache_override(pattern, value)?;
+        }
+
         let options = get_or_init_mutex();
         let mut options_guard = options
             .lock()
             .map_err(|_| anyhow::Error::msg("Failed to lock proxy options during build"))?;
 
+        // The startup banner times everything from the first build of the options
+        #[cfg(feature = "spawn")]
+        crate::startup_banner::begin(self.print_startup_banner);
+
         // Update the global state with the new options
         *options_guard = self;
+        BUILT.store(true, Ordering::SeqCst);
 
         Ok(())
     }
@@ -88,6 +1244,86 @@ impl ProxyViteOptions {
     }
 }
 
+// Whether `path` is `prefix` or below it, so `/api` covers `/api/users` but not `/apiextra`
+pub(crate) fn has_path_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+// Compile a `cache_control` override of the options.
+fn parse_cache_override(pattern: &str, value: &str) -> anyhow::Result<(Regex, HeaderValue)> {
+    let regex = Regex::new(pattern)
+        .map_err(|e| anyhow::anyhow!("Invalid cache_control pattern {:?}: {}", pattern, e))?;
+    let value = HeaderValue::from_str(value)
+        .map_err(|_| anyhow::anyhow!("Invalid Cache-Control value {:?}", value))?;
+    Ok((regex, value))
+}
+
+// Why Actix cannot route `pattern` of `proxy_routes`. Actix only reports a bad pattern by
+// panicking where the resource is registered, so the checks of its `ResourceDef` are
+// repeated here, building the same regex, to fail before the server starts instead.
+pub(crate) fn route_problem(pattern: &str) -> Result<(), String> {
+    // The most dynamic segments a pattern may have.
+    const MAX_SEGMENTS: usize = 16;
+
+    if !pattern.contains('{') {
+        // Matched as it is.
+        return Ok(());
+    }
+    let mut regex = String::from("(?s-m)^");
+    let mut unprocessed = pattern;
+    let mut segments = 0;
+    let mut has_tail = false;
+    while let Some(start) = unprocessed.find('{') {
+        let (prefix, segment) = unprocessed.split_at(start);
+        regex.push_str(&regex::escape(prefix));
+        // The segment ends at the brace that closes its first one; its regex may have more.
+        let mut nesting = 0usize;
+        let end = segment
+            .find(|c| match c {
+                '{' => {
+                    nesting += 1;
+                    false
+                }
+                '}' => {
+                    nesting -= 1;
+                    nesting == 0
+                }
+                _ => false,
+            })
+            .ok_or("a dynamic segment is not closed")?;
+        let (param, rest) = segment.split_at(end + 1);
+        let param = &param[1..param.len() - 1];
+        let tail = rest == "*";
+        let (name, param_regex) = match param.split_once(':') {
+            Some(_) if tail => return Err("a tail segment cannot have a regex".to_string()),
+            Some((name, param_regex)) => (name, param_regex),
+            None if tail => (param, ".*"),
+            None => (param, "[^/]+"),
+        };
+        regex.push_str(&format!("(?P<{}>{})", name, param_regex));
+        unprocessed = if tail { "" } else { rest };
+        has_tail |= tail;
+        segments += 1;
+    }
+    if segments > MAX_SEGMENTS {
+        return Err(format!(
+            "it has {} dynamic segments, Actix allows {}",
+            segments, MAX_SEGMENTS
+        ));
+    }
+    if !has_tail {
+        regex.push_str(&regex::escape(unprocessed));
+        regex.push('$');
+    }
+    Regex::new(&format!("({})", regex))
+        .map(drop)
+        .map_err(|e| format!("its regex does not compile: {}", e))
+}
+
 // Helper function to initialize the mutex if needed and return a reference to it
 fn get_or_init_mutex() -> &'static Mutex<ProxyViteOptions> {
     PROXY_VITE_OPTIONS.get_or_init(|| {
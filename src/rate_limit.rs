@@ -0,0 +1,138 @@
+//! A limit on the requests the proxy forwards to Vite, set with
+//! [`ProxyViteOptions::rate_limit`], for dev servers reachable from a network, or a page
+//! stuck in a reload loop.
+//!
+//! The limit is a token bucket: it holds up to `burst` requests, every forwarded request
+//! takes one, and `requests_per_second` flow back in over time. A request that finds the
+//! bucket empty is answered with `429 Too Many Requests` and a `Retry-After` of the seconds
+//! until the next one is let through, without reaching Vite. The bucket is shared by all
+//! the requests, or with [`RateLimitKey::PerClient`] there is one per client IP address.
+//! There is no limit by default.
+//!
+//! The time is passed in, so a [`RateLimiter`] is tried with a clock of its own.
+//!
+//! # Example
+//! ```rust
+//! use std::net::{IpAddr, Ipv4Addr};
+//! use std::time::{Duration, Instant};
+//! use vite_actix::rate_limit::{RateLimitKey, RateLimiter};
+//!
+//! let start = Instant::now();
+//! let at = |ms: u64| start + Duration::from_millis(ms);
+//! let client = Some(IpAddr::V4(Ipv4Addr::LOCALHOST));
+//!
+//! // A burst of 5 is let through at once, the 6th has to wait for the next token.
+//! let limiter = RateLimiter::new(10, 5, RateLimitKey::Global);
+//! for _ in 0..5 {
+//!     assert_eq!(limiter.check(client, at(0)), Ok(()));
+//! }
+//! assert_eq!(limiter.check(client, at(0)), Err(Duration::from_millis(100)));
+//! assert_eq!(limiter.check(client, at(40)), Err(Duration::from_millis(60)));
+//!
+//! // Then 10 a second go through, one every 100ms.
+//! let allowed = (0..100)
+//!     .filter(|tick| limiter.check(client, at(100 + tick * 10)).is_ok())
+//!     .count();
+//! assert_eq!(allowed, 10);
+//!
+//! // An idle bucket fills up to the burst, not beyond.
+//! let later = at(60_000);
+//! let allowed = (0..20).filter(|_| limiter.check(client, later).is_ok()).count();
+//! assert_eq!(allowed, 5);
+//!
+//! // Per client, every client has a bucket of its own.
+//! let limiter = RateLimiter::new(1, 1, RateLimitKey::PerClient);
+//! let other = Some(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+//! assert_eq!(limiter.check(client, at(0)), Ok(()));
+//! assert!(limiter.check(client, at(0)).is_err());
+//! assert_eq!(limiter.check(other, at(0)), Ok(()));
+//! ```
+//!
+//! [`ProxyViteOptions::rate_limit`]: crate::proxy_vite_options::ProxyViteOptions::rate_limit
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Past this many clients, the buckets that filled up again are dropped.
+const MAX_IDLE_CLIENTS: usize = 1024;
+
+/// What the requests of a [`RateLimiter`] are counted by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// One bucket for all the requests.
+    #[default]
+    Global,
+    /// A bucket for each client IP address; requests without a peer address share one.
+    PerClient,
+}
+
+/// The token buckets of [`ProxyViteOptions::rate_limit`], shared by every clone of the
+/// options; see [`crate::rate_limit`].
+///
+/// [`ProxyViteOptions::rate_limit`]: crate::proxy_vite_options::ProxyViteOptions::rate_limit
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: u32,
+    burst: u32,
+    // The time one token takes to flow back in.
+    interval: Duration,
+    // How far ahead of now a bucket may be used up: the burst less the request at hand.
+    tolerance: Duration,
+    key: RateLimitKey,
+    // When each bucket is full again; a bucket used up to its burst is full again after
+    // `tolerance` and one `interval`.
+    buckets: Mutex<HashMap<Option<IpAddr>, Instant>>,
+}
+
+impl RateLimiter {
+    /// A limiter letting `burst` requests through at once, and `requests_per_second` after
+    /// that; 0 is taken as 1 for both.
+    pub fn new(requests_per_second: u32, burst: u32, key: RateLimitKey) -> Self {
+        let interval = Duration::from_secs(1) / requests_per_second.max(1);
+        Self {
+            requests_per_second,
+            burst,
+            interval,
+            tolerance: interval * (burst.max(1) - 1),
+            key,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The requests let through per second once the burst is used up.
+    pub fn requests_per_second(&self) -> u32 {
+        self.requests_per_second
+    }
+
+    /// The requests let through at once.
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// What the requests are counted by.
+    pub fn key(&self) -> RateLimitKey {
+        self.key
+    }
+
+    /// Takes a token for a request of `client` at `now`, or returns how long until the
+    /// next one is there.
+    pub fn check(&self, client: Option<IpAddr>, now: Instant) -> Result<(), Duration> {
+        let client = match self.key {
+            RateLimitKey::Global => None,
+            RateLimitKey::PerClient => client,
+        };
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if buckets.len() >= MAX_IDLE_CLIENTS && !buckets.contains_key(&client) {
+            buckets.retain(|_, full_at| *full_at > now);
+        }
+        let full_at = buckets.entry(client).or_insert(now);
+        let used = full_at.saturating_duration_since(now);
+        if used > self.tolerance {
+            return Err(used - self.tolerance);
+        }
+        *full_at = now.max(*full_at) + self.interval;
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+// `RealVite`, re-exported from `crate::test` with the `integration-tests` feature.
+
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::vite_app_factory::ViteAppFactory;
+use crate::vite_server_handle::ViteServerHandle;
+use actix_web::dev::ServerHandle;
+use actix_web::http::StatusCode;
+use actix_web::{App, HttpServer};
+use anyhow::{Context, bail};
+use futures_util::StreamExt;
+use regex::Regex;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard};
+
+// The files of the project `RealVite` scaffolds; `{port}` is the port Vite listens on.
+const PACKAGE_JSON: &str = r#"{
+  "name": "vite-actix-real-vite",
+  "private": true,
+  "type": "module",
+  "scripts": { "dev": "vite" },
+  "devDependencies": { "vite": "^6.0.0" }
+}
+"#;
+const VITE_CONFIG: &str = "export default {
+  clearScreen: false,
+  server: { port: {port}, strictPort: true },
+};
+";
+const INDEX_HTML: &str = r#"<!DOCTYPE html>
+<html>
+  <head><title>vite-actix</title></head>
+  <body>
+    <div id="app"></div>
+    <script type="module" src="/src/main.js"></script>
+  </body>
+</html>
+"#;
+/// The module of the project [`RealVite`] scaffolds, at `src/main.js`, which accepts its own
+/// updates.
+pub const MAIN_JS: &str = "document.querySelector('#app').textContent = 'hello from vite';
+if (import.meta.hot) import.meta.hot.accept();
+";
+
+// How long `npm install`, Vite's start and an HMR update may take.
+const INSTALL_TIMEOUT: Duration = Duration::from_secs(300);
+const START_TIMEOUT: Duration = Duration::from_secs(60);
+const HMR_TIMEOUT: Duration = Duration::from_secs(30);
+
+// `start_vite_server_managed` works with the global options, so one `RealVite` runs at a time.
+static RUNNING: Mutex<()> = Mutex::const_new(());
+
+/// A real Vite dev server for integration tests, with the `integration-tests` feature.
+///
+/// [`RealVite::start`] scaffolds a minimal Vite project into a temporary directory, a
+/// `package.json`, `vite.config.js`, `index.html` and [`MAIN_JS`] at `src/main.js`, installs
+/// Vite with `npm install`, starts it with
+/// [`start_vite_server_managed`](crate::start_vite_server_managed), and serves an Actix app
+/// with [`configure_vite`](ViteAppFactory::configure_vite) in front of it.
+/// [`RealVite::fetch`] requests a path through that app, and [`RealVite::edit_module`]
+/// rewrites the module and returns the HMR message Vite sends for it.
+///
+/// Tests are skipped, not failed, where Node.js or npm is missing: `start` returns `None`
+/// and says so. The installation needs access to the npm registry. As Vite is started with
+/// the global options, only one `RealVite` runs at a time; `start` waits for the previous
+/// one to be dropped, which stops Vite and removes the project.
+///
+/// # Example
+/// ```rust,ignore
+/// #[actix_web::test]
+/// #[ignore = "runs npm install and a real Vite"]
+/// async fn hmr() {
+///     let Some(vite) = RealVite::start().await.unwrap() else {
+///         return;
+///     };
+///     let (status, body) = vite.fetch("/").await.unwrap();
+///     assert_eq!(status, 200);
+///     assert!(body.contains("/@vite/client"));
+///     let message = vite.edit_module("console.log('edited');").await.unwrap();
+///     assert!(message.contains("\"update\""));
+/// }
+/// ```
+pub struct RealVite {
+    project: PathBuf,
+    handle: ViteServerHandle,
+    port: u16,
+    app_port: u16,
+    server: ServerHandle,
+    _running: MutexGuard<'static, ()>,
+}
+
+impl RealVite {
+    /// Scaffolds, installs and starts the project, and the Actix app in front of it, see
+    /// [`RealVite`]; `None` if Node.js or npm is missing.
+    ///
+    /// Has to be called from within an actix runtime, e.g. an `#[actix_web::test]`.
+    pub async fn start() -> anyhow::Result<Option<Self>> {
+        for tool in ["node", "npm"] {
+            if !runs(tool).await {
+                eprintln!("Skipping the test: RealVite needs {} on the PATH", tool);
+                return Ok(None);
+            }
+        }
+        let running = RUNNING.lock().await;
+
+        let project = std::env::temp_dir().join(format!(
+            "vite-actix-real-vite-{}-{}",
+            std::process::id(),
+            free_port()?
+        ));
+        let port = free_port()?;
+        scaffold(&project, port)?;
+        install(&project).await?;
+
+        ProxyViteOptions::new()
+            .dev_mode(true)
+            .working_directory(project.to_str().context("The temp dir is not UTF-8")?)
+            .port(port)
+            .build()?;
+        let handle = crate::start_vite_server_managed()?;
+        crate::wait_for_vite_port_async(START_TIMEOUT).await?;
+
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let app_port = listener.local_addr()?.port();
+        let server = HttpServer::new(|| App::new().configure_vite())
+            .workers(1)
+            .disable_signals()
+            .listen(listener)?
+            .run();
+        let server_handle = server.handle();
+        actix_web::rt::spawn(server);
+
+        Ok(Some(Self {
+            project,
+            handle,
+            port,
+            app_port,
+            server: server_handle,
+            _running: running,
+        }))
+    }
+
+    /// The handle of the Vite process.
+    pub fn handle(&self) -> &ViteServerHandle {
+        &self.handle
+    }
+
+    /// The port Vite listens on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The port of the Actix app in front of Vite, on `127.0.0.1`.
+    pub fn app_port(&self) -> u16 {
+        self.app_port
+    }
+
+    /// The directory of the project.
+    pub fn project(&self) -> &Path {
+        &self.project
+    }
+
+    /// Requests `path` through the Actix app, and returns the status and body of the answer.
+    pub async fn fetch(&self, path: &str) -> anyhow::Result<(StatusCode, String)> {
+        let url = format!("http://127.0.0.1:{}{}", self.app_port, path);
+        let mut res = awc::Client::new()
+            .get(&url)
+            .insert_header(("accept", "text/html,*/*"))
+            .send()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch {}: {}", url, e))?;
+        let body = res.body().limit(10 * 1024 * 1024).await?;
+        Ok((res.status(), String::from_utf8_lossy(&body).into_owned()))
+    }
+
+    /// Rewrites `src/main.js` with `contents`, and returns the HMR message Vite sends for
+    /// it over the HMR socket of the Actix app, an `update` or a `full-reload`.
+    ///
+    /// The module is fetched first, so Vite knows it, as it would after a page loaded it.
+    pub async fn edit_module(&self, contents: &str) -> anyhow::Result<String> {
+        self.fetch("/src/main.js").await?;
+        // Vite 6 only takes HMR connections with the token its client script carries.
+        let (_, client) = self.fetch("/@vite/client").await?;
+        let token = Regex::new(r#"wsToken = "([^"]*)""#)?
+            .captures(&client)
+            .map(|captures| captures[1].to_string());
+        let path = match token {
+            Some(token) => format!("/?token={}", token),
+            None => String::from("/"),
+        };
+        let (_, mut socket) = awc::Client::new()
+            .ws(format!("ws://127.0.0.1:{}{}", self.app_port, path))
+            .protocols(["vite-hmr"])
+            .connect()
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to open the HMR socket: {}", e))?;
+
+        let mut edited = false;
+        let message = actix_web::rt::time::timeout(HMR_TIMEOUT, async {
+            while let Some(frame) = socket.next().await {
+                let awc::ws::Frame::Text(text) = frame? else {
+                    continue;
+                };
+                let text = String::from_utf8_lossy(&text).into_owned();
+                if !edited && text.contains(r#""type":"connected""#) {
+                    std::fs::write(self.project.join("src/main.js"), contents)?;
+                    edited = true;
+                } else if edited
+                    && (text.contains(r#""type":"update""#)
+                        || text.contains(r#""type":"full-reload""#))
+                {
+                    return Ok(text);
+                }
+            }
+            bail!("The HMR socket closed before Vite sent an update")
+        })
+        .await;
+        message.context("Vite sent no HMR update in time")?
+    }
+}
+
+impl Drop for RealVite {
+    fn drop(&mut self) {
+        drop(self.server.stop(false));
+        if let Err(e) = self.handle.shutdown() {
+            log::warn!(
+                "Failed to stop the Vite of {}: {}",
+                self.project.display(),
+                e
+            );
+        }
+        let _ = std::fs::remove_dir_all(&self.project);
+    }
+}
+
+// Whether `tool --version` runs.
+async fn runs(tool: &str) -> bool {
+    tokio::process::Command::new(tool)
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+fn free_port() -> anyhow::Result<u16> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+fn scaffold(project: &Path, port: u16) -> anyhow::Result<()> {
+    std::fs::create_dir_all(project.join("src"))?;
+    std::fs::write(project.join("package.json"), PACKAGE_JSON)?;
+    let config = VITE_CONFIG.replace("{port}", &port.to_string());
+    std::fs::write(project.join("vite.config.js"), config)?;
+    std::fs::write(project.join("index.html"), INDEX_HTML)?;
+    std::fs::write(project.join("src/main.js"), MAIN_JS)?;
+    Ok(())
+}
+
+// Run `npm install` in `project`, failing with its output.
+async fn install(project: &Path) -> anyhow::Result<()> {
+    let install = tokio::process::Command::new("npm")
+        .args(["install", "--no-audit", "--no-fund"])
+        .current_dir(project)
+        .kill_on_drop(true)
+        .output();
+    let output = actix_web::rt::time::timeout(INSTALL_TIMEOUT, install)
+        .await
+        .with_context(|| format!("npm install took longer than {:?}", INSTALL_TIMEOUT))??;
+    if !output.status.success() {
+        bail!(
+            "npm install failed with {}:\n{}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
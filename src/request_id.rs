@@ -0,0 +1,90 @@
+//! The ID that ties a proxied request to the log lines written for it, on both sides of the
+//! proxy.
+//!
+//! The proxy takes the ID from the [`REQUEST_ID_HEADER`] the request came with, e.g. from a
+//! load balancer, or makes one up, and sends it on to Vite in that header for middlewares of
+//! the dev server to log. The proxy's own log lines for the request start with it in
+//! brackets, and the response carries it back unless Vite answered with one of its own.
+//! [`RequestId::of`] gives the same ID to the application, e.g. for its access log.
+//!
+//! Vite's own output is not tied to a request, so with
+//! [`ProxyViteOptions::log_proxy_spans`], the proxy brackets every call to Vite with a
+//! `proxy start` and `proxy end` line at the `trace` level, with the ID and path.
+//!
+//! # Example
+//! ```rust,no_run
+//! use actix_web::{HttpRequest, HttpResponse};
+//! use vite_actix::request_id::RequestId;
+//!
+//! async fn users(req: HttpRequest) -> HttpResponse {
+//!     log::info!("[{}] listing users", RequestId::of(&req));
+//!     HttpResponse::Ok().finish()
+//! }
+//! ```
+//!
+//! [`ProxyViteOptions::log_proxy_spans`]: crate::proxy_vite_options::ProxyViteOptions::log_proxy_spans
+
+use actix_web::HttpMessage;
+use actix_web::HttpRequest;
+use std::fmt::{self, Display, Formatter};
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The header the ID is read from and sent on in.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+// The longest ID taken from a request; longer ones are replaced.
+const MAX_LENGTH: usize = 128;
+
+/// The ID of a request, see [`crate::request_id`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RequestId(String);
+
+impl RequestId {
+    /// The ID of `req`: the one given to it before, the one it came with in the
+    /// [`REQUEST_ID_HEADER`] if that is up to 128 visible ASCII characters, or a new one.
+    /// It is kept in the extensions of the request, so every call returns the same.
+    pub fn of(req: &HttpRequest) -> Self {
+        if let Some(id) = req.extensions().get::<RequestId>() {
+            return id.clone();
+        }
+        let id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| is_valid(value))
+            .map_or_else(generate, String::from);
+        let id = RequestId(id);
+        req.extensions_mut().insert(id.clone());
+        id
+    }
+
+    /// The ID as it is sent in the header.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for RequestId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_LENGTH && id.bytes().all(|byte| byte.is_ascii_graphic())
+}
+
+// A new ID: when the process first made one, and how many it made since.
+fn generate() -> String {
+    static STARTED: OnceLock<u64> = OnceLock::new();
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let started = STARTED.get_or_init(|| {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        nanos ^ u64::from(std::process::id()) << 32
+    });
+    format!("{:x}-{}", started, NEXT.fetch_add(1, Ordering::Relaxed))
+}
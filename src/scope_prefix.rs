@@ -0,0 +1,105 @@
+use actix_web::HttpRequest;
+use actix_web::http::header::{CONTENT_LOCATION, HeaderName, HeaderValue, LOCATION};
+
+// The number of path segments of `prefix`, e.g. 2 for `/app/{tenant}/`.
+fn segments(prefix: &str) -> usize {
+    prefix.trim_end_matches('/').matches('/').count()
+}
+
+// Response headers holding a URL that the browser resolves against the mounted prefix.
+const REWRITTEN_HEADERS: [HeaderName; 2] = [LOCATION, CONTENT_LOCATION];
+
+/// A request to a proxy mounted in a scope, split into the part of the path the scopes matched
+/// and the part Vite knows about.
+pub(crate) struct ScopedRequest {
+    // The path of the scope, e.g. `/app`, as sent by the browser; empty at the root.
+    pub prefix: String,
+    // The path and query to forward to Vite, e.g. `/src/main.ts?v=1`.
+    pub forwarded: String,
+    // Vite's `base` when it is a path other than `/`, e.g. `/admin`; empty otherwise.
+    pub base: String,
+}
+
+impl ScopedRequest {
+    // Split a request that reached the default service of a scope.
+    //
+    // The unprocessed part of the match info is what is left after every scope, nested or
+    // dynamic, took its part of the path. The match info is percent-decoded, so the prefix
+    // is found by counting path segments and cut from the path as it was sent.
+    //
+    // `vite_base` is Vite's `base`, the `asset_base` of the options, which a project mounted
+    // in a scope sets to that scope so its pages link to it.
+    pub(crate) fn new(req: &HttpRequest, vite_base: &str) -> Self {
+        let matched = req.match_info().as_str();
+        let tail = req.match_info().unprocessed();
+        let prefix = matched.strip_suffix(tail).unwrap_or(matched);
+        Self::split(req, segments(prefix), vite_base)
+    }
+
+    // Split a request that reached a resource with `pattern` in a scope.
+    //
+    // The resource matched the whole path, so the part of the scopes is what the full pattern
+    // of the match has in front of the resource's own.
+    pub(crate) fn of_route(req: &HttpRequest, pattern: &str, vite_base: &str) -> Self {
+        let full = req.match_pattern().unwrap_or_default();
+        let prefix = full.strip_suffix(pattern).unwrap_or_default();
+        Self::split(req, segments(prefix), vite_base)
+    }
+
+    // Split the path of `req` after `prefix_segments` segments.
+    fn split(req: &HttpRequest, prefix_segments: usize, vite_base: &str) -> Self {
+        let path = req.uri().path();
+        let split = path
+            .match_indices('/')
+            .nth(prefix_segments)
+            .map_or(path.len(), |(index, _)| index);
+        let (prefix, rest) = path.split_at(split);
+        let rest = if rest.is_empty() { "/" } else { rest };
+        let forwarded = match req.uri().query() {
+            Some(query) => format!("{}?{}", rest, query),
+            None => rest.to_string(),
+        };
+        Self {
+            prefix: prefix.to_string(),
+            forwarded,
+            base: base_path(vite_base).to_string(),
+        }
+    }
+
+    // The path and query Vite serves the request under, the forwarded path under its base.
+    pub(crate) fn upstream_path(&self) -> String {
+        format!("{}{}", self.base, self.forwarded)
+    }
+
+    // Put the prefix back in front of the root-relative URLs of the response headers Vite
+    // sent, e.g. a redirect to `/index.html`, so the browser stays inside the scope. Vite's
+    // base, if it has one, gives way to the prefix.
+    pub(crate) fn rewrite_header(&self, name: &HeaderName, value: &HeaderValue) -> HeaderValue {
+        if self.prefix.is_empty() || !REWRITTEN_HEADERS.contains(name) {
+            return value.clone();
+        }
+        match value.to_str() {
+            // `//host/path` is a URL on another host, not a path.
+            Ok(url) if url.starts_with('/') && !url.starts_with("//") => {
+                let url = match url.strip_prefix(self.base.as_str()) {
+                    Some(rest) if !self.base.is_empty() && rest.is_empty() => "/",
+                    Some(rest) if !self.base.is_empty() && rest.starts_with(['/', '?']) => rest,
+                    _ => url,
+                };
+                HeaderValue::from_str(&format!("{}{}", self.prefix, url))
+                    .unwrap_or_else(|_| value.clone())
+            }
+            _ => value.clone(),
+        }
+    }
+}
+
+// Vite's `base` as a path without the trailing slash, e.g. `/admin`, or empty for `/` and
+// for a URL, which is not where the dev server is.
+pub(crate) fn base_path(vite_base: &str) -> &str {
+    if vite_base.starts_with('/') && !vite_base.starts_with("//") {
+        vite_base.trim_end_matches('/')
+    } else {
+        ""
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::Path;
+use std::sync::Arc;
+
+/// Decides whether Vite should not be spawned at all, e.g. on CI or under `cargo test`.
+///
+/// Returns the reason to skip, which is logged, or `None` to spawn Vite as usual.
+pub type SkipSpawnDetector = Arc<dyn Fn() -> Option<String> + Send + Sync>;
+
+/// A detector for environments where spawning Vite is unwanted.
+///
+/// Skips when:
+/// - `VITE_ACTIX_SKIP_SPAWN` is set to anything but `0` or `false`,
+/// - `CI` is set to `true` or `1`, as done by most CI providers,
+/// - the current executable is a test binary built by `cargo test`, or the tests are run
+///   by cargo-nextest.
+///
+/// `VITE_ACTIX_SKIP_SPAWN=0` forces spawning even when one of the other checks matches.
+///
+/// # Example
+/// ```rust,no_run
+/// # fn run() -> anyhow::Result<()> {
+/// use std::sync::Arc;
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+/// use vite_actix::skip_spawn::default_detector;
+///
+/// ProxyViteOptions::new()
+///     .skip_spawn_when(Arc::new(default_detector))
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn default_detector() -> Option<String> {
+    if let Some(value) = env_value("VITE_ACTIX_SKIP_SPAWN") {
+        return is_truthy(&value).then(|| "VITE_ACTIX_SKIP_SPAWN is set".to_string());
+    }
+    if env_value("CI").is_some_and(|value| is_truthy(&value)) {
+        return Some("running on CI (CI is set)".to_string());
+    }
+    if env_value("NEXTEST").is_some() || is_cargo_test_binary() {
+        return Some("running under cargo test".to_string());
+    }
+    None
+}
+
+// The trimmed value of an environment variable that is set and not empty.
+pub(crate) fn env_value(name: &str) -> Option<String> {
+    let value = std::env::var(name).ok()?;
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+pub(crate) fn is_truthy(value: &str) -> bool {
+    !(value == "0" || value.eq_ignore_ascii_case("false"))
+}
+
+// `cargo test` builds unit and integration tests into `target/<profile>/deps`, unlike
+// binaries and examples started with `cargo run`.
+fn is_cargo_test_binary() -> bool {
+    std::env::current_exe().is_ok_and(|exe| {
+        exe.parent()
+            .and_then(Path::file_name)
+            .is_some_and(|dir| dir == "deps")
+    })
+}
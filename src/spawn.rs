@@ -0,0 +1,1074 @@
+// Starting, supervising and stopping the Vite process, with the `spawn` feature.
+
+use std::sync::Arc;
+use std::time::Duration;
+use crate::compile_error::{CompileErrorParser, ViteCompileError};
+use crate::error::{StartViteError, ViteConfigError};
+use crate::events::ViteEvent;
+use crate::launcher::ViteLauncher;
+use crate::log_format::{LogFormat, ViteLogRecord};
+use crate::output_log::LogFile;
+use crate::package_script::PackageScript;
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::pid_file::StalePolicy;
+use crate::port_conflict::PortConflictStrategy;
+use crate::skip_spawn::SkipSpawnDetector;
+use crate::supervisor::RestartPolicy;
+use crate::vite_command::ViteCommand;
+use crate::vite_server_handle::{
+    OnExitCallback, OnReadyCallback, OnRebuildCallback, ViteServerHandle,
+};
+use crate::vite_state::{ReadyPhase, ViteReadyInfo, ViteShared, ViteState};
+use crate::vite_stdio::{ViteOutputLine, ViteStdio, ViteStream};
+use crate::vite_version::ViteVersion;
+use actix_web::body::MessageBody;
+use actix_http::Request;
+use actix_service::IntoServiceFactory;
+use actix_web::dev::{AppConfig, Response, Service, ServiceFactory};
+use actix_web::{Error, HttpServer};
+use log::{debug, error, info, warn};
+use regex::Regex;
+use url::Url;
+use crate::{
+    network_urls, output_log, pid_file, port_conflict, process_lifetime, process_priority,
+    startup_banner, vite_server_handle, vite_stdio, vite_version, which,
+};
+
+// How often, and how many times, the configured port is probed when Vite's output is not
+// piped and the `Local:` banner cannot be read (one minute in total).
+const READY_PROBE_INTERVAL: Duration = Duration::from_millis(250);
+const READY_PROBE_ATTEMPTS: u32 = 240;
+
+// How long to wait for the dev server to finish optimizing dependencies after its banner,
+// before assuming it is done anyway.
+const DEPS_OPTIMIZE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Starts a Vite server by locating the installation of the Vite command using the system's
+/// `where` or `which` command (based on OS) and spawning the server in the configured working
+/// directory.
+///
+/// # Returns
+///
+/// Returns a result containing the spawned process's [`std::process::Child`] handle if successful,
+/// or a [`StartViteError`] if an error occurs. The error converts into an [`anyhow::Error`]
+/// with `?`.
+///
+/// # Errors
+///
+/// - [`StartViteError::ExecutableNotFound`] if the `vite` command cannot be found.
+/// - [`StartViteError::WorkingDirMissing`] if the working directory does not exist.
+/// - [`StartViteError::SpawnFailed`] if the `vite` command fails to execute.
+/// - [`StartViteError::OutputCaptureFailed`] if the output of the process cannot be read.
+/// - [`StartViteError::SpawnSkipped`] if [`ProxyViteOptions::skip_spawn_when`] decided not to
+///   start Vite. There is no process to return in that case; use
+///   [`start_vite_server_managed`], which returns a no-op handle instead.
+///
+/// # Notes
+///
+/// - The working directory for Vite is set with the `VITE_WORKING_DIR` environment variable,
+///   falling back to the result of `try_find_vite_dir` or the current directory (".").
+///
+/// # Example
+/// ```no-rust
+/// let server = start_vite_server().expect("Failed to start Vite server");
+/// println!("Vite server started with PID: {}", server.id());
+/// ```
+///
+/// # Platform-Specific
+/// - On Windows, it uses `where` to find the `vite` executable.
+/// - On other platforms, it uses `which`.
+///
+/// # Clippy:
+/// You may want to allow zombie processes in your code.   
+/// `#[allow(clippy::zombie_processes)]`
+pub fn start_vite_server() -> Result<std::process::Child, StartViteError> {
+    let options = ProxyViteOptions::global();
+    if let Some(reason) = options.skip_spawn.as_ref().and_then(|detector| detector()) {
+        info!("Not starting Vite: {}", reason);
+        return Err(StartViteError::SpawnSkipped(reason));
+    }
+
+    let spec = Arc::new(SpawnSpec {
+        // The caller owns the child, so nothing would clean up a PID file, and it stays in
+        // our process group so Ctrl+C in the terminal still reaches it.
+        pid_file: false,
+        kill_on_parent_exit: false,
+        on_ready: startup_banner_on_ready(&options),
+        on_port: Some(Arc::new(update_global_port)),
+        ..SpawnSpec::from_options(&options)
+    });
+
+    let shared = ViteShared::global();
+    shared.reset(options.port);
+    shared.set_output_capacity(spec.output_buffer_lines);
+    if let Some(log_file) = &spec.log_file {
+        output_log::start(&shared, log_file);
+    }
+    let reader_spec = spec.clone();
+    spawn_vite(&spec, options.port, move |event| {
+        handle_output_event(&reader_spec, &shared, event)
+    })
+}
+
+/// Starts a Vite server like [`start_vite_server`], but returns a managed [`ViteServerHandle`].
+///
+/// The handle tracks the detected port and lifecycle state, kills the process when the
+/// last clone is dropped, and reports the exit through [`ViteServerHandle::wait_exited`]
+/// and the [`ProxyViteOptions::on_exit`] callback. While the handle's process is not
+/// running, the proxy answers with an error naming the exit status instead of a generic
+/// connection error.
+///
+/// If Vite reports that its port is in use, the configured
+/// [`port_conflict::PortConflictStrategy`] decides whether to fail or to respawn Vite on the
+/// next free port. If Vite reports another port than the configured one anyway, e.g. because
+/// its config moved it, the proxy follows it with a warning, a [`ViteEvent::PortChanged`] and
+/// both ports in the [`crate::dev_toolbar::STATUS_PATH`] status. With
+/// [`ProxyViteOptions::strict_port`], Vite is stopped instead and the handle is
+/// [`ViteState::Failed`].
+///
+/// # Example
+/// ```no-rust
+/// let handle = start_vite_server_managed()?;
+/// let exited = handle.wait_exited();
+/// tokio::spawn(async move {
+///     let status = exited.await;
+///     log::warn!("Vite exited with {}", status);
+/// });
+/// ```
+pub fn start_vite_server_managed() -> Result<ViteServerHandle, StartViteError> {
+    let options = ProxyViteOptions::global();
+    let spec = SpawnSpec {
+        on_ready: startup_banner_on_ready(&options),
+        on_port: Some(Arc::new(update_global_port)),
+        ..SpawnSpec::from_options(&options)
+    };
+
+    let shared = ViteShared::global();
+    let handle = ViteServerHandle::spawn("default", shared.clone(), spec).inspect_err(|_| {
+        shared.set_state(ViteState::Idle);
+    })?;
+    handle.set_default();
+    Ok(handle)
+}
+
+/// Starts a Vite server configured by `options` without touching the global options.
+///
+/// Unlike [`start_vite_server_managed`], the working directory, port, arguments, log level
+/// and callbacks are taken from `options` as given, and the port Vite reports is only
+/// recorded on the returned handle, see [`ViteServerHandle::port`]. This allows running
+/// several differently configured Vite processes side by side.
+///
+/// Because the global options are left alone, `configure_vite()` does not proxy to a
+/// process started this way unless its port is also set there.
+///
+/// # Example
+/// ```no-rust
+/// let docs = start_vite_server_with(&ProxyViteOptions::new().working_directory("./docs"))?;
+/// let app = start_vite_server_with(
+///     &ProxyViteOptions::new().working_directory("./app").port(5174),
+/// )?;
+/// println!("docs on {:?}, app on {:?}", docs.port(), app.port());
+/// ```
+///
+/// The global options and state stay as they were. Vite's output is read past lines that
+/// only look like its banner, whatever they hold.
+pub fn start_vite_server_with(
+    options: &ProxyViteOptions,
+) -> Result<ViteServerHandle, StartViteError> {
+    let spec = SpawnSpec::from_options(options);
+    ViteServerHandle::spawn("default", ViteShared::new(options.port), spec)
+}
+
+/// Blocks until the Vite process started with the global options reports the port it
+/// listens on, and returns it.
+///
+/// Call it between [`start_vite_server_managed`] or [`start_vite_server`] and
+/// `HttpServer::bind`, so the server only takes requests once the proxy knows where to send
+/// them, e.g. when Vite may move to another port. It fails with the last lines Vite printed
+/// if the port is not known within `timeout`, if Vite exits first, or if it is ready without
+/// a port, like `vite build --watch`; a timeout downcasts to
+/// [`StartViteError::ReadyTimeout`] with those lines. Use [`wait_for_vite_port_async`] from
+/// async code.
+///
+/// # Example
+/// ```no-rust
+/// let vite = start_vite_server_managed()?;
+/// let port = wait_for_vite_port(Duration::from_secs(30))?;
+/// info!("Vite is listening on port {}", port);
+/// let server = HttpServer::new(|| App::new().configure_vite())
+///     .bind("127.0.0.1:8080")?
+///     .run();
+/// ```
+pub fn wait_for_vite_port(timeout: Duration) -> anyhow::Result<u16> {
+    // The wait runs on its own runtime, which cannot be started on a runtime thread.
+    let wait = wait_for_vite_port_async(timeout);
+    std::thread::spawn(move || {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?
+            .block_on(wait)
+    })
+    .join()
+    .map_err(|_| anyhow::Error::msg("The thread waiting for Vite's port panicked"))?
+}
+
+/// Like [`wait_for_vite_port`], but returns a future that resolves with the port.
+pub fn wait_for_vite_port_async(
+    timeout: Duration,
+) -> impl Future<Output = anyhow::Result<u16>> + Send + 'static {
+    let shared = ViteShared::global();
+    async move {
+        let mut state = shared.subscribe();
+        let waited = actix_web::rt::time::timeout(timeout, async {
+            loop {
+                let current = state.borrow_and_update().clone();
+                match current {
+                    ViteState::Ready | ViteState::FullyReady => return Ok(shared.port()),
+                    ViteState::Exited(status) | ViteState::Failed { status, .. } => {
+                        return Err(format!(
+                            "Vite exited with {} before it reported its port",
+                            status
+                        ));
+                    }
+                    _ => {}
+                }
+                // The sender lives in `shared`, which this future owns, so this never fails.
+                let _ = state.changed().await;
+            }
+        })
+        .await;
+        let timed_out = waited.is_err();
+        let problem = match waited {
+            Ok(Ok(Some(port))) => return Ok(port),
+            Ok(Ok(None)) => String::from("Vite is ready but did not report a port"),
+            Ok(Err(problem)) => problem,
+            Err(_) => format!("Vite did not report its port within {:?}", timeout),
+        };
+        let output = shared.recent_output();
+        let message = if output.is_empty() {
+            format!("{}; it printed nothing", problem)
+        } else {
+            format!("{}; its last output was:\n{}", problem, output.join("\n"))
+        };
+        // A timeout carries the output as a `StartViteError::ReadyTimeout` to downcast to.
+        if timed_out {
+            let error = StartViteError::ReadyTimeout { captured: output };
+            return Err(anyhow::Error::new(error).context(message));
+        }
+        Err(anyhow::Error::msg(message))
+    }
+}
+
+/// Runs an Actix server and shuts the managed Vite process down once the server has stopped.
+///
+/// The returned future resolves after both the server and Vite are gone, so the usual
+/// `HttpServer::new(...).bind(...)?.run().await` becomes
+/// `serve_with_vite(HttpServer::new(...).bind(...)?.run(), vite).await`.
+///
+/// The server stops when [`actix_web::dev::ServerHandle::stop`] is called, or on
+/// SIGINT/SIGTERM. Signals are also watched here so Vite is stopped even if the server was
+/// built with `disable_signals()`.
+///
+/// # Example
+/// ```no-rust
+/// let vite = start_vite_server_managed()?;
+/// let server = HttpServer::new(|| App::new().configure_vite())
+///     .bind("127.0.0.1:8080")?
+///     .run();
+/// serve_with_vite(server, vite).await?;
+/// ```
+pub async fn serve_with_vite(
+    server: actix_web::dev::Server,
+    vite: ViteServerHandle,
+) -> std::io::Result<()> {
+    let server_handle = server.handle();
+    let mut server = std::pin::pin!(server);
+
+    let result = tokio::select! {
+        result = &mut server => result,
+        _ = shutdown_signal() => {
+            info!("Shutdown signal received, stopping the server");
+            // The server future runs the stop command, so keep polling it while stopping.
+            let (_, result) = tokio::join!(server_handle.stop(true), &mut server);
+            result
+        }
+    };
+
+    // Stopping Vite waits for the process to exit, so keep it off the async workers.
+    match tokio::task::spawn_blocking(move || vite.shutdown()).await {
+        Ok(Ok(())) => debug!("Vite was stopped after the server shut down"),
+        Ok(Err(e)) => error!("Failed to stop Vite: {}", e),
+        Err(e) => error!("Failed to stop Vite: {}", e),
+    }
+
+    result
+}
+
+/// Builds `options`, starts Vite in dev mode, and runs an Actix server on `addr` until it
+/// is stopped, then stops Vite too.
+///
+/// This is the whole usual `main` in one call: the options are built and checked like
+/// [`ViteAppFactory::try_configure_vite`] does, the managed Vite process is started with
+/// [`start_vite_server_managed`] in [`ProxyViteOptions::dev_mode`], the server is bound and
+/// runs with [`serve_with_vite`], and the startup banner with the app's URL and Vite's status
+/// is logged, whatever [`ProxyViteOptions::print_startup_banner`] says. `app_factory` is what
+/// `HttpServer::new` takes; use [`serve_with`] to configure the server further.
+///
+/// # Example
+/// ```no-rust
+/// vite_actix::serve(|| App::new().configure_vite(), "127.0.0.1:8080", ProxyViteOptions::new())
+///     .await
+/// ```
+///
+/// [`ViteAppFactory::try_configure_vite`]: crate::vite_app_factory::ViteAppFactory::try_configure_vite
+pub async fn serve<F, I, S, B>(
+    app_factory: F,
+    addr: impl std::net::ToSocketAddrs,
+    options: ProxyViteOptions,
+) -> anyhow::Result<()>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S, Request>,
+    S: ServiceFactory<Request, Config = AppConfig> + 'static,
+    S::Error: Into<Error> + 'static,
+    S::InitError: std::fmt::Debug,
+    S::Response: Into<Response<B>> + 'static,
+    <S::Service as Service<Request>>::Future: 'static,
+    S::Service: 'static,
+    B: MessageBody + 'static,
+{
+    serve_with(app_factory, addr, options, |server| server).await
+}
+
+/// Like [`serve`], with `configure` applied to the `HttpServer` before it is bound, e.g. to
+/// set the number of workers or a shutdown timeout.
+///
+/// # Example
+/// ```no-rust
+/// vite_actix::serve_with(app, "0.0.0.0:8080", options, |server| {
+///     server.workers(2).shutdown_timeout(5)
+/// })
+/// .await
+/// ```
+pub async fn serve_with<F, I, S, B>(
+    app_factory: F,
+    addr: impl std::net::ToSocketAddrs,
+    options: ProxyViteOptions,
+    configure: impl FnOnce(HttpServer<F, I, S, B>) -> HttpServer<F, I, S, B>,
+) -> anyhow::Result<()>
+where
+    F: Fn() -> I + Send + Clone + 'static,
+    I: IntoServiceFactory<S, Request>,
+    S: ServiceFactory<Request, Config = AppConfig> + 'static,
+    S::Error: Into<Error> + 'static,
+    S::InitError: std::fmt::Debug,
+    S::Response: Into<Response<B>> + 'static,
+    <S::Service as Service<Request>>::Future: 'static,
+    S::Service: 'static,
+    B: MessageBody + 'static,
+{
+    // Known before the build, which fails if Vite would get the same port.
+    let actix_port = std::net::ToSocketAddrs::to_socket_addrs(&addr)
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .map(|addr| addr.port())
+        .filter(|port| *port != 0);
+    let options = match actix_port {
+        Some(port) if options.actix_port.is_none() => options.actix_port(port),
+        _ => options,
+    };
+    options.print_startup_banner(true).build()?;
+    let options = ProxyViteOptions::global();
+    let problems = options.problems(options.dev_mode, false);
+    if !problems.is_empty() {
+        return Err(ViteConfigError { problems }.into());
+    }
+
+    // Started first, so Vite gets ready while the server binds.
+    let vite = match options.dev_mode {
+        true => Some(start_vite_server_managed()?),
+        false => None,
+    };
+
+    let server = configure(HttpServer::new(app_factory)).bind(addr)?;
+    for addr in server.addrs() {
+        report_server_addr(addr);
+    }
+    let server = server.run();
+
+    match vite {
+        Some(vite) => serve_with_vite(server, vite).await?,
+        None => {
+            startup_banner::record_vite_ready(None, Duration::ZERO);
+            server.await?
+        }
+    }
+    Ok(())
+}
+
+// Resolves on SIGINT, or SIGTERM on unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    _ = terminate.recv() => {},
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Reports the address the Actix server is bound to, for the startup banner.
+///
+/// With [`ProxyViteOptions::print_startup_banner`] enabled, the banner is logged once both
+/// this address is known and Vite is ready. Actix does not expose the bound address from a
+/// running server, so pass it from [`actix_web::HttpServer::addrs`] before calling `run()`.
+///
+/// # Example
+/// ```no-rust
+/// let server = HttpServer::new(|| App::new().configure_vite()).bind("127.0.0.1:8080")?;
+/// for addr in server.addrs() {
+///     vite_actix::report_server_addr(addr);
+/// }
+/// server.run().await?;
+/// ```
+pub fn report_server_addr(addr: std::net::SocketAddr) {
+    startup_banner::record_server_addr(addr);
+}
+
+/// The URLs the Actix server on `actix_port` is reachable at from other devices on the
+/// network, e.g. for opening the app on a phone.
+///
+/// These are the `Network:` URLs of the Vite process started with the global options, which
+/// are only printed when Vite runs with `--host`, with the port swapped for `actix_port`.
+/// The Actix server has to be bound to those interfaces too, e.g. to `0.0.0.0`.
+///
+/// # Example
+/// ```no-rust
+/// for url in vite_actix::lan_urls(8080) {
+///     println!("Open {} on your phone", url);
+/// }
+/// ```
+pub fn lan_urls(actix_port: u16) -> Vec<Url> {
+    network_urls::with_port(&ViteShared::global().network_urls(), actix_port)
+}
+
+// The on_ready callback of the default Vite process, extended to feed the startup banner.
+fn startup_banner_on_ready(options: &ProxyViteOptions) -> Option<OnReadyCallback> {
+    if !options.print_startup_banner {
+        return options.on_ready.clone();
+    }
+
+    let spawned = std::time::Instant::now();
+    let on_ready = options.on_ready.clone();
+    Some(Arc::new(move |info: ViteReadyInfo| {
+        startup_banner::record_vite_ready(info.port, spawned.elapsed());
+        if let Some(on_ready) = &on_ready {
+            on_ready(info);
+        }
+    }))
+}
+
+// Point the proxy at the port Vite reported.
+fn update_global_port(port: u16) {
+    if let Err(e) = ProxyViteOptions::update_port(port) {
+        debug!("Failed to update Vite port to {}: {}", port, e);
+    } else {
+        debug!("Successfully updated Vite port to {}", port);
+    }
+}
+
+/// Locates the `vite` executable using the system's `where` or `which` command.
+///
+/// Returns the path of the last match reported by the lookup command, or
+/// [`StartViteError::ExecutableNotFound`] listing the `PATH` directories that were searched.
+pub(crate) fn find_vite_executable() -> Result<String, StartViteError> {
+    let Some(vite) = which("vite")? else {
+        let searched = std::env::var_os("PATH")
+            .map(|path| std::env::split_paths(&path).collect())
+            .unwrap_or_default();
+        let err = StartViteError::ExecutableNotFound { searched };
+        error!("{}", err);
+        return Err(err);
+    };
+
+    debug!("found vite at: {:?}", vite); // Log the found Vite path for debugging.
+
+    Ok(vite)
+}
+
+// Everything needed to (re)spawn a Vite process.
+pub(crate) struct SpawnSpec {
+    pub working_directory: String,
+    pub port: Option<u16>,
+    pub command: ViteCommand,
+    pub args: Vec<String>,
+    pub log_level: Option<log::Level>,
+    pub log_format: LogFormat,
+    pub strict_port: bool,
+    pub port_conflict: PortConflictStrategy,
+    pub pid_file: bool,
+    pub stale_policy: StalePolicy,
+    pub stdio: ViteStdio,
+    pub skip_spawn: Option<SkipSpawnDetector>,
+    pub package_script: bool,
+    pub kill_on_parent_exit: bool,
+    pub low_priority: bool,
+    pub restart_policy: Option<RestartPolicy>,
+    pub log_file: Option<LogFile>,
+    pub output_buffer_lines: usize,
+    pub ready_phase: ReadyPhase,
+    pub on_exit: Option<OnExitCallback>,
+    pub on_ready: Option<OnReadyCallback>,
+    pub on_rebuild: Option<OnRebuildCallback>,
+    // Extra work to do when the banner reports a port, e.g. updating the global options
+    pub on_port: Option<Arc<dyn Fn(u16) + Send + Sync>>,
+}
+
+impl SpawnSpec {
+    // Describe the Vite process configured by `options`
+    pub(crate) fn from_options(options: &ProxyViteOptions) -> Self {
+        Self {
+            working_directory: options.working_directory.clone(),
+            port: options.port,
+            command: options.command,
+            args: options.args.clone(),
+            log_level: options.log_level,
+            log_format: options.vite_log_format,
+            strict_port: options.strict_port,
+            port_conflict: options.port_conflict,
+            pid_file: options.pid_file,
+            stale_policy: options.stale_policy,
+            stdio: options.stdio,
+            skip_spawn: options.skip_spawn.clone(),
+            package_script: options.package_script,
+            kill_on_parent_exit: options.kill_on_parent_exit,
+            low_priority: options.low_priority,
+            restart_policy: options.restart_policy,
+            log_file: options.log_file.as_ref().map(|path| LogFile {
+                path: path.clone(),
+                max_size: options.log_file_max_size,
+                max_files: options.log_file_max_files,
+            }),
+            output_buffer_lines: options.output_buffer_lines,
+            ready_phase: options.ready_phase,
+            on_exit: options.on_exit.clone(),
+            on_ready: options.on_ready.clone(),
+            on_rebuild: options.on_rebuild.clone(),
+            on_port: None,
+        }
+    }
+}
+
+/// Something the output reader noticed in a line printed by Vite.
+pub(crate) enum ViteOutputEvent {
+    /// The process was spawned with the given id, before any of its output was read.
+    Spawned(u32),
+    /// Vite reported the port it is listening on in its `Local:` banner line.
+    Port(u16),
+    /// Vite reported a URL it can be reached at from other devices in a `Network:` line.
+    NetworkUrl(Url),
+    /// Vite reported that the port it tried to bind is already in use.
+    PortConflict(u16),
+    /// `vite build` finished a build in the given time.
+    Built(Duration),
+    /// Vite printed a line.
+    Line(ViteOutputLine),
+    /// `vite --version` reported the version of the Vite being started.
+    Version(ViteVersion),
+    /// Vite printed an error block for a module it failed to transform.
+    CompileError(ViteCompileError),
+    /// Vite started (re-)optimizing dependencies, e.g. `Forced re-optimization of dependencies`.
+    OptimizingDeps,
+    /// Vite finished optimizing dependencies, e.g. `✨ new dependencies optimized: vue`.
+    DepsOptimized,
+    /// Vite sent the browser an `hmr update` or a `page reload`.
+    HmrUpdate,
+}
+
+// React to an output event of the process described by `spec`, recording it in `shared`.
+pub(crate) fn handle_output_event(
+    spec: &Arc<SpawnSpec>,
+    shared: &Arc<ViteShared>,
+    event: ViteOutputEvent,
+) {
+    match event {
+        ViteOutputEvent::Spawned(pid) => {
+            shared.set_pid(pid);
+            shared.publish(ViteEvent::Spawned { pid });
+            if let Some(old_pid) = shared.take_restarted_from() {
+                shared.publish(ViteEvent::Restarted {
+                    old_pid,
+                    new_pid: pid,
+                });
+            }
+        }
+        ViteOutputEvent::Port(port) => {
+            if let Some(configured) = shared
+                .requested_port()
+                .filter(|configured| *configured != port)
+            {
+                if spec.strict_port {
+                    let actual = port;
+                    error!("{}", StartViteError::PortMismatch { configured, actual });
+                    shared.reject_port(configured, port);
+                    return;
+                }
+                warn!(
+                    "Vite listens on port {} instead of the configured port {}, forwarding to it instead",
+                    port, configured
+                );
+            }
+            let was_ready = shared.state().is_ready();
+            let moved = shared.port() != Some(port);
+            // The proxy targets the new port before anyone waiting for `Ready` looks.
+            if let Some(on_port) = &spec.on_port {
+                on_port(port);
+            }
+            if moved {
+                shared.publish(ViteEvent::PortChanged(port));
+            }
+            // Before `Ready`, or a shutdown right after it could find the PID file already
+            // removed and have it written again.
+            if spec.pid_file
+                && let Some(pid) = shared.pid()
+            {
+                vite_server_handle::write_pid_file(spec, pid, Some(port));
+            }
+            shared.set_port(port);
+            if !was_ready {
+                if spec.command.is_dev_server() {
+                    spawn_deps_probe(spec.clone(), shared.clone(), port);
+                }
+                if spec.ready_phase == ReadyPhase::Ready || !spec.command.is_dev_server() {
+                    notify_ready(spec, shared, ViteReadyInfo { port: Some(port) });
+                }
+            }
+        }
+        ViteOutputEvent::NetworkUrl(url) => {
+            debug!("Vite is reachable on the network at {}", url);
+            shared.add_network_url(url);
+        }
+        ViteOutputEvent::OptimizingDeps => {
+            debug!("Vite is optimizing dependencies");
+            shared.set_optimizing_deps(true);
+        }
+        ViteOutputEvent::DepsOptimized => {
+            shared.set_optimizing_deps(false);
+            mark_fully_ready(spec, shared);
+        }
+        ViteOutputEvent::PortConflict(port) => {
+            warn!("Vite reported that port {} is already in use", port);
+            shared.record_port_conflict(port);
+        }
+        ViteOutputEvent::Line(line) => shared.record_output(line),
+        ViteOutputEvent::HmrUpdate => shared.record_hmr_update(),
+        ViteOutputEvent::CompileError(error) => {
+            warn!(
+                "Vite failed to compile {}: {}",
+                error.file.as_deref().unwrap_or("a module"),
+                error.message
+            );
+            shared.record_compile_error(error);
+        }
+        ViteOutputEvent::Version(version) => {
+            // Respawns detect the version again; only the first or a changed one is news.
+            if shared.set_vite_version(version) {
+                vite_version::warn_if_untested(version);
+            }
+        }
+        ViteOutputEvent::Built(duration) => {
+            debug!("Vite finished a build in {:?}", duration);
+            if shared.mark_ready() {
+                notify_ready(spec, shared, ViteReadyInfo { port: None });
+            }
+            if let Some(on_rebuild) = &spec.on_rebuild {
+                on_rebuild(duration);
+            }
+        }
+    }
+}
+
+// Move a dev server to `FullyReady`, firing `on_ready` if that is the phase it waits for.
+fn mark_fully_ready(spec: &SpawnSpec, shared: &ViteShared) {
+    if shared.mark_fully_ready() {
+        debug!("Vite is done optimizing dependencies");
+        if spec.ready_phase == ReadyPhase::FullyReady {
+            let port = shared.port();
+            notify_ready(spec, shared, ViteReadyInfo { port });
+        }
+    }
+}
+
+// Tell event subscribers and the `on_ready` callback that the process is ready.
+fn notify_ready(spec: &SpawnSpec, shared: &ViteShared, info: ViteReadyInfo) {
+    shared.publish(ViteEvent::Ready(info));
+    if let Some(on_ready) = &spec.on_ready {
+        on_ready(info);
+    }
+}
+
+// Probe the dev server on `port` until it serves modules quickly, then mark it fully ready.
+//
+// Vite only prints a line when the dependencies it found changed, so the probe is what
+// usually gets there. It stops once the state moves on, e.g. because of that line or an exit,
+// and assumes Vite is done after `DEPS_OPTIMIZE_TIMEOUT`.
+fn spawn_deps_probe(spec: Arc<SpawnSpec>, shared: Arc<ViteShared>, port: u16) {
+    std::thread::spawn(move || {
+        let started = std::time::Instant::now();
+        while started.elapsed() < DEPS_OPTIMIZE_TIMEOUT {
+            if shared.state() != ViteState::Ready {
+                return;
+            }
+            if !shared.optimizing_deps() && vite_stdio::probe_module(port) {
+                mark_fully_ready(&spec, &shared);
+                return;
+            }
+            std::thread::sleep(READY_PROBE_INTERVAL);
+        }
+        debug!(
+            "Vite did not finish optimizing dependencies within {:?}, assuming it is done",
+            DEPS_OPTIMIZE_TIMEOUT
+        );
+        mark_fully_ready(&spec, &shared);
+    });
+}
+
+// Whether a line reports that Vite started or finished optimizing dependencies.
+fn parse_deps_optimization(line: &str) -> Option<ViteOutputEvent> {
+    if line.contains("new dependencies optimized")
+        || line.contains("optimized dependencies changed")
+    {
+        Some(ViteOutputEvent::DepsOptimized)
+    } else if line.contains("Forced re-optimization of dependencies")
+        || line.contains("optimizing dependencies...")
+        || line.contains("Pre-bundling dependencies")
+    {
+        Some(ViteOutputEvent::OptimizingDeps)
+    } else {
+        None
+    }
+}
+
+// Whether a line reports that Vite updated the modules in the browser or reloaded the page,
+// e.g. `10:21:33 AM [vite] (client) hmr update /src/App.tsx`.
+fn is_hmr_update(line: &str) -> bool {
+    line.contains("[vite]") && (line.contains(" hmr update ") || line.contains(" page reload "))
+}
+
+// Parse the duration out of a `✓ built in 1.23s` / `built in 456ms` line.
+fn parse_build_duration(line: &str) -> Option<Duration> {
+    static BUILT_IN: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let regex = BUILT_IN.get_or_init(|| {
+        Regex::new(r"built in (?P<value>\d+(?:\.\d+)?)(?P<unit>ms|s)\b").unwrap()
+    });
+    let caps = regex.captures(line)?;
+    let value: f64 = caps.name("value")?.as_str().parse().ok()?;
+    match caps.name("unit")?.as_str() {
+        "ms" => Some(Duration::from_secs_f64(value / 1000.0)),
+        _ => Some(Duration::from_secs_f64(value)),
+    }
+}
+
+/// Spawns a Vite process and wires up its stdout and stderr for port detection and log forwarding.
+///
+/// This is the shared implementation behind [`start_vite_server`] and the managed
+/// [`ViteServerHandle`]. Every Vite process gets its own output pipeline, a task on the
+/// current Tokio runtime or a thread with a runtime of its own outside of one, so multiple
+/// instances are parsed independently; `on_event` is invoked for each banner or port
+/// conflict line the readers recognize.
+pub(crate) fn spawn_vite(
+    spec: &SpawnSpec,
+    port: Option<u16>,
+    on_event: impl Fn(ViteOutputEvent) + Send + Sync + 'static,
+) -> Result<std::process::Child, StartViteError> {
+    let working_directory = std::path::Path::new(&spec.working_directory);
+    if !working_directory.is_dir() {
+        return Err(StartViteError::WorkingDirMissing(
+            working_directory.to_path_buf(),
+        ));
+    }
+
+    let log_level = spec.log_level;
+    let log_format = spec.log_format;
+
+    // `vite build` does not start a server, so it takes no port flags.
+    let mut flags = Vec::new();
+    if spec.command.is_server() {
+        if let Some(port) = port {
+            flags.push("--port".to_string());
+            flags.push(port.to_string());
+        }
+        if spec.strict_port {
+            flags.push("--strictPort".to_string());
+        }
+    }
+    flags.extend(spec.args.iter().cloned());
+
+    // Prefer the project's own dev script, which may pass flags of its own, over the binary.
+    let dev_script = if spec.package_script && spec.command.is_dev_server() {
+        PackageScript::find(working_directory)
+    } else {
+        None
+    };
+    // The vite the project resolves to is also asked for its version, even when a package
+    // script starts it.
+    let (mut vite_process, launcher) = match dev_script {
+        Some((manager, script)) => {
+            debug!(
+                "Starting Vite through the '{}' script of package.json: {}",
+                script.name, script.command
+            );
+            let mut command = std::process::Command::new(manager.program());
+            command.args(manager.run_args(&script.name, &script.merge_flags(&flags)));
+            (command, ViteLauncher::resolve(working_directory).ok())
+        }
+        None => {
+            let launcher = ViteLauncher::resolve(working_directory)?;
+            let mut command = launcher.command();
+            command.args(spec.command.subcommand());
+            command.args(&flags);
+            (command, Some(launcher))
+        }
+    };
+    vite_process.current_dir(&spec.working_directory);
+    if spec.kill_on_parent_exit {
+        process_lifetime::configure(&mut vite_process);
+    }
+    if spec.low_priority {
+        process_priority::lower_priority(&mut vite_process);
+    }
+    if spec.stdio.is_piped() {
+        vite_process.stdout(std::process::Stdio::piped());
+        vite_process.stderr(std::process::Stdio::piped());
+    }
+
+    let vite_process = if spec.kill_on_parent_exit {
+        process_lifetime::spawn(vite_process)
+    } else {
+        vite_process.spawn()
+    };
+    let mut vite_process = vite_process.map_err(StartViteError::SpawnFailed)?;
+
+    let on_event = Arc::new(on_event);
+    on_event(ViteOutputEvent::Spawned(vite_process.id()));
+
+    // Ask the same Vite for its version on the side, so startup is not held up by it.
+    if let Some(launcher) = launcher {
+        let working_directory = working_directory.to_path_buf();
+        let on_event = on_event.clone();
+        std::thread::spawn(move || {
+            if let Some(version) = vite_version::detect(&launcher, &working_directory) {
+                on_event(ViteOutputEvent::Version(version));
+            }
+        });
+    }
+
+    // Vite writes to our terminal, so there is no banner to read; watch the port instead.
+    if !spec.stdio.is_piped() {
+        if let Some(port) = port.or_else(vite_stdio::env_port)
+            && spec.command.is_server()
+        {
+            spawn_ready_probe(vite_process.id(), port, move |event| on_event(event));
+        }
+        return Ok(vite_process);
+    }
+
+    // Take both output streams so they can be read by the log pipeline.
+    let (Some(vite_stdout), Some(vite_stderr)) =
+        (vite_process.stdout.take(), vite_process.stderr.take())
+    else {
+        // Without its output there is no way to tell when Vite is ready, so don't leak it.
+        let _ = vite_process.kill();
+        let _ = vite_process.wait();
+        return Err(StartViteError::OutputCaptureFailed);
+    };
+
+    // Read both streams concurrently; every line is logged and then checked for events.
+    let pipeline = async move {
+        let stdout = tokio::process::ChildStdout::from_std(vite_stdout);
+        let stderr = tokio::process::ChildStderr::from_std(vite_stderr);
+        match (stdout, stderr) {
+            (Ok(stdout), Ok(stderr)) => {
+                tokio::join!(
+                    read_vite_output(stdout, ViteStream::Stdout, log_level, log_format, on_event.as_ref()),
+                    read_vite_output(stderr, ViteStream::Stderr, log_level, log_format, on_event.as_ref()),
+                );
+            }
+            (Err(err), _) | (_, Err(err)) => {
+                error!("Failed to read output from Vite process: {}", err);
+            }
+        }
+        debug!("Vite output streams closed, exiting log pipeline");
+    };
+
+    // Run the pipeline as a task on the current Tokio runtime if there is one. Otherwise
+    // run it on a single dedicated thread with its own runtime.
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        handle.spawn(pipeline);
+    } else {
+        std::thread::spawn(move || {
+            match tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+            {
+                Ok(rt) => rt.block_on(pipeline),
+                Err(err) => error!("Failed to create Tokio runtime for Vite output: {}", err),
+            }
+        });
+    }
+
+    // Return the process, which will continue running and logging output
+    Ok(vite_process)
+}
+
+// Probe `port` until Vite answers over HTTP, then report it like the `Local:` banner line.
+//
+// Gives up when the process with `pid` exits or after `READY_PROBE_ATTEMPTS` probes.
+pub(crate) fn spawn_ready_probe(
+    pid: u32,
+    port: u16,
+    on_event: impl Fn(ViteOutputEvent) + Send + Sync + 'static,
+) {
+    std::thread::spawn(move || {
+        for _ in 0..READY_PROBE_ATTEMPTS {
+            if !pid_file::is_process_alive(pid) {
+                break;
+            }
+            if vite_stdio::probe_http(port) {
+                on_event(ViteOutputEvent::Port(port));
+                return;
+            }
+            std::thread::sleep(READY_PROBE_INTERVAL);
+        }
+        debug!("Stopped waiting for Vite to answer on port {}", port);
+    });
+}
+
+// Read one of Vite's output streams line by line, logging every line at `log_level` in
+// `log_format` and reporting it, as well as recognized banner, build and port conflict
+// lines, through `on_event`.
+pub(crate) async fn read_vite_output(
+    stream: impl tokio::io::AsyncRead + Unpin,
+    source: ViteStream,
+    log_level: Option<log::Level>,
+    log_format: LogFormat,
+    on_event: &(impl Fn(ViteOutputEvent) + ?Sized),
+) {
+    use tokio::io::AsyncBufReadExt;
+    let mut reader = tokio::io::BufReader::new(stream);
+    let mut buffer = Vec::new();
+    let mut compile_errors = CompileErrorParser::default();
+    // Vite prints its `Network:` lines right after the `Local:` one, so the port is held
+    // back until the lines written with it are read and `on_ready` can see those URLs.
+    let mut pending_port = None;
+    let log_json = |record: ViteLogRecord| {
+        if let Some(level) = log_level
+            && log_format == LogFormat::Json
+        {
+            log::log!(level, "{}", record.to_json());
+        }
+    };
+    let report_error = |error: ViteCompileError| {
+        log_json(ViteLogRecord::from_compile_error(&error));
+        on_event(ViteOutputEvent::CompileError(error));
+    };
+
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer).await {
+            Ok(0) => break, // End of stream, the process has likely terminated
+            Ok(_) => {
+                let raw = String::from_utf8_lossy(&buffer);
+                let line = raw.trim();
+                if let Some(level) = log_level
+                    && log_format == LogFormat::Text
+                {
+                    log::log!(level, "{}", line);
+                }
+                // Error blocks are recognized by their indentation, so keep it.
+                let plain = String::from_utf8_lossy(&strip_ansi_escapes::strip(raw.trim_end()))
+                    .into_owned();
+                match parse_output_line(plain.trim()) {
+                    Some(ViteOutputEvent::Port(port)) => pending_port = Some(port),
+                    Some(event) => on_event(event),
+                    None => {}
+                }
+                if let Some(error) = compile_errors.push(&plain) {
+                    report_error(error);
+                }
+                // The lines of an error block are logged as one record once it ends.
+                if !compile_errors.is_collecting() {
+                    log_json(ViteLogRecord::parse(line));
+                }
+                // Vite writes an error block or its banner at once, so nothing left to read
+                // means it is over.
+                if reader.buffer().is_empty() {
+                    if let Some(error) = compile_errors.finish() {
+                        report_error(error);
+                    }
+                    if let Some(port) = pending_port.take() {
+                        on_event(ViteOutputEvent::Port(port));
+                    }
+                }
+                on_event(ViteOutputEvent::Line(ViteOutputLine {
+                    stream: source,
+                    line: plain,
+                    at: std::time::SystemTime::now(),
+                }));
+            }
+            Err(err) => {
+                error!("Failed to read line from Vite process: {}", err);
+                break;
+            }
+        }
+    }
+    if let Some(error) = compile_errors.finish() {
+        report_error(error);
+    }
+    if let Some(port) = pending_port {
+        on_event(ViteOutputEvent::Port(port));
+    }
+}
+
+// Recognize the lines of Vite's output that the crate reacts to, given without their ANSI
+// escape codes, which `read_vite_output` strips.
+fn parse_output_line(line: &str) -> Option<ViteOutputEvent> {
+    // `vite` and `vite preview` both print a `Local:` line, but depending on the version and
+    // host configuration the URL may use `localhost` or the loopback address. The label has
+    // to be followed directly by the URL so other lines mentioning "Local" are ignored.
+    static LOCAL_URL: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+    let regex = LOCAL_URL.get_or_init(|| {
+        Regex::new(r"\bLocal:\s+http://(?:localhost|127\.0\.0\.1):(?P<port>\d{1,5})\b").unwrap()
+    });
+
+    // A line that looks like the banner but does not parse falls through to the other checks
+    // instead of taking the reader down with it.
+    if let Some(port) = regex
+        .captures(line)
+        .and_then(|caps| caps.name("port")?.as_str().parse::<u16>().ok())
+    {
+        Some(ViteOutputEvent::Port(port))
+    } else if let Some(url) = network_urls::detect_network_url(line) {
+        Some(ViteOutputEvent::NetworkUrl(url))
+    } else if let Some(port) = port_conflict::detect_port_conflict(line) {
+        Some(ViteOutputEvent::PortConflict(port))
+    } else if let Some(event) = parse_deps_optimization(line) {
+        Some(event)
+    } else if is_hmr_update(line) {
+        Some(ViteOutputEvent::HmrUpdate)
+    } else {
+        parse_build_duration(line).map(ViteOutputEvent::Built)
+    }
+}
@@ -0,0 +1,292 @@
+//! Server-side rendering in dev mode, enabled with the `ssr` feature.
+//!
+//! [`DevSsr`] starts a small Node script next to the Vite dev server. It runs Vite in
+//! middleware mode and loads the server entry through Vite's module runner, so edits to the
+//! render code apply on the next request, without restarting anything. The rendered page
+//! can go through Vite's HTML transforms too, by giving a template with an
+//! `<!--app-html-->` placeholder, like in Vite's SSR guide.
+//!
+//! The entry exports a `render(url)` function, as `render` or as the default export,
+//! returning the HTML of the app, or `{ html, head }` to fill an `<!--app-head-->`
+//! placeholder as well.
+//!
+//! This is for development only; in production, render with the built server entry. Assets
+//! keep going through the proxy of `configure_vite`.
+//!
+//! # Example
+//! ```no-rust
+//! let ssr = web::Data::new(DevSsr::start_with_template("/src/entry-server.tsx", "index.html")?);
+//! HttpServer::new(move || {
+//!     App::new()
+//!         .app_data(ssr.clone())
+//!         .route("/", web::get().to(render_page))
+//!         .configure_vite()
+//! })
+//!
+//! async fn render_page(req: HttpRequest, ssr: web::Data<DevSsr>) -> Result<HttpResponse, SsrError> {
+//!     let page = ssr.render(&req.uri().to_string()).await?;
+//!     Ok(HttpResponse::Ok().content_type("text/html; charset=utf-8").body(page.html))
+//! }
+//! ```
+
+use crate::assets::escape;
+use crate::proxy_vite_options::ProxyViteOptions;
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use anyhow::Context;
+use awc::Client;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use std::fmt::{self, Display, Formatter};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+use std::sync::mpsc;
+use std::time::Duration;
+
+// The Node script, written next to the project's node_modules so that it finds its `vite`.
+const SIDECAR: &str = include_str!("ssr_sidecar.mjs");
+const SIDECAR_PATH: &str = "node_modules/.vite-actix/ssr.mjs";
+
+// What the script prints once it listens, followed by the port.
+const LISTENING: &str = "vite-actix-ssr listening on ";
+
+// How long Vite gets to start in middleware mode.
+const START_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The largest page accepted from the renderer.
+const MAX_PAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// A page rendered by [`DevSsr::render`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+pub struct SsrPage {
+    /// The rendered HTML, inside the transformed template if there is one.
+    pub html: String,
+    /// The URLs of the modules and stylesheets the entry imports, directly or not.
+    #[serde(default)]
+    pub preloads: Vec<String>,
+}
+
+impl SsrPage {
+    /// `<link rel="stylesheet">` tags for the stylesheets of [`SsrPage::preloads`] and
+    /// `<link rel="modulepreload">` tags for the modules, one per line, to add to the head
+    /// of the page so styles apply before the client script runs.
+    pub fn preload_tags(&self) -> String {
+        self.preloads
+            .iter()
+            .map(|url| {
+                let path = url.split('?').next().unwrap_or_default();
+                if path.ends_with(".css") {
+                    format!("<link rel=\"stylesheet\" href=\"{}\">", escape(url))
+                } else {
+                    format!("<link rel=\"modulepreload\" href=\"{}\">", escape(url))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Why [`DevSsr::render`] failed.
+///
+/// As an actix error it is a `500 Internal Server Error` whose body is the error, with the
+/// stack trace of the server code, mapped back to its sources, if it threw.
+#[derive(Debug)]
+pub enum SsrError {
+    /// The server entry threw while rendering.
+    Render { message: String, stack: String },
+    /// The renderer could not be reached or answered with something unexpected.
+    Unavailable(String),
+}
+
+impl Display for SsrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SsrError::Render { message, .. } => write!(f, "Server-side rendering failed: {}", message),
+            SsrError::Unavailable(reason) => {
+                write!(f, "The server-side renderer is unavailable: {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SsrError {}
+
+impl ResponseError for SsrError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let body = match self {
+            // The stack starts with the message.
+            SsrError::Render { stack, .. } if !stack.is_empty() => {
+                format!("Server-side rendering failed:\n\n{}", stack)
+            }
+            _ => self.to_string(),
+        };
+        HttpResponse::InternalServerError()
+            .content_type("text/plain; charset=utf-8")
+            .body(body)
+    }
+}
+
+// The body of a failed render.
+#[derive(Deserialize)]
+struct RenderFailure {
+    message: String,
+    #[serde(default)]
+    stack: String,
+}
+
+/// A server-side renderer for the Vite project of the global options, see [`crate::ssr`].
+///
+/// The Node process stops when this is dropped. Share one instance between the workers,
+/// e.g. in `web::Data`.
+#[derive(Debug)]
+pub struct DevSsr {
+    port: u16,
+    entry: String,
+    template: Option<String>,
+    process: Mutex<Child>,
+}
+
+impl DevSsr {
+    /// Starts the renderer for the server entry `entry`, e.g. `/src/entry-server.tsx`, and
+    /// waits until it is ready.
+    ///
+    /// Needs `node`, and `vite` installed in the `node_modules` of the working directory
+    /// of the global options.
+    pub fn start(entry: impl AsRef<str>) -> anyhow::Result<Self> {
+        Self::spawn(entry.as_ref(), None)
+    }
+
+    /// Like [`DevSsr::start`], but every page is put into `template`, e.g. `index.html`,
+    /// after Vite's HTML transforms ran on it, in place of its `<!--app-html-->` or
+    /// `<!--ssr-outlet-->` placeholder.
+    pub fn start_with_template(
+        entry: impl AsRef<str>,
+        template: impl AsRef<str>,
+    ) -> anyhow::Result<Self> {
+        Self::spawn(entry.as_ref(), Some(template.as_ref()))
+    }
+
+    fn spawn(entry: &str, template: Option<&str>) -> anyhow::Result<Self> {
+        let options = ProxyViteOptions::global();
+        let working_directory = Path::new(&options.working_directory);
+        if !working_directory.join("node_modules").is_dir() {
+            return Err(anyhow::anyhow!(
+                "{} has no node_modules, install the project's dependencies first",
+                working_directory.display()
+            ));
+        }
+        let node = crate::which("node")?
+            .context("node was not found, install it or add it to the PATH")?;
+
+        let script = working_directory.join(SIDECAR_PATH);
+        if let Some(dir) = script.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        std::fs::write(&script, SIDECAR)
+            .with_context(|| format!("Failed to write {}", script.display()))?;
+
+        let mut process = Command::new(node)
+            .arg(&script)
+            .current_dir(working_directory)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("Failed to start the server-side renderer")?;
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let stdout = process.stdout.take().context("Failed to capture its output")?;
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                match line.strip_prefix(LISTENING).map(str::trim).map(str::parse::<u16>) {
+                    Some(Ok(port)) => {
+                        let _ = ready_tx.send(Ok(port));
+                    }
+                    _ => info!("[ssr] {}", line),
+                }
+            }
+            let _ = ready_tx.send(Err(()));
+        });
+        let (error_tx, error_rx) = mpsc::channel();
+        if let Some(stderr) = process.stderr.take() {
+            std::thread::spawn(move || {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    warn!("[ssr] {}", line);
+                    let _ = error_tx.send(line);
+                }
+            });
+        }
+
+        let port = match ready_rx.recv_timeout(START_TIMEOUT) {
+            Ok(Ok(port)) => port,
+            failed => {
+                let _ = process.kill();
+                let _ = process.wait();
+                let output: Vec<String> = error_rx.try_iter().collect();
+                let reason = match failed {
+                    Err(_) => "did not start in time",
+                    _ => "exited",
+                };
+                return Err(anyhow::anyhow!(
+                    "The server-side renderer {}:\n{}",
+                    reason,
+                    output.join("\n")
+                ));
+            }
+        };
+        debug!("Server-side renderer for {} listening on port {}", entry, port);
+
+        Ok(Self {
+            port,
+            entry: format!("/{}", entry.trim_start_matches('/')),
+            template: template.map(String::from),
+            process: Mutex::new(process),
+        })
+    }
+
+    /// Renders the page for `url`, the path and query of the request, e.g. `/users?page=2`.
+    pub async fn render(&self, url: &str) -> Result<SsrPage, SsrError> {
+        let client = Client::builder().timeout(Duration::from_secs(60)).finish();
+        let request = serde_json::json!({
+            "entry": self.entry,
+            "url": url,
+            "template": self.template,
+        });
+        let mut res = client
+            .post(format!("http://127.0.0.1:{}/render", self.port))
+            .send_json(&request)
+            .await
+            .map_err(|e| SsrError::Unavailable(e.to_string()))?;
+        let body = res
+            .body()
+            .limit(MAX_PAGE_SIZE)
+            .await
+            .map_err(|e| SsrError::Unavailable(e.to_string()))?;
+
+        if res.status().is_success() {
+            serde_json::from_slice(&body).map_err(|e| SsrError::Unavailable(e.to_string()))
+        } else {
+            let failure: RenderFailure =
+                serde_json::from_slice(&body).map_err(|e| SsrError::Unavailable(e.to_string()))?;
+            Err(SsrError::Render {
+                message: failure.message,
+                stack: failure.stack,
+            })
+        }
+    }
+}
+
+impl Drop for DevSsr {
+    fn drop(&mut self) {
+        let process = self.process.get_mut().unwrap_or_else(|e| e.into_inner());
+        let _ = process.kill();
+        let _ = process.wait();
+    }
+}
@@ -0,0 +1,77 @@
+// Renders the server entry of a Vite project for `vite_actix::ssr::DevSsr`.
+//
+// Runs Vite in middleware mode next to the dev server that serves the assets, and loads
+// the entry through its module runner, so edits to the server code apply on the next
+// render. Listens on a free port of 127.0.0.1 and prints it, then answers
+// `POST /render` with `{ entry, url, template }` as JSON. Exits when its stdin closes,
+// i.e. when the Rust process goes away.
+import fs from 'node:fs/promises';
+import http from 'node:http';
+import path from 'node:path';
+import { createServer } from 'vite';
+
+const root = process.cwd();
+const vite = await createServer({
+    root,
+    appType: 'custom',
+    logLevel: 'warn',
+    // The dev server already runs HMR for the browser.
+    server: { middlewareMode: true, hmr: false, ws: false },
+});
+
+// The URLs of the modules the entry imports, directly or not, for preload links.
+async function collectPreloads(entry) {
+    const urls = [];
+    const seen = new Set();
+    const visit = (mod) => {
+        if (!mod || seen.has(mod)) return;
+        seen.add(mod);
+        if (mod.url && !mod.url.startsWith('\0') && mod.url !== entry) urls.push(mod.url);
+        for (const imported of mod.ssrImportedModules ?? mod.importedModules ?? []) visit(imported);
+    };
+    visit(await vite.moduleGraph.getModuleByUrl(entry, true));
+    return urls;
+}
+
+async function render({ entry, url, template }) {
+    const mod = await vite.ssrLoadModule(entry);
+    const renderPage = mod.render ?? mod.default;
+    if (typeof renderPage !== 'function') {
+        throw new Error(`${entry} has no render function, export one as render or default`);
+    }
+    const rendered = await renderPage(url);
+    let html = typeof rendered === 'string' ? rendered : rendered.html;
+    const head = typeof rendered === 'string' ? '' : rendered.head ?? '';
+    if (template) {
+        const page = await fs.readFile(path.resolve(root, template), 'utf-8');
+        html = (await vite.transformIndexHtml(url, page))
+            .replace('<!--app-head-->', () => head)
+            .replace('<!--app-html-->', () => html)
+            .replace('<!--ssr-outlet-->', () => html);
+    }
+    return { html, preloads: await collectPreloads(entry) };
+}
+
+const server = http.createServer(async (req, res) => {
+    if (req.method !== 'POST' || req.url !== '/render') {
+        res.writeHead(404).end();
+        return;
+    }
+    let body = '';
+    for await (const chunk of req) body += chunk;
+    try {
+        const page = await render(JSON.parse(body));
+        res.writeHead(200, { 'content-type': 'application/json' }).end(JSON.stringify(page));
+    } catch (e) {
+        if (e instanceof Error) vite.ssrFixStacktrace(e);
+        const error = { message: String(e?.message ?? e), stack: String(e?.stack ?? '') };
+        res.writeHead(500, { 'content-type': 'application/json' }).end(JSON.stringify(error));
+    }
+});
+
+server.listen(0, '127.0.0.1', () => {
+    console.log(`vite-actix-ssr listening on ${server.address().port}`);
+});
+
+process.stdin.on('end', () => process.exit(0));
+process.stdin.resume();
@@ -0,0 +1,105 @@
+//! What the proxy answers while the managed Vite process is starting, instead of an error
+//! the browser has to be refreshed past.
+//!
+//! Page navigations, `GET` requests that prefer `text/html`, get a holding page with a
+//! spinner that polls [`HEALTH_PATH`] every 500 ms and reloads once it reports Vite ready.
+//! Everything else, modules, `fetch` calls and WebSocket upgrades, gets a
+//! `503 Service Unavailable` with `Retry-After: 1`. The proxy answers the health path
+//! itself, with or without [`ProxyViteOptions::enable_health_route`], so the page works
+//! either way.
+//!
+//! [`ProxyViteOptions::starting_page`] replaces the page with one of the application's own;
+//! the polling script is added in front of its `</body>`. With
+//! [`ProxyViteOptions::csp_nonce`], the script and the styles of the default page carry the
+//! nonce, and [`NONCE_PLACEHOLDER`] in a page of the application's own is replaced with it.
+//!
+//! [`HEALTH_PATH`]: crate::health::HEALTH_PATH
+//! [`ProxyViteOptions::enable_health_route`]: crate::proxy_vite_options::ProxyViteOptions::enable_health_route
+//! [`ProxyViteOptions::starting_page`]: crate::proxy_vite_options::ProxyViteOptions::starting_page
+//! [`ProxyViteOptions::csp_nonce`]: crate::proxy_vite_options::ProxyViteOptions::csp_nonce
+//! [`NONCE_PLACEHOLDER`]: crate::html::NONCE_PLACEHOLDER
+
+use crate::assets::{escape, nonce_attribute};
+use crate::health::HEALTH_PATH;
+use crate::html::NONCE_PLACEHOLDER;
+use crate::html_transform::{append_to_body, js_string, provide_nonce};
+use crate::navigation_fallback::prefers_html;
+use crate::proxy_vite_options::ProxyViteOptions;
+use actix_web::http::header::{ACCEPT, CACHE_CONTROL, CONTENT_TYPE, RETRY_AFTER};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{HttpRequest, HttpResponse};
+
+/// The page shown while Vite is starting, unless [`ProxyViteOptions::starting_page`] sets
+/// another.
+///
+/// [`ProxyViteOptions::starting_page`]: crate::proxy_vite_options::ProxyViteOptions::starting_page
+pub const DEFAULT_STARTING_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Vite is starting</title>
+<style>
+  html, body { height: 100%; margin: 0; }
+  body {
+    display: flex; flex-direction: column; align-items: center; justify-content: center;
+    gap: 1.25rem; font-family: system-ui, sans-serif; color: #3c3c43; background: #f6f6f7;
+  }
+  .spinner {
+    width: 2.5rem; height: 2.5rem; border-radius: 50%;
+    border: 0.25rem solid #dcdce0; border-top-color: #646cff;
+    animation: spin 0.8s linear infinite;
+  }
+  @keyframes spin { to { transform: rotate(360deg); } }
+  @media (prefers-color-scheme: dark) { body { color: #dfdfd6; background: #1b1b1f; } }
+</style>
+</head>
+<body>
+<div class="spinner"></div>
+<p>Vite is starting, the page reloads once it is ready.</p>
+</body>
+</html>
+"#;
+
+// How long clients are asked to wait before retrying, in seconds.
+const RETRY_AFTER_SECONDS: &str = "1";
+
+// The answer to `req` while Vite is starting. `prefix` is the path of the scope the proxy
+// is mounted in, which the health path of the page is under.
+pub(crate) fn respond(req: &HttpRequest, prefix: &str, options: &ProxyViteOptions) -> HttpResponse {
+    let accept = req.headers().get(ACCEPT).and_then(|value| value.to_str().ok());
+    let navigation = (req.method() == Method::GET || req.method() == Method::HEAD)
+        && accept.is_some_and(prefers_html);
+    let mut res = HttpResponse::build(StatusCode::SERVICE_UNAVAILABLE);
+    res.insert_header((RETRY_AFTER, RETRY_AFTER_SECONDS))
+        .insert_header((CACHE_CONTROL, "no-store"));
+    if !navigation {
+        return res.body("Vite is starting, retry shortly");
+    }
+    let nonce = provide_nonce(req, options.csp_nonce.as_ref());
+    let page = match (&options.starting_page, &nonce) {
+        (Some(page), Some(nonce)) => page.replace(NONCE_PLACEHOLDER, &escape(nonce)),
+        (Some(page), None) => page.clone(),
+        (None, nonce) => DEFAULT_STARTING_PAGE.replacen(
+            "<style>",
+            &format!("<style{}>", nonce_attribute(nonce.as_deref())),
+            1,
+        ),
+    };
+    let health_url = format!("{}{}", prefix, HEALTH_PATH);
+    res.insert_header((CONTENT_TYPE, "text/html; charset=utf-8"))
+        .body(with_poll_script(&page, &health_url, nonce.as_deref()))
+}
+
+// `page` with the script that reloads it once `health_url` answers, with `nonce`, in front
+// of its `</body>` or at its end.
+fn with_poll_script(page: &str, health_url: &str, nonce: Option<&str>) -> String {
+    let script = format!(
+        "<script{}>const poll = () => fetch({}, {{ cache: \"no-store\" }})\
+         .then((res) => res.ok ? location.reload() : setTimeout(poll, 500))\
+         .catch(() => setTimeout(poll, 500)); setTimeout(poll, 500);</script>",
+        nonce_attribute(nonce),
+        js_string(health_url)
+    );
+    append_to_body(page, &script)
+}
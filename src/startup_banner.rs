@@ -0,0 +1,207 @@
+use crate::network_urls;
+use crate::vite_state::ViteShared;
+use log::info;
+use std::net::SocketAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use url::Url;
+
+// What is known so far about the startup of the Actix server and the default Vite process.
+struct StartupState {
+    started: Instant,
+    server: Option<(SocketAddr, Duration)>,
+    vite: Option<(Option<u16>, Duration)>,
+    enabled: bool,
+    printed: bool,
+}
+
+static STARTUP_STATE: OnceLock<Mutex<StartupState>> = OnceLock::new();
+
+fn state() -> &'static Mutex<StartupState> {
+    STARTUP_STATE.get_or_init(|| {
+        Mutex::new(StartupState {
+            started: Instant::now(),
+            server: None,
+            vite: None,
+            enabled: false,
+            printed: false,
+        })
+    })
+}
+
+/// Formats the startup banner for the Actix server and the Vite process.
+///
+/// `server` is the address Actix is bound to and how long it took to get there; `vite` is
+/// the port Vite reported (`None` for `vite build --watch`) and how long it took to be ready.
+/// Either may be unknown, in which case the line says so. `network` are the `Network:` URLs
+/// of Vite, listed along with the app's own on those interfaces if it is bound to all of them.
+///
+/// # Example
+/// ```rust
+/// use std::time::Duration;
+/// use url::Url;
+/// use vite_actix::startup_banner::format_startup_banner;
+///
+/// let ms = Duration::from_millis;
+/// let banner = format_startup_banner(
+///     Some(("127.0.0.1:8080".parse().unwrap(), ms(42))),
+///     Some((Some(5173), ms(1500))),
+///     &[],
+/// );
+/// assert_eq!(
+///     banner,
+///     "vite-actix is ready\n\
+///      \x20 ➜  App:   http://127.0.0.1:8080/ (ready in 42 ms)\n\
+///      \x20 ➜  Vite:  http://localhost:5173/ (ready in 1.50 s, proxied)"
+/// );
+///
+/// // Nothing known yet.
+/// assert_eq!(
+///     format_startup_banner(None, None, &[]),
+///     "vite-actix is ready\n\
+///      \x20 ➜  App:   unknown, report it with vite_actix::report_server_addr\n\
+///      \x20 ➜  Vite:  not ready"
+/// );
+///
+/// // IPv6, and `vite build --watch`, which serves no pages.
+/// let banner = format_startup_banner(
+///     Some(("[::1]:8080".parse().unwrap(), ms(7))),
+///     Some((None, ms(2346))),
+///     &[],
+/// );
+/// assert!(banner.contains("App:   http://[::1]:8080/ (ready in 7 ms)"));
+/// assert!(banner.contains("Vite:  no dev server, serving the build output (ready in 2.35 s)"));
+///
+/// // Bound to every interface: opened on localhost, and on the LAN at Vite's addresses.
+/// let network = [Url::parse("http://192.168.1.5:5173/").unwrap()];
+/// let banner = format_startup_banner(
+///     Some(("0.0.0.0:8080".parse().unwrap(), ms(3))),
+///     Some((Some(5173), ms(300))),
+///     &network,
+/// );
+/// let lines: Vec<&str> = banner.lines().skip(1).collect();
+/// assert_eq!(
+///     lines,
+///     [
+///         "  ➜  App:   http://localhost:8080/ (ready in 3 ms)",
+///         "  ➜  Vite:  http://localhost:5173/ (ready in 300 ms, proxied)",
+///         "  ➜  Network: http://192.168.1.5:8080/ (app)",
+///         "  ➜  Network: http://192.168.1.5:5173/ (Vite)",
+///     ]
+/// );
+/// // Otherwise only Vite is reachable there.
+/// let banner = format_startup_banner(
+///     Some(("127.0.0.1:8080".parse().unwrap(), ms(3))),
+///     Some((Some(5173), ms(300))),
+///     &network,
+/// );
+/// assert!(!banner.contains("(app)"));
+/// assert!(banner.ends_with("Network: http://192.168.1.5:5173/ (Vite)"));
+/// ```
+///
+/// With [`ProxyViteOptions::print_startup_banner`], it is logged at `info` once, when both
+/// the server address and Vite are known, in either order.
+///
+/// [`ProxyViteOptions::print_startup_banner`]: crate::proxy_vite_options::ProxyViteOptions::print_startup_banner
+pub fn format_startup_banner(
+    server: Option<(SocketAddr, Duration)>,
+    vite: Option<(Option<u16>, Duration)>,
+    network: &[Url],
+) -> String {
+    let app = match server {
+        Some((addr, took)) => {
+            // An unspecified bind address can't be opened in a browser, localhost can.
+            let host = if addr.ip().is_unspecified() {
+                "localhost".to_string()
+            } else if addr.is_ipv6() {
+                format!("[{}]", addr.ip())
+            } else {
+                addr.ip().to_string()
+            };
+            format!(
+                "http://{}:{}/ (ready in {})",
+                host,
+                addr.port(),
+                format_duration(took)
+            )
+        }
+        None => "unknown, report it with vite_actix::report_server_addr".to_string(),
+    };
+    let vite = match vite {
+        Some((Some(port), took)) => format!(
+            "http://localhost:{}/ (ready in {}, proxied)",
+            port,
+            format_duration(took)
+        ),
+        Some((None, took)) => format!(
+            "no dev server, serving the build output (ready in {})",
+            format_duration(took)
+        ),
+        None => "not ready".to_string(),
+    };
+
+    let mut banner = format!(
+        "vite-actix is ready\n  ➜  App:   {}\n  ➜  Vite:  {}",
+        app, vite
+    );
+    // The app is only reachable on the network if it listens on every interface.
+    if let Some((addr, _)) = server
+        && addr.ip().is_unspecified()
+    {
+        for url in network_urls::with_port(network, addr.port()) {
+            banner.push_str(&format!("\n  ➜  Network: {} (app)", url));
+        }
+    }
+    for url in network {
+        banner.push_str(&format!("\n  ➜  Network: {} (Vite)", url));
+    }
+    banner
+}
+
+// Milliseconds below a second, seconds with two decimals above, like Vite prints them.
+fn format_duration(duration: Duration) -> String {
+    if duration < Duration::from_secs(1) {
+        format!("{} ms", duration.as_millis())
+    } else {
+        format!("{:.2} s", duration.as_secs_f64())
+    }
+}
+
+// Start the startup clock, if it isn't running yet, and record whether to print the banner.
+// Called whenever the global options are built.
+pub(crate) fn begin(enabled: bool) {
+    if let Ok(mut state) = state().lock() {
+        state.enabled = enabled;
+    }
+}
+
+// Record the address the Actix server is bound to.
+pub(crate) fn record_server_addr(addr: SocketAddr) {
+    if let Ok(mut state) = state().lock() {
+        let took = state.started.elapsed();
+        state.server = Some((addr, took));
+        print_if_complete(&mut state);
+    }
+}
+
+// Record that the default Vite process is ready, on `port` if it serves HTTP, `took` after
+// it was spawned.
+pub(crate) fn record_vite_ready(port: Option<u16>, took: Duration) {
+    if let Ok(mut state) = state().lock() {
+        state.vite = Some((port, took));
+        print_if_complete(&mut state);
+    }
+}
+
+// Log the banner once both the server address and Vite readiness are known.
+fn print_if_complete(state: &mut StartupState) {
+    if !state.enabled || state.printed || state.server.is_none() || state.vite.is_none() {
+        return;
+    }
+    state.printed = true;
+    let network = ViteShared::global().network_urls();
+    info!(
+        "{}",
+        format_startup_banner(state.server, state.vite, &network)
+    );
+}
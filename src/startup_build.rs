@@ -0,0 +1,195 @@
+//! Running `vite build` from [`ProxyViteOptions::build`] when the build to serve is missing
+//! or older than the sources, set with [`ProxyViteOptions::build_if_missing`] and
+//! [`ProxyViteOptions::build_if_stale`], for deployments that forget the separate
+//! `npm run build` step.
+//!
+//! Outside of dev mode, before the dist directory is checked, a dist directory without an
+//! `index.html` or a manifest is built, and with `build_if_stale` also one whose newest file
+//! is older than the newest of `src/`, `public/`, `index.html` and `vite.config.*`. The
+//! build runs the project's `vite build` in the working directory, with `--outDir` if
+//! [`ProxyViteOptions::dist_directory`] is set, and its output goes through the same log
+//! pipeline as that of the dev server. `build()` blocks until it is done, and fails with
+//! the output of a build that fails or takes longer than
+//! [`ProxyViteOptions::build_timeout`], five minutes by default.
+//!
+//! [`SKIP_BUILD_ENV`], `VITE_ACTIX_SKIP_BUILD`, set to anything but `0` or `false` turns
+//! the build off, e.g. on CI, where the build is a step of its own.
+//!
+//! [`ProxyViteOptions::build`]: crate::proxy_vite_options::ProxyViteOptions::build
+//! [`ProxyViteOptions::build_if_missing`]: crate::proxy_vite_options::ProxyViteOptions::build_if_missing
+//! [`ProxyViteOptions::build_if_stale`]: crate::proxy_vite_options::ProxyViteOptions::build_if_stale
+//! [`ProxyViteOptions::build_timeout`]: crate::proxy_vite_options::ProxyViteOptions::build_timeout
+//! [`ProxyViteOptions::dist_directory`]: crate::proxy_vite_options::ProxyViteOptions::dist_directory
+
+use crate::error::StartViteError;
+use crate::launcher::ViteLauncher;
+use crate::manifest::MANIFEST_PATHS;
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::skip_spawn::{env_value, is_truthy};
+use crate::spawn::{ViteOutputEvent, read_vite_output};
+use crate::vite_stdio::ViteStream;
+use log::{error, info};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The environment variable that turns the build at startup off when set to anything but
+/// `0` or `false`.
+pub const SKIP_BUILD_ENV: &str = "VITE_ACTIX_SKIP_BUILD";
+
+/// The default of [`ProxyViteOptions::build_timeout`].
+///
+/// [`ProxyViteOptions::build_timeout`]: crate::proxy_vite_options::ProxyViteOptions::build_timeout
+pub const DEFAULT_BUILD_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+// The files and directories of a project whose changes make its build stale.
+pub(crate) const SOURCES: [&str; 9] = [
+    "src",
+    "public",
+    "index.html",
+    "vite.config.js",
+    "vite.config.ts",
+    "vite.config.mjs",
+    "vite.config.mts",
+    "vite.config.cjs",
+    "vite.config.cts",
+];
+
+// Build the dist directory of `options` if it has to be, see the module docs.
+pub(crate) fn build_if_needed(options: &ProxyViteOptions) -> Result<(), StartViteError> {
+    let Some(reason) = build_reason(options) else {
+        return Ok(());
+    };
+    if env_value(SKIP_BUILD_ENV).is_some_and(|value| is_truthy(&value)) {
+        info!(
+            "Not running vite build although {}: {} is set",
+            reason, SKIP_BUILD_ENV
+        );
+        return Ok(());
+    }
+    info!(
+        "Running vite build in {}: {}",
+        options.working_directory, reason
+    );
+    let started = Instant::now();
+    run_build(options)?;
+    info!("vite build finished in {:.1?}", started.elapsed());
+    Ok(())
+}
+
+// Why the dist directory of `options` has to be built, if it has to.
+fn build_reason(options: &ProxyViteOptions) -> Option<String> {
+    let dist_dir = options.dist_dir();
+    let usable = std::iter::once("index.html")
+        .chain(MANIFEST_PATHS)
+        .any(|file| dist_dir.join(file).is_file());
+    if !usable {
+        return Some(format!("{} has no build", dist_dir.display()));
+    }
+    if !options.build_if_stale {
+        return None;
+    }
+    let project = Path::new(&options.working_directory);
+    let sources = SOURCES
+        .iter()
+        .filter_map(|source| newest_modification(&project.join(source)))
+        .max()?;
+    let built = newest_modification(&dist_dir)?;
+    (sources > built).then(|| format!("{} is older than the sources", dist_dir.display()))
+}
+
+// When the newest file at `path`, or below it for a directory, was last modified.
+fn newest_modification(path: &Path) -> Option<SystemTime> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_dir() {
+        return metadata.modified().ok();
+    }
+    std::fs::read_dir(path)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name() != "node_modules")
+        .filter_map(|entry| newest_modification(&entry.path()))
+        .max()
+}
+
+// Run `vite build` for `options` and wait for it, logging its output.
+pub(crate) fn run_build(options: &ProxyViteOptions) -> Result<(), StartViteError> {
+    let working_directory = Path::new(&options.working_directory);
+    if !working_directory.is_dir() {
+        return Err(StartViteError::WorkingDirMissing(
+            working_directory.to_path_buf(),
+        ));
+    }
+    let mut command = ViteLauncher::resolve(working_directory)?.command();
+    command.arg("build");
+    // Vite resolves `--outDir` against the project, the options against the current directory.
+    if options.dist_directory.is_some() {
+        let dist_dir = std::path::absolute(options.dist_dir()).unwrap_or(options.dist_dir());
+        command.arg("--outDir").arg(dist_dir);
+    }
+    command
+        .current_dir(working_directory)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let log_level = options.log_level;
+    let log_format = options.vite_log_format;
+    let timeout = options.build_timeout;
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let lines = captured.clone();
+    let on_event = move |event: ViteOutputEvent| {
+        if let ViteOutputEvent::Line(line) = event
+            && let Ok(mut lines) = lines.lock()
+        {
+            lines.push(line.line);
+        }
+    };
+
+    // `build()` may be called from within a runtime, so the build gets a thread of its own.
+    let build = std::thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(StartViteError::SpawnFailed)?;
+        runtime.block_on(async {
+            let mut child = tokio::process::Command::from(command)
+                .kill_on_drop(true)
+                .spawn()
+                .map_err(StartViteError::SpawnFailed)?;
+            let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+                return Err(StartViteError::OutputCaptureFailed);
+            };
+            let finished = tokio::time::timeout(timeout, async {
+                tokio::join!(
+                    read_vite_output(stdout, ViteStream::Stdout, log_level, log_format, &on_event),
+                    read_vite_output(stderr, ViteStream::Stderr, log_level, log_format, &on_event),
+                );
+                child.wait().await
+            })
+            .await;
+            match finished {
+                Ok(Ok(status)) => Ok(Some(status)),
+                Ok(Err(e)) => Err(StartViteError::SpawnFailed(e)),
+                Err(_) => {
+                    let _ = child.kill().await;
+                    Ok(None)
+                }
+            }
+        })
+    });
+    let finished = build.join().unwrap_or_else(|_| {
+        error!("The thread running vite build panicked");
+        Err(StartViteError::OutputCaptureFailed)
+    })?;
+    let captured = captured
+        .lock()
+        .map(|lines| lines.clone())
+        .unwrap_or_default();
+    match finished {
+        Some(status) if status.success() => Ok(()),
+        Some(status) => Err(StartViteError::BuildFailed { status, captured }),
+        None => Err(StartViteError::BuildTimeout { timeout, captured }),
+    }
+}
@@ -0,0 +1,252 @@
+use crate::built_dist::{
+    BuiltDist, CACHE_NO_CACHE, accepts_encoding, is_fresh, reject_method, resolve_dist_path,
+};
+use crate::html_transform::{HtmlFiles, is_html_file};
+use actix_files::NamedFile;
+use actix_web::http::StatusCode;
+use actix_web::http::header::{
+    CACHE_CONTROL, ContentEncoding, ETAG, HeaderValue, HttpDate, LAST_MODIFIED, RANGE, VARY,
+};
+use actix_web::{Error, HttpRequest, HttpResponse};
+use std::fs::File;
+use std::path::Path;
+#[cfg(feature = "dev-proxy")]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Precompressed siblings of a file, e.g. from `vite-plugin-compression`, in order of preference.
+const PRECOMPRESSED: [(&str, ContentEncoding); 2] =
+    [("br", ContentEncoding::Brotli), ("gz", ContentEncoding::Gzip)];
+
+/// Serves a file of a production build.
+///
+/// Hashed files, named like `logo-BxYz1234.svg`, are cached for a year. Everything else,
+/// like `index.html`, is revalidated on every request so a new deployment is picked up
+/// right away, unless an override of the options says otherwise. Page navigations to paths
+/// that do not exist get `index.html` if the SPA fallback is on; other missing files,
+/// anything with an extension or under the assets directory, are a 404, logged for built
+/// assets.
+///
+/// A `.br` or `.gz` file next to the requested one, like those `vite-plugin-compression`
+/// writes, is sent instead to clients that accept its encoding, Brotli first, unless the
+/// request asks for a range.
+///
+/// Page navigations to the form of a path the trailing slash policy does not prefer are
+/// redirected to the other one first.
+///
+/// Files the dist directory does not have are looked up in the extra directories of the
+/// options whose URL prefix matches, and revalidated on every request.
+///
+/// With a serve prefix, the build is served below it, and paths outside of it are a 404.
+///
+/// Files carry an `ETag`, from their size, modification time and encoding, and a
+/// `Last-Modified` date; `If-None-Match` and `If-Modified-Since` are answered with a
+/// `304 Not Modified`. Transformed HTML pages differ per request and have neither.
+///
+/// Files are only read: other methods than `GET` and `HEAD` get a `405 Method Not Allowed`.
+pub(crate) async fn serve_built_file(
+    req: HttpRequest,
+    built: Arc<BuiltDist>,
+) -> Result<HttpResponse, Error> {
+    // Paths outside of the serve prefix belong to the backend, and paths trying to leave the
+    // directory are a 404, not a page for the client-side router.
+    let Some(route) = built.route(req.path()) else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let Some(path) = resolve_dist_path(&built.dist_dir, route) else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if let Some(res) = reject_method(&req) {
+        return Ok(res);
+    }
+    if let Some(redirect) = built.trailing_slash_redirect(&req) {
+        return Ok(redirect);
+    }
+    let cache_control = built.cache_control(req.path());
+    let res = serve_built(&req, &path, cache_control, &built);
+    if res.status() != StatusCode::NOT_FOUND {
+        return Ok(res);
+    }
+    // The extra directories hold no hashed files, so they are revalidated like `index.html`.
+    if let Some(path) = built.extra_file(req.path()) {
+        let cache_control = built
+            .cache_override(req.path())
+            .unwrap_or(HeaderValue::from_static(CACHE_NO_CACHE));
+        return Ok(serve_built(&req, &path, cache_control, &built));
+    }
+    if built.falls_back(&req) {
+        let page = built.fallback_page(req.path());
+        let cache_control = built.cache_control(&format!("/{}", page));
+        let path = built.dist_dir.join(page);
+        return Ok(serve_built(&req, &path, cache_control, &built));
+    }
+    Ok(built.not_found(&req))
+}
+
+// Answer `req` with the file at `path` of `built`, compressed if compression is on and
+// applies to it.
+fn serve_built(
+    req: &HttpRequest,
+    path: &Path,
+    cache_control: HeaderValue,
+    built: &BuiltDist,
+) -> HttpResponse {
+    let html = built.html.as_deref();
+    #[cfg(feature = "compress")]
+    if let Some(compressor) = &built.compressor
+        && !(html.is_some() && is_html_file(path))
+        && compressor.applies(path)
+    {
+        if let Some(res) = compressor.respond(req, path, cache_control.clone()) {
+            return res;
+        }
+        // The client gets the file as it is, which still depends on `Accept-Encoding`.
+        let mut res = serve_file(req, path, cache_control, html);
+        res.headers_mut()
+            .insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+        return res;
+    }
+    serve_file(req, path, cache_control, html)
+}
+
+/// Serves a file from `dist_dir` for the request path, with the given `Cache-Control` value.
+/// HTML pages go through `html` first, if given.
+///
+/// Directory requests resolve to their `index.html`. Paths that would escape `dist_dir`
+/// and files that do not exist are answered with a 404.
+#[cfg(feature = "dev-proxy")]
+pub(crate) async fn serve_dist_file(
+    req: HttpRequest,
+    dist_dir: PathBuf,
+    cache_control: HeaderValue,
+    html: Option<Arc<HtmlFiles>>,
+) -> Result<HttpResponse, Error> {
+    let Some(path) = resolve_dist_path(&dist_dir, req.path()) else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    if let Some(res) = reject_method(&req) {
+        return Ok(res);
+    }
+
+    Ok(serve_file(&req, &path, cache_control, html.as_deref()))
+}
+
+// Answer `req` with the file at `path`, or a 404 if it does not exist.
+fn serve_file(
+    req: &HttpRequest,
+    path: &Path,
+    cache_control: HeaderValue,
+    html: Option<&HtmlFiles>,
+) -> HttpResponse {
+    // The page differs per request, so it has no ETag or Last-Modified to revalidate with.
+    if let Some(html) = html
+        && is_html_file(path)
+        && let Some(page) = html.render(req, path)
+    {
+        return HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .insert_header((CACHE_CONTROL, cache_control))
+            .body(page);
+    }
+
+    if !path.is_file() {
+        return HttpResponse::NotFound().finish();
+    }
+    let (file, has_variants) = match open_precompressed(req, path) {
+        (Some(file), has_variants) => (file, has_variants),
+        (None, has_variants) => match NamedFile::open(path) {
+            Ok(file) => (file, has_variants),
+            Err(_) => return HttpResponse::NotFound().finish(),
+        },
+    };
+
+    let etag = file_etag(&file);
+    let modified = file.modified();
+    let mut res = if is_fresh(req, &etag, modified) {
+        HttpResponse::NotModified().finish()
+    } else {
+        // The validators are ours, so `NamedFile` does not compare its own ETag.
+        file.use_etag(false).into_response(req)
+    };
+    set_validators(&mut res, &etag, modified, cache_control, has_variants);
+    res
+}
+
+// Set the `ETag`, `Last-Modified` and `Cache-Control` of the answer with a file, and
+// `Vary: Accept-Encoding` if it `varies` by the encodings the client accepts.
+pub(crate) fn set_validators(
+    res: &mut HttpResponse,
+    etag: &str,
+    modified: Option<SystemTime>,
+    cache_control: HeaderValue,
+    varies: bool,
+) {
+    let headers = res.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(etag) {
+        headers.insert(ETAG, etag);
+    }
+    if let Some(modified) = modified
+        && let Ok(modified) = HeaderValue::from_str(&HttpDate::from(modified).to_string())
+    {
+        headers.insert(LAST_MODIFIED, modified);
+    }
+    headers.insert(CACHE_CONTROL, cache_control);
+    if varies {
+        headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+    }
+}
+
+// A strong ETag for `file` from its size and modification time, and its encoding, so each
+// precompressed variant has its own.
+pub(crate) fn file_etag(file: &NamedFile) -> String {
+    let modified = file
+        .modified()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    let encoding = match file.content_encoding() {
+        Some(encoding) => format!("-{}", encoding.as_str()),
+        None => String::new(),
+    };
+    format!(
+        "\"{:x}-{:x}.{:x}{}\"",
+        file.metadata().len(),
+        modified.as_secs(),
+        modified.subsec_nanos(),
+        encoding
+    )
+}
+
+// The precompressed sibling of `path` to send for `req`, if there is one the client
+// accepts, with the type and name of the original file; and whether `path` has any, so
+// caches know the response depends on `Accept-Encoding`. Range requests get the original.
+fn open_precompressed(req: &HttpRequest, path: &Path) -> (Option<NamedFile>, bool) {
+    let mut has_variants = false;
+    for (extension, encoding) in PRECOMPRESSED {
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(".");
+        sibling.push(extension);
+        let Ok(file) = File::open(&sibling) else {
+            continue;
+        };
+        has_variants = true;
+        if req.headers().contains_key(RANGE) || !accepts_encoding(req, encoding.as_str()) {
+            continue;
+        }
+        if let Ok(file) = NamedFile::from_file(file, path) {
+            return (Some(file.set_content_encoding(encoding)), true);
+        }
+    }
+    (None, has_variants)
+}
+
+// Whether `path` has a precompressed sibling, whether or not the client accepts it.
+#[cfg(feature = "compress")]
+pub(crate) fn has_precompressed(path: &Path) -> bool {
+    PRECOMPRESSED.iter().any(|(extension, _)| {
+        let mut sibling = path.as_os_str().to_owned();
+        sibling.push(".");
+        sibling.push(extension);
+        Path::new(&sibling).is_file()
+    })
+}
@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+/// How a managed Vite process is restarted after it crashes.
+///
+/// Each restart waits `base_delay * 2^(n - 1)` for the `n`-th failure within `window`, capped
+/// at `max_delay` and randomly shortened by up to half so several processes don't restart in
+/// lockstep. Once `max_failures` failures happen within `window`, the process is left down
+/// and its state becomes [`crate::vite_state::ViteState::Failed`] until
+/// [`crate::vite_server_handle::SupervisorHandle::resume`] is called.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RestartPolicy {
+    /// The delay before the first restart.
+    pub base_delay: Duration,
+    /// The longest delay between two restarts.
+    pub max_delay: Duration,
+    /// How many failures within `window` are tolerated before giving up.
+    pub max_failures: u32,
+    /// How far back failures are counted.
+    pub window: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            max_failures: 5,
+            window: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// The delay before restarting after the `failures`-th failure, without jitter.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::time::Duration;
+    /// use vite_actix::supervisor::RestartPolicy;
+    ///
+    /// let policy = RestartPolicy::default();
+    /// assert_eq!(policy.delay(1), Duration::from_millis(500));
+    /// assert_eq!(policy.delay(2), Duration::from_secs(1));
+    /// assert_eq!(policy.delay(6), Duration::from_secs(16));
+    /// // 32 seconds, over `max_delay`.
+    /// assert_eq!(policy.delay(7), Duration::from_secs(30));
+    /// // Counts that would overflow stay at `max_delay`, and there is no 0th failure.
+    /// assert_eq!(policy.delay(u32::MAX), Duration::from_secs(30));
+    /// assert_eq!(policy.delay(0), policy.delay(1));
+    /// ```
+    pub fn delay(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(31);
+        self.base_delay
+            .checked_mul(1 << exponent)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// [`RestartPolicy::delay`] shortened by `jitter`, a fraction between 0 and 1 of half
+    /// the delay.
+    ///
+    /// # Example
+    /// ```rust
+    /// use std::time::Duration;
+    /// use vite_actix::supervisor::RestartPolicy;
+    ///
+    /// let policy = RestartPolicy::default();
+    /// assert_eq!(policy.jittered_delay(3, 0.0), Duration::from_secs(2));
+    /// assert_eq!(policy.jittered_delay(3, 0.5), Duration::from_millis(1500));
+    /// assert_eq!(policy.jittered_delay(3, 1.0), Duration::from_secs(1));
+    /// // Whatever the jitter, the delay stays between half and all of the unjittered one.
+    /// for jitter in [-1.0, 0.25, 0.999, 2.0, f64::INFINITY] {
+    ///     let delay = policy.jittered_delay(7, jitter);
+    ///     assert!(delay >= Duration::from_secs(15) && delay <= Duration::from_secs(30));
+    /// }
+    /// ```
+    pub fn jittered_delay(&self, failures: u32, jitter: f64) -> Duration {
+        let delay = self.delay(failures);
+        delay - (delay / 2).mul_f64(jitter.clamp(0.0, 1.0))
+    }
+
+    /// Whether a process with `failures` failures within `window`, as counted by
+    /// [`FailureWindow::record`], is left down instead of restarted.
+    pub fn gives_up(&self, failures: u32) -> bool {
+        failures >= self.max_failures
+    }
+}
+
+/// The failures of a supervised process within the window of its [`RestartPolicy`].
+///
+/// # Example
+/// ```rust
+/// use std::time::{Duration, Instant};
+/// use vite_actix::supervisor::{FailureWindow, RestartPolicy};
+///
+/// let policy = RestartPolicy { max_failures: 3, ..RestartPolicy::default() };
+/// let start = Instant::now();
+/// let at = |secs| start + Duration::from_secs(secs);
+/// let mut window = FailureWindow::default();
+///
+/// // Failures spread out over more than a minute are forgotten before they add up.
+/// for (secs, counted) in [(0, 1), (40, 2), (80, 2), (120, 2), (160, 2)] {
+///     let failures = window.record(at(secs), policy.window);
+///     assert_eq!(failures, counted);
+///     assert!(!policy.gives_up(failures));
+/// }
+/// // Three within a minute are too many.
+/// let failures = window.record(at(170), policy.window);
+/// assert_eq!(failures, 3);
+/// assert!(policy.gives_up(failures));
+///
+/// // A fresh start forgets them all.
+/// window.clear();
+/// assert_eq!(window.record(at(171), policy.window), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct FailureWindow {
+    failures: VecDeque<Instant>,
+}
+
+impl FailureWindow {
+    /// Records a failure at `now`, forgets those older than `window`, and returns how many
+    /// are left including this one.
+    pub fn record(&mut self, now: Instant, window: Duration) -> u32 {
+        while self
+            .failures
+            .front()
+            .is_some_and(|failure| now.duration_since(*failure) > window)
+        {
+            self.failures.pop_front();
+        }
+        self.failures.push_back(now);
+        self.failures.len() as u32
+    }
+
+    /// Forgets all failures.
+    pub fn clear(&mut self) {
+        self.failures.clear();
+    }
+}
+
+// A random fraction between 0 and 1 for jittering restart delays. Every `RandomState` is
+// seeded differently, which is random enough here without pulling in a crate.
+pub(crate) fn random_jitter() -> f64 {
+    let hash = RandomState::new().build_hasher().finish();
+    (hash >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,194 @@
+//! Template functions for [Tera](https://keats.github.io/tera/), enabled with the `tera`
+//! feature.
+//!
+//! [`register`] adds three functions to a `Tera` instance, which render what
+//! [`crate::assets`] and [`crate::html`] do for the given options:
+//!
+//! - `vite_assets(entry="src/main.tsx")`, the tags that load an entry, or several with
+//!   `entries=["src/main.tsx", "src/admin.tsx"]`.
+//! - `vite_asset_url(path="src/assets/logo.svg")`, the URL of a single file.
+//! - `vite_react_refresh()`, the preamble of `@vitejs/plugin-react`, empty outside of dev
+//!   mode.
+//!
+//! `vite_assets` and `vite_react_refresh` take an optional `nonce`, e.g.
+//! `vite_assets(entry="src/main.tsx", nonce=nonce)`, which every tag they return carries,
+//! for a Content Security Policy with nonces.
+//!
+//! They return HTML, and URLs of the build, so mark them `safe`; Tera would escape every `/`
+//! of a URL otherwise. Outside of dev mode they read the manifest of the build, which is
+//! loaded once and again only when it changes. An entry or file the build does not have
+//! fails the render, with its name in the error.
+//!
+//! # Example
+//! ```rust
+//! use tera::{Context, Tera};
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//!
+//! let template = "{{ vite_react_refresh() | safe }}\n\
+//!                 {{ vite_assets(entry=\"src/main.tsx\") | safe }}\n\
+//!                 <img src=\"{{ vite_asset_url(path=\"src/assets/react.svg\") | safe }}\">";
+//!
+//! // In dev mode, everything comes from the dev server.
+//! let mut dev = Tera::default();
+//! dev.add_raw_template("page.html", template).unwrap();
+//! vite_actix::tera::register(&mut dev, ProxyViteOptions::new().dev_mode(true));
+//! let page = dev.render("page.html", &Context::new()).unwrap();
+//! assert!(page.starts_with("<script type=\"module\">\nimport "));
+//! assert!(page.ends_with(
+//!     "<script type=\"module\" src=\"/@vite/client\"></script>\n\
+//!      <script type=\"module\" src=\"/src/main.tsx\"></script>\n\
+//!      <img src=\"/src/assets/react.svg\">"
+//! ));
+//!
+//! // Otherwise from the manifest of the build.
+//! let mut prod = Tera::default();
+//! prod.add_raw_template("page.html", template).unwrap();
+//! let options = ProxyViteOptions::new()
+//!     .dev_mode(false)
+//!     .dist_directory("examples/manifests/vite5");
+//! vite_actix::tera::register(&mut prod, options);
+//! let page = prod.render("page.html", &Context::new()).unwrap();
+//! assert!(page.starts_with(
+//!     "\n<link rel=\"stylesheet\" href=\"/assets/main-DiwrgTda.css\">\n"
+//! ));
+//! assert!(page.contains("<script type=\"module\" src=\"/assets/main-CuXyZ8bL.js\"></script>"));
+//! assert!(page.ends_with("<img src=\"/assets/react-CHdo91hT.svg\">"));
+//!
+//! // A nonce lands on every tag, escaped.
+//! prod.add_raw_template("nonce.html", "{{ vite_assets(entry=\"src/main.tsx\", nonce=nonce) | safe }}")
+//!     .unwrap();
+//! let mut context = Context::new();
+//! context.insert("nonce", "r4nd\"><b>");
+//! let page = prod.render("nonce.html", &context).unwrap();
+//! assert_eq!(page.lines().count(), page.matches(" nonce=\"r4nd&quot;&gt;&lt;b&gt;\">").count());
+//! assert!(!page.contains("<b>"));
+//!
+//! // Entries the build does not have fail the render.
+//! prod.add_raw_template("typo.html", "{{ vite_assets(entry=\"src/mian.tsx\") | safe }}")
+//!     .unwrap();
+//! let error = prod.render("typo.html", &Context::new()).unwrap_err();
+//! let mut cause: &dyn std::error::Error = &error;
+//! while let Some(source) = cause.source() {
+//!     cause = source;
+//! }
+//! assert_eq!(
+//!     cause.to_string(),
+//!     "vite_assets: the Vite manifest in examples/manifests/vite5 has no entry \"src/mian.tsx\""
+//! );
+//! ```
+
+use crate::assets::{AssetTags, asset_url_with};
+use crate::html::react_refresh_preamble_with_nonce;
+use crate::manifest::ViteManifest;
+use crate::proxy_vite_options::ProxyViteOptions;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tera::{Error, Tera, Value};
+
+/// Registers `vite_assets`, `vite_asset_url` and `vite_react_refresh` on `tera`, for
+/// `options`, e.g. [`ProxyViteOptions::global`] after `build()`, see [`crate::tera`].
+pub fn register(tera: &mut Tera, options: ProxyViteOptions) {
+    let options = Arc::new(options);
+
+    let assets_options = options.clone();
+    tera.register_function("vite_assets", move |args: &HashMap<String, Value>| {
+        let entries = entries_arg(args)?;
+        if !serves_sources(&assets_options) {
+            let manifest = manifest("vite_assets", &assets_options)?;
+            if let Some(missing) = entries.iter().find(|entry| manifest.entry(entry).is_none()) {
+                return Err(Error::msg(format!(
+                    "vite_assets: the Vite manifest in {} has no entry {:?}",
+                    assets_options.dist_dir().display(),
+                    missing
+                )));
+            }
+        }
+        let entries: Vec<&str> = entries.iter().map(String::as_str).collect();
+        let mut tags = AssetTags::for_entries_with(&assets_options, &entries);
+        if let Some(nonce) = nonce_arg("vite_assets", args)? {
+            tags = tags.with_nonce(nonce);
+        }
+        Ok(Value::String(tags.to_string()))
+    });
+
+    let url_options = options.clone();
+    tera.register_function("vite_asset_url", move |args: &HashMap<String, Value>| {
+        let path = match args.get("path") {
+            Some(Value::String(path)) => path,
+            _ => {
+                return Err(Error::msg(
+                    "vite_asset_url: expected a `path` argument, a string",
+                ));
+            }
+        };
+        let is_url = path.starts_with("//") || path.contains("://") || path.starts_with("data:");
+        if !is_url && !serves_sources(&url_options) {
+            let manifest = manifest("vite_asset_url", &url_options)?;
+            let dist_dir = url_options.dist_dir();
+            let public_file = dist_dir.join(path.trim_start_matches('/')).is_file();
+            if manifest.entry(path).is_none() && !public_file {
+                return Err(Error::msg(format!(
+                    "vite_asset_url: {:?} is neither in the Vite manifest nor in {}",
+                    path,
+                    dist_dir.display()
+                )));
+            }
+        }
+        Ok(Value::String(
+            asset_url_with(&url_options, path).into_owned(),
+        ))
+    });
+
+    tera.register_function(
+        "vite_react_refresh",
+        move |args: &HashMap<String, Value>| {
+            let nonce = nonce_arg("vite_react_refresh", args)?;
+            let preamble = react_refresh_preamble_with_nonce(&options, nonce.as_deref());
+            Ok(Value::String(preamble))
+        },
+    );
+}
+
+// Whether the dev server serves the sources, so there is no manifest to check against.
+fn serves_sources(options: &ProxyViteOptions) -> bool {
+    options.dev_mode && options.command.is_dev_server()
+}
+
+// The manifest of the build, for the error of `function` if it can't be loaded.
+fn manifest(function: &str, options: &ProxyViteOptions) -> tera::Result<Arc<ViteManifest>> {
+    ViteManifest::cached(&options.dist_dir()).map_err(|e| {
+        Error::msg(format!(
+            "{}: failed to load the Vite manifest: {:#}",
+            function, e
+        ))
+    })
+}
+
+// The `entry`, or `entries`, argument of `vite_assets`.
+fn entries_arg(args: &HashMap<String, Value>) -> tera::Result<Vec<String>> {
+    let invalid = || {
+        Error::msg(
+            "vite_assets: expected an `entry` argument, a string, or `entries`, a list of strings",
+        )
+    };
+    match (args.get("entry"), args.get("entries")) {
+        (Some(Value::String(entry)), None) => Ok(vec![entry.clone()]),
+        (None, Some(Value::Array(entries))) => entries
+            .iter()
+            .map(|entry| entry.as_str().map(String::from).ok_or_else(invalid))
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+// The optional `nonce` argument of `function`.
+fn nonce_arg(function: &str, args: &HashMap<String, Value>) -> tera::Result<Option<String>> {
+    match args.get("nonce") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(nonce)) => Ok(Some(nonce.clone())),
+        Some(_) => Err(Error::msg(format!(
+            "{}: expected the `nonce` argument to be a string",
+            function
+        ))),
+    }
+}
@@ -0,0 +1,750 @@
+//! Helpers for testing applications that use [`configure_vite`], enabled with the
+//! `test-util` feature.
+//!
+//! [`MockVite`] is a stub in place of Vite with options pointing at it, which
+//! [`ViteAppFactory::configure_vite_with`] uses without reading or changing the global
+//! options, so every test can build its own application with
+//! `actix_web::test::init_service`, and tests can run in parallel. It answers with what
+//! the test scripted, and records what the proxy sent it.
+//!
+//! [`ViteShim`] is a fake `vite` executable instead, for the process the crate starts and
+//! the output it reads.
+//!
+//! With the `integration-tests` feature, `RealVite` installs and starts an actual Vite
+//! instead, for the HMR socket, the banner and the readiness of a real one.
+//!
+//! # Example
+//! ```rust
+//! use actix_web::{App, HttpResponse, test, web};
+//! use vite_actix::test::MockVite;
+//! use vite_actix::vite_app_factory::ViteAppFactory;
+//!
+//! # #[actix_web::main]
+//! # async fn main() {
+//! let upstream = MockVite::start().await;
+//! let app = test::init_service(
+//!     App::new()
+//!         .route("/api/ping", web::get().to(|| async { HttpResponse::Ok().body("pong") }))
+//!         .configure_vite_with(upstream.options()),
+//! )
+//! .await;
+//!
+//! // Routes of the application answer as usual.
+//! let req = test::TestRequest::get().uri("/api/ping").to_request();
+//! assert_eq!(test::call_and_read_body(&app, req).await, "pong");
+//!
+//! // Everything else goes to the mock.
+//! let req = test::TestRequest::get().uri("/src/main.ts?v=1").to_request();
+//! let res = test::call_service(&app, req).await;
+//! assert!(res.status().is_success());
+//! assert_eq!(test::read_body(res).await, "mock vite: GET /src/main.ts?v=1");
+//! # }
+//! ```
+//!
+//! # Several Frontends
+//! Every `configure_vite_with` proxies to the server of its own options, and one mounted in a
+//! scope forwards the path without the scope, WebSockets included:
+//! ```rust
+//! use actix_web::{App, HttpServer, test, web};
+//! use futures_util::StreamExt;
+//! use vite_actix::test::{MOCK_HEADER, MockVite};
+//! use vite_actix::vite_app_factory::ViteAppFactory;
+//!
+//! # #[actix_web::main]
+//! # async fn main() {
+//! let admin = MockVite::start().await;
+//! let public = MockVite::start().await;
+//! let (admin_options, public_options) = (admin.options(), public.options());
+//! let app = move || {
+//!     App::new()
+//!         .service(web::scope("/admin").configure_vite_with(admin_options.clone()))
+//!         .configure_vite_with(public_options.clone())
+//! };
+//!
+//! let service = test::init_service(app()).await;
+//! for (uri, upstream, forwarded) in [
+//!     ("/admin/src/main.ts", &admin, "/src/main.ts"),
+//!     ("/admin", &admin, "/"),
+//!     ("/src/main.ts", &public, "/src/main.ts"),
+//!     ("/administrator", &public, "/administrator"),
+//! ] {
+//!     let req = test::TestRequest::get().uri(uri).to_request();
+//!     let res = test::call_service(&service, req).await;
+//!     assert_eq!(res.headers().get(MOCK_HEADER).unwrap(), &upstream.port().to_string());
+//!     assert_eq!(test::read_body(res).await, format!("mock vite: GET {}", forwarded));
+//! }
+//!
+//! // The HMR socket of a page under `/admin` reaches the admin server.
+//! let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+//! let port = listener.local_addr().unwrap().port();
+//! let server = HttpServer::new(app).workers(1).disable_signals().listen(listener).unwrap().run();
+//! # let handle = server.handle();
+//! actix_web::rt::spawn(server);
+//! for (path, forwarded) in [("/admin/?token=a", "/?token=a"), ("/?token=b", "/?token=b")] {
+//!     let (res, mut socket) = awc::Client::new()
+//!         .ws(format!("ws://127.0.0.1:{}{}", port, path))
+//!         .protocols(["vite-hmr"])
+//!         .connect()
+//!         .await
+//!         .unwrap();
+//!     assert_eq!(res.headers().get("sec-websocket-protocol").unwrap(), "vite-hmr");
+//!     let Some(Ok(awc::ws::Frame::Text(text))) = socket.next().await else {
+//!         panic!("expected a text message");
+//!     };
+//!     assert_eq!(text, format!("mock vite: GET {}", forwarded));
+//! }
+//! # handle.stop(false).await;
+//! # }
+//! ```
+//!
+//! # The HMR Socket
+//! WebSocket upgrades at the HMR path go straight to Vite's HMR server, on its `hmr_port` if
+//! it has one of its own, while plain requests to the same path go through the proxy:
+//! ```rust
+//! use actix_web::http::StatusCode;
+//! use actix_web::http::header::{CONNECTION, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_VERSION, UPGRADE};
+//! use actix_web::{App, test};
+//! use vite_actix::test::{MOCK_HEADER, MockVite};
+//! use vite_actix::vite_app_factory::ViteAppFactory;
+//!
+//! # #[actix_web::main]
+//! # async fn main() {
+//! let vite = MockVite::start().await;
+//! let hmr = MockVite::start().await;
+//! let app = test::init_service(
+//!     App::new().configure_vite_with(vite.options().hmr_port(hmr.port())),
+//! )
+//! .await;
+//! let upgrade = |uri: &str| {
+//!     test::TestRequest::get()
+//!         .uri(uri)
+//!         .insert_header((UPGRADE, "websocket"))
+//!         .insert_header((CONNECTION, "Upgrade"))
+//!         .insert_header((SEC_WEBSOCKET_VERSION, "13"))
+//!         .insert_header((SEC_WEBSOCKET_KEY, "dGhlIHNhbXBsZSBub25jZQ=="))
+//!         .to_request()
+//! };
+//!
+//! let req = test::TestRequest::get().uri("/").to_request();
+//! let res = test::call_service(&app, req).await;
+//! assert_eq!(res.headers().get(MOCK_HEADER).unwrap(), &vite.port().to_string());
+//!
+//! let res = test::call_service(&app, upgrade("/?token=a")).await;
+//! assert_eq!(res.status(), StatusCode::SWITCHING_PROTOCOLS);
+//!
+//! // Vite's HMR server is the one the socket is opened to.
+//! let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+//! let app = test::init_service(App::new().configure_vite_with(vite.options().hmr_port(closed)))
+//!     .await;
+//! let res = test::call_service(&app, upgrade("/?token=a")).await;
+//! assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+//! let body = test::read_body(res).await;
+//! assert!(String::from_utf8_lossy(&body).contains(&format!("ws://localhost:{}/?token=a", closed)));
+//! # }
+//! ```
+//!
+//! # Scripted Responses
+//! [`MockVite::respond`] scripts the answer for a path, and [`MockVite::requests`] shows what
+//! the proxy forwarded:
+//! ```rust
+//! use actix_web::http::StatusCode;
+//! use actix_web::http::header::{CONTENT_TYPE, LOCATION};
+//! use actix_web::{App, test, web};
+//! use vite_actix::test::{MockResponse, MockVite};
+//! use vite_actix::vite_app_factory::ViteAppFactory;
+//!
+//! # #[actix_web::main]
+//! # async fn main() {
+//! let vite = MockVite::start().await;
+//! vite.respond(
+//!     "/src/missing.ts",
+//!     MockResponse::new(StatusCode::NOT_FOUND).body("no such module"),
+//! )
+//! .respond("/items", MockResponse::new(StatusCode::CREATED).header((LOCATION, "/items/1")))
+//! .respond(
+//!     "/favicon.ico",
+//!     MockResponse::ok().header((CONTENT_TYPE, "text/html")).body("<!doctype html>"),
+//! );
+//! let app = test::init_service(
+//!     App::new().service(web::scope("/app").configure_vite_with(vite.options())),
+//! )
+//! .await;
+//!
+//! // Vite's status and body reach the client.
+//! let req = test::TestRequest::get().uri("/app/src/missing.ts").to_request();
+//! let res = test::call_service(&app, req).await;
+//! assert_eq!(res.status(), StatusCode::NOT_FOUND);
+//! assert_eq!(test::read_body(res).await, "no such module");
+//!
+//! // Locations stay in the scope.
+//! let req = test::TestRequest::post().uri("/app/items").to_request();
+//! let res = test::call_service(&app, req).await;
+//! assert_eq!(res.headers().get(LOCATION).unwrap(), "/app/items/1");
+//!
+//! // A missing favicon is a 404, not Vite's index.html.
+//! let req = test::TestRequest::get().uri("/app/favicon.ico").to_request();
+//! assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+//!
+//! // Bodies are forwarded, and Vite learns about the scope.
+//! let req = test::TestRequest::post().uri("/app/api?x=1").set_payload("{}").to_request();
+//! test::call_service(&app, req).await;
+//! let forwarded = vite.requests().pop().unwrap();
+//! assert_eq!(forwarded.method, "POST");
+//! assert_eq!(forwarded.uri, "/api?x=1");
+//! assert_eq!(forwarded.body, "{}");
+//! assert_eq!(forwarded.header("x-forwarded-prefix"), Some("/app"));
+//! assert_eq!(vite.requests().len(), 4);
+//! # }
+//! ```
+//!
+//! # Slow and Broken Upstreams
+//! A response can be delayed, or cut off after its headers:
+//! ```rust
+//! use actix_web::http::StatusCode;
+//! use actix_web::{App, test};
+//! use std::time::{Duration, Instant};
+//! use vite_actix::test::{MockResponse, MockVite};
+//! use vite_actix::vite_app_factory::ViteAppFactory;
+//!
+//! # #[actix_web::main]
+//! # async fn main() {
+//! let vite = MockVite::start().await;
+//! vite.respond("/slow.ts", MockResponse::ok().delay(Duration::from_millis(200)))
+//!     .respond("/crash.ts", MockResponse::ok().drop_connection());
+//! let app = test::init_service(App::new().configure_vite_with(vite.options())).await;
+//!
+//! let started = Instant::now();
+//! let req = test::TestRequest::get().uri("/slow.ts").to_request();
+//! assert!(test::call_service(&app, req).await.status().is_success());
+//! assert!(started.elapsed() >= Duration::from_millis(200));
+//!
+//! let req = test::TestRequest::get().uri("/crash.ts").to_request();
+//! let res = test::call_service(&app, req).await;
+//! assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+//! # }
+//! ```
+//!
+//! # A Fake Vite
+//! [`ViteShim`] writes a `vite` that prints what the test wants, to go through the startup
+//! of the managed process and what it reads from Vite's output:
+//! ```rust,standalone_crate
+//! # #[cfg(all(unix, feature = "spawn"))]
+//! # {
+//! use std::time::Duration;
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//! use vite_actix::test::ViteShim;
+//! use vite_actix::vite_version::ViteVersion;
+//!
+//! let project = std::env::temp_dir().join(format!("vite-actix-shim-{}", std::process::id()));
+//! ViteShim::new()
+//!     .version("6.0.1")
+//!     .banner(5199)
+//!     .pause(Duration::from_millis(100))
+//!     .stderr_line("12:00:00 [vite] Internal server error: Unexpected token")
+//!     .stderr_line("  File: /app/src/main.ts:3:10")
+//!     .write(&project)
+//!     .unwrap();
+//!
+//! actix_web::rt::System::new().block_on(async {
+//!     ProxyViteOptions::new()
+//!         .dev_mode(true)
+//!         .working_directory(project.to_str().unwrap())
+//!         .build()
+//!         .unwrap();
+//!     let vite = vite_actix::start_vite_server_managed().unwrap();
+//!     vite.wait_ready().await.unwrap();
+//!     assert_eq!(vite.port(), Some(5199));
+//!
+//!     while vite.recent_errors().is_empty() || vite.vite_version().is_none() {
+//!         actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+//!     }
+//!     assert_eq!(vite.vite_version(), ViteVersion::parse("6.0.1"));
+//!     let error = &vite.recent_errors()[0];
+//!     assert_eq!(error.message, "Unexpected token");
+//!     assert_eq!(error.file.as_deref(), Some("/app/src/main.ts:3:10"));
+//!     vite.shutdown().unwrap();
+//! });
+//!
+//! // A Vite that fails to start.
+//! ViteShim::new().stderr_line("error when starting dev server").exit(1).write(&project).unwrap();
+//! actix_web::rt::System::new().block_on(async {
+//!     let vite = vite_actix::start_vite_server_managed().unwrap();
+//!     let status = vite.wait_ready().await.unwrap_err();
+//!     assert_eq!(status.code(), Some(1));
+//! });
+//! std::fs::remove_dir_all(&project).unwrap();
+//! # }
+//! ```
+//!
+//! [`configure_vite`]: crate::vite_app_factory::ViteAppFactory::configure_vite
+//! [`ViteAppFactory::configure_vite_with`]: crate::vite_app_factory::ViteAppFactory::configure_vite_with
+
+use crate::proxy_vite_options::ProxyViteOptions;
+use actix_codec::Encoder;
+use actix_http::ws;
+use actix_web::dev::ServerHandle;
+use actix_web::http::header::{
+    HeaderMap, HeaderName, HeaderValue, SEC_WEBSOCKET_PROTOCOL, TryIntoHeaderPair,
+};
+use actix_web::http::{Method, StatusCode, Uri};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, web};
+use std::collections::HashMap;
+use std::io;
+use std::net::TcpListener;
+#[cfg(all(unix, feature = "spawn"))]
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[cfg(feature = "integration-tests")]
+pub use crate::real_vite::{MAIN_JS, RealVite};
+
+/// The header every response of the mock upstream carries, to tell it apart from the
+/// responses of the application under test. Its value is the port of the stub, which tells
+/// several stubs apart.
+pub const MOCK_HEADER: &str = "x-vite-actix-mock";
+
+/// A stub server standing in for Vite, started by [`MockVite::start`].
+///
+/// Paths without a response scripted with [`MockVite::respond`] are answered with a
+/// `200 OK` and a `text/plain` body of `mock vite: <method> <path and query>`. A WebSocket,
+/// like the HMR socket of Vite's client, gets the same text as its one message before it
+/// closes. Every request is recorded for [`MockVite::requests`], and the stub stops when
+/// dropped.
+#[derive(Debug)]
+pub struct MockVite {
+    port: u16,
+    server: ServerHandle,
+    state: Arc<MockState>,
+}
+
+/// The former name of [`MockVite`].
+pub type MockUpstream = MockVite;
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: Mutex<HashMap<String, MockResponse>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+}
+
+impl MockVite {
+    /// Starts the stub on a free port of `127.0.0.1`.
+    ///
+    /// Has to be called from within an actix runtime, e.g. an `#[actix_web::test]`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no port could be bound.
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind the mock upstream");
+        let port = listener
+            .local_addr()
+            .expect("Failed to read the port of the mock upstream")
+            .port();
+        let state = Arc::new(MockState::default());
+        let data = web::Data::from(state.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .default_service(web::to(answer))
+        })
+        .workers(1)
+        .disable_signals()
+        .listen(listener)
+        .expect("Failed to start the mock upstream")
+        .run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        Self {
+            port,
+            server: handle,
+            state,
+        }
+    }
+
+    /// The port the stub listens on, on `127.0.0.1`.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Options that proxy to the stub in dev mode, to pass to
+    /// [`configure_vite_with`](crate::vite_app_factory::ViteAppFactory::configure_vite_with).
+    pub fn options(&self) -> ProxyViteOptions {
+        // The field, not the builder, which would set the global port too.
+        ProxyViteOptions {
+            port: Some(self.port),
+            ..ProxyViteOptions::new().dev_mode(true)
+        }
+    }
+
+    /// Answers requests for `path` with `response` from now on, replacing what was scripted
+    /// for it before.
+    ///
+    /// `path` is compared with the path of a request, or with its path and query if it has
+    /// a `?`, e.g. `/src/main.ts?v=1`, which wins over the path alone.
+    pub fn respond(&self, path: impl Into<String>, response: MockResponse) -> &Self {
+        if let Ok(mut responses) = self.state.responses.lock() {
+            responses.insert(path.into(), response);
+        }
+        self
+    }
+
+    /// The requests the stub received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state
+            .requests
+            .lock()
+            .map(|requests| requests.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for MockVite {
+    fn drop(&mut self) {
+        // The stop command is sent right away; the returned future only waits for it.
+        drop(self.server.stop(false));
+    }
+}
+
+/// Starts a [`MockVite`], like [`MockVite::start`].
+///
+/// Has to be called from within an actix runtime, e.g. an `#[actix_web::test]`.
+///
+/// # Panics
+///
+/// Panics if no port could be bound.
+pub async fn mock_upstream() -> MockVite {
+    MockVite::start().await
+}
+
+/// A response scripted with [`MockVite::respond`].
+///
+/// Every response also carries the [`MOCK_HEADER`].
+#[derive(Clone, Debug)]
+pub struct MockResponse {
+    status: StatusCode,
+    headers: Vec<(HeaderName, HeaderValue)>,
+    body: web::Bytes,
+    delay: Duration,
+    drop_connection: bool,
+}
+
+impl MockResponse {
+    /// A response with `status`, without headers and with an empty body.
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: web::Bytes::new(),
+            delay: Duration::ZERO,
+            drop_connection: false,
+        }
+    }
+
+    /// A `200 OK`, without headers and with an empty body.
+    pub fn ok() -> Self {
+        Self::new(StatusCode::OK)
+    }
+
+    /// Adds a header, e.g. `(CONTENT_TYPE, "text/html")`. A header added more than once is
+    /// sent once for every value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the name or the value is not a valid header.
+    pub fn header(mut self, header: impl TryIntoHeaderPair) -> Self {
+        let Ok(header) = header.try_into_pair() else {
+            panic!("Invalid header for a mock response");
+        };
+        self.headers.push(header);
+        self
+    }
+
+    /// Sets the body.
+    pub fn body(mut self, body: impl Into<web::Bytes>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    /// Waits for `delay` before answering, like a Vite that is still transforming a module.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Closes the connection after the status line and the headers instead of sending the
+    /// body, like a Vite that crashes while answering.
+    pub fn drop_connection(mut self) -> Self {
+        self.drop_connection = true;
+        self
+    }
+
+    async fn into_response(self, port: String) -> HttpResponse {
+        if !self.delay.is_zero() {
+            actix_web::rt::time::sleep(self.delay).await;
+        }
+        let mut res = HttpResponse::build(self.status);
+        res.insert_header((MOCK_HEADER, port));
+        for header in self.headers {
+            res.append_header(header);
+        }
+        if self.drop_connection {
+            // Actix closes the connection when the body fails.
+            return res.streaming(futures_util::stream::once(async {
+                Err::<web::Bytes, _>(io::Error::new(
+                    io::ErrorKind::ConnectionAborted,
+                    "The mock upstream dropped the connection",
+                ))
+            }));
+        }
+        res.body(self.body)
+    }
+}
+
+/// A request [`MockVite`] received, as the proxy forwarded it.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub method: Method,
+    /// The path and query, e.g. `/src/main.ts?v=1`.
+    pub uri: Uri,
+    pub headers: HeaderMap,
+    pub body: web::Bytes,
+}
+
+impl RecordedRequest {
+    /// The value of the header `name`, if the request has it and it is text.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)?.to_str().ok()
+    }
+}
+
+// Record the request, then answer with its scripted response, or the echo.
+async fn answer(
+    req: HttpRequest,
+    payload: web::Payload,
+    state: web::Data<MockState>,
+) -> Result<HttpResponse, actix_web::Error> {
+    // The payload of an upgrade is the socket, which never ends.
+    let body = if crate::ws_proxy::is_websocket(&req) {
+        web::Bytes::new()
+    } else {
+        payload.to_bytes().await?
+    };
+    if let Ok(mut requests) = state.requests.lock() {
+        requests.push(RecordedRequest {
+            method: req.method().clone(),
+            uri: req.uri().clone(),
+            headers: req.headers().clone(),
+            body,
+        });
+    }
+    let scripted = state.responses.lock().ok().and_then(|responses| {
+        responses
+            .get(&req.uri().to_string())
+            .or_else(|| responses.get(req.path()))
+            .cloned()
+    });
+    let port = req.app_config().local_addr().port().to_string();
+    match scripted {
+        Some(response) => Ok(response.into_response(port).await),
+        None => echo(&req, port),
+    }
+}
+
+// Answer with the method and the path the proxy forwarded.
+fn echo(req: &HttpRequest, port: String) -> Result<HttpResponse, actix_web::Error> {
+    let text = format!("mock vite: {} {}", req.method(), req.uri());
+    if !crate::ws_proxy::is_websocket(req) {
+        return Ok(HttpResponse::Ok()
+            .content_type("text/plain")
+            .insert_header((MOCK_HEADER, port))
+            .body(text));
+    }
+
+    let mut response = ws::handshake(req.head())?;
+    if let Some(protocol) = req.headers().get(SEC_WEBSOCKET_PROTOCOL) {
+        response.insert_header((SEC_WEBSOCKET_PROTOCOL, protocol.clone()));
+    }
+    response.insert_header((MOCK_HEADER, port));
+    let mut frames = web::BytesMut::new();
+    let mut codec = ws::Codec::new();
+    codec.encode(ws::Message::Text(text.into()), &mut frames)?;
+    codec.encode(ws::Message::Close(None), &mut frames)?;
+    Ok(response.body(frames.freeze()).map_into_boxed_body().into())
+}
+
+/// A fake `vite` executable that prints what it is told to, for testing how the crate reads
+/// Vite's output without Node, e.g. with
+/// [`start_vite_server_managed`](crate::start_vite_server_managed).
+///
+/// [`ViteShim::write`] puts it where the crate looks for Vite first, the project's
+/// `node_modules/.bin`. It answers `vite --version`, prints its lines, and then runs until
+/// it is killed, or for ten minutes, unless [`ViteShim::exit`] makes it exit, or
+/// [`ViteShim::builds`] makes `vite build` write a build and exit. It is a
+/// `sh` script, so it is only available on Unix, and with the `spawn` feature.
+#[cfg(all(unix, feature = "spawn"))]
+#[derive(Clone, Debug)]
+pub struct ViteShim {
+    version: String,
+    steps: Vec<ShimStep>,
+    exit: Option<i32>,
+    builds: bool,
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+#[derive(Clone, Debug)]
+enum ShimStep {
+    Banner(u16),
+    Stdout(String),
+    Stderr(String),
+    Pause(Duration),
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+impl Default for ViteShim {
+    fn default() -> Self {
+        Self {
+            version: String::from("5.4.0"),
+            steps: Vec::new(),
+            exit: None,
+            builds: false,
+        }
+    }
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+impl ViteShim {
+    /// A shim of Vite 5.4.0 that prints nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The version `vite --version` and the banner report, e.g. `6.0.1`.
+    pub fn version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    /// Prints the banner of a dev server listening on `port`, as Vite 5 and 6 print it.
+    pub fn banner(mut self, port: u16) -> Self {
+        self.steps.push(ShimStep::Banner(port));
+        self
+    }
+
+    /// Prints `line` to stdout, e.g. a `Network:` line of the banner.
+    pub fn line(mut self, line: impl Into<String>) -> Self {
+        self.steps.push(ShimStep::Stdout(line.into()));
+        self
+    }
+
+    /// Prints `line` to stderr, e.g. a line of an error block.
+    pub fn stderr_line(mut self, line: impl Into<String>) -> Self {
+        self.steps.push(ShimStep::Stderr(line.into()));
+        self
+    }
+
+    /// Waits for `duration` before printing the next lines.
+    pub fn pause(mut self, duration: Duration) -> Self {
+        self.steps.push(ShimStep::Pause(duration));
+        self
+    }
+
+    /// Exits with `code` after printing, like a Vite that fails to start.
+    pub fn exit(mut self, code: i32) -> Self {
+        self.exit = Some(code);
+        self
+    }
+
+    /// Answers `vite build` by writing a `dist/index.html`, or one in the directory of
+    /// `--outDir`, after printing, and exiting, unless [`ViteShim::exit`] makes it fail.
+    pub fn builds(mut self) -> Self {
+        self.builds = true;
+        self
+    }
+
+    /// The script of the shim.
+    pub fn script(&self) -> String {
+        let mut script = format!(
+            "#!/bin/sh\n[ \"$1\" = --version ] && echo {} && exit\n",
+            quote(&format!("vite/{} linux-x64 node-v20.11.0", self.version))
+        );
+        // Lines in a row go to a stream at once, like Vite writes its banner and error
+        // blocks, which the crate reads as a whole.
+        let mut lines: Vec<String> = Vec::new();
+        let mut to_stderr = false;
+        for step in &self.steps {
+            let (stderr, printed) = match step {
+                ShimStep::Banner(port) => (
+                    false,
+                    vec![
+                        String::new(),
+                        format!("  VITE v{}  ready in 312 ms", self.version),
+                        String::new(),
+                        format!("  ➜  Local:   http://localhost:{}/", port),
+                        String::from("  ➜  Network: use --host to expose"),
+                        String::from("  ➜  press h + enter to show help"),
+                    ],
+                ),
+                ShimStep::Stdout(line) => (false, vec![line.clone()]),
+                ShimStep::Stderr(line) => (true, vec![line.clone()]),
+                ShimStep::Pause(duration) => {
+                    print_lines(&mut script, &mut lines, to_stderr);
+                    script.push_str(&format!("sleep {}\n", duration.as_secs_f64()));
+                    continue;
+                }
+            };
+            if stderr != to_stderr {
+                print_lines(&mut script, &mut lines, to_stderr);
+                to_stderr = stderr;
+            }
+            lines.extend(printed);
+        }
+        print_lines(&mut script, &mut lines, to_stderr);
+        if self.builds && self.exit.unwrap_or(0) == 0 {
+            script.push_str(concat!(
+                "if [ \"$1\" = build ]; then\n",
+                "  out=dist; [ \"$2\" = --outDir ] && out=\"$3\"\n",
+                "  mkdir -p \"$out\" && echo '<!DOCTYPE html><p>built by the vite shim</p>' > \"$out/index.html\"\n",
+                "  exit\n",
+                "fi\n",
+            ));
+        }
+        match self.exit {
+            Some(code) => script.push_str(&format!("exit {}\n", code)),
+            None => script.push_str("exec sleep 600\n"),
+        }
+        script
+    }
+
+    /// Writes the shim to `node_modules/.bin/vite` in `project`, creating the directories
+    /// if needed, and returns its path.
+    pub fn write(&self, project: impl AsRef<Path>) -> io::Result<PathBuf> {
+        use std::os::unix::fs::PermissionsExt;
+        let bin = project.as_ref().join("node_modules").join(".bin");
+        std::fs::create_dir_all(&bin)?;
+        let path = bin.join("vite");
+        std::fs::write(&path, self.script())?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+        Ok(path)
+    }
+}
+
+// Add a command that prints `lines`, if any, to the script, and clear them.
+#[cfg(all(unix, feature = "spawn"))]
+fn print_lines(script: &mut String, lines: &mut Vec<String>, to_stderr: bool) {
+    if lines.is_empty() {
+        return;
+    }
+    script.push_str("printf '%s\\n'");
+    for line in lines.drain(..) {
+        script.push(' ');
+        script.push_str(&quote(&line));
+    }
+    script.push_str(if to_stderr { " >&2\n" } else { "\n" });
+}
+
+// `text` as a single-quoted word of `sh`.
+#[cfg(all(unix, feature = "spawn"))]
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
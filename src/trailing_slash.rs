@@ -0,0 +1,154 @@
+//! Whether page routes end with a slash, set with [`ProxyViteOptions::trailing_slash`].
+//!
+//! Vite serves the HTML entry `about/index.html` of a multi-page app under `/about/`, and
+//! the client-side router of a single-page app under both `/about` and `/about/`. The two
+//! are then different URLs with the same content, and relative URLs in the page resolve
+//! against the one with the slash differently. With [`TrailingSlash::RedirectToSlash`] or
+//! [`TrailingSlash::RedirectToNoSlash`], page navigations to the other form get a
+//! `308 Permanent Redirect` to the preferred one, with the query string kept, before they
+//! reach Vite or the dist directory.
+//!
+//! Only page navigations are redirected, see [`is_page_route`]: modules, assets, `fetch`
+//! calls and paths under Vite's own prefixes are served as they are asked for.
+//!
+//! [`ProxyViteOptions::trailing_slash`]: crate::proxy_vite_options::ProxyViteOptions::trailing_slash
+
+use crate::navigation_fallback::is_navigation;
+use crate::proxy_vite_options::has_path_prefix;
+use actix_web::http::Method;
+#[cfg(any(feature = "proxy", feature = "prod-serve", feature = "embed"))]
+use actix_web::http::header::{ACCEPT, LOCATION};
+#[cfg(any(feature = "proxy", feature = "prod-serve", feature = "embed"))]
+use actix_web::{HttpRequest, HttpResponse};
+
+/// Whether page routes end with a slash, see [`crate::trailing_slash`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailingSlash {
+    /// Pages are served under the path they are asked for, with or without a slash.
+    #[default]
+    None,
+    /// `/about` is redirected to `/about/`, where the page of `about/index.html` is.
+    RedirectToSlash,
+    /// `/about/` is redirected to `/about`.
+    RedirectToNoSlash,
+}
+
+impl TrailingSlash {
+    /// `path` in the form the policy prefers, or `None` if it is in that form already. The
+    /// root `/` is never changed.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::trailing_slash::TrailingSlash;
+    ///
+    /// assert_eq!(TrailingSlash::RedirectToSlash.normalize("/about"), Some("/about/".into()));
+    /// assert_eq!(TrailingSlash::RedirectToSlash.normalize("/about/"), None);
+    /// assert_eq!(TrailingSlash::RedirectToNoSlash.normalize("/about//"), Some("/about".into()));
+    /// assert_eq!(TrailingSlash::RedirectToNoSlash.normalize("/about"), None);
+    /// assert_eq!(TrailingSlash::RedirectToNoSlash.normalize("/"), None);
+    /// assert_eq!(TrailingSlash::None.normalize("/about"), None);
+    /// ```
+    pub fn normalize(self, path: &str) -> Option<String> {
+        match self {
+            TrailingSlash::None => None,
+            TrailingSlash::RedirectToSlash if path.ends_with('/') => None,
+            TrailingSlash::RedirectToSlash => Some(format!("{}/", path)),
+            TrailingSlash::RedirectToNoSlash => {
+                let trimmed = path.trim_end_matches('/');
+                (trimmed.len() != path.len() && !trimmed.is_empty()).then(|| trimmed.to_string())
+            }
+        }
+    }
+}
+
+/// Whether a request for `path` is a page route the policy applies to: a page navigation,
+/// see [`is_navigation`], that is not under one of `excluded_prefixes`, e.g. Vite's own
+/// `/@vite` or `/src`.
+///
+/// # Example
+/// ```rust
+/// use actix_web::http::Method;
+/// use vite_actix::navigation_fallback::DEFAULT_VITE_PREFIXES;
+/// use vite_actix::trailing_slash::is_page_route;
+///
+/// let prefixes: Vec<String> = DEFAULT_VITE_PREFIXES.map(String::from).to_vec();
+/// let page = Some("text/html,application/xhtml+xml,*/*;q=0.8");
+///
+/// assert!(is_page_route(&Method::GET, page, "/about", &prefixes));
+/// assert!(is_page_route(&Method::GET, page, "/docs/guide/", &prefixes));
+/// // Files, modules and scripts' requests are not pages.
+/// assert!(!is_page_route(&Method::GET, page, "/about/logo.svg", &prefixes));
+/// assert!(!is_page_route(&Method::GET, page, "/src/pages", &prefixes));
+/// assert!(!is_page_route(&Method::GET, Some("*/*"), "/about", &prefixes));
+/// assert!(!is_page_route(&Method::POST, page, "/about", &prefixes));
+/// ```
+pub fn is_page_route(
+    method: &Method,
+    accept: Option<&str>,
+    path: &str,
+    excluded_prefixes: &[String],
+) -> bool {
+    is_navigation(method, accept, path)
+        && !excluded_prefixes
+            .iter()
+            .any(|prefix| has_path_prefix(path, prefix))
+}
+
+/// The `Location` of the redirect for a request for `path` with `query`, the part of the
+/// URL after the `?`, or `None` if the policy is happy with `path`. The query is kept as
+/// it is, and a path that would start with `//`, which browsers read as another host, is
+/// given a single slash.
+///
+/// # Example
+/// ```rust
+/// use vite_actix::trailing_slash::{TrailingSlash, redirect_location};
+///
+/// let to_slash = TrailingSlash::RedirectToSlash;
+/// assert_eq!(redirect_location(to_slash, "/about", ""), Some("/about/".into()));
+/// assert_eq!(
+///     redirect_location(to_slash, "/about", "tab=team&q=a%20b"),
+///     Some("/about/?tab=team&q=a%20b".into())
+/// );
+/// assert_eq!(redirect_location(to_slash, "/about/", "tab=team"), None);
+/// // Never to another host.
+/// assert_eq!(redirect_location(to_slash, "//evil.example", ""), Some("/evil.example/".into()));
+///
+/// let to_no_slash = TrailingSlash::RedirectToNoSlash;
+/// assert_eq!(redirect_location(to_no_slash, "/about/", "x=1"), Some("/about?x=1".into()));
+/// assert_eq!(redirect_location(to_no_slash, "//evil.example/", ""), Some("/evil.example".into()));
+/// ```
+pub fn redirect_location(policy: TrailingSlash, path: &str, query: &str) -> Option<String> {
+    let path = policy.normalize(path)?;
+    let path = format!("/{}", path.trim_start_matches('/'));
+    Some(match query {
+        "" => path,
+        query => format!("{}?{}", path, query),
+    })
+}
+
+// The redirect of `req` if it is a page route, by the path `route` the app sees, which
+// `policy` wants in the other form, e.g. under the scope of the proxy.
+#[cfg(any(feature = "proxy", feature = "prod-serve", feature = "embed"))]
+pub(crate) fn redirect(
+    req: &HttpRequest,
+    route: &str,
+    policy: TrailingSlash,
+    excluded_prefixes: &[String],
+) -> Option<HttpResponse> {
+    if policy == TrailingSlash::None || route == "/" {
+        return None;
+    }
+    let accept = req
+        .headers()
+        .get(ACCEPT)
+        .and_then(|value| value.to_str().ok());
+    if !is_page_route(req.method(), accept, route, excluded_prefixes) {
+        return None;
+    }
+    let location = redirect_location(policy, req.path(), req.query_string())?;
+    Some(
+        HttpResponse::PermanentRedirect()
+            .insert_header((LOCATION, location))
+            .finish(),
+    )
+}
@@ -0,0 +1,133 @@
+//! What the proxy does with a request that asks to switch protocols with an `Upgrade`
+//! header.
+//!
+//! A WebSocket, like Vite's HMR socket, is relayed to Vite. Other protocols, like `h2c`,
+//! cleartext HTTP/2, are nothing the proxy or Vite speak: copying the `Upgrade` header
+//! along leaves Vite's server holding a connection it does not know what to do with. As a
+//! server is free to answer an upgrade request without switching, the upgrade is dropped
+//! instead, with the headers that belong to it, and the request is forwarded as plain
+//! HTTP/1.1. Only a `CONNECT` asking for a protocol, which means nothing without the tunnel,
+//! is answered with `501 Not Implemented`.
+//!
+//! # Example
+//! ```rust
+//! use actix_web::http::Method;
+//! use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+//! use vite_actix::upgrade::{Upgrade, negotiate};
+//!
+//! let headers = |pairs: &[(&'static str, &'static str)]| {
+//!     let mut headers = HeaderMap::new();
+//!     for (name, value) in pairs {
+//!         headers.append(HeaderName::from_static(name), HeaderValue::from_static(value));
+//!     }
+//!     headers
+//! };
+//! let get = |pairs| negotiate(&Method::GET, &headers(pairs));
+//!
+//! // No Upgrade header, nothing to negotiate.
+//! assert_eq!(get(&[]), Upgrade::None);
+//! assert_eq!(get(&[("connection", "keep-alive")]), Upgrade::None);
+//!
+//! // WebSockets are relayed, whatever the case.
+//! assert_eq!(get(&[("connection", "Upgrade"), ("upgrade", "websocket")]), Upgrade::WebSocket);
+//! assert_eq!(get(&[("upgrade", "WebSocket")]), Upgrade::WebSocket);
+//!
+//! // h2c is dropped.
+//! let h2c = [
+//!     ("connection", "Upgrade, HTTP2-Settings"),
+//!     ("upgrade", "h2c"),
+//!     ("http2-settings", "AAMAAABkAAQAoAAAAAIAAAAA"),
+//! ];
+//! assert_eq!(get(&h2c), Upgrade::Downgrade(vec!["h2c".to_string()]));
+//!
+//! // Of several protocols, in one header or more, a WebSocket wins.
+//! assert_eq!(get(&[("upgrade", "h2c, websocket")]), Upgrade::WebSocket);
+//! assert_eq!(get(&[("upgrade", "h2c"), ("upgrade", "websocket")]), Upgrade::WebSocket);
+//! assert_eq!(
+//!     get(&[("upgrade", "h2c, TLS/1.2,, HTTP/2.0")]),
+//!     Upgrade::Downgrade(vec!["h2c".to_string(), "TLS/1.2".to_string(), "HTTP/2.0".to_string()])
+//! );
+//!
+//! // A CONNECT has no plain request to fall back to.
+//! assert_eq!(
+//!     negotiate(&Method::CONNECT, &headers(&[("upgrade", "h2c")])),
+//!     Upgrade::Unsupported(vec!["h2c".to_string()])
+//! );
+//! ```
+
+use actix_web::http::Method;
+use actix_web::http::header::{CONNECTION, HeaderMap, HeaderName, UPGRADE};
+
+/// How the proxy answers the `Upgrade` header of a request, see [`crate::upgrade`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Upgrade {
+    /// The request has no `Upgrade` header.
+    None,
+    /// `websocket` is among the protocols: the socket is relayed to Vite.
+    WebSocket,
+    /// Only other protocols, e.g. `h2c`: the request is forwarded without the upgrade.
+    Downgrade(Vec<String>),
+    /// A `CONNECT` for other protocols: answered with `501 Not Implemented`.
+    Unsupported(Vec<String>),
+}
+
+/// What to do with a request with `method` and `headers`, from the protocols of its
+/// `Upgrade` headers.
+pub fn negotiate(method: &Method, headers: &HeaderMap) -> Upgrade {
+    let protocols = protocols(headers);
+    if protocols.is_empty() {
+        Upgrade::None
+    } else if protocols
+        .iter()
+        .any(|protocol| protocol.eq_ignore_ascii_case("websocket"))
+    {
+        Upgrade::WebSocket
+    } else if method == Method::CONNECT {
+        Upgrade::Unsupported(protocols)
+    } else {
+        Upgrade::Downgrade(protocols)
+    }
+}
+
+// Whether `headers` are a complete WebSocket upgrade: `Upgrade: websocket` and `upgrade`
+// among the options of the `Connection` header.
+#[cfg(feature = "dev-proxy")]
+pub(crate) fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    negotiate(&Method::GET, headers) == Upgrade::WebSocket
+        && connection_options(headers).any(|option| option.eq_ignore_ascii_case("upgrade"))
+}
+
+// Remove the upgrade from the `headers` of a request that is forwarded without it:
+// `Upgrade`, `Connection` and the headers it names as its own, like `HTTP2-Settings`.
+pub(crate) fn strip(headers: &mut HeaderMap) {
+    let named: Vec<HeaderName> = connection_options(headers)
+        .filter_map(|option| HeaderName::from_bytes(option.as_bytes()).ok())
+        .collect();
+    for name in named {
+        headers.remove(name);
+    }
+    headers.remove(UPGRADE);
+    headers.remove(CONNECTION);
+}
+
+// The protocols of the `Upgrade` headers, e.g. `h2c` and `websocket`, in order.
+fn protocols(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(UPGRADE)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|protocol| !protocol.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+// The options of the `Connection` headers, e.g. `upgrade` and `keep-alive`.
+fn connection_options(headers: &HeaderMap) -> impl Iterator<Item = &str> {
+    headers
+        .get_all(CONNECTION)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|option| !option.is_empty())
+}
@@ -0,0 +1,108 @@
+//! Which requests the proxy asks Vite to answer uncompressed, so the changes it makes to
+//! bodies see plain bytes without turning off compression for everything else.
+//!
+//! The [`ProxyViteOptions::html_transform`], the [`ProxyViteOptions::csp_nonce`] and the
+//! [`ProxyViteOptions::dev_toolbar`] change pages. With any of them, requests that may be answered with a page are forwarded with
+//! `Accept-Encoding: identity` instead of the client's header: `GET` and `HEAD` requests for
+//! paths without an extension, which Vite answers with `index.html` if it has nothing else,
+//! or with an `.html` or `.htm` one, outside of Vite's own modules under `/@vite`, `/@id`,
+//! `/@fs` and `/@react-refresh`, and accepting HTML or anything. Every other request keeps
+//! the client's `Accept-Encoding`, so modules and assets still arrive compressed if Vite, or
+//! a plugin of it, compresses them.
+//!
+//! A page a request is not classified for, and which Vite sends compressed anyway, is passed
+//! on unchanged, unless [`ProxyViteOptions::decompress_upstream`] is set.
+//!
+//! # Example
+//! ```rust
+//! use actix_web::http::Method;
+//! use std::sync::Arc;
+//! use vite_actix::proxy_vite_options::ProxyViteOptions;
+//! use vite_actix::upstream_encoding::needs_identity;
+//!
+//! let page = Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8");
+//! let requests = [
+//!     (Method::GET, page, "/"),
+//!     (Method::GET, page, "/settings/profile"),
+//!     (Method::GET, Some("*/*"), "/about.html"),
+//!     (Method::HEAD, None, "/docs/"),
+//! ];
+//! let others = [
+//!     (Method::GET, Some("*/*"), "/src/main.ts"),
+//!     (Method::GET, Some("text/css,*/*;q=0.1"), "/src/index.css"),
+//!     (Method::GET, Some("*/*"), "/@vite/client"),
+//!     (Method::GET, Some("*/*"), "/@react-refresh"),
+//!     (Method::GET, Some("*/*"), "/node_modules/.vite/deps/vue.js"),
+//!     (Method::GET, Some("application/json"), "/api/users"),
+//!     (Method::POST, page, "/settings/profile"),
+//! ];
+//!
+//! // Without a transform, nothing is asked for uncompressed.
+//! let options = ProxyViteOptions::new();
+//! for (method, accept, path) in requests.iter().chain(&others) {
+//!     assert!(!needs_identity(&options, method, *accept, path), "{}", path);
+//! }
+//!
+//! // With one, only what may be a page is.
+//! for options in [
+//!     ProxyViteOptions::new().html_transform(Arc::new(|_, html| html)),
+//!     ProxyViteOptions::new().dev_toolbar(true),
+//! ] {
+//!     for (method, accept, path) in &requests {
+//!         assert!(needs_identity(&options, method, *accept, path), "{}", path);
+//!     }
+//!     for (method, accept, path) in &others {
+//!         assert!(!needs_identity(&options, method, *accept, path), "{}", path);
+//!     }
+//! }
+//! ```
+//!
+//! [`ProxyViteOptions::html_transform`]: crate::proxy_vite_options::ProxyViteOptions::html_transform
+//! [`ProxyViteOptions::dev_toolbar`]: crate::proxy_vite_options::ProxyViteOptions::dev_toolbar
+//! [`ProxyViteOptions::csp_nonce`]: crate::proxy_vite_options::ProxyViteOptions::csp_nonce
+//! [`ProxyViteOptions::decompress_upstream`]: crate::proxy_vite_options::ProxyViteOptions::decompress_upstream
+
+use crate::proxy_vite_options::ProxyViteOptions;
+use actix_web::http::Method;
+
+// Path prefixes of the modules Vite serves itself, which are never pages.
+const VITE_PREFIXES: [&str; 4] = ["/@vite", "/@id", "/@fs", "/@react-refresh"];
+
+/// Whether a request for `path`, as Vite sees it, is forwarded with
+/// `Accept-Encoding: identity`: the options change pages, and it may be answered with one.
+pub fn needs_identity(
+    options: &ProxyViteOptions,
+    method: &Method,
+    accept: Option<&str>,
+    path: &str,
+) -> bool {
+    options.changes_pages() && may_be_page(method, accept, path)
+}
+
+/// Whether Vite may answer a request for `path` with an HTML page, see
+/// [`crate::upstream_encoding`].
+pub fn may_be_page(method: &Method, accept: Option<&str>, path: &str) -> bool {
+    if method != Method::GET && method != Method::HEAD {
+        return false;
+    }
+    if VITE_PREFIXES
+        .iter()
+        .any(|prefix| crate::proxy_vite_options::has_path_prefix(path, prefix))
+    {
+        return false;
+    }
+    let accepts_html = accept.is_none_or(|accept| {
+        accept.is_empty()
+            || accept.contains("text/html")
+            || accept.contains("text/*")
+            || accept.contains("*/*")
+    });
+    let name = path.rsplit('/').next().unwrap_or_default();
+    let page_name = match name.rsplit_once('.') {
+        Some((_, extension)) => {
+            extension.eq_ignore_ascii_case("html") || extension.eq_ignore_ascii_case("htm")
+        }
+        None => true,
+    };
+    accepts_html && page_name
+}
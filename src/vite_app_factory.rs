@@ -1,24 +1,269 @@
-use crate::proxy_to_vite;
-use actix_web::{web, App, Error};
+use crate::error::{ConfigProblem, ViteConfigError};
+use crate::health::{self, Build};
+#[cfg(feature = "dev-proxy")]
+use crate::proxy;
+use crate::proxy::ProxyTarget;
+#[cfg(feature = "dev-proxy")]
+use crate::{hmr_route, ws_proxy};
+use crate::proxy_vite_options::ProxyViteOptions;
+#[cfg(feature = "dev-proxy")]
+use crate::proxy_vite_options::route_problem;
+#[cfg(all(feature = "dev-proxy", feature = "prod-serve"))]
+use crate::built_dist::CACHE_NO_CACHE;
+#[cfg(feature = "prod-serve")]
+use crate::static_files::serve_built_file;
+#[cfg(all(feature = "dev-proxy", feature = "prod-serve"))]
+use crate::static_files::serve_dist_file;
+#[cfg(feature = "dev-proxy")]
+use crate::vite_command::ViteCommand;
+#[cfg(all(feature = "dev-proxy", feature = "prod-serve"))]
+use actix_web::http::header::HeaderValue;
+#[cfg(feature = "dev-proxy")]
+use actix_web::guard;
+#[cfg(any(feature = "dev-proxy", feature = "prod-serve"))]
+use actix_web::web;
+use actix_web::{App, Error, Resource, Route};
+use log::warn;
+#[cfg(feature = "prod-serve")]
+use std::sync::Arc;
 
 /// Trait for configuring a Vite development proxy in an Actix web application.
 ///
 /// This trait provides a method `configure_vite` to configure a web application
 /// for proxying requests to the Vite development server during development,
-/// while leaving the application unchanged in production.
+/// and for serving the built `dist` directory in production.
 pub trait ViteAppFactory {
     /// Configures the application to integrate with a Vite development proxy.
     ///
     /// This method configures the application to forward requests to a Vite
     /// development server, enabling features such as hot module replacement (HMR)
-    /// during development. In a production environment, this configuration
-    /// typically has no effect, ensuring no unnecessary overhead when serving
-    /// static files or pre-compiled assets.
+    /// during development. In a production environment, it serves the files of
+    /// [`ProxyViteOptions::dist_dir`] instead, caching hashed assets for a year, unless
+    /// that is turned off with [`ProxyViteOptions::serve_dist`] or by disabling the
+    /// `prod-serve` feature; then the application is left unchanged.
+    ///
+    /// Whether the proxy is registered is decided by [`ProxyViteOptions::dev_mode`],
+    /// which defaults to `cfg!(debug_assertions)`. Without the `dev-proxy` feature, the
+    /// proxy is not compiled in at all, and the dist directory is served in any mode.
+    ///
+    /// With the dev server, a resource at [`ProxyViteOptions::hmr_path`] relays the WebSocket
+    /// of Vite's HMR client, to [`ProxyViteOptions::hmr_port`] if set. A guard lets only
+    /// WebSocket upgrades match it, so other requests to that path still reach the default
+    /// service; WebSocket routes of the application at the same path have to be registered
+    /// before it.
+    ///
+    /// The proxy takes every request nothing else matched, as the default service. An
+    /// application with a default service of its own, or one that should only hand some
+    /// paths to Vite, lists them as Actix route patterns with
+    /// [`ProxyViteOptions::proxy_routes`] and turns the default service off with
+    /// [`ProxyViteOptions::register_default_service`]:
+    ///
+    /// ```rust,no_run
+    /// # #[cfg(feature = "dev-proxy")]
+    /// # {
+    /// use actix_web::{App, HttpResponse, web};
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    /// use vite_actix::vite_app_factory::ViteAppFactory;
+    ///
+    /// let options = ProxyViteOptions::global()
+    ///     .proxy_routes(&["/assets/{file:.*}", "/src/{file:.*}", "/@{rest:.*}"])
+    ///     .register_default_service(false);
+    /// let app = App::new()
+    ///     .configure_vite_with(options)
+    ///     .default_service(web::to(|| async { HttpResponse::NotFound().body("app") }));
+    /// # }
+    /// ```
+    ///
+    /// Routes of the application win over the proxy wherever they are registered, before
+    /// `configure_vite` or after it.
+    ///
+    /// Requests are proxied whatever their method, with their body, e.g. for the mock API of
+    /// a Vite plugin. The files of the build are only read, and other methods than `GET` and
+    /// `HEAD` get a `405 Method Not Allowed`.
     ///
     /// # Returns
     ///
     /// Returns the modified application instance with the Vite proxy configuration applied.
-    fn configure_vite(self) -> Self;
+    fn configure_vite(self) -> Self
+    where
+        Self: Sized,
+    {
+        let dev_mode = ProxyViteOptions::global().dev_mode;
+        self.configure_vite_if(|| dev_mode)
+    }
+
+    /// Like [`ViteAppFactory::configure_vite`], but checks the global options first and
+    /// returns every problem found instead of configuring anything, where `configure_vite`
+    /// only logs them as warnings:
+    ///
+    /// - [`ProxyViteOptions::build`] was never called.
+    /// - In dev mode, the proxy has no port and cannot learn it from Vite's output.
+    /// - Outside of dev mode, the dist directory, or an extra static directory, to serve
+    ///   does not exist.
+    /// - A prefix of [`ProxyViteOptions::exclude_prefix`] is not a path.
+    /// - The hashed asset pattern or a cache control override does not compile.
+    /// - [`ProxyViteOptions::proxy_extensions`] and
+    ///   [`ProxyViteOptions::navigation_fallback_only`] are both set.
+    ///
+    /// Call it where the `App` is built, e.g. in the `HttpServer::new` factory, and unwrap,
+    /// or build one `App` up front to fail before the server starts.
+    ///
+    /// # Example
+    /// ```rust,standalone_crate
+    /// use actix_web::App;
+    /// use vite_actix::error::ConfigProblem;
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    /// use vite_actix::vite_app_factory::ViteAppFactory;
+    ///
+    /// // Without `build()`, the defaults would be used.
+    /// let error = App::new().try_configure_vite().err().unwrap();
+    /// assert!(error.problems.contains(&ConfigProblem::NotBuilt));
+    ///
+    /// # #[cfg(feature = "dev-proxy")]
+    /// # {
+    /// ProxyViteOptions::new().port(5173).dev_mode(true).build().unwrap();
+    /// assert!(App::new().try_configure_vite().is_ok());
+    /// # }
+    /// ```
+    fn try_configure_vite(self) -> Result<Self, ViteConfigError>
+    where
+        Self: Sized,
+    {
+        let options = ProxyViteOptions::global();
+        check(options.problems(dev_proxy(options.dev_mode), false))?;
+        Ok(self.configure_vite_if(|| options.dev_mode))
+    }
+
+    /// Like [`ViteAppFactory::try_configure_vite`], but for the `options` of
+    /// [`ViteAppFactory::configure_vite_with`], which need a port in dev mode since no Vite
+    /// process reports one to them.
+    ///
+    /// # Example
+    /// ```rust
+    /// # #[cfg(feature = "dev-proxy")]
+    /// # {
+    /// use actix_web::App;
+    /// use std::path::PathBuf;
+    /// use vite_actix::error::ConfigProblem;
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    /// use vite_actix::vite_app_factory::ViteAppFactory;
+    ///
+    /// let problems = |options: ProxyViteOptions| {
+    ///     App::new().try_configure_vite_with(options).err().unwrap().problems
+    /// };
+    /// let dev = || ProxyViteOptions::new().dev_mode(true).port(5173);
+    ///
+    /// assert!(App::new().try_configure_vite_with(dev()).is_ok());
+    /// assert_eq!(
+    ///     problems(ProxyViteOptions::new().dev_mode(true)),
+    ///     [ConfigProblem::NoPort]
+    /// );
+    /// assert_eq!(
+    ///     problems(dev().exclude_prefix("api").exclude_prefix("/auth/*")),
+    ///     [
+    ///         ConfigProblem::InvalidExcludePrefix("api".to_string()),
+    ///         ConfigProblem::InvalidExcludePrefix("/auth/*".to_string()),
+    ///     ]
+    /// );
+    /// assert!(matches!(
+    ///     problems(dev().hashed_asset_pattern("([a-z")).as_slice(),
+    ///     [ConfigProblem::InvalidPattern(_)]
+    /// ));
+    /// assert!(matches!(
+    ///     problems(dev().cache_control(r"\.js$", "max-age=60\n")).as_slice(),
+    ///     [ConfigProblem::InvalidPattern(_)]
+    /// ));
+    /// // Actix's reason for a route it cannot match.
+    /// assert!(matches!(
+    ///     problems(dev().proxy_routes(&["/src/{file:.*}", "/assets/{file"])).as_slice(),
+    ///     [ConfigProblem::InvalidRoute(pattern, reason)]
+    ///         if pattern == "/assets/{file" && !reason.is_empty()
+    /// ));
+    /// assert_eq!(
+    ///     problems(dev().hop_header("x hop")),
+    ///     [ConfigProblem::InvalidHopHeader("x hop".to_string())]
+    /// );
+    /// assert_eq!(
+    ///     problems(dev().proxy_extensions(&["js"]).navigation_fallback_only(true)),
+    ///     [ConfigProblem::Conflict("proxy_extensions", "navigation_fallback_only")]
+    /// );
+    ///
+    /// # #[cfg(feature = "prod-serve")] {
+    /// // Outside of dev mode, the directories to serve have to exist.
+    /// let prod = ProxyViteOptions::new()
+    ///     .dev_mode(false)
+    ///     .dist_directory("examples/no_such_dist")
+    ///     .extra_static_dir("/media", "examples/no_such_media");
+    /// assert_eq!(
+    ///     problems(prod),
+    ///     [
+    ///         ConfigProblem::DistMissing(PathBuf::from("examples/no_such_dist")),
+    ///         ConfigProblem::ExtraStaticDirMissing(PathBuf::from("examples/no_such_media")),
+    ///     ]
+    /// );
+    /// # }
+    /// # }
+    /// ```
+    fn try_configure_vite_with(self, options: ProxyViteOptions) -> Result<Self, ViteConfigError>
+    where
+        Self: Sized,
+    {
+        check(options.problems(dev_proxy(options.dev_mode), true))?;
+        Ok(self.configure_vite_with(options))
+    }
+
+    /// Like [`ViteAppFactory::configure_vite`], but whether the proxy is registered is
+    /// decided by `condition` instead of [`ProxyViteOptions::dev_mode`], e.g. to proxy only
+    /// when an environment variable is set and the process runs in a terminal.
+    ///
+    /// When `condition` returns `false` the production behavior applies, i.e. the dist
+    /// directory is served or the application is left unchanged.
+    ///
+    /// `condition` is called every time this method is, which for an `App` built in the
+    /// `HttpServer::new` factory means once per worker. Keep it cheap, and decide anything
+    /// expensive, like asking a feature flag service, once at startup.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use actix_web::{App, HttpServer};
+    /// use std::io::IsTerminal;
+    /// use vite_actix::vite_app_factory::ViteAppFactory;
+    ///
+    /// # #[actix_web::main]
+    /// # async fn main() -> std::io::Result<()> {
+    /// // Decided once, not in every worker.
+    /// let proxy = std::env::var_os("VITE_PROXY").is_some() && std::io::stdout().is_terminal();
+    /// HttpServer::new(move || App::new().configure_vite_if(move || proxy))
+    ///     .bind("127.0.0.1:8080")?
+    ///     .run()
+    ///     .await
+    /// # }
+    /// ```
+    fn configure_vite_if(self, condition: impl Fn() -> bool) -> Self;
+
+    /// Like [`ViteAppFactory::configure_vite`], but with `options` in place of the global
+    /// ones, which this method neither reads nor changes.
+    ///
+    /// The proxy forwards to the port in `options`, so it does not follow a Vite process
+    /// started with [`start_vite_server`](crate::start_vite_server), which reports its port
+    /// through the global options. Meant for tests, where every test can build its own
+    /// application with `actix_web::test::init_service`, e.g. against
+    /// [`MockVite`](crate::test::MockVite) with the `test-util` feature.
+    ///
+    /// Directories of [`ProxyViteOptions::extra_static_dir`] are served after the dist
+    /// directory, which wins for files both have.
+    ///
+    /// With [`ProxyViteOptions::serve_prefix`], the build is served under a path, like one
+    /// made with Vite's `base: '/app/'`, and the routes outside of it are the app's.
+    fn configure_vite_with(self, options: ProxyViteOptions) -> Self;
+
+    /// Like [`ViteAppFactory::configure_vite`], but outside of dev mode the files embedded
+    /// in `E` are served in place of the dist directory, see [`crate::embedded`].
+    ///
+    /// [`ProxyViteOptions::serve_dist`] does not apply; turn it off so `build()` does not
+    /// look for a dist directory that is not shipped.
+    #[cfg(feature = "embed")]
+    fn configure_vite_embedded<E: rust_embed::Embed + 'static>(self) -> Self;
 }
 
 // Implementation of the `AppConfig` trait for Actix `App` instances.
@@ -31,20 +276,21 @@ where
             InitError = (),                 // No initialization error is expected.
         >,
 {
-    fn configure_vite(self) -> Self {
-        if cfg!(debug_assertions) {
-            // Add a default service to catch all unmatched routes and proxy them to Vite.
-            self.default_service(web::route().to(proxy_to_vite))
-                // Route requests for static assets to the Vite server (e.g., "/assets/<file>").
-                .service(web::resource("/{file:.*}").route(web::get().to(proxy_to_vite)))
-                // Route requests for Node modules to the Vite server (e.g., "/node_modules/<file>").
-                .service(
-                    web::resource("/node_modules/{file:.*}").route(web::get().to(proxy_to_vite)),
-                )
-        } else {
-            // If not in development mode, return the application without any additional configuration.
-            self
-        }
+    fn configure_vite_if(self, condition: impl Fn() -> bool) -> Self {
+        let options = ProxyViteOptions::global();
+        let dev_mode = dev_proxy(condition());
+        register(self, &options, dev_mode, ProxyTarget::Global)
+    }
+
+    fn configure_vite_with(self, options: ProxyViteOptions) -> Self {
+        let dev_mode = dev_proxy(options.dev_mode);
+        let target = ProxyTarget::from(options.clone());
+        register(self, &options, dev_mode, target)
+    }
+
+    #[cfg(feature = "embed")]
+    fn configure_vite_embedded<E: rust_embed::Embed + 'static>(self) -> Self {
+        register_embedded::<_, E>(self)
     }
 }
 impl<T> ViteAppFactory for actix_web::Scope<T>
@@ -56,15 +302,270 @@ where
             InitError = (),                 // No initialization error is expected.
         >,
 {
-    fn configure_vite(self) -> Self {
-        if cfg!(debug_assertions) {
-            self.default_service(web::route().to(proxy_to_vite))
-                .service(web::resource("/{file:.*}").route(web::get().to(proxy_to_vite)))
-                .service(
-                    web::resource("/node_modules/{file:.*}").route(web::get().to(proxy_to_vite)),
-                )
-        } else {
-            self
-        }
+    fn configure_vite_if(self, condition: impl Fn() -> bool) -> Self {
+        let options = ProxyViteOptions::global();
+        let dev_mode = dev_proxy(condition());
+        register(self, &options, dev_mode, ProxyTarget::Global)
+    }
+
+    fn configure_vite_with(self, options: ProxyViteOptions) -> Self {
+        let dev_mode = dev_proxy(options.dev_mode);
+        let target = ProxyTarget::from(options.clone());
+        register(self, &options, dev_mode, target)
+    }
+
+    #[cfg(feature = "embed")]
+    fn configure_vite_embedded<E: rust_embed::Embed + 'static>(self) -> Self {
+        register_embedded::<_, E>(self)
+    }
+}
+
+// What `register` needs of an `App` or a `Scope`, which have the same methods but no trait
+// for them.
+trait Services: Sized {
+    // Whether requests reach the services with the path of the scope in front.
+    const IN_SCOPE: bool;
+
+    fn add_service(self, resource: Resource) -> Self;
+
+    fn add_default_service(self, route: Route) -> Self;
+}
+
+impl<T> Services for App<T>
+where
+    T: actix_web::dev::ServiceFactory<
+            actix_web::dev::ServiceRequest,
+            Config = (),
+            Error = Error,
+            InitError = (),
+        >,
+{
+    const IN_SCOPE: bool = false;
+
+    fn add_service(self, resource: Resource) -> Self {
+        self.service(resource)
+    }
+
+    fn add_default_service(self, route: Route) -> Self {
+        self.default_service(route)
+    }
+}
+
+impl<T> Services for actix_web::Scope<T>
+where
+    T: actix_web::dev::ServiceFactory<
+            actix_web::dev::ServiceRequest,
+            Config = (),
+            Error = Error,
+            InitError = (),
+        >,
+{
+    const IN_SCOPE: bool = true;
+
+    fn add_service(self, resource: Resource) -> Self {
+        self.service(resource)
+    }
+
+    fn add_default_service(self, route: Route) -> Self {
+        self.default_service(route)
+    }
+}
+
+// Everything `configure_vite` registers on `services`, proxying to Vite when `dev_mode`
+// with the options of `target`.
+fn register<S: Services>(
+    services: S,
+    options: &ProxyViteOptions,
+    dev_mode: bool,
+    target: ProxyTarget,
+) -> S {
+    let pinned = matches!(target, ProxyTarget::Options(_));
+    warn_problems(&options.problems(dev_mode, pinned));
+    let build = Build::of(options);
+    let services = match health::resource(options, dev_mode, target.clone(), build) {
+        Some(health) => services.add_service(health),
+        None => services,
+    };
+    let services = match hmr_resource(options, dev_mode, target.clone(), S::IN_SCOPE) {
+        Some(hmr) => services.add_service(hmr),
+        None => services,
+    };
+    let services = proxy_resources(options, dev_mode, target.clone(), S::IN_SCOPE)
+        .into_iter()
+        .fold(services, |services, resource| services.add_service(resource));
+    match vite_route(options, dev_mode, target, S::IN_SCOPE) {
+        Some(route) => services.add_default_service(route),
+        // If not in development mode, return the application without any additional configuration.
+        None => services,
     }
 }
+
+// What `configure_vite_embedded` registers on `services`: the dev server as usual, or else
+// the files embedded in `E`.
+#[cfg(feature = "embed")]
+fn register_embedded<S, E>(services: S) -> S
+where
+    S: Services + ViteAppFactory,
+    E: rust_embed::Embed + 'static,
+{
+    let options = ProxyViteOptions::global();
+    if dev_proxy(options.dev_mode) {
+        services.configure_vite()
+    } else {
+        let build = Build::embedded::<E>();
+        let services = match health::resource(&options, false, ProxyTarget::Global, build) {
+            Some(health) => services.add_service(health),
+            None => services,
+        };
+        services.add_default_service(crate::embedded::serve_embedded::<E>())
+    }
+}
+
+// The problems of the options as the error of the `try_` methods.
+fn check(problems: Vec<ConfigProblem>) -> Result<(), ViteConfigError> {
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(ViteConfigError { problems })
+    }
+}
+
+// What `try_configure_vite` would fail with, for the methods that cannot.
+fn warn_problems(problems: &[ConfigProblem]) {
+    for problem in problems {
+        warn!("{}", problem);
+    }
+}
+
+// Whether `configure_vite` proxies to Vite for `dev_mode`.
+#[cfg(feature = "dev-proxy")]
+fn dev_proxy(dev_mode: bool) -> bool {
+    dev_mode
+}
+
+// Without the `dev-proxy` feature, `configure_vite` only serves the build, in any mode.
+#[cfg(not(feature = "dev-proxy"))]
+fn dev_proxy(_: bool) -> bool {
+    false
+}
+
+// The resource `configure_vite` relays Vite's HMR socket with, for the dev server. It only
+// matches WebSocket upgrades, so other requests to its path still reach the default service.
+#[cfg(feature = "dev-proxy")]
+fn hmr_resource(
+    options: &ProxyViteOptions,
+    dev_mode: bool,
+    target: ProxyTarget,
+    in_scope: bool,
+) -> Option<Resource> {
+    if !dev_mode || !options.command.is_dev_server() {
+        return None;
+    }
+    let hmr = web::resource(options.hmr_path_for(in_scope))
+        .guard(guard::fn_guard(|ctx| ws_proxy::is_upgrade(ctx.head().headers())))
+        .route(hmr_route(target, in_scope));
+    Some(hmr)
+}
+
+// Without the `dev-proxy` feature, there is no socket to relay.
+#[cfg(not(feature = "dev-proxy"))]
+fn hmr_resource(_: &ProxyViteOptions, _: bool, _: ProxyTarget, _: bool) -> Option<Resource> {
+    None
+}
+
+// The resources of `proxy_routes` that `configure_vite` proxies with, for the dev server.
+// Invalid patterns are left out, as the problems of the options report them.
+#[cfg(feature = "dev-proxy")]
+fn proxy_resources(
+    options: &ProxyViteOptions,
+    dev_mode: bool,
+    target: ProxyTarget,
+    in_scope: bool,
+) -> Vec<Resource> {
+    if !dev_mode || options.command == ViteCommand::BuildWatch {
+        return Vec::new();
+    }
+    options
+        .proxy_routes
+        .iter()
+        .filter(|pattern| route_problem(pattern).is_ok())
+        .map(|pattern| {
+            let handler = proxy::handler(target.clone());
+            let handler = if in_scope {
+                handler.strip_scope_of_route(pattern)
+            } else {
+                handler
+            };
+            web::resource(pattern.as_str()).route(web::route().to(handler))
+        })
+        .collect()
+}
+
+// Without the `dev-proxy` feature, nothing is proxied.
+#[cfg(not(feature = "dev-proxy"))]
+fn proxy_resources(_: &ProxyViteOptions, _: bool, _: ProxyTarget, _: bool) -> Vec<Resource> {
+    Vec::new()
+}
+
+// The default service `configure_vite` registers, or `None` to leave the application
+// unchanged. The proxy forwards with the options of `target`.
+#[cfg(feature = "dev-proxy")]
+fn vite_route(
+    options: &ProxyViteOptions,
+    dev_mode: bool,
+    target: ProxyTarget,
+    in_scope: bool,
+) -> Option<Route> {
+    if dev_mode && options.command == ViteCommand::BuildWatch {
+        build_watch_route(options)
+    } else if dev_mode && !options.register_default_service {
+        None
+    } else if dev_mode {
+        // Catch all unmatched routes and proxy them to Vite. Only the default service, so
+        // routes registered before or after always win.
+        let handler = proxy::handler(target);
+        let handler = if in_scope { handler.strip_scope() } else { handler };
+        Some(web::route().to(handler))
+    } else {
+        prod_route(options)
+    }
+}
+
+// Without the `dev-proxy` feature, the default service only ever serves the build.
+#[cfg(not(feature = "dev-proxy"))]
+fn vite_route(options: &ProxyViteOptions, _: bool, _: ProxyTarget, _: bool) -> Option<Route> {
+    prod_route(options)
+}
+
+// `vite build --watch` keeps rewriting dist, so serve it directly without caching.
+#[cfg(all(feature = "dev-proxy", feature = "prod-serve"))]
+fn build_watch_route(options: &ProxyViteOptions) -> Option<Route> {
+    let dist_dir = options.dist_dir();
+    let html = options.html_files();
+    Some(web::get().to(move |req| {
+        let cache_control = HeaderValue::from_static(CACHE_NO_CACHE);
+        serve_dist_file(req, dist_dir.clone(), cache_control, html.clone())
+    }))
+}
+
+// Without the `prod-serve` feature, there is nothing to serve the rebuilt dist with.
+#[cfg(all(feature = "dev-proxy", not(feature = "prod-serve")))]
+fn build_watch_route(_: &ProxyViteOptions) -> Option<Route> {
+    None
+}
+
+// Serve the production build, with `index.html` at `/`.
+#[cfg(feature = "prod-serve")]
+fn prod_route(options: &ProxyViteOptions) -> Option<Route> {
+    if !options.serve_dist {
+        return None;
+    }
+    let built = Arc::new(options.built_dist());
+    Some(web::get().to(move |req| serve_built_file(req, built.clone())))
+}
+
+// Without the `prod-serve` feature, the build is served elsewhere.
+#[cfg(not(feature = "prod-serve"))]
+fn prod_route(_: &ProxyViteOptions) -> Option<Route> {
+    None
+}
@@ -0,0 +1,41 @@
+/// Which Vite command the crate launches.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViteCommand {
+    /// `vite` — the development server with HMR.
+    #[default]
+    Dev,
+    /// `vite preview` — serves the built `dist/` directory with the production module graph.
+    ///
+    /// Useful for release-candidate testing behind the same Actix origin. Run `vite build`
+    /// first; dev-only behaviors such as HMR are not available in this mode.
+    Preview,
+    /// `vite build --watch` — rebuilds `dist/` on every change, which Actix then serves
+    /// directly instead of proxying.
+    ///
+    /// Meant for targets that cannot talk to a dev server, such as embedded webviews.
+    /// Every finished rebuild is reported through [`ProxyViteOptions::on_rebuild`].
+    ///
+    /// [`ProxyViteOptions::on_rebuild`]: crate::proxy_vite_options::ProxyViteOptions::on_rebuild
+    BuildWatch,
+}
+
+impl ViteCommand {
+    /// The subcommand arguments passed to the `vite` executable for this command.
+    pub fn subcommand(&self) -> &'static [&'static str] {
+        match self {
+            ViteCommand::Dev => &[],
+            ViteCommand::Preview => &["preview"],
+            ViteCommand::BuildWatch => &["build", "--watch"],
+        }
+    }
+
+    /// Whether this command starts an HTTP server that requests can be proxied to.
+    pub fn is_server(&self) -> bool {
+        !matches!(self, ViteCommand::BuildWatch)
+    }
+
+    /// Whether this command runs the development server.
+    pub fn is_dev_server(&self) -> bool {
+        matches!(self, ViteCommand::Dev)
+    }
+}
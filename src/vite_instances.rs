@@ -0,0 +1,234 @@
+use crate::spawn::SpawnSpec;
+use crate::error::StartViteError;
+use crate::proxy_vite_options::{ProxyViteOptions, try_find_vite_dir};
+use crate::vite_command::ViteCommand;
+use crate::vite_server_handle::ViteServerHandle;
+use crate::vite_state::ViteShared;
+use crate::vite_stdio::ViteStdio;
+use log::debug;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+
+// Running instances, keyed by the name they were started under.
+static VITE_INSTANCES: OnceLock<Mutex<HashMap<String, ViteServerHandle>>> = OnceLock::new();
+
+/// Configuration for a single named Vite instance.
+///
+/// Instances are registered on the global options with [`ProxyViteOptions::instance`]
+/// and started with [`ViteInstances::start`].
+#[derive(Clone)]
+pub struct ViteInstanceOptions {
+    pub port: Option<u16>,
+    pub working_directory: String,
+    pub command: ViteCommand,
+    pub args: Vec<String>,
+    pub log_level: Option<log::Level>,
+}
+
+impl Default for ViteInstanceOptions {
+    fn default() -> Self {
+        Self {
+            port: None,
+            working_directory: try_find_vite_dir().unwrap_or(String::from("./")),
+            command: ViteCommand::default(),
+            args: Vec::new(),
+            log_level: Some(log::Level::Debug),
+        }
+    }
+}
+
+impl ViteInstanceOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    pub fn working_directory(mut self, working_directory: impl AsRef<str>) -> Self {
+        self.working_directory = working_directory.as_ref().to_string();
+        self
+    }
+
+    pub fn command(mut self, command: ViteCommand) -> Self {
+        self.command = command;
+        self
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<str>) -> Self {
+        self.args.push(arg.as_ref().to_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_string()));
+        self
+    }
+
+    pub fn log_level(mut self, log_level: log::Level) -> Self {
+        self.log_level = Some(log_level);
+        self
+    }
+
+    pub fn disable_logging(mut self) -> Self {
+        self.log_level = None;
+        self
+    }
+}
+
+/// Registry of named Vite instances.
+///
+/// Each instance runs its own Vite process with its own stdout reader, and the port it
+/// reports is tracked under its name so it can be looked up with [`ViteInstances::port`].
+///
+/// # Example
+/// ```rust,no_run
+/// # fn run() -> anyhow::Result<()> {
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+/// use vite_actix::vite_instances::{ViteInstanceOptions, ViteInstances};
+///
+/// ProxyViteOptions::new()
+///     .instance("admin", ViteInstanceOptions::new().working_directory("./admin"))
+///     .build()?;
+/// let _admin = ViteInstances::start("admin")?;
+/// println!("admin is on port {:?}", ViteInstances::port("admin"));
+/// ViteInstances::shutdown_all()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ViteInstances;
+
+impl ViteInstances {
+    /// Starts the instance registered under `name`.
+    ///
+    /// # Errors
+    ///
+    /// - Returns an error if no instance with that name was registered.
+    /// - Returns an error if an instance with that name is already running.
+    /// - Returns an error if the Vite process cannot be spawned.
+    pub fn start(name: &str) -> Result<ViteServerHandle, StartViteError> {
+        let options = ProxyViteOptions::global();
+        let instance = options
+            .instances
+            .get(name)
+            .ok_or_else(|| StartViteError::UnknownInstance(name.to_string()))?;
+
+        // A poisoned registry still holds valid handles, so keep using it.
+        let mut instances = get_or_init_instances()
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if let Some(existing) = instances.get(name)
+            && existing.try_wait().is_ok_and(|status| status.is_none())
+        {
+            return Err(StartViteError::AlreadyRunning(name.to_string()));
+        }
+
+        let instance_name = name.to_string();
+        let spec = SpawnSpec {
+            working_directory: instance.working_directory.clone(),
+            port: instance.port,
+            command: instance.command,
+            args: instance.args.clone(),
+            log_level: instance.log_level,
+            log_format: options.vite_log_format,
+            strict_port: options.strict_port,
+            port_conflict: options.port_conflict,
+            pid_file: options.pid_file,
+            stale_policy: options.stale_policy,
+            // Several instances writing to one terminal would be unreadable.
+            stdio: ViteStdio::Piped,
+            skip_spawn: options.skip_spawn.clone(),
+            package_script: options.package_script,
+            kill_on_parent_exit: options.kill_on_parent_exit,
+            low_priority: options.low_priority,
+            restart_policy: options.restart_policy,
+            // Several instances rotating the same file would clobber each other.
+            log_file: None,
+            output_buffer_lines: options.output_buffer_lines,
+            ready_phase: options.ready_phase,
+            on_exit: options.on_exit.clone(),
+            on_ready: options.on_ready.clone(),
+            on_rebuild: options.on_rebuild.clone(),
+            on_port: Some(Arc::new(move |port| {
+                debug!(
+                    "Vite instance '{}' is listening on port {}",
+                    instance_name, port
+                );
+            })),
+        };
+        let handle = ViteServerHandle::spawn(name, ViteShared::new(instance.port), spec)?;
+        instances.insert(name.to_string(), handle.clone());
+        Ok(handle)
+    }
+
+    /// Returns the handle of the instance running under `name`, if any.
+    pub fn get(name: &str) -> Option<ViteServerHandle> {
+        get_or_init_instances().lock().ok()?.get(name).cloned()
+    }
+
+    /// Returns the port the instance running under `name` is listening on, if known.
+    pub fn port(name: &str) -> Option<u16> {
+        Self::get(name)?.port()
+    }
+
+    /// Returns the known ports of all running instances, keyed by instance name.
+    pub fn ports() -> HashMap<String, u16> {
+        get_or_init_instances()
+            .lock()
+            .map(|instances| {
+                instances
+                    .iter()
+                    .filter_map(|(name, handle)| Some((name.clone(), handle.port()?)))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Stops the instance running under `name` and removes it from the registry.
+    pub fn shutdown(name: &str) -> anyhow::Result<()> {
+        let handle = get_or_init_instances()
+            .lock()
+            .map_err(|_| anyhow::Error::msg("Failed to lock Vite instances"))?
+            .remove(name);
+
+        match handle {
+            Some(handle) => handle.shutdown(),
+            None => Ok(()),
+        }
+    }
+
+    /// Stops every running instance.
+    ///
+    /// All instances are stopped even if some of them fail; the first error is returned.
+    pub fn shutdown_all() -> anyhow::Result<()> {
+        let handles: Vec<ViteServerHandle> = get_or_init_instances()
+            .lock()
+            .map_err(|_| anyhow::Error::msg("Failed to lock Vite instances"))?
+            .drain()
+            .map(|(_, handle)| handle)
+            .collect();
+
+        let mut result = Ok(());
+        for handle in handles {
+            if let Err(e) = handle.shutdown()
+                && result.is_ok()
+            {
+                result = Err(e);
+            }
+        }
+        result
+    }
+}
+
+// Helper function to initialize the instance registry if needed and return a reference to it
+fn get_or_init_instances() -> &'static Mutex<HashMap<String, ViteServerHandle>> {
+    VITE_INSTANCES.get_or_init(|| Mutex::new(HashMap::new()))
+}
@@ -0,0 +1,176 @@
+//! The probes Vite's client sends to learn whether the dev server is back, after the HMR
+//! socket dropped: `GET /__vite_ping` up to Vite 4, a request with
+//! `Accept: text/x-vite-ping` in Vite 5.0, and since 5.1 a WebSocket with the `vite-ping`
+//! subprotocol.
+//!
+//! The client sends one every second until it gets an answer, so the proxy keeps them
+//! cheap. While the managed Vite process is starting or down, or there is no port to
+//! forward to, a probe is answered with `503 Service Unavailable` at once, without trying
+//! Vite. Otherwise an HTTP probe is forwarded without reading its body, by a client shared
+//! by the worker thread with a [`PING_TIMEOUT`] in place of the minute of other requests,
+//! and a WebSocket probe is relayed like the HMR socket. Probes are not rate limited, not
+//! filtered by `navigation_fallback_only`, and not logged, neither by
+//! [`ProxyViteOptions::log_proxy_spans`] nor when they fail, unless
+//! [`ProxyViteOptions::log_vite_pings`] is set.
+//!
+//! # Example
+//! ```rust
+//! use actix_web::http::Method;
+//! use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+//! use vite_actix::vite_ping::{Probe, probe};
+//!
+//! let headers = |pairs: &[(&'static str, &'static str)]| {
+//!     let mut headers = HeaderMap::new();
+//!     for (name, value) in pairs {
+//!         headers.append(HeaderName::from_static(name), HeaderValue::from_static(value));
+//!     }
+//!     headers
+//! };
+//!
+//! assert_eq!(probe(&Method::GET, "/__vite_ping", &headers(&[])), Some(Probe::Http));
+//! // Under Vite's base.
+//! assert_eq!(probe(&Method::GET, "/app/__vite_ping", &headers(&[])), Some(Probe::Http));
+//! let accept = headers(&[("accept", "text/x-vite-ping")]);
+//! assert_eq!(probe(&Method::GET, "/", &accept), Some(Probe::Http));
+//! let socket = headers(&[
+//!     ("connection", "Upgrade"),
+//!     ("upgrade", "websocket"),
+//!     ("sec-websocket-protocol", "vite-ping"),
+//! ]);
+//! assert_eq!(probe(&Method::GET, "/", &socket), Some(Probe::WebSocket));
+//!
+//! // The HMR socket itself, and other requests, are no probes.
+//! let hmr = headers(&[("upgrade", "websocket"), ("sec-websocket-protocol", "vite-hmr")]);
+//! assert_eq!(probe(&Method::GET, "/", &hmr), None);
+//! assert_eq!(probe(&Method::POST, "/__vite_ping", &headers(&[])), None);
+//! assert_eq!(probe(&Method::GET, "/__vite_ping.js", &headers(&[])), None);
+//! ```
+//!
+//! [`ProxyViteOptions::log_proxy_spans`]: crate::proxy_vite_options::ProxyViteOptions::log_proxy_spans
+//! [`ProxyViteOptions::log_vite_pings`]: crate::proxy_vite_options::ProxyViteOptions::log_vite_pings
+
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::request_id::{REQUEST_ID_HEADER, RequestId};
+use crate::upgrade::{self, Upgrade};
+use crate::vite_state::{ViteProcess, ViteState};
+use actix_web::http::Method;
+use actix_web::http::header::{
+    ACCEPT, CONTENT_LENGTH, HeaderMap, HeaderName, HeaderValue, SEC_WEBSOCKET_PROTOCOL,
+};
+use actix_web::{HttpRequest, HttpResponse};
+use awc::Client;
+use log::debug;
+use std::time::{Duration, Instant};
+
+/// The path Vite's client up to Vite 4 probes, under Vite's base.
+pub const PING_PATH: &str = "/__vite_ping";
+/// The `Accept` header of the probe of Vite 5.0's client.
+pub const PING_ACCEPT: &str = "text/x-vite-ping";
+/// The WebSocket subprotocol of the probe of Vite's client since 5.1.
+pub const PING_PROTOCOL: &str = "vite-ping";
+/// How long the proxy waits for Vite to answer a probe.
+pub const PING_TIMEOUT: Duration = Duration::from_secs(2);
+
+// The most of Vite's answer to a probe that is passed on.
+const MAX_PING_BODY: usize = 64 * 1024;
+
+thread_local! {
+    // `awc::Client` is not `Send`, so every worker keeps its own, with its connections.
+    static CLIENT: Client = Client::builder().timeout(PING_TIMEOUT).finish();
+}
+
+/// How a probe of Vite's client asks, see [`crate::vite_ping`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Probe {
+    /// `GET /__vite_ping`, or a request with `Accept: text/x-vite-ping`.
+    Http,
+    /// A WebSocket with the `vite-ping` subprotocol.
+    WebSocket,
+}
+
+/// The probe a request with `method`, for `path` at Vite, and `headers` is, if any.
+pub fn probe(method: &Method, path: &str, headers: &HeaderMap) -> Option<Probe> {
+    if upgrade::negotiate(method, headers) == Upgrade::WebSocket {
+        let ping = headers
+            .get_all(SEC_WEBSOCKET_PROTOCOL)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|protocol| protocol.trim() == PING_PROTOCOL);
+        return ping.then_some(Probe::WebSocket);
+    }
+    if method != Method::GET && method != Method::HEAD {
+        return None;
+    }
+    let accepts_ping = headers
+        .get_all(ACCEPT)
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains(PING_ACCEPT));
+    (path.ends_with(PING_PATH) || accepts_ping).then_some(Probe::Http)
+}
+
+// Whether a probe is answered without Vite, as its managed `process` is starting or down.
+pub(crate) fn is_down(process: &ViteProcess) -> bool {
+    matches!(
+        process.state(),
+        Some(ViteState::Starting | ViteState::Exited(_) | ViteState::Failed { .. })
+    )
+}
+
+// The answer to a probe while Vite is down.
+pub(crate) fn unavailable() -> HttpResponse {
+    HttpResponse::ServiceUnavailable().finish()
+}
+
+// Forward the HTTP probe `req` to `url`, with the hop header the caller stamped, and pass
+// Vite's answer on; a `503` if there is none in time.
+pub(crate) async fn forward(
+    req: &HttpRequest,
+    url: &str,
+    hop: (HeaderName, HeaderValue),
+    request_id: &RequestId,
+    options: &ProxyViteOptions,
+) -> HttpResponse {
+    let started = Instant::now();
+    let forwarded = CLIENT
+        .with(Client::clone)
+        .request_from(url, req.head())
+        .no_decompress()
+        .insert_header((REQUEST_ID_HEADER, request_id.as_str()))
+        .insert_header(hop.clone())
+        .send()
+        .await;
+    let mut forwarded = match forwarded {
+        Ok(forwarded) => forwarded,
+        Err(err) => {
+            if options.log_vite_pings {
+                debug!(
+                    "[{}] Vite did not answer the ping {}: {}",
+                    request_id,
+                    req.path(),
+                    err
+                );
+            }
+            return unavailable();
+        }
+    };
+    let Ok(body) = forwarded.body().limit(MAX_PING_BODY).await else {
+        return unavailable();
+    };
+    if options.log_vite_pings {
+        debug!(
+            "[{}] Vite answered the ping {}: {} in {:?}",
+            request_id,
+            req.path(),
+            forwarded.status(),
+            started.elapsed()
+        );
+    }
+
+    let mut res = HttpResponse::build(forwarded.status());
+    for (name, value) in forwarded.headers() {
+        if *name != CONTENT_LENGTH && *name != hop.0 {
+            res.insert_header((name.clone(), value.clone()));
+        }
+    }
+    res.body(body)
+}
@@ -0,0 +1,139 @@
+use crate::chunked_body::ChunkedBody;
+use crate::proxy_vite_options::ProxyViteOptions;
+use crate::vite_state::ViteProcess;
+use crate::{forward_to_vite, read_payload, ws_proxy};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{Payload, Service, ServiceRequest, ServiceResponse, Transform, forward_ready};
+use actix_web::error::PayloadError;
+use actix_web::http::StatusCode;
+use actix_web::{Error, HttpMessage, HttpResponse, web};
+use futures_util::future::{LocalBoxFuture, Ready, ready};
+use futures_util::stream::Stream;
+use std::pin::Pin;
+use std::rc::Rc;
+
+/// Middleware that proxies requests to Vite which the application has no route for.
+///
+/// An alternative to [`crate::vite_app_factory::ViteAppFactory::configure_vite`] for
+/// applications that use `default_service` themselves. Every request goes to the
+/// application first and is only sent on to Vite if no route matched it and the
+/// application answered with a 404, so routes of the application always win, including
+/// routes that answer with a 404 of their own. Wrap a not-found handler around this
+/// middleware to take over the 404s Vite does not answer.
+///
+/// Like `configure_vite`, it only proxies in [`ProxyViteOptions::dev_mode`] and reads the
+/// port and everything else from the global options.
+///
+/// Request bodies are buffered before they reach the application, so they can still be
+/// forwarded to Vite afterwards. WebSocket upgrades, like Vite's HMR socket, go to the
+/// application untouched; point Vite's `server.hmr.clientPort` at its own port to keep HMR.
+///
+/// # Example
+/// ```no-rust
+/// HttpServer::new(|| {
+///     App::new()
+///         .wrap(ViteProxyMiddleware::new())
+///         .service(api_routes())
+///         .default_service(web::to(not_found))
+/// })
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ViteProxyMiddleware {
+    intercept_method_not_allowed: bool,
+}
+
+impl ViteProxyMiddleware {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Also proxy requests the application answered with a 405, e.g. a POST to a path it
+    // only has a GET route for
+    pub fn intercept_method_not_allowed(mut self, intercept: bool) -> Self {
+        self.intercept_method_not_allowed = intercept;
+        self
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ViteProxyMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = ViteProxyService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(ViteProxyService {
+            service: Rc::new(service),
+            intercept_method_not_allowed: self.intercept_method_not_allowed,
+        }))
+    }
+}
+
+/// The service created by [`ViteProxyMiddleware`].
+pub struct ViteProxyService<S> {
+    service: Rc<S>,
+    intercept_method_not_allowed: bool,
+}
+
+impl<S, B> Service<ServiceRequest> for ViteProxyService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let intercept_method_not_allowed = self.intercept_method_not_allowed;
+        Box::pin(async move {
+            let options = ProxyViteOptions::global();
+            // A WebSocket's body never ends, so it cannot be kept for later; those are only
+            // relayed by the default service of `configure_vite`.
+            if !options.dev_mode || ws_proxy::is_websocket(req.request()) {
+                return Ok(service.call(req).await?.map_into_left_body());
+            }
+
+            // The application consumes the body, so keep a copy to forward.
+            let body = read_payload(&mut req.take_payload()).await?;
+            req.set_payload(chunks_payload(body.clone()));
+
+            let res = service.call(req).await?;
+            // A 404 from a matched route is the application's answer, not a missing route.
+            let unrouted = res.status() == StatusCode::NOT_FOUND
+                && res.request().match_pattern().is_none();
+            let wrong_method =
+                intercept_method_not_allowed && res.status() == StatusCode::METHOD_NOT_ALLOWED;
+            if !unrouted && !wrong_method {
+                return Ok(res.map_into_left_body());
+            }
+
+            let req = res.request().clone();
+            let proxied = forward_to_vite(
+                req.clone(),
+                body.into_stream(),
+                None,
+                options,
+                ViteProcess::global(),
+            )
+            .await
+            .unwrap_or_else(HttpResponse::from_error);
+            Ok(ServiceResponse::new(req, proxied).map_into_right_body())
+        })
+    }
+}
+
+// A payload that yields `body` in the chunks it was read in.
+fn chunks_payload(body: ChunkedBody) -> Payload {
+    let stream: Pin<Box<dyn Stream<Item = Result<web::Bytes, PayloadError>>>> =
+        Box::pin(body.into_stream());
+    Payload::from(stream)
+}
@@ -0,0 +1,920 @@
+use crate::compile_error::ViteCompileError;
+use crate::error::StartViteError;
+use crate::events::ViteEvent;
+use crate::pid_file::{self, PidFile, StalePolicy};
+use crate::port_conflict::{self, PortConflictStrategy};
+use crate::process_lifetime;
+use crate::supervisor::{self, FailureWindow};
+use crate::vite_state::{ReadyPhase, ViteReadyInfo, ViteShared, ViteState};
+use crate::vite_stdio::ViteOutputLine;
+use crate::vite_version::ViteVersion;
+use crate::spawn::{SpawnSpec, handle_output_event, spawn_vite};
+use log::{debug, error, info, warn};
+use std::future::Future;
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// Callback invoked once when a managed Vite process exits.
+pub type OnExitCallback = Arc<dyn Fn(ExitStatus) + Send + Sync>;
+
+/// Callback invoked when a Vite process becomes ready to serve requests.
+pub type OnReadyCallback = Arc<dyn Fn(ViteReadyInfo) + Send + Sync>;
+
+/// Callback invoked with the build time each time `vite build --watch` finishes a rebuild.
+pub type OnRebuildCallback = Arc<dyn Fn(Duration) + Send + Sync>;
+
+// How often the reaper checks whether the Vite process is still alive.
+const REAP_INTERVAL: Duration = Duration::from_millis(100);
+
+// How long Vite gets to restart itself after its config changed before it is checked on.
+#[cfg(feature = "watch")]
+const CONFIG_SETTLE_TIME: Duration = Duration::from_secs(1);
+
+// The process of `start_vite_server_managed`, for the admin routes; weak, so it does not
+// keep Vite alive.
+static DEFAULT_HANDLE: Mutex<Weak<HandleInner>> = Mutex::new(Weak::new());
+
+/// A handle to a Vite process spawned and managed by this crate.
+///
+/// The handle is cheap to clone; all clones refer to the same process. When the last clone
+/// is dropped the process is killed, so keep a handle alive for as long as you need Vite.
+#[derive(Clone)]
+pub struct ViteServerHandle {
+    inner: Arc<HandleInner>,
+}
+
+struct HandleInner {
+    name: String,
+    pid: AtomicU32,
+    exited: AtomicBool,
+    // Set by `shutdown`, so the reaper does not take the exit for a crash.
+    stopped: AtomicBool,
+    // Asks the reaper to spawn the process again once it has exited.
+    restart_requested: AtomicBool,
+    failures: Mutex<FailureWindow>,
+    // Held for the whole of a `restart`, so concurrent calls wait for the running one.
+    restart_lock: tokio::sync::Mutex<()>,
+    // How many restarts have finished, so callers that waited for one know to skip theirs.
+    restarts: AtomicU64,
+    process: Mutex<ManagedProcess>,
+    shared: Arc<ViteShared>,
+    spec: Arc<SpawnSpec>,
+    #[cfg(feature = "watch")]
+    config_watcher: Mutex<Option<notify::RecommendedWatcher>>,
+}
+
+impl ViteServerHandle {
+    // Spawn the process described by `spec` and start its reaper; `shared` is fed by the
+    // output readers of that process and of any process respawned in its place.
+    pub(crate) fn spawn(
+        name: impl AsRef<str>,
+        shared: Arc<ViteShared>,
+        spec: SpawnSpec,
+    ) -> Result<Self, StartViteError> {
+        if let Some(reason) = spec.skip_spawn.as_ref().and_then(|detector| detector()) {
+            info!("Not starting Vite instance '{}': {}", name.as_ref(), reason);
+            return Ok(Self::skipped(name, shared, spec));
+        }
+
+        let mut port = spec.port;
+
+        // A strict port that is already taken would make Vite exit right away, so check
+        // it up front instead of waiting for the error message.
+        if let Some(configured) = port
+            && spec.strict_port
+            && !port_conflict::is_port_free(configured)
+        {
+            port = Some(resolve_port_conflict(&spec, configured)?);
+        }
+
+        shared.set_output_capacity(spec.output_buffer_lines);
+        // Subscribe before spawning so the first lines make it into the file.
+        if let Some(log_file) = &spec.log_file {
+            crate::output_log::start(&shared, log_file);
+        }
+
+        let spec = Arc::new(spec);
+        let process = match Self::handle_stale_process(&spec, &shared) {
+            Some(process) => process,
+            None => {
+                shared.reset(port);
+                ManagedProcess::Child(spawn_child(&spec, port, &shared)?)
+            }
+        };
+
+        let pid = process.id();
+        shared.set_pid(pid);
+        if spec.pid_file {
+            write_pid_file(&spec, pid, port);
+        }
+
+        let handle = Self {
+            inner: Arc::new(HandleInner {
+                name: name.as_ref().to_string(),
+                pid: AtomicU32::new(pid),
+                exited: AtomicBool::new(false),
+                stopped: AtomicBool::new(false),
+                restart_requested: AtomicBool::new(false),
+                failures: Mutex::new(FailureWindow::default()),
+                restart_lock: tokio::sync::Mutex::new(()),
+                restarts: AtomicU64::new(0),
+                process: Mutex::new(process),
+                shared,
+                spec,
+                #[cfg(feature = "watch")]
+                config_watcher: Mutex::new(None),
+            }),
+        };
+        spawn_reaper(Arc::downgrade(&handle.inner));
+        #[cfg(feature = "watch")]
+        handle.watch_config();
+        Ok(handle)
+    }
+
+    // A handle without a process, for when spawning was skipped. The proxy keeps using the
+    // configured port, so a Vite server started by other means still works.
+    fn skipped(name: impl AsRef<str>, shared: Arc<ViteShared>, spec: SpawnSpec) -> Self {
+        shared.reset(spec.port);
+        shared.set_state(ViteState::Idle);
+        Self {
+            inner: Arc::new(HandleInner {
+                name: name.as_ref().to_string(),
+                pid: AtomicU32::new(0),
+                // There is nothing to exit, so never report an exit.
+                exited: AtomicBool::new(true),
+                stopped: AtomicBool::new(false),
+                restart_requested: AtomicBool::new(false),
+                failures: Mutex::new(FailureWindow::default()),
+                restart_lock: tokio::sync::Mutex::new(()),
+                restarts: AtomicU64::new(0),
+                process: Mutex::new(ManagedProcess::Skipped),
+                shared,
+                spec: Arc::new(spec),
+                #[cfg(feature = "watch")]
+                config_watcher: Mutex::new(None),
+            }),
+        }
+    }
+
+    // Watch the project's vite.config.* file and check on Vite after every change.
+    #[cfg(feature = "watch")]
+    fn watch_config(&self) {
+        let Some(config) = crate::config_watch::find_vite_config(&self.inner.spec.working_directory)
+        else {
+            return;
+        };
+        let inner = Arc::downgrade(&self.inner);
+        match crate::config_watch::watch_config(config, move || {
+            after_config_change(inner.clone())
+        }) {
+            Ok(watcher) => {
+                if let Ok(mut guard) = self.inner.config_watcher.lock() {
+                    *guard = Some(watcher);
+                }
+            }
+            Err(e) => warn!("Failed to watch the Vite config: {}", e),
+        }
+    }
+
+    // Apply the stale policy to a Vite process recorded in the PID file by a previous run.
+    //
+    // Returns the process to attach to when it should be reused instead of spawning.
+    fn handle_stale_process(spec: &SpawnSpec, shared: &ViteShared) -> Option<ManagedProcess> {
+        if !spec.pid_file {
+            return None;
+        }
+        let stale = pid_file::find_stale_process(&spec.working_directory)?;
+        match spec.stale_policy {
+            StalePolicy::Kill => {
+                pid_file::kill_stale_process(&spec.working_directory, stale);
+                None
+            }
+            StalePolicy::Reuse => {
+                let port = stale.port?;
+                info!(
+                    "Reusing Vite process {} from a previous run on port {}",
+                    stale.pid, port
+                );
+                shared.reset(Some(port));
+                shared.set_port(port);
+                if let Some(on_port) = &spec.on_port {
+                    on_port(port);
+                }
+                Some(ManagedProcess::Attached(stale.pid))
+            }
+            StalePolicy::Ignore => {
+                debug!("Ignoring Vite process {} from a previous run", stale.pid);
+                None
+            }
+        }
+    }
+
+    // Make this the process `default_handle` returns.
+    pub(crate) fn set_default(&self) {
+        if let Ok(mut default) = DEFAULT_HANDLE.lock() {
+            *default = Arc::downgrade(&self.inner);
+        }
+    }
+
+    // The process of `start_vite_server_managed`, while any handle to it is alive.
+    #[cfg(feature = "proxy")]
+    pub(crate) fn default_handle() -> Option<Self> {
+        let inner = DEFAULT_HANDLE.lock().ok()?.upgrade()?;
+        Some(Self { inner })
+    }
+
+    /// The name this Vite instance was started under.
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    /// The OS process id of the Vite process.
+    ///
+    /// This changes if the process is respawned, e.g. after a port conflict, and is `0` if
+    /// spawning was skipped.
+    pub fn pid(&self) -> u32 {
+        self.inner.pid.load(Ordering::SeqCst)
+    }
+
+    /// The port Vite reported in its `Local:` banner line, or the configured port
+    /// if the banner has not been seen yet.
+    pub fn port(&self) -> Option<u16> {
+        self.inner.shared.port()
+    }
+
+    /// The version of Vite, once `vite --version` has answered.
+    ///
+    /// The version is detected alongside startup, so this is `None` for a moment after
+    /// spawning, and also if Vite was not spawned or its version could not be read.
+    pub fn vite_version(&self) -> Option<ViteVersion> {
+        self.inner.shared.vite_version()
+    }
+
+    /// Subscribes to the lines Vite prints from now on, on both streams and without ANSI
+    /// escape codes.
+    ///
+    /// Output of processes respawned in place of this one is included. Only captured output
+    /// is sent, see [`crate::vite_stdio::ViteStdio::Piped`], and a subscriber that falls
+    /// too far behind misses lines.
+    pub fn subscribe_output(&self) -> tokio::sync::broadcast::Receiver<ViteOutputLine> {
+        self.inner.shared.subscribe_output()
+    }
+
+    /// The last `n` lines Vite printed, oldest first, timestamped and without ANSI escape
+    /// codes, from the buffer of the last [`ProxyViteOptions::output_buffer_lines`] lines.
+    ///
+    /// The buffer is filled as lines are read, so it can be read while Vite is printing.
+    /// It starts over when the process is respawned; only captured output is kept, see
+    /// [`crate::vite_stdio::ViteStdio::Piped`].
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # fn run() -> anyhow::Result<()> {
+    /// let vite = vite_actix::start_vite_server_managed()?;
+    /// for line in vite.recent_logs(20) {
+    ///     eprintln!("{:?}: {}", line.stream, line.line);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`ProxyViteOptions::output_buffer_lines`]: crate::proxy_vite_options::ProxyViteOptions::output_buffer_lines
+    pub fn recent_logs(&self, n: usize) -> Vec<ViteOutputLine> {
+        self.inner.shared.recent_logs(n)
+    }
+
+    /// The last compile errors Vite reported, oldest first.
+    ///
+    /// Errors are kept until the process is respawned. Only captured output is parsed, see
+    /// [`crate::vite_stdio::ViteStdio::Piped`].
+    pub fn recent_errors(&self) -> Vec<ViteCompileError> {
+        self.inner.shared.recent_errors()
+    }
+
+    /// Subscribes to the compile errors Vite reports from now on.
+    pub fn subscribe_errors(&self) -> tokio::sync::broadcast::Receiver<ViteCompileError> {
+        self.inner.shared.subscribe_errors()
+    }
+
+    /// Subscribes to the [`ViteEvent`]s of this process and of processes respawned in its
+    /// place from now on, see [`crate::events`].
+    pub fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<ViteEvent> {
+        self.inner.shared.subscribe_events()
+    }
+
+    /// The URLs Vite reported in its `Network:` banner lines, one per network interface.
+    ///
+    /// Vite only prints these when started with `--host`, so this is empty otherwise. See
+    /// [`crate::lan_urls`] for the same addresses with the Actix port.
+    pub fn network_urls(&self) -> Vec<url::Url> {
+        self.inner.shared.network_urls()
+    }
+
+    /// The current lifecycle state of the Vite process.
+    pub fn state(&self) -> ViteState {
+        self.inner.shared.state()
+    }
+
+    /// A handle to restart Vite after it crashed too often, see
+    /// [`crate::proxy_vite_options::ProxyViteOptions::restart_on_crash`].
+    pub fn supervisor(&self) -> SupervisorHandle {
+        SupervisorHandle {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+
+    /// Checks whether the Vite process has exited without blocking.
+    ///
+    /// Returns `Ok(Some(status))` if the process has exited, `Ok(None)` if it is still running.
+    pub fn try_wait(&self) -> anyhow::Result<Option<ExitStatus>> {
+        let mut process = self
+            .inner
+            .process
+            .lock()
+            .map_err(|_| anyhow::Error::msg("Failed to lock Vite process handle"))?;
+        Ok(process.try_wait()?)
+    }
+
+    /// Returns a future that resolves once Vite is ready, in the
+    /// [`ReadyPhase`] configured with
+    /// [`ProxyViteOptions::ready_phase`](crate::proxy_vite_options::ProxyViteOptions::ready_phase),
+    /// or with the exit status as the error if it exits first.
+    ///
+    /// Like [`ViteServerHandle::wait_exited`], the future neither borrows the handle nor
+    /// keeps the process alive. It never resolves if spawning was skipped.
+    pub fn wait_ready(&self) -> impl Future<Output = Result<(), ExitStatus>> + Send + 'static {
+        let shared = self.inner.shared.clone();
+        let fully_ready = self.inner.waits_for_fully_ready();
+        async move {
+            let mut state = shared.subscribe();
+            loop {
+                match *state.borrow_and_update() {
+                    ViteState::FullyReady => return Ok(()),
+                    ViteState::Ready if !fully_ready => return Ok(()),
+                    ViteState::Exited(status) | ViteState::Failed { status, .. } => {
+                        return Err(status);
+                    }
+                    _ => {}
+                }
+                // The sender lives in `shared`, which this future owns, so this never fails.
+                let _ = state.changed().await;
+            }
+        }
+    }
+
+    /// Returns a future that resolves with the exit status once the Vite process has exited
+    /// or the supervisor gave up restarting it.
+    ///
+    /// The future does not borrow the handle, and any number of callers may wait at once.
+    /// It does not keep the process alive either: if every handle is dropped the process is
+    /// killed and the future resolves with that exit status.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # #[actix_web::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let vite = vite_actix::start_vite_server_managed()?;
+    /// let exited = vite.wait_exited();
+    /// actix_web::rt::spawn(async move {
+    ///     log::warn!("Vite exited with {}", exited.await);
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait_exited(&self) -> impl Future<Output = ExitStatus> + Send + 'static {
+        let shared = self.inner.shared.clone();
+        async move {
+            let mut state = shared.subscribe();
+            loop {
+                if let ViteState::Exited(status) | ViteState::Failed { status, .. } =
+                    *state.borrow_and_update()
+                {
+                    return status;
+                }
+                // The sender lives in `shared`, which this future owns, so this never fails.
+                let _ = state.changed().await;
+            }
+        }
+    }
+
+    /// Stops the Vite process and waits for it to exit.
+    ///
+    /// Calling this on a process that has already exited is not an error.
+    pub fn shutdown(&self) -> anyhow::Result<()> {
+        self.inner.stopped.store(true, Ordering::SeqCst);
+        let mut process = self
+            .inner
+            .process
+            .lock()
+            .map_err(|_| anyhow::Error::msg("Failed to lock Vite process handle"))?;
+        let status = process.stop(&self.inner.name, self.inner.spec.kill_on_parent_exit)?;
+        drop(process);
+        self.inner.mark_exited(status);
+        Ok(())
+    }
+
+    /// Stops the Vite process, starts a new one in its place and waits until it is ready,
+    /// e.g. when Vite got stuck on a stale dependency optimization.
+    ///
+    /// The new process is started with the same options, but the `vite` executable is looked
+    /// up again. It reports to the same state, so the proxy follows it to its port and output
+    /// and error subscribers keep receiving. `on_exit` is called for the old process.
+    ///
+    /// Blocks until Vite is ready; use [`ViteServerHandle::restart_async`] from async code,
+    /// e.g. an admin route. Calls made while a restart is running wait for it instead of
+    /// restarting again. Fails if the new process exits before it is ready, or if spawning
+    /// was skipped.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # fn run() -> anyhow::Result<()> {
+    /// let vite = vite_actix::start_vite_server_managed()?;
+    /// let old_pid = vite.pid();
+    /// vite.restart()?;
+    /// println!("Vite {} replaced {}", vite.pid(), old_pid);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn restart(&self) -> anyhow::Result<()> {
+        // The restart runs on its own runtime, which cannot be started on a runtime thread.
+        let restart = self.restart_async();
+        std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?
+                .block_on(restart)
+        })
+        .join()
+        .map_err(|_| anyhow::Error::msg("The Vite restart thread panicked"))?
+    }
+
+    /// Like [`ViteServerHandle::restart`], but returns a future that resolves once the new
+    /// process is ready.
+    ///
+    /// The future keeps the handle's process alive until it resolves.
+    pub fn restart_async(&self) -> impl Future<Output = anyhow::Result<()>> + Send + 'static {
+        let inner = self.inner.clone();
+        async move {
+            let seen = inner.restarts.load(Ordering::SeqCst);
+            let _guard = inner.restart_lock.lock().await;
+            if inner.restarts.load(Ordering::SeqCst) != seen {
+                // Another restart started after this call and has finished, so use its result.
+                let state = inner.shared.state();
+                if !state.is_ready() {
+                    anyhow::bail!(
+                        "Vite instance '{}' is not ready after restarting: {:?}",
+                        inner.name,
+                        state
+                    );
+                }
+                return Ok(());
+            }
+
+            // Stopping Vite waits for the process to exit, so keep it off the async workers.
+            let stopping = inner.clone();
+            let result = match tokio::task::spawn_blocking(move || stopping.stop_for_restart())
+                .await
+            {
+                Ok(Ok(old_pid)) => inner.wait_restarted(old_pid).await,
+                Ok(Err(e)) => Err(e),
+                Err(e) => Err(e.into()),
+            };
+            inner.restarts.fetch_add(1, Ordering::SeqCst);
+            result
+        }
+    }
+}
+
+/// Controls the crash restarts of a managed Vite process.
+///
+/// The handle does not keep the process alive; once every [`ViteServerHandle`] is dropped
+/// there is nothing left to resume.
+#[derive(Clone)]
+pub struct SupervisorHandle {
+    inner: Weak<HandleInner>,
+}
+
+impl SupervisorHandle {
+    /// Forgets earlier crashes and restarts Vite if it is not running, e.g. after the
+    /// supervisor gave up on it and the cause has been fixed.
+    ///
+    /// Returns `false` if there was nothing to restart because Vite is still running, or
+    /// spawning was skipped, or every handle was dropped.
+    pub fn resume(&self) -> bool {
+        let Some(inner) = self.inner.upgrade() else {
+            return false;
+        };
+        if let Ok(mut failures) = inner.failures.lock() {
+            failures.clear();
+        }
+        let skipped = inner
+            .process
+            .lock()
+            .is_ok_and(|process| matches!(*process, ManagedProcess::Skipped));
+        if skipped || !inner.exited.load(Ordering::SeqCst) {
+            return false;
+        }
+        info!("Resuming Vite instance '{}'", inner.name);
+        inner.stopped.store(false, Ordering::SeqCst);
+        inner.restart_requested.store(true, Ordering::SeqCst);
+        true
+    }
+}
+
+impl HandleInner {
+    // Whether readiness means `ViteState::FullyReady` rather than `ViteState::Ready`.
+    fn waits_for_fully_ready(&self) -> bool {
+        self.spec.ready_phase == ReadyPhase::FullyReady && self.spec.command.is_dev_server()
+    }
+
+    // Stop the process and ask the reaper to spawn a new one, returning the old process id.
+    //
+    // `stopped` stays set until the reaper respawns, so the exit is never taken for a crash.
+    fn stop_for_restart(&self) -> anyhow::Result<u32> {
+        let mut process = self
+            .process
+            .lock()
+            .map_err(|_| anyhow::Error::msg("Failed to lock Vite process handle"))?;
+        if matches!(*process, ManagedProcess::Skipped) {
+            anyhow::bail!(
+                "Vite instance '{}' was not spawned, so it cannot be restarted",
+                self.name
+            );
+        }
+        info!("Restarting Vite instance '{}'", self.name);
+        let old_pid = process.id();
+        self.stopped.store(true, Ordering::SeqCst);
+        let status = process.stop(&self.name, self.spec.kill_on_parent_exit)?;
+        drop(process);
+        self.mark_exited(status);
+        if let Ok(mut failures) = self.failures.lock() {
+            failures.clear();
+        }
+        self.restart_requested.store(true, Ordering::SeqCst);
+        Ok(old_pid)
+    }
+
+    // Wait until the process started in place of `old_pid` is ready.
+    async fn wait_restarted(&self, old_pid: u32) -> anyhow::Result<()> {
+        let fully_ready = self.waits_for_fully_ready();
+        let mut state = self.shared.subscribe();
+        loop {
+            match &*state.borrow_and_update() {
+                ViteState::FullyReady => return Ok(()),
+                ViteState::Ready if !fully_ready => return Ok(()),
+                // Until the reaper respawns, the state is still the exit of the old process.
+                ViteState::Exited(status) if self.pid.load(Ordering::SeqCst) != old_pid => {
+                    anyhow::bail!(
+                        "Vite instance '{}' exited with {} while restarting",
+                        self.name,
+                        status
+                    );
+                }
+                ViteState::Failed { output, .. } => anyhow::bail!(
+                    "Vite instance '{}' failed to restart: {}",
+                    self.name,
+                    output.join("\n")
+                ),
+                _ => {}
+            }
+            // The sender lives in `shared`, which `self` owns, so this never fails.
+            let _ = state.changed().await;
+        }
+    }
+
+    // Record the exit in the shared state and fire the on_exit callback, exactly once.
+    fn mark_exited(&self, status: ExitStatus) {
+        // The shared state may already belong to a newer process, so track this per handle.
+        if self.exited.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        debug!("Vite instance '{}' exited with {}", self.name, status);
+        if self.spec.pid_file {
+            PidFile::remove(
+                &self.spec.working_directory,
+                self.pid.load(Ordering::SeqCst),
+            );
+        }
+        self.shared.set_state(ViteState::Exited(status));
+        self.shared.publish(ViteEvent::Exited(status));
+        if let Some(on_exit) = &self.spec.on_exit {
+            on_exit(status);
+        }
+    }
+
+    // Stop the process that listens on `actual` although `strict_port` pinned it to
+    // `configured`, and fail instead of restarting it.
+    fn fail_on_port(&self, configured: u16, actual: u16) {
+        self.stopped.store(true, Ordering::SeqCst);
+        let status = match self.process.lock() {
+            Ok(mut process) => process.stop(&self.name, self.spec.kill_on_parent_exit),
+            Err(_) => return,
+        };
+        match status {
+            Ok(status) => {
+                self.mark_exited(status);
+                let err = StartViteError::PortMismatch { configured, actual };
+                self.shared.set_state(ViteState::Failed {
+                    status,
+                    output: vec![err.to_string()],
+                });
+            }
+            Err(e) => error!("Failed to stop Vite instance '{}': {}", self.name, e),
+        }
+    }
+
+    // Respawn the process on another port after Vite exited because `port` was taken.
+    //
+    // Returns `false` if the process should be treated as exited instead.
+    fn respawn_after_conflict(&self, port: u16) -> bool {
+        let next = match resolve_port_conflict(&self.spec, port) {
+            Ok(next) => next,
+            Err(e) => {
+                error!("Vite instance '{}' failed to start: {}", self.name, e);
+                return false;
+            }
+        };
+
+        match self.respawn(Some(next)) {
+            Ok(()) => true,
+            Err(e) => {
+                error!("Failed to respawn Vite instance '{}': {}", self.name, e);
+                false
+            }
+        }
+    }
+
+    // Record a crash and decide how long to wait before restarting, if at all.
+    //
+    // Gives up and moves to `ViteState::Failed` once the policy's failure limit is reached.
+    fn restart_delay(&self, status: ExitStatus) -> Option<Duration> {
+        let policy = self.spec.restart_policy?;
+        // A clean exit, e.g. after pressing `q` in Vite's terminal, is not a crash.
+        if status.success() || self.stopped.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let failures = self
+            .failures
+            .lock()
+            .ok()?
+            .record(Instant::now(), policy.window);
+        if policy.gives_up(failures) {
+            error!(
+                "Vite instance '{}' crashed {} times within {:?}, not restarting it again",
+                self.name, failures, policy.window
+            );
+            self.shared.set_state(ViteState::Failed {
+                status,
+                output: self.shared.recent_output(),
+            });
+            return None;
+        }
+
+        let delay = policy.jittered_delay(failures, supervisor::random_jitter());
+        warn!(
+            "Vite instance '{}' exited with {}, restarting it in {:?}",
+            self.name, status, delay
+        );
+        Some(delay)
+    }
+
+    // Spawn a fresh process on `port` in place of the one that exited.
+    fn respawn(&self, port: Option<u16>) -> Result<(), StartViteError> {
+        self.shared.reset(port);
+        self.shared.set_restarted_from(Some(self.pid.load(Ordering::SeqCst)));
+        let child = spawn_child(&self.spec, port, &self.shared).inspect_err(|_| {
+            self.shared.set_restarted_from(None);
+        })?;
+        let pid = child.id();
+        self.pid.store(pid, Ordering::SeqCst);
+        self.shared.set_pid(pid);
+        if self.spec.pid_file {
+            write_pid_file(&self.spec, pid, port);
+        }
+        if let Ok(mut guard) = self.process.lock() {
+            *guard = ManagedProcess::Child(child);
+        }
+        self.exited.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Drop for HandleInner {
+    fn drop(&mut self) {
+        let result = match self.process.get_mut() {
+            Ok(process) => process.stop(&self.name, self.spec.kill_on_parent_exit),
+            Err(_) => return,
+        };
+        match result {
+            Ok(status) => self.mark_exited(status),
+            Err(e) => debug!("Failed to stop Vite instance '{}': {}", self.name, e),
+        }
+    }
+}
+
+// Spawn a Vite process for `spec` on `port`, reporting its output events to `shared`.
+fn spawn_child(
+    spec: &Arc<SpawnSpec>,
+    port: Option<u16>,
+    shared: &Arc<ViteShared>,
+) -> Result<Child, StartViteError> {
+    let shared = shared.clone();
+    let reader_spec = spec.clone();
+    let child = spawn_vite(spec, port, move |event| {
+        handle_output_event(&reader_spec, &shared, event)
+    })?;
+    if spec.kill_on_parent_exit {
+        process_lifetime::kill_with_parent(&child);
+    }
+    Ok(child)
+}
+
+// Apply the configured strategy to a busy port, returning the port to use instead.
+fn resolve_port_conflict(spec: &SpawnSpec, port: u16) -> Result<u16, StartViteError> {
+    match spec.port_conflict {
+        PortConflictStrategy::FailFast => Err(port_conflict::port_conflict_error(port)),
+        PortConflictStrategy::NextFreePort => {
+            let next = port
+                .checked_add(1)
+                .and_then(port_conflict::find_free_port)
+                .ok_or(StartViteError::NoFreePort { from: port })?;
+            warn!(
+                "Port {} is already in use, starting Vite on port {}",
+                port, next
+            );
+            Ok(next)
+        }
+    }
+}
+
+// The process behind a handle: one we spawned, or one left behind by a previous run.
+enum ManagedProcess {
+    Child(Child),
+    Attached(u32),
+    Skipped,
+}
+
+impl ManagedProcess {
+    fn id(&self) -> u32 {
+        match self {
+            ManagedProcess::Child(child) => child.id(),
+            ManagedProcess::Attached(pid) => *pid,
+            ManagedProcess::Skipped => 0,
+        }
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        match self {
+            ManagedProcess::Child(child) => child.try_wait(),
+            // The exit status of a process we did not spawn cannot be observed.
+            ManagedProcess::Attached(pid) if pid_file::is_process_alive(*pid) => Ok(None),
+            ManagedProcess::Attached(_) => Ok(Some(ExitStatus::default())),
+            // Never started, so it never exits either.
+            ManagedProcess::Skipped => Ok(None),
+        }
+    }
+
+    // Kill the process if it is still running and reap it so no zombie is left behind.
+    // A child spawned in its own process group is stopped gracefully along with its group.
+    fn stop(&mut self, name: &str, process_group: bool) -> std::io::Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+        debug!("Stopping Vite instance '{}' (pid {})", name, self.id());
+        match self {
+            ManagedProcess::Child(child) if process_group => process_lifetime::stop_group(child),
+            ManagedProcess::Child(child) => {
+                pid_file::kill_descendants(child.id());
+                child.kill()?;
+                child.wait()
+            }
+            ManagedProcess::Attached(pid) => {
+                pid_file::kill_descendants(*pid);
+                pid_file::kill_process(*pid)?;
+                Ok(ExitStatus::default())
+            }
+            ManagedProcess::Skipped => Ok(ExitStatus::default()),
+        }
+    }
+}
+
+// Record `pid` and `port` in the PID file of the spec's working directory.
+pub(crate) fn write_pid_file(spec: &SpawnSpec, pid: u32, port: Option<u16>) {
+    if let Err(e) = (PidFile { pid, port }).write(&spec.working_directory) {
+        debug!("Failed to write Vite PID file: {}", e);
+    }
+}
+
+// Check on Vite once it had time to restart itself after a config change.
+//
+// If the restart crashed Vite, have the reaper spawn it again, since the config may be fixed
+// by now, forgetting the crashes caused by the old config. If it is
+// running but no longer answers on its port, probe until it does; a moved port is picked up
+// from the new banner line.
+#[cfg(feature = "watch")]
+fn after_config_change(inner: Weak<HandleInner>) {
+    std::thread::spawn(move || {
+        std::thread::sleep(CONFIG_SETTLE_TIME);
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+
+        if inner.exited.load(Ordering::SeqCst) {
+            if inner.stopped.load(Ordering::SeqCst) {
+                return;
+            }
+            info!(
+                "Vite config changed, restarting Vite instance '{}'",
+                inner.name
+            );
+            if let Ok(mut failures) = inner.failures.lock() {
+                failures.clear();
+            }
+            inner.restart_requested.store(true, Ordering::SeqCst);
+            return;
+        }
+
+        let Some(port) = inner.shared.port() else {
+            return;
+        };
+        if !inner.spec.command.is_server() || crate::vite_stdio::probe_http(port) {
+            return;
+        }
+        debug!(
+            "Vite instance '{}' is not answering on port {} after its config changed",
+            inner.name, port
+        );
+        inner.shared.set_state(ViteState::Starting);
+        let shared = inner.shared.clone();
+        let spec = inner.spec.clone();
+        crate::spawn::spawn_ready_probe(inner.pid.load(Ordering::SeqCst), port, move |event| {
+            handle_output_event(&spec, &shared, event)
+        });
+    });
+}
+
+// Poll the child until every handle is dropped, recording its exit and spawning it again
+// when the restart policy or a restart request says so.
+//
+// All restarts happen here so there is only ever one process per handle. The reaper only
+// holds a weak reference so it never keeps a process alive on its own.
+fn spawn_reaper(inner: Weak<HandleInner>) {
+    std::thread::spawn(move || {
+        let mut restart_at: Option<Instant> = None;
+        let mut last_status = ExitStatus::default();
+        loop {
+            std::thread::sleep(REAP_INTERVAL);
+            let Some(inner) = inner.upgrade() else {
+                break;
+            };
+
+            if inner.exited.load(Ordering::SeqCst) {
+                let requested = inner.restart_requested.swap(false, Ordering::SeqCst);
+                if requested || restart_at.is_some_and(|at| Instant::now() >= at) {
+                    restart_at = None;
+                    inner.stopped.store(false, Ordering::SeqCst);
+                    if let Err(e) = inner.respawn(inner.spec.port) {
+                        error!("Failed to restart Vite instance '{}': {}", inner.name, e);
+                        inner.shared.set_state(ViteState::Failed {
+                            status: last_status,
+                            output: vec![e.to_string()],
+                        });
+                    }
+                }
+                continue;
+            }
+
+            if let Some((configured, actual)) = inner.shared.take_rejected_port() {
+                inner.fail_on_port(configured, actual);
+                continue;
+            }
+
+            let status = match inner.process.lock() {
+                Ok(mut process) => process.try_wait(),
+                Err(_) => break,
+            };
+            match status {
+                Ok(Some(status)) => {
+                    if let Some(port) = inner.shared.take_port_conflict()
+                        && inner.respawn_after_conflict(port)
+                    {
+                        continue;
+                    }
+                    inner.mark_exited(status);
+                    last_status = status;
+                    restart_at = inner
+                        .restart_delay(status)
+                        .map(|delay| Instant::now() + delay);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    debug!("Failed to poll Vite instance '{}': {}", inner.name, e);
+                    break;
+                }
+            }
+        }
+    });
+}
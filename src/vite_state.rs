@@ -0,0 +1,537 @@
+#[cfg(feature = "spawn")]
+use std::collections::VecDeque;
+#[cfg(feature = "spawn")]
+use crate::compile_error::ViteCompileError;
+#[cfg(feature = "spawn")]
+use crate::events::ViteEvent;
+#[cfg(feature = "spawn")]
+use crate::vite_stdio::ViteOutputLine;
+#[cfg(feature = "spawn")]
+use crate::vite_version::ViteVersion;
+#[cfg(feature = "spawn")]
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+#[cfg(feature = "spawn")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "spawn")]
+use std::sync::{Arc, Mutex, OnceLock};
+#[cfg(feature = "spawn")]
+use std::time::SystemTime;
+#[cfg(feature = "spawn")]
+use tokio::sync::{broadcast, watch};
+#[cfg(feature = "spawn")]
+use url::Url;
+
+// Shared state of the Vite process started through the global options.
+#[cfg(feature = "spawn")]
+static GLOBAL_VITE_SHARED: OnceLock<Arc<ViteShared>> = OnceLock::new();
+
+// How many of the most recent output lines go into `ViteState::Failed`, error values and
+// error pages.
+#[cfg(feature = "spawn")]
+const OUTPUT_HISTORY: usize = 20;
+
+/// The default of [`ProxyViteOptions::output_buffer_lines`], how many output lines are kept
+/// for [`ViteServerHandle::recent_logs`].
+///
+/// [`ProxyViteOptions::output_buffer_lines`]: crate::proxy_vite_options::ProxyViteOptions::output_buffer_lines
+/// [`ViteServerHandle::recent_logs`]: crate::vite_server_handle::ViteServerHandle::recent_logs
+#[cfg(feature = "spawn")]
+pub const DEFAULT_OUTPUT_BUFFER_LINES: usize = 500;
+
+// How many compile errors are kept for `ViteServerHandle::recent_errors`.
+#[cfg(feature = "spawn")]
+const ERROR_HISTORY: usize = 10;
+
+// How many output lines a slow subscriber may fall behind before it misses some.
+#[cfg(feature = "spawn")]
+const OUTPUT_CHANNEL_CAPACITY: usize = 1024;
+
+// How many events a slow subscriber may fall behind before it misses some.
+#[cfg(feature = "spawn")]
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// The lifecycle state of a Vite process managed by this crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ViteState {
+    /// No Vite process has been started by this crate.
+    Idle,
+    /// The process was spawned but has not reported its `Local:` URL yet.
+    Starting,
+    /// The process reported the port it is listening on.
+    Ready,
+    /// The dev server has also finished optimizing the dependencies it found on startup, so
+    /// requests no longer stall. Only `vite` dev servers get here, see [`ReadyPhase`].
+    FullyReady,
+    /// The process exited with the given status.
+    Exited(ExitStatus),
+    /// The process crashed too often in a row and is no longer restarted, see
+    /// [`crate::supervisor::RestartPolicy`]. Holds the last exit status and the last lines
+    /// Vite printed, if its output was captured.
+    Failed {
+        status: ExitStatus,
+        output: Vec<String>,
+    },
+}
+
+/// Which state counts as ready for the `on_ready` callback and
+/// [`ViteServerHandle::wait_ready`](crate::vite_server_handle::ViteServerHandle::wait_ready).
+///
+/// # Example
+/// ```rust,no_run
+/// # #[cfg(feature = "spawn")]
+/// # fn run() -> anyhow::Result<()> {
+/// use vite_actix::proxy_vite_options::ProxyViteOptions;
+/// use vite_actix::vite_state::ReadyPhase;
+///
+/// // Not ready before the first page load stops stalling on the dependency optimizer.
+/// ProxyViteOptions::new()
+///     .ready_phase(ReadyPhase::FullyReady)
+///     .build()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReadyPhase {
+    /// As soon as Vite prints its `Local:` URL. Requests may still stall for a while when
+    /// Vite is optimizing dependencies.
+    #[default]
+    Ready,
+    /// Once the dev server is done optimizing dependencies: Vite printed
+    /// `✨ new dependencies optimized`, or it serves its client module quickly. Commands
+    /// other than the dev server are ready at [`ViteState::Ready`].
+    FullyReady,
+}
+
+/// Information passed to the `on_ready` callback.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViteReadyInfo {
+    /// The port Vite is listening on, or `None` for commands that don't start a server
+    /// such as `vite build --watch`.
+    pub port: Option<u16>,
+}
+
+impl ViteState {
+    /// Returns the state of the Vite process started with the global options. Always
+    /// [`ViteState::Idle`] without the `spawn` feature, which starts no process.
+    pub fn current() -> Self {
+        #[cfg(feature = "spawn")]
+        return ViteShared::global().state();
+        #[cfg(not(feature = "spawn"))]
+        return ViteState::Idle;
+    }
+
+    /// Whether the process is [`ViteState::Ready`] or [`ViteState::FullyReady`].
+    pub fn is_ready(&self) -> bool {
+        matches!(self, ViteState::Ready | ViteState::FullyReady)
+    }
+}
+
+// The Vite process a proxy target reports on, for the starting page, error pages, health
+// and toolbar: the process of the global options for the global target. Options of their
+// own have none, even for the same project, as no process reports to them. Without the
+// `spawn` feature, no target has one.
+#[cfg(feature = "proxy")]
+#[derive(Clone, Default)]
+pub(crate) struct ViteProcess {
+    #[cfg(feature = "spawn")]
+    shared: Option<Arc<ViteShared>>,
+}
+
+#[cfg(feature = "proxy")]
+impl ViteProcess {
+    // The process started with the global options.
+    pub(crate) fn global() -> Self {
+        Self {
+            #[cfg(feature = "spawn")]
+            shared: Some(ViteShared::global()),
+        }
+    }
+
+    #[cfg(feature = "spawn")]
+    pub(crate) fn shared(&self) -> Option<&ViteShared> {
+        self.shared.as_deref()
+    }
+
+    // The state of the process, `None` without one.
+    pub(crate) fn state(&self) -> Option<ViteState> {
+        #[cfg(feature = "spawn")]
+        return self.shared().map(ViteShared::state);
+        #[cfg(not(feature = "spawn"))]
+        return None;
+    }
+
+    pub(crate) fn pid(&self) -> Option<u32> {
+        #[cfg(feature = "spawn")]
+        return self.shared()?.pid();
+        #[cfg(not(feature = "spawn"))]
+        return None;
+    }
+
+    // The last compile error Vite printed.
+    pub(crate) fn last_error(&self) -> Option<String> {
+        #[cfg(feature = "spawn")]
+        return Some(self.shared()?.recent_errors().pop()?.to_string());
+        #[cfg(not(feature = "spawn"))]
+        return None;
+    }
+
+    // The last lines Vite printed.
+    pub(crate) fn recent_output(&self) -> Vec<String> {
+        #[cfg(feature = "spawn")]
+        return self
+            .shared()
+            .map(ViteShared::recent_output)
+            .unwrap_or_default();
+        #[cfg(not(feature = "spawn"))]
+        return Vec::new();
+    }
+
+    // When Vite last reported a hot update.
+    pub(crate) fn last_hmr_update(&self) -> Option<std::time::SystemTime> {
+        #[cfg(feature = "spawn")]
+        return self.shared()?.last_hmr_update();
+        #[cfg(not(feature = "spawn"))]
+        return None;
+    }
+
+    // The (requested, actual) ports, when Vite listens on another port than it was asked to.
+    pub(crate) fn port_mismatch(&self) -> Option<(u16, u16)> {
+        #[cfg(feature = "spawn")]
+        return self.shared()?.port_mismatch();
+        #[cfg(not(feature = "spawn"))]
+        return None;
+    }
+}
+
+// State shared between a `ViteServerHandle`, its stdout reader and its reaper.
+#[cfg(feature = "spawn")]
+pub(crate) struct ViteShared {
+    port: Mutex<Option<u16>>,
+    // The port the process was asked to listen on, which Vite may not have gotten.
+    requested_port: Mutex<Option<u16>>,
+    // The (requested, actual) ports of a process that moved although `strict_port` was set.
+    rejected_port: Mutex<Option<(u16, u16)>>,
+    network_urls: Mutex<Vec<Url>>,
+    pid: Mutex<Option<u32>>,
+    port_conflict: Mutex<Option<u16>>,
+    // The last lines Vite printed, up to `output_capacity` of them.
+    recent_output: Mutex<VecDeque<ViteOutputLine>>,
+    output_capacity: AtomicUsize,
+    output: broadcast::Sender<ViteOutputLine>,
+    log_file: Mutex<Option<PathBuf>>,
+    optimizing_deps: AtomicBool,
+    recent_errors: Mutex<VecDeque<ViteCompileError>>,
+    errors: broadcast::Sender<ViteCompileError>,
+    vite_version: Mutex<Option<ViteVersion>>,
+    last_hmr_update: Mutex<Option<SystemTime>>,
+    events: broadcast::Sender<ViteEvent>,
+    // The process the next spawned one replaces, if it is a respawn.
+    restarted_from: Mutex<Option<u32>>,
+    state: watch::Sender<ViteState>,
+}
+
+#[cfg(feature = "spawn")]
+impl ViteShared {
+    pub(crate) fn new(port: Option<u16>) -> Arc<Self> {
+        Arc::new(Self {
+            port: Mutex::new(port),
+            requested_port: Mutex::new(port),
+            rejected_port: Mutex::new(None),
+            network_urls: Mutex::new(Vec::new()),
+            pid: Mutex::new(None),
+            port_conflict: Mutex::new(None),
+            recent_output: Mutex::new(VecDeque::new()),
+            output_capacity: AtomicUsize::new(DEFAULT_OUTPUT_BUFFER_LINES),
+            output: broadcast::Sender::new(OUTPUT_CHANNEL_CAPACITY),
+            log_file: Mutex::new(None),
+            optimizing_deps: AtomicBool::new(false),
+            recent_errors: Mutex::new(VecDeque::new()),
+            errors: broadcast::Sender::new(ERROR_HISTORY),
+            vite_version: Mutex::new(None),
+            last_hmr_update: Mutex::new(None),
+            events: broadcast::Sender::new(EVENT_CHANNEL_CAPACITY),
+            restarted_from: Mutex::new(None),
+            state: watch::Sender::new(ViteState::Idle),
+        })
+    }
+
+    // The shared state backing the global options and the proxy's error reporting
+    pub(crate) fn global() -> Arc<Self> {
+        GLOBAL_VITE_SHARED
+            .get_or_init(|| ViteShared::new(None))
+            .clone()
+    }
+
+    // Prepare the state for a freshly spawned process
+    pub(crate) fn reset(&self, port: Option<u16>) {
+        if let Ok(mut guard) = self.port.lock() {
+            *guard = port;
+        }
+        if let Ok(mut guard) = self.requested_port.lock() {
+            *guard = port;
+        }
+        self.take_rejected_port();
+        self.take_port_conflict();
+        self.set_optimizing_deps(false);
+        if let Ok(mut urls) = self.network_urls.lock() {
+            urls.clear();
+        }
+        if let Ok(mut output) = self.recent_output.lock() {
+            output.clear();
+        }
+        if let Ok(mut last_hmr_update) = self.last_hmr_update.lock() {
+            *last_hmr_update = None;
+        }
+        if let Ok(mut errors) = self.recent_errors.lock() {
+            errors.clear();
+        }
+        self.set_state(ViteState::Starting);
+    }
+
+    pub(crate) fn port(&self) -> Option<u16> {
+        self.port.lock().ok().and_then(|port| *port)
+    }
+
+    pub(crate) fn requested_port(&self) -> Option<u16> {
+        self.requested_port.lock().ok().and_then(|port| *port)
+    }
+
+    // The (requested, actual) ports, when Vite listens on another port than it was asked to
+    #[cfg(feature = "proxy")]
+    pub(crate) fn port_mismatch(&self) -> Option<(u16, u16)> {
+        match (self.requested_port(), self.port()) {
+            (Some(requested), Some(actual)) if requested != actual => Some((requested, actual)),
+            _ => None,
+        }
+    }
+
+    // Remember that Vite moved from `requested` to `actual` although it must not
+    pub(crate) fn reject_port(&self, requested: u16, actual: u16) {
+        if let Ok(mut guard) = self.rejected_port.lock() {
+            *guard = Some((requested, actual));
+        }
+    }
+
+    pub(crate) fn take_rejected_port(&self) -> Option<(u16, u16)> {
+        self.rejected_port
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take())
+    }
+
+    pub(crate) fn set_port(&self, port: u16) {
+        if let Ok(mut guard) = self.port.lock() {
+            *guard = Some(port);
+        }
+        self.set_state(ViteState::Ready);
+    }
+
+    pub(crate) fn network_urls(&self) -> Vec<Url> {
+        self.network_urls
+            .lock()
+            .map(|urls| urls.clone())
+            .unwrap_or_default()
+    }
+
+    // Remember a `Network:` URL, once; Vite prints the banner again after restarting itself
+    pub(crate) fn add_network_url(&self, url: Url) {
+        if let Ok(mut urls) = self.network_urls.lock()
+            && !urls.contains(&url)
+        {
+            urls.push(url);
+        }
+    }
+
+    // Move to `Ready`, returning `true` if the process was not ready before
+    pub(crate) fn mark_ready(&self) -> bool {
+        self.state.send_if_modified(|state| {
+            let changed = *state != ViteState::Ready;
+            *state = ViteState::Ready;
+            changed
+        })
+    }
+
+    // Move from `Ready` to `FullyReady`, returning `true` if that happened
+    pub(crate) fn mark_fully_ready(&self) -> bool {
+        self.state.send_if_modified(|state| {
+            let changed = *state == ViteState::Ready;
+            if changed {
+                *state = ViteState::FullyReady;
+            }
+            changed
+        })
+    }
+
+    // Whether Vite said it is re-optimizing dependencies and has not finished yet
+    pub(crate) fn optimizing_deps(&self) -> bool {
+        self.optimizing_deps.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn set_optimizing_deps(&self, optimizing: bool) {
+        self.optimizing_deps.store(optimizing, Ordering::SeqCst);
+    }
+
+    pub(crate) fn pid(&self) -> Option<u32> {
+        self.pid.lock().ok().and_then(|pid| *pid)
+    }
+
+    pub(crate) fn set_pid(&self, pid: u32) {
+        if let Ok(mut guard) = self.pid.lock() {
+            *guard = Some(pid);
+        }
+    }
+
+    // Remember that Vite reported `port` as taken; consumed by the reaper once it exits
+    pub(crate) fn record_port_conflict(&self, port: u16) {
+        if let Ok(mut guard) = self.port_conflict.lock() {
+            *guard = Some(port);
+        }
+    }
+
+    pub(crate) fn take_port_conflict(&self) -> Option<u16> {
+        self.port_conflict
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take())
+    }
+
+    // Keep `line` among the last lines Vite printed and pass it on to output subscribers
+    pub(crate) fn record_output(&self, line: ViteOutputLine) {
+        let capacity = self.output_capacity.load(Ordering::Relaxed);
+        if capacity > 0
+            && let Ok(mut output) = self.recent_output.lock()
+        {
+            while output.len() >= capacity {
+                output.pop_front();
+            }
+            output.push_back(line.clone());
+        }
+        // Nobody listening is fine.
+        let _ = self.output.send(line);
+    }
+
+    pub(crate) fn subscribe_output(&self) -> broadcast::Receiver<ViteOutputLine> {
+        self.output.subscribe()
+    }
+
+    // Keep `error` among the last compile errors and pass it on to error subscribers
+    pub(crate) fn record_compile_error(&self, error: ViteCompileError) {
+        if let Ok(mut errors) = self.recent_errors.lock() {
+            if errors.len() == ERROR_HISTORY {
+                errors.pop_front();
+            }
+            errors.push_back(error.clone());
+        }
+        self.publish(ViteEvent::CompileError(error.clone()));
+        let _ = self.errors.send(error);
+    }
+
+    // The last compile errors, oldest first
+    pub(crate) fn recent_errors(&self) -> Vec<ViteCompileError> {
+        self.recent_errors
+            .lock()
+            .map(|errors| errors.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Remember that Vite just sent an HMR update or a full reload to the browser
+    pub(crate) fn record_hmr_update(&self) {
+        if let Ok(mut last_hmr_update) = self.last_hmr_update.lock() {
+            *last_hmr_update = Some(SystemTime::now());
+        }
+    }
+
+    #[cfg(feature = "proxy")]
+    pub(crate) fn last_hmr_update(&self) -> Option<SystemTime> {
+        self.last_hmr_update.lock().ok().and_then(|at| *at)
+    }
+
+    pub(crate) fn subscribe_errors(&self) -> broadcast::Receiver<ViteCompileError> {
+        self.errors.subscribe()
+    }
+
+    // Claim writing the output to `path`, returning `false` if that is already being done
+    pub(crate) fn claim_log_file(&self, path: &Path) -> bool {
+        self.log_file.lock().is_ok_and(|mut log_file| {
+            let claimed = log_file.as_deref() != Some(path);
+            *log_file = Some(path.to_path_buf());
+            claimed
+        })
+    }
+
+    // How many output lines to keep for `recent_logs`
+    pub(crate) fn set_output_capacity(&self, capacity: usize) {
+        self.output_capacity.store(capacity, Ordering::Relaxed);
+        if let Ok(mut output) = self.recent_output.lock() {
+            let excess = output.len().saturating_sub(capacity);
+            output.drain(..excess);
+        }
+    }
+
+    // The last few lines Vite printed, for error values and pages
+    pub(crate) fn recent_output(&self) -> Vec<String> {
+        self.recent_logs(OUTPUT_HISTORY)
+            .into_iter()
+            .map(|line| line.line)
+            .collect()
+    }
+
+    // The last `n` lines Vite printed, oldest first
+    pub(crate) fn recent_logs(&self, n: usize) -> Vec<ViteOutputLine> {
+        self.recent_output
+            .lock()
+            .map(|output| {
+                let skip = output.len().saturating_sub(n);
+                output.iter().skip(skip).cloned().collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn vite_version(&self) -> Option<ViteVersion> {
+        self.vite_version.lock().ok().and_then(|version| *version)
+    }
+
+    // Record the detected Vite version, returning `true` if it was not known before
+    pub(crate) fn set_vite_version(&self, version: ViteVersion) -> bool {
+        self.vite_version
+            .lock()
+            .is_ok_and(|mut guard| guard.replace(version) != Some(version))
+    }
+
+    pub(crate) fn state(&self) -> ViteState {
+        self.state.borrow().clone()
+    }
+
+    pub(crate) fn set_state(&self, state: ViteState) {
+        self.state.send_replace(state);
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<ViteState> {
+        self.state.subscribe()
+    }
+
+    // Pass `event` on to event subscribers
+    pub(crate) fn publish(&self, event: ViteEvent) {
+        // Nobody listening is fine.
+        let _ = self.events.send(event);
+    }
+
+    pub(crate) fn subscribe_events(&self) -> broadcast::Receiver<ViteEvent> {
+        self.events.subscribe()
+    }
+
+    // Remember that the next process spawned replaces `pid`
+    pub(crate) fn set_restarted_from(&self, pid: Option<u32>) {
+        if let Ok(mut guard) = self.restarted_from.lock() {
+            *guard = pid;
+        }
+    }
+
+    pub(crate) fn take_restarted_from(&self) -> Option<u32> {
+        self.restarted_from
+            .lock()
+            .ok()
+            .and_then(|mut guard| guard.take())
+    }
+}
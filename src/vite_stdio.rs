@@ -0,0 +1,105 @@
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, SystemTime};
+
+// How long a single readiness probe may take to connect or answer.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+// How fast Vite has to serve a module to count as done optimizing its dependencies.
+const MODULE_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where the output of the Vite process goes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViteStdio {
+    /// Capture stdout and stderr, forward every line to the `log` crate and parse them for
+    /// the port banner, build times and port conflicts.
+    #[default]
+    Piped,
+    /// Let Vite write straight to the terminal of this process, keeping its colors and
+    /// progress output.
+    ///
+    /// The output cannot be parsed in this mode, so readiness is detected by probing the
+    /// configured port (or the `VITE_PORT` environment variable) over HTTP instead. Port
+    /// conflicts are only caught by the up-front check of
+    /// [`ProxyViteOptions::strict_port`](crate::proxy_vite_options::ProxyViteOptions::strict_port),
+    /// and `vite build --watch` does not report its builds.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # fn run() -> anyhow::Result<()> {
+    /// use vite_actix::proxy_vite_options::ProxyViteOptions;
+    /// use vite_actix::vite_stdio::ViteStdio;
+    ///
+    /// ProxyViteOptions::new()
+    ///     .stdio(ViteStdio::Inherit)
+    ///     .port(5173)
+    ///     .build()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    Inherit,
+}
+
+impl ViteStdio {
+    /// Whether Vite's output is captured and parsed by this crate.
+    pub fn is_piped(&self) -> bool {
+        matches!(self, ViteStdio::Piped)
+    }
+}
+
+/// Which of Vite's output streams a line was printed to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ViteStream {
+    Stdout,
+    Stderr,
+}
+
+/// A line of Vite's captured output, as sent to output subscribers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ViteOutputLine {
+    /// The stream the line was printed to.
+    pub stream: ViteStream,
+    /// The line without its line ending and ANSI escape codes.
+    pub line: String,
+    /// When the line was read.
+    pub at: SystemTime,
+}
+
+/// The port from the `VITE_PORT` environment variable, if it is set to a valid port.
+pub fn env_port() -> Option<u16> {
+    std::env::var("VITE_PORT").ok()?.trim().parse().ok()
+}
+
+// Check whether an HTTP server answers on `localhost:port`.
+pub(crate) fn probe_http(port: u16) -> bool {
+    probe(port, "HEAD", "/", PROBE_TIMEOUT)
+}
+
+// Check whether the Vite dev server on `port` serves its client module quickly, which it
+// doesn't while it is still optimizing dependencies.
+pub(crate) fn probe_module(port: u16) -> bool {
+    probe(port, "GET", "/@vite/client", MODULE_PROBE_TIMEOUT)
+}
+
+// Send `method path` to `localhost:port` and check that an HTTP response starts within
+// `timeout`.
+fn probe(port: u16, method: &str, path: &str, timeout: Duration) -> bool {
+    let Ok(addrs) = ("localhost", port).to_socket_addrs() else {
+        return false;
+    };
+    addrs.into_iter().any(|addr| {
+        let Ok(mut stream) = TcpStream::connect_timeout(&addr, timeout) else {
+            return false;
+        };
+        let _ = stream.set_read_timeout(Some(timeout));
+        let _ = stream.set_write_timeout(Some(timeout));
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost:{}\r\nConnection: close\r\n\r\n",
+            method, path, port
+        );
+        let mut status = [0u8; 5];
+        stream.write_all(request.as_bytes()).is_ok()
+            && stream.read_exact(&mut status).is_ok()
+            && &status == b"HTTP/"
+    })
+}
@@ -0,0 +1,134 @@
+#[cfg(feature = "spawn")]
+use crate::launcher::ViteLauncher;
+#[cfg(feature = "spawn")]
+use crate::vite_state::ViteShared;
+#[cfg(feature = "spawn")]
+use log::{debug, warn};
+use regex::Regex;
+use std::fmt::{self, Display, Formatter};
+use std::ops::RangeInclusive;
+#[cfg(feature = "spawn")]
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The major Vite versions this crate is tested with.
+pub const TESTED_MAJOR_VERSIONS: RangeInclusive<u64> = 4..=6;
+
+/// A Vite release, as reported by `vite --version`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ViteVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ViteVersion {
+    /// Parses the output of `vite --version`, e.g. `vite/6.0.11 linux-x64 node-v22.12.0`,
+    /// or a bare version such as `5.4.2` or `v5.4.2`.
+    ///
+    /// Pre-release and build suffixes like `-beta.1` are ignored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use vite_actix::vite_version::ViteVersion;
+    ///
+    /// let version = |major, minor, patch| Some(ViteVersion { major, minor, patch });
+    /// assert_eq!(ViteVersion::parse("vite/6.0.11 linux-x64 node-v22"), version(6, 0, 11));
+    /// assert_eq!(ViteVersion::parse("vite/5.4.2 win32-x64 node-v20.11.0\r\n"), version(5, 4, 2));
+    /// assert_eq!(ViteVersion::parse("5.4.2"), version(5, 4, 2));
+    /// assert_eq!(ViteVersion::parse("v4.5.3"), version(4, 5, 3));
+    /// let beta = "vite/7.0.0-beta.1 darwin-arm64 node-v24.0.0";
+/// assert_eq!(ViteVersion::parse(beta), version(7, 0, 0));
+    /// // Node's version is not Vite's.
+    /// assert_eq!(ViteVersion::parse("node-v22.12.0"), None);
+    /// for output in ["", "vite/6.0 linux-x64", "command not found: vite"] {
+    ///     assert_eq!(ViteVersion::parse(output), None, "{:?}", output);
+    /// }
+    ///
+    /// let version = ViteVersion::parse("vite/6.0.11 linux-x64 node-v22").unwrap();
+    /// assert_eq!(version.to_string(), "6.0.11");
+    /// assert!(version.is_tested());
+    /// assert!(version > ViteVersion::parse("5.4.2").unwrap());
+    /// assert!(!ViteVersion::parse("3.2.10").unwrap().is_tested());
+    /// assert!(!ViteVersion::parse("7.0.0").unwrap().is_tested());
+    /// ```
+    pub fn parse(output: &str) -> Option<Self> {
+        static VERSION: OnceLock<Regex> = OnceLock::new();
+        let regex = VERSION.get_or_init(|| {
+            Regex::new(r"(?:^|\s|vite/)v?(?P<major>\d+)\.(?P<minor>\d+)\.(?P<patch>\d+)").unwrap()
+        });
+        let caps = regex.captures(output.trim())?;
+        Some(Self {
+            major: caps.name("major")?.as_str().parse().ok()?,
+            minor: caps.name("minor")?.as_str().parse().ok()?,
+            patch: caps.name("patch")?.as_str().parse().ok()?,
+        })
+    }
+
+    /// The version of the Vite process started with the global options, once detected.
+    /// Always `None` without the `spawn` feature, which starts no process.
+    pub fn current() -> Option<Self> {
+        #[cfg(feature = "spawn")]
+        return ViteShared::global().vite_version();
+        #[cfg(not(feature = "spawn"))]
+        return None;
+    }
+
+    /// Whether this crate is tested with this version's major release.
+    pub fn is_tested(&self) -> bool {
+        TESTED_MAJOR_VERSIONS.contains(&self.major)
+    }
+}
+
+impl Display for ViteVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+// Run `<launcher> --version` in `working_directory` and parse what it prints.
+#[cfg(feature = "spawn")]
+pub(crate) fn detect(launcher: &ViteLauncher, working_directory: &Path) -> Option<ViteVersion> {
+    let output = launcher
+        .command()
+        .arg("--version")
+        .current_dir(working_directory)
+        .stdin(std::process::Stdio::null())
+        .output();
+    let output = match output {
+        Ok(output) => output,
+        Err(e) => {
+            debug!("Failed to run {} --version: {}", launcher.program, e);
+            return None;
+        }
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = ViteVersion::parse(&stdout);
+    if version.is_none() {
+        debug!("Could not read the Vite version from {:?}", stdout.trim());
+    }
+    version
+}
+
+/// Warn about a Vite version the crate's banner parsing and proxying were not tested with.
+#[cfg(feature = "spawn")]
+pub(crate) fn warn_if_untested(version: ViteVersion) {
+    if version.is_tested() {
+        debug!("Detected Vite {}", version);
+        return;
+    }
+    let relation = if version.major < *TESTED_MAJOR_VERSIONS.start() {
+        "older"
+    } else {
+        "newer"
+    };
+    warn!(
+        "Vite {} is {} than the versions vite-actix is tested with ({} to {}). The startup \
+         banner, the HMR client path and `server.allowedHosts` differ between major versions, \
+         so port detection and proxying may not work; see https://vite.dev/guide/migration",
+        version,
+        relation,
+        TESTED_MAJOR_VERSIONS.start(),
+        TESTED_MAJOR_VERSIONS.end()
+    );
+}
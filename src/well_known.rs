@@ -0,0 +1,64 @@
+// Static files browsers and crawlers request on their own, which Vite serves from `public/`.
+pub(crate) const DEFAULT_WELL_KNOWN_PATHS: [&str; 5] = [
+    "/favicon.ico",
+    "/robots.txt",
+    "/sitemap.xml",
+    "/manifest.json",
+    "/apple-touch-icon.png",
+];
+
+/// Whether Vite answered a request for the file at `path` with its `index.html` because the
+/// file does not exist: the response is HTML, but the extension says the file is not.
+#[cfg(feature = "proxy")]
+pub(crate) fn is_html_fallback(path: &str, content_type: Option<&str>) -> bool {
+    let is_html = content_type.is_some_and(|content_type| {
+        content_type
+            .trim_start()
+            .to_ascii_lowercase()
+            .starts_with("text/html")
+    });
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension {
+        Some(extension) => is_html && !matches!(extension.as_str(), "html" | "htm"),
+        None => false,
+    }
+}
+
+#[cfg(all(test, feature = "proxy"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn html_for_a_file_of_another_type_is_a_fallback() {
+        assert!(is_html_fallback("/favicon.ico", Some("text/html")));
+        assert!(is_html_fallback("/robots.txt", Some("text/html; charset=utf-8")));
+        assert!(is_html_fallback("/manifest.json", Some("Text/HTML; charset=UTF-8")));
+        assert!(is_html_fallback("/sitemap.xml", Some("  text/html")));
+    }
+
+    #[test]
+    fn a_missing_content_type_is_not_a_fallback() {
+        assert!(!is_html_fallback("/favicon.ico", None));
+        assert!(!is_html_fallback("/robots.txt", None));
+    }
+
+    #[test]
+    fn other_content_types_are_not_a_fallback() {
+        assert!(!is_html_fallback("/favicon.ico", Some("image/x-icon")));
+        assert!(!is_html_fallback("/sitemap.xml", Some("application/xml")));
+        assert!(!is_html_fallback("/robots.txt", Some("text/plain; charset=utf-8")));
+        assert!(!is_html_fallback("/robots.txt", Some("")));
+    }
+
+    #[test]
+    fn html_files_and_paths_without_an_extension_are_not_a_fallback() {
+        assert!(!is_html_fallback("/index.html", Some("text/html")));
+        assert!(!is_html_fallback("/legacy/PAGE.HTM", Some("text/html; charset=utf-8")));
+        assert!(!is_html_fallback("/about", Some("text/html")));
+        assert!(!is_html_fallback("/v1.2/about", Some("text/html")));
+    }
+}
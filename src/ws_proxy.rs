@@ -0,0 +1,110 @@
+use crate::upgrade::{self, Upgrade};
+use actix_codec::{Decoder, Encoder};
+use actix_http::ws::{self, Codec, Frame, Message, ProtocolError};
+use actix_web::body::BodyStream;
+use actix_web::error::{ErrorBadGateway, PayloadError};
+#[cfg(feature = "dev-proxy")]
+use actix_web::http::header::HeaderMap;
+use actix_web::http::header::{COOKIE, HeaderName, HeaderValue, ORIGIN, SEC_WEBSOCKET_PROTOCOL};
+use actix_web::{Error, HttpRequest, HttpResponse, web};
+use futures_util::{SinkExt, Stream, StreamExt};
+use log::debug;
+use std::io;
+
+// The largest frame relayed in either direction; Vite's HMR messages are far smaller.
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+// Whether `req` asks to switch the connection to a WebSocket.
+pub(crate) fn is_websocket(req: &HttpRequest) -> bool {
+    upgrade::negotiate(req.method(), req.headers()) == Upgrade::WebSocket
+}
+
+// Whether a request with `headers` is a complete WebSocket upgrade: `Upgrade: websocket` and
+// `upgrade` among the options of its `Connection` header.
+#[cfg(feature = "dev-proxy")]
+pub(crate) fn is_upgrade(headers: &HeaderMap) -> bool {
+    upgrade::is_websocket_upgrade(headers)
+}
+
+// Relay the WebSocket that `req` opens, like Vite's HMR socket, to `url` on the Vite server.
+//
+// The upstream socket is connected first, so the browser only gets its `101 Switching
+// Protocols` once Vite accepted, with the subprotocol Vite chose (`vite-hmr`). Frames are
+// then passed through as they are, until either side closes. The upstream request carries
+// the hop header the caller stamped, see `hops`.
+pub(crate) async fn forward(
+    req: HttpRequest,
+    mut payload: impl Stream<Item = Result<web::Bytes, PayloadError>> + Unpin + 'static,
+    url: String,
+    hop: (HeaderName, HeaderValue),
+) -> Result<HttpResponse, Error> {
+    let mut response = ws::handshake(req.head())?;
+
+    let mut upstream = awc::Client::new().ws(&url).max_frame_size(MAX_FRAME_SIZE);
+    if let Some(protocols) = req.headers().get(SEC_WEBSOCKET_PROTOCOL) {
+        upstream = upstream.header(SEC_WEBSOCKET_PROTOCOL, protocols.clone());
+    }
+    if let Some(origin) = req.headers().get(ORIGIN) {
+        upstream = upstream.origin(origin.clone());
+    }
+    if let Some(cookie) = req.headers().get(COOKIE) {
+        upstream = upstream.header(COOKIE, cookie.clone());
+    }
+    upstream = upstream.header(hop.0, hop.1);
+    let (upstream_res, socket) = upstream.connect().await.map_err(|err| {
+        ErrorBadGateway(format!("Failed to open a WebSocket to {}: {}", url, err))
+    })?;
+    if let Some(protocol) = upstream_res.headers().get(SEC_WEBSOCKET_PROTOCOL) {
+        response.insert_header((SEC_WEBSOCKET_PROTOCOL, protocol.clone()));
+    }
+    debug!("Relaying a WebSocket to {}", url);
+    let (mut to_vite, from_vite) = socket.split();
+
+    // Browser to Vite: the request body is the raw stream of the browser's frames.
+    actix_web::rt::spawn(async move {
+        let mut codec = Codec::new().max_size(MAX_FRAME_SIZE);
+        let mut buffer = web::BytesMut::new();
+        while let Some(Ok(chunk)) = payload.next().await {
+            buffer.extend_from_slice(&chunk);
+            while let Ok(Some(frame)) = codec.decode(&mut buffer) {
+                let Ok(message) = into_message(frame) else {
+                    return;
+                };
+                if to_vite.send(message).await.is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = to_vite.close().await;
+    });
+
+    // Vite to browser: the response body, encoded as frames of a server.
+    let mut codec = Codec::new().max_size(MAX_FRAME_SIZE);
+    let body = from_vite.map(move |frame| {
+        let message = into_message(frame?)?;
+        let mut buffer = web::BytesMut::new();
+        codec.encode(message, &mut buffer)?;
+        Ok::<_, ProtocolError>(buffer.freeze())
+    });
+
+    Ok(response
+        .message_body(BodyStream::new(body))?
+        .map_into_boxed_body()
+        .into())
+}
+
+// A frame received on one side as the message to send on the other.
+fn into_message(frame: Frame) -> Result<Message, ProtocolError> {
+    Ok(match frame {
+        Frame::Text(text) => {
+            let text = std::str::from_utf8(&text)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            Message::Text(text.into())
+        }
+        Frame::Binary(data) => Message::Binary(data),
+        Frame::Continuation(item) => Message::Continuation(item),
+        Frame::Ping(data) => Message::Ping(data),
+        Frame::Pong(data) => Message::Pong(data),
+        Frame::Close(reason) => Message::Close(reason),
+    })
+}
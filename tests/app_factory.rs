@@ -0,0 +1,231 @@
+//! Where `configure_vite` and its variants put the proxy and the build in an application.
+
+mod common;
+
+use actix_web::{App, HttpResponse, test, web};
+use vite_actix::test::MockVite;
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+#[actix_web::test]
+async fn proxy_routes_take_only_their_paths() {
+    let vite = MockVite::start().await;
+    let options = vite
+        .options()
+        .proxy_routes(&["/assets/{file:.*}", "/src/{file:.*}", "/@{rest:.*}"])
+        .register_default_service(false);
+    let app = App::new()
+        .configure_vite_with(options.clone())
+        .service(web::scope("/admin").configure_vite_with(options))
+        .default_service(web::to(|| async { HttpResponse::NotFound().body("app") }));
+    let app = test::init_service(app).await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    for uri in [
+        "/src/main.ts",
+        "/@vite/client",
+        "/assets/logo.svg",
+        "/admin/src/main.ts",
+    ] {
+        assert_eq!(
+            test::call_service(&app, get(uri)).await.status(),
+            200,
+            "{}",
+            uri
+        );
+    }
+    for uri in ["/", "/about", "/srcs/main.ts", "/admin/about"] {
+        assert_eq!(
+            test::call_and_read_body(&app, get(uri)).await,
+            "app",
+            "{}",
+            uri
+        );
+    }
+    let paths: Vec<_> = vite.requests().iter().map(|r| r.uri.to_string()).collect();
+    // Vite gets the path without the scope.
+    assert_eq!(
+        paths,
+        [
+            "/src/main.ts",
+            "/@vite/client",
+            "/assets/logo.svg",
+            "/src/main.ts"
+        ]
+    );
+}
+
+#[actix_web::test]
+async fn routes_of_the_application_win_over_the_proxy() {
+    let vite = MockVite::start().await;
+    let app = App::new()
+        .route(
+            "/health",
+            web::get().to(|| async { HttpResponse::Ok().body("before") }),
+        )
+        .configure_vite_with(vite.options())
+        .route(
+            "/version",
+            web::get().to(|| async { HttpResponse::Ok().body("after") }),
+        );
+    let app = test::init_service(app).await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    assert_eq!(
+        test::call_and_read_body(&app, get("/health")).await,
+        "before"
+    );
+    assert_eq!(
+        test::call_and_read_body(&app, get("/version")).await,
+        "after"
+    );
+    assert_eq!(
+        test::call_and_read_body(&app, get("/about")).await,
+        "mock vite: GET /about"
+    );
+    let paths: Vec<_> = vite.requests().iter().map(|r| r.uri.to_string()).collect();
+    assert_eq!(paths, ["/about"]);
+}
+
+#[cfg(feature = "prod-serve")]
+#[actix_web::test]
+async fn files_of_the_build_are_only_read() {
+    use actix_web::http::Method;
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+
+    let prod = ProxyViteOptions::new()
+        .dev_mode(false)
+        .dist_directory("examples/embedded_dist");
+    let app = test::init_service(App::new().configure_vite_with(prod)).await;
+    for method in [Method::POST, Method::PUT, Method::DELETE] {
+        let req = test::TestRequest::default()
+            .method(method)
+            .uri("/robots.txt")
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 405);
+        assert_eq!(res.headers().get("allow").unwrap(), "GET, HEAD");
+    }
+    let req = test::TestRequest::default()
+        .method(Method::HEAD)
+        .uri("/robots.txt")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[cfg(feature = "prod-serve")]
+#[actix_web::test]
+async fn configure_vite_if_proxies_or_serves_the_build() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+    let condition = |proxy: bool| {
+        move || {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            proxy
+        }
+    };
+
+    let vite = MockVite::start().await;
+    let options = vite.options().dist_directory("examples/embedded_dist");
+    options.clone().build().unwrap();
+    let page = || {
+        test::TestRequest::get()
+            .uri("/about")
+            .insert_header(("accept", "text/html"))
+            .to_request()
+    };
+    let index = std::fs::read("examples/embedded_dist/index.html").unwrap();
+
+    // Proxied to Vite, even though the dist directory exists.
+    let app = test::init_service(App::new().configure_vite_if(condition(true))).await;
+    assert_eq!(
+        test::call_and_read_body(&app, page()).await,
+        "mock vite: GET /about"
+    );
+    // The build is served instead, and nothing reaches Vite.
+    let app = test::init_service(App::new().configure_vite_if(condition(false))).await;
+    assert_eq!(test::call_and_read_body(&app, page()).await, index);
+    let robots = test::TestRequest::get().uri("/robots.txt").to_request();
+    assert_eq!(test::call_service(&app, robots).await.status(), 200);
+    assert_eq!(vite.requests().len(), 1);
+    // Once per application built, not per request.
+    assert_eq!(CALLS.load(Ordering::SeqCst), 2);
+
+    // `configure_vite` decides with the dev mode of the global options.
+    let app = test::init_service(App::new().configure_vite()).await;
+    assert_eq!(
+        test::call_and_read_body(&app, page()).await,
+        "mock vite: GET /about"
+    );
+    options.dev_mode(false).build().unwrap();
+    let app = test::init_service(App::new().configure_vite()).await;
+    assert_eq!(test::call_and_read_body(&app, page()).await, index);
+    assert_eq!(vite.requests().len(), 2);
+}
+
+#[actix_web::test]
+async fn routes_actix_cannot_match_are_refused_without_a_panic() {
+    use vite_actix::error::ConfigProblem;
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+
+    // Set in the copy of this test that `output` runs, whose stderr any panic would go to.
+    const CHILD: &str = "VITE_ACTIX_TEST_ROUTE_CHILD";
+    if std::env::var_os(CHILD).is_none() {
+        let output = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "routes_actix_cannot_match_are_refused_without_a_panic",
+                "--exact",
+                "--nocapture",
+            ])
+            .env(CHILD, "1")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+        return;
+    }
+
+    let problems = |pattern: &str| {
+        let options = ProxyViteOptions::new()
+            .dev_mode(true)
+            .port(5173)
+            .proxy_routes(&["/src/{file:.*}", pattern]);
+        match App::new().try_configure_vite_with(options) {
+            Ok(_) => Vec::new(),
+            Err(error) => error.problems,
+        }
+    };
+    let seventeen = "/{a}{b}{c}{d}{e}{f}{g}{h}{i}{j}{k}{l}{m}{n}{o}{p}{q}";
+    for (pattern, reason) in [
+        ("/assets/{file", "a dynamic segment is not closed"),
+        ("/assets/{file:.*}*", "a tail segment cannot have a regex"),
+        (seventeen, "it has 17 dynamic segments, Actix allows 16"),
+    ] {
+        let invalid = ConfigProblem::InvalidRoute(pattern.to_string(), reason.to_string());
+        assert_eq!(problems(pattern), [invalid]);
+    }
+    let found = problems("/assets/{file:([a-z}");
+    let [ConfigProblem::InvalidRoute(pattern, reason)] = found.as_slice() else {
+        panic!("expected an invalid route: {:?}", found);
+    };
+    assert_eq!(pattern, "/assets/{file:([a-z}");
+    assert!(
+        reason.starts_with("its regex does not compile"),
+        "{}",
+        reason
+    );
+
+    // Patterns Actix takes, with regexes that have braces of their own.
+    for pattern in [
+        "/static/app.js",
+        "/assets/{file}*",
+        "/@{rest:.*}",
+        r"/chunks/{id:\d{2,4}}.js",
+        "/{a}{b}{c}{d}{e}{f}{g}{h}{i}{j}{k}{l}{m}{n}{o}{p}",
+    ] {
+        assert_eq!(problems(pattern), [], "{}", pattern);
+    }
+}
@@ -0,0 +1,171 @@
+//! Building the frontend, at startup and from a build script.
+#![cfg(unix)]
+
+mod common;
+
+use common::Project;
+use std::ffi::OsStr;
+use std::time::Duration;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::test::ViteShim;
+
+// The kill switch, for builds where there is no Node.js.
+const SKIP_BUILD: &str = "VITE_ACTIX_SKIP_BUILD";
+
+#[cfg(feature = "prod-serve")]
+#[test]
+fn missing_and_stale_builds_are_built_at_startup() {
+    use std::time::SystemTime;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("build");
+    let index = project.join("dist/index.html");
+    let options = || {
+        ProxyViteOptions::new()
+            .dev_mode(false)
+            .working_directory(project.dir())
+    };
+
+    // Without the build, there is nothing to serve.
+    ViteShim::new()
+        .line("vite v5.4.0 building for production...")
+        .builds()
+        .write(&project)
+        .unwrap();
+    assert!(options().build().is_err());
+    options().build_if_missing(true).build().unwrap();
+    assert!(
+        std::fs::read_to_string(&index)
+            .unwrap()
+            .contains("built by the vite shim")
+    );
+
+    // A build older than the sources is built again only with `build_if_stale`.
+    std::fs::write(&index, "old").unwrap();
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    let main = std::fs::File::create(project.join("src/main.ts")).unwrap();
+    main.set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    options().build_if_missing(true).build().unwrap();
+    assert_eq!(std::fs::read_to_string(&index).unwrap(), "old");
+    options().build_if_stale(true).build().unwrap();
+    assert_ne!(std::fs::read_to_string(&index).unwrap(), "old");
+
+    // A failing build fails `build()`, with its output.
+    std::fs::remove_dir_all(project.join("dist")).unwrap();
+    ViteShim::new()
+        .stderr_line("error during build:")
+        .exit(1)
+        .write(&project)
+        .unwrap();
+    let error = options()
+        .build_if_missing(true)
+        .build()
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.starts_with("vite build failed with exit status: 1"),
+        "{}",
+        error
+    );
+    assert!(error.ends_with("error during build:"), "{}", error);
+
+    // So does one that takes too long.
+    ViteShim::new()
+        .pause(Duration::from_secs(5))
+        .builds()
+        .write(&project)
+        .unwrap();
+    let error = options()
+        .build_if_missing(true)
+        .build_timeout(Duration::from_millis(300))
+        .build()
+        .unwrap_err();
+    assert_eq!(error.to_string(), "vite build did not finish within 300ms");
+}
+
+#[cfg(feature = "prod-serve")]
+#[test]
+fn nothing_is_built_at_startup_with_the_kill_switch() {
+    if !common::in_own_process(&[(SKIP_BUILD, OsStr::new("1"))]) {
+        return;
+    }
+    let project = Project::new("skip-build");
+    ViteShim::new().builds().write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .dev_mode(false)
+        .working_directory(project.dir())
+        .build_if_missing(true);
+    assert!(options.build().is_err());
+    assert!(!project.join("dist/index.html").exists());
+}
+
+#[cfg(feature = "build-helper")]
+fn build_script_project(name: &str) -> Project {
+    let project = Project::new(name);
+    std::fs::create_dir_all(project.join("src")).unwrap();
+    std::fs::write(project.join("index.html"), "<!DOCTYPE html>").unwrap();
+    std::fs::write(project.join("package.json"), "{}").unwrap();
+    std::fs::write(project.join("pnpm-lock.yaml"), "").unwrap();
+    ViteShim::new().builds().write(&project).unwrap();
+    project
+}
+
+#[cfg(feature = "build-helper")]
+#[test]
+fn build_scripts_build_the_frontend() {
+    use vite_actix::build::{rerun_if_changed, run_vite_build};
+
+    let project = build_script_project("build-rs");
+    let options = ProxyViteOptions::new().working_directory(project.dir());
+
+    // Only the sources that exist are watched, as cargo reruns for a missing one every time.
+    let dir = project.display();
+    assert_eq!(
+        rerun_if_changed(&options),
+        [
+            format!("cargo:rerun-if-changed={}/src", dir),
+            format!("cargo:rerun-if-changed={}/index.html", dir),
+            format!("cargo:rerun-if-changed={}/package.json", dir),
+            format!("cargo:rerun-if-changed={}/pnpm-lock.yaml", dir),
+            format!("cargo:rerun-if-env-changed={}", SKIP_BUILD),
+        ]
+    );
+
+    run_vite_build(&options).unwrap();
+    let index = project.join("dist/index.html");
+    assert!(
+        std::fs::read_to_string(&index)
+            .unwrap()
+            .contains("built by the vite shim")
+    );
+
+    // A failing build fails the build script, with its output.
+    ViteShim::new()
+        .stderr_line("error during build:")
+        .exit(1)
+        .write(&project)
+        .unwrap();
+    let error = format!("{:?}", run_vite_build(&options).unwrap_err());
+    assert!(
+        error.contains("vite build failed with exit status: 1"),
+        "{}",
+        error
+    );
+    assert!(error.contains("error during build:"), "{}", error);
+}
+
+#[cfg(feature = "build-helper")]
+#[test]
+fn build_scripts_build_nothing_with_the_kill_switch() {
+    use vite_actix::build::run_vite_build;
+
+    if !common::in_own_process(&[(SKIP_BUILD, OsStr::new("1"))]) {
+        return;
+    }
+    let project = build_script_project("build-rs-skip");
+    run_vite_build(&ProxyViteOptions::new().working_directory(project.dir())).unwrap();
+    assert!(!project.join("dist/index.html").exists());
+}
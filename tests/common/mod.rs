@@ -0,0 +1,142 @@
+//! Fixtures shared by the integration tests, each of which uses some of them.
+#![allow(dead_code)]
+
+use std::ffi::OsStr;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Set in the process `in_own_process` runs a test in, to the PID of the one that started it.
+const OWN_PROCESS: &str = "VITE_ACTIX_TEST_OWN_PROCESS";
+
+/// A directory of its own for the files of a test, e.g. the fake `vite` of
+/// `vite_actix::test::ViteShim`, created empty and removed when dropped.
+pub struct Project(PathBuf);
+
+impl Project {
+    pub fn new(name: &str) -> Self {
+        // A test run again by `in_own_process` gets the directory of the first run, which the
+        // environment it was given may point into.
+        let pid = std::env::var(OWN_PROCESS).unwrap_or_else(|_| std::process::id().to_string());
+        let dir = std::env::temp_dir().join(format!("vite-actix-{}-{}", name, pid));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Project(dir)
+    }
+
+    /// The directory as the options take it.
+    pub fn dir(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl Deref for Project {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for Project {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for Project {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Runs the calling test again, alone in a process of its own with `envs` added to its
+/// environment, for the tests that change or depend on what a process shares: the global
+/// options, the state and events of the managed Vite, the logger, the environment and
+/// signals. Returns `true` in that process, where the test goes on, and `false` in this one
+/// once it passed there.
+pub fn in_own_process(envs: &[(&str, &OsStr)]) -> bool {
+    if std::env::var_os(OWN_PROCESS).is_some() {
+        return true;
+    }
+    let thread = std::thread::current();
+    let test = thread
+        .name()
+        .expect("the test harness names threads after their test");
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact", "--nocapture", "--test-threads=1"])
+        .env(OWN_PROCESS, std::process::id().to_string())
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        output.status.success() && stdout.contains("1 passed"),
+        "{} failed in its own process:\n{}{}",
+        test,
+        stdout,
+        stderr
+    );
+    false
+}
+
+/// A record that was logged after [`capture_logs`].
+#[derive(Clone, Debug)]
+pub struct Logged {
+    pub level: log::Level,
+    pub target: String,
+    pub message: String,
+}
+
+static LOGGED: Mutex<Vec<Logged>> = Mutex::new(Vec::new());
+
+struct Capture;
+
+impl log::Log for Capture {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        LOGGED.lock().unwrap().push(Logged {
+            level: record.level(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        });
+    }
+
+    fn flush(&self) {}
+}
+
+/// Keeps everything logged from now on, at every level, for [`logged`]. The logger is set
+/// for the whole process, so tests that check what is logged run in one of their own.
+pub fn capture_logs() {
+    log::set_logger(&Capture).expect("the logs of a process are only captured once");
+    log::set_max_level(log::LevelFilter::Trace);
+}
+
+/// What was logged since [`capture_logs`] or the last [`take_logged`].
+pub fn logged() -> Vec<Logged> {
+    LOGGED.lock().unwrap().clone()
+}
+
+/// Like [`logged`], forgetting it.
+pub fn take_logged() -> Vec<Logged> {
+    std::mem::take(&mut *LOGGED.lock().unwrap())
+}
+
+/// Waits until `check` passes, failing the test after ten seconds.
+pub async fn until(what: &str, check: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !check() {
+        assert!(
+            Instant::now() < deadline,
+            "timed out waiting until {}",
+            what
+        );
+        actix_web::rt::time::sleep(Duration::from_millis(20)).await;
+    }
+}
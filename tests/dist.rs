@@ -0,0 +1,440 @@
+//! Serving the build outside of dev mode: the files of the dist directory, and those embedded
+//! into the binary.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::http::header::ACCEPT;
+use actix_web::{App, test};
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+const NAVIGATION: &str = "text/html,application/xhtml+xml,*/*;q=0.8";
+
+fn built() -> ProxyViteOptions {
+    ProxyViteOptions::new()
+        .dev_mode(false)
+        .dist_directory("examples/embedded_dist")
+}
+
+fn get(uri: &str, accept: &str) -> actix_http::Request {
+    test::TestRequest::get()
+        .uri(uri)
+        .insert_header((ACCEPT, accept))
+        .to_request()
+}
+
+fn file(name: &str) -> Vec<u8> {
+    std::fs::read(format!("examples/embedded_dist/{}", name)).unwrap()
+}
+
+#[cfg(not(feature = "dev-proxy"))]
+#[actix_web::test]
+async fn build_is_served_in_dev_mode_without_the_proxy() {
+    let options = built().dev_mode(true);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let req = test::TestRequest::get().uri("/robots.txt").to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        file("robots.txt")
+    );
+}
+
+#[actix_web::test]
+async fn navigations_get_the_page_and_other_requests_a_404() {
+    let options = built().spa_fallback_exclude("/api");
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+
+    // A deep link of the client-side router gets the page, not a redirect.
+    let res = test::call_service(&app, get("/settings/profile", NAVIGATION)).await;
+    assert_eq!(res.status(), 200);
+    let body = test::read_body(res).await;
+    assert!(
+        std::str::from_utf8(&body)
+            .unwrap()
+            .contains("/assets/index-BxQw2a1z.js")
+    );
+
+    // Missing assets, requests that do not want a page and the backend's paths do not.
+    for (uri, accept) in [
+        ("/assets/missing-BxQw2a1z.js", "*/*"),
+        ("/logo.png", NAVIGATION),
+        ("/settings/profile", "application/json"),
+        ("/api/users", NAVIGATION),
+        ("/api", NAVIGATION),
+    ] {
+        let res = test::call_service(&app, get(uri, accept)).await;
+        assert_eq!(res.status(), 404, "{} {}", uri, accept);
+    }
+}
+
+#[actix_web::test]
+async fn scripts_of_an_older_deployment_are_a_404() {
+    let app = test::init_service(App::new().configure_vite_with(built())).await;
+
+    let res = test::call_service(&app, get("/settings/profile", NAVIGATION)).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, file("index.html"));
+    for uri in [
+        "/assets/index-0ld0ne5x.js",
+        "/assets/chunks/vendor",
+        "/logo.svg",
+    ] {
+        let res = test::call_service(&app, get(uri, NAVIGATION)).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND, "{}", uri);
+        assert!(test::read_body(res).await.is_empty());
+    }
+
+    // And JSON if it asks for that.
+    let res = test::call_service(&app, get("/assets/data.json", "application/json")).await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    let expected = serde_json::json!({ "error": "not found", "path": "/assets/data.json" });
+    assert_eq!(body, expected);
+}
+
+#[actix_web::test]
+async fn precompressed_files_are_sent_to_clients_that_accept_them() {
+    let app = test::init_service(App::new().configure_vite_with(built())).await;
+    let script = |name: &str| file(&format!("assets/{}", name));
+
+    for (accept_encoding, encoding, body) in [
+        (None, None, script("index-BxQw2a1z.js")),
+        (Some("gzip"), Some("gzip"), script("index-BxQw2a1z.js.gz")),
+        (Some("br"), Some("br"), script("index-BxQw2a1z.js.br")),
+        (
+            Some("gzip, deflate, br"),
+            Some("br"),
+            script("index-BxQw2a1z.js.br"),
+        ),
+        (
+            Some("br;q=0, gzip"),
+            Some("gzip"),
+            script("index-BxQw2a1z.js.gz"),
+        ),
+        (Some("deflate"), None, script("index-BxQw2a1z.js")),
+    ] {
+        let mut req = test::TestRequest::get().uri("/assets/index-BxQw2a1z.js");
+        if let Some(accept_encoding) = accept_encoding {
+            req = req.insert_header(("accept-encoding", accept_encoding));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let headers = res.headers();
+        assert_eq!(headers.get("content-type").unwrap(), "text/javascript");
+        let sent = headers.get("content-encoding").map(|e| e.to_str().unwrap());
+        assert_eq!(sent, encoding);
+        assert_eq!(headers.get("vary").unwrap(), "Accept-Encoding");
+        assert_eq!(test::read_body(res).await, body);
+    }
+
+    // Ranges are of the original file.
+    let req = test::TestRequest::get()
+        .uri("/assets/index-BxQw2a1z.js")
+        .insert_header(("accept-encoding", "br"))
+        .insert_header(("range", "bytes=0-3"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 206);
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(test::read_body(res).await, script("index-BxQw2a1z.js")[..4]);
+
+    // Every encoding has its own ETag, and revalidating with it gets a 304.
+    let mut etags = Vec::new();
+    for accept_encoding in ["identity", "gzip", "br"] {
+        let req = test::TestRequest::get()
+            .uri("/assets/index-BxQw2a1z.js")
+            .insert_header(("accept-encoding", accept_encoding))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        let etag = res.headers().get("etag").unwrap().clone();
+        let last_modified = res.headers().get("last-modified").unwrap().clone();
+        assert!(!etags.contains(&etag));
+        etags.push(etag.clone());
+
+        for (header, value, status) in [
+            ("if-none-match", etag, 304),
+            ("if-none-match", "\"changed\"".parse().unwrap(), 200),
+            ("if-modified-since", last_modified.clone(), 304),
+            (
+                "if-modified-since",
+                "Thu, 01 Jan 1970 00:00:00 GMT".parse().unwrap(),
+                200,
+            ),
+        ] {
+            let req = test::TestRequest::get()
+                .uri("/assets/index-BxQw2a1z.js")
+                .insert_header(("accept-encoding", accept_encoding))
+                .insert_header((header, value))
+                .to_request();
+            let res = test::call_service(&app, req).await;
+            assert_eq!(res.status(), status);
+            assert_eq!(res.headers().get("etag"), etags.last());
+            assert_eq!(res.headers().get("last-modified"), Some(&last_modified));
+            assert_eq!(test::read_body(res).await.is_empty(), status == 304);
+        }
+    }
+    // An ETag of another encoding does not match.
+    let req = test::TestRequest::get()
+        .uri("/assets/index-BxQw2a1z.js")
+        .insert_header(("accept-encoding", "br"))
+        .insert_header(("if-none-match", etags[1].clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_web::test]
+async fn extra_static_dirs_are_served_after_the_dist_directory() {
+    let options = built()
+        .extra_static_dir("/", "examples/extra_static")
+        .extra_static_dir("/uploads", "examples/extra_static/media");
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+
+    for (uri, file) in [
+        ("/media/intro.txt", "examples/extra_static/media/intro.txt"),
+        (
+            "/uploads/intro.txt",
+            "examples/extra_static/media/intro.txt",
+        ),
+        ("/robots.txt", "examples/embedded_dist/robots.txt"),
+    ] {
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers().get("cache-control").unwrap(), "no-cache");
+        assert_eq!(test::read_body(res).await, std::fs::read(file).unwrap());
+    }
+
+    // Nothing outside of the directories, even for clients that would get index.html.
+    for uri in [
+        "/..%2fCargo.toml",
+        "/uploads/..%2f..%2f..%2fCargo.toml",
+        "/uploads/%2e%2e/%2e%2e/%2e%2e/Cargo.toml",
+        "/uploads/../../../Cargo.toml",
+        "/uploads/..%5c..%5c..%5cCargo.toml",
+        "/media/..%2f..%2fsrc%2flib",
+    ] {
+        let req = get(uri, "text/html");
+        assert_eq!(test::call_service(&app, req).await.status(), 404, "{}", uri);
+    }
+
+    // `build()` checks that the directories exist.
+    let missing = built().extra_static_dir("/media", "examples/no_such_dir");
+    assert!(missing.build().is_err());
+}
+
+#[actix_web::test]
+async fn build_is_served_under_its_prefix() {
+    use actix_web::{HttpResponse, web};
+    use vite_actix::assets::{asset_url_with, tags_for_with};
+
+    let dist = common::Project::new("prefix");
+    std::fs::create_dir_all(dist.join(".vite")).unwrap();
+    std::fs::create_dir_all(dist.join("assets")).unwrap();
+    for file in [".vite/manifest.json", "assets/index-BxQw2a1z.js"] {
+        std::fs::copy(format!("examples/embedded_dist/{}", file), dist.join(file)).unwrap();
+    }
+    let index = r#"<script type="module" src="/app/assets/index-BxQw2a1z.js"></script>"#;
+    std::fs::write(dist.join("index.html"), index).unwrap();
+
+    let options = ProxyViteOptions::new()
+        .dev_mode(false)
+        .dist_directory(dist.dir())
+        .serve_prefix("/app");
+    // The helpers make URLs under the prefix too.
+    let tags = tags_for_with(&options, &["src/main.ts"]);
+    assert_eq!(tags.scripts(), ["/app/assets/index-BxQw2a1z.js"]);
+    assert_eq!(asset_url_with(&options, "robots.txt"), "/app/robots.txt");
+
+    let app = App::new()
+        .route(
+            "/api/users",
+            web::get().to(|| async { HttpResponse::Ok().body("users") }),
+        )
+        .configure_vite_with(options);
+    let app = test::init_service(app).await;
+    let page = |uri: &str| get(uri, "text/html");
+
+    let req = page("/app/assets/index-BxQw2a1z.js");
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        file("assets/index-BxQw2a1z.js")
+    );
+    // Deep links under the prefix get its index.html.
+    for uri in ["/app", "/app/", "/app/settings/profile"] {
+        assert_eq!(
+            test::call_and_read_body(&app, page(uri)).await,
+            index,
+            "{}",
+            uri
+        );
+    }
+    // Everything else is the backend's.
+    assert_eq!(
+        test::call_and_read_body(&app, page("/api/users")).await,
+        "users"
+    );
+    for uri in [
+        "/api/unknown",
+        "/settings",
+        "/assets/index-BxQw2a1z.js",
+        "/application",
+    ] {
+        assert_eq!(
+            test::call_service(&app, page(uri)).await.status(),
+            404,
+            "{}",
+            uri
+        );
+    }
+}
+
+#[cfg(feature = "compress")]
+#[actix_web::test]
+async fn text_files_are_compressed_on_the_fly() {
+    use std::io::Read;
+    use vite_actix::compression::Compression;
+
+    let dist = common::Project::new("compress");
+    let vendor = "export const answer = 42;\n".repeat(2000);
+    std::fs::write(dist.join("vendor.js"), &vendor).unwrap();
+    std::fs::write(dist.join("small.css"), "body{margin:0}").unwrap();
+    std::fs::write(dist.join("font.woff2"), vec![7u8; 4096]).unwrap();
+    std::fs::write(dist.join("index.html"), "<!doctype html>").unwrap();
+
+    let options = ProxyViteOptions::new()
+        .dev_mode(false)
+        .dist_directory(dist.dir())
+        .compression(Compression::default().level(9));
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let get = |uri: &str, accept_encoding: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("accept-encoding", accept_encoding))
+            .to_request()
+    };
+
+    // Brotli first, gzip for clients without it; both decode to the file.
+    let res = test::call_service(&app, get("/vendor.js", "gzip, deflate, br")).await;
+    assert_eq!(res.headers().get("content-encoding").unwrap(), "br");
+    assert_eq!(res.headers().get("vary").unwrap(), "Accept-Encoding");
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/javascript"
+    );
+    let br_etag = res.headers().get("etag").unwrap().clone();
+    let body = test::read_body(res).await;
+    assert!(body.len() < vendor.len() / 10);
+    let mut decoded = String::new();
+    brotli::Decompressor::new(&body[..], 4096)
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, vendor);
+
+    let res = test::call_service(&app, get("/vendor.js", "gzip")).await;
+    assert_eq!(res.headers().get("content-encoding").unwrap(), "gzip");
+    assert_ne!(res.headers().get("etag").unwrap(), br_etag);
+    let body = test::read_body(res).await;
+    let mut decoded = String::new();
+    flate2::read::GzDecoder::new(&body[..])
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, vendor);
+
+    // Revalidating with the ETag gets a 304.
+    let req = test::TestRequest::get()
+        .uri("/vendor.js")
+        .insert_header(("accept-encoding", "br"))
+        .insert_header(("if-none-match", br_etag.clone()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 304);
+    assert_eq!(res.headers().get("etag").unwrap(), br_etag);
+
+    // Clients that accept neither get the file, which still varies by the header.
+    let res = test::call_service(&app, get("/vendor.js", "identity")).await;
+    assert!(res.headers().get("content-encoding").is_none());
+    assert_eq!(res.headers().get("vary").unwrap(), "Accept-Encoding");
+    assert_eq!(test::read_body(res).await, vendor.as_bytes());
+
+    // Small files and compressed types are sent as they are.
+    for uri in ["/small.css", "/font.woff2"] {
+        let res = test::call_service(&app, get(uri, "br, gzip")).await;
+        assert!(res.headers().get("content-encoding").is_none(), "{}", uri);
+        assert!(res.headers().get("vary").is_none(), "{}", uri);
+    }
+
+    // A precompressed file wins.
+    std::fs::write(dist.join("vendor.js.gz"), b"precompressed").unwrap();
+    let res = test::call_service(&app, get("/vendor.js", "gzip")).await;
+    assert_eq!(test::read_body(res).await, "precompressed");
+}
+
+#[cfg(feature = "embed")]
+#[actix_web::test]
+async fn embedded_files_are_served_like_the_dist_directory() {
+    use rust_embed::Embed;
+
+    #[derive(Embed)]
+    #[folder = "examples/embedded_dist/"]
+    struct Assets;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    ProxyViteOptions::new()
+        .dev_mode(false)
+        .serve_dist(false)
+        .build()
+        .unwrap();
+    let app = test::init_service(App::new().configure_vite_embedded::<Assets>()).await;
+
+    let req = test::TestRequest::get()
+        .uri("/assets/index-BxQw2a1z.js")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(
+        res.headers().get("content-type").unwrap(),
+        "text/javascript"
+    );
+    let immutable = "public, max-age=31536000, immutable";
+    assert_eq!(res.headers().get("cache-control").unwrap(), immutable);
+    let etag = res.headers().get("etag").unwrap().clone();
+    let last_modified = res.headers().get("last-modified").unwrap().clone();
+
+    // The client's copy is still current.
+    for (header, value) in [
+        ("if-none-match", etag.clone()),
+        ("if-modified-since", last_modified),
+    ] {
+        let req = test::TestRequest::get()
+            .uri("/assets/index-BxQw2a1z.js")
+            .insert_header((header, value))
+            .to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), 304);
+        assert_eq!(res.headers().get("etag").unwrap(), etag);
+        assert!(test::read_body(res).await.is_empty());
+    }
+    // Or not.
+    let req = test::TestRequest::get()
+        .uri("/assets/index-BxQw2a1z.js")
+        .insert_header(("if-none-match", "\"changed\""))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    // Clients accepting gzip get the precompressed file, with an ETag of its own.
+    let req = test::TestRequest::get()
+        .uri("/assets/index-BxQw2a1z.js")
+        .insert_header(("accept-encoding", "gzip, br"))
+        .insert_header(("if-none-match", etag.clone()))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers().get("content-encoding").unwrap(), "gzip");
+    assert_ne!(res.headers().get("etag").unwrap(), etag);
+
+    // Client-side routes get index.html.
+    let res = test::call_service(&app, get("/settings/profile", "text/html")).await;
+    assert_eq!(res.headers().get("cache-control").unwrap(), "no-cache");
+    assert!(test::read_body(res).await.starts_with(b"<!doctype html>"));
+}
@@ -0,0 +1,252 @@
+//! The errors of starting Vite, and of building the frontend, as the application gets them.
+#![cfg(unix)]
+
+mod common;
+
+use common::Project;
+use std::net::TcpListener;
+use std::time::Duration;
+use vite_actix::error::StartViteError;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::test::ViteShim;
+
+// What to tell whoever runs the application, which names every kind of error.
+fn hint(error: &StartViteError) -> &'static str {
+    match error {
+        StartViteError::ExecutableNotFound { .. } | StartViteError::YarnNotFound { .. } => {
+            "install the frontend dependencies"
+        }
+        StartViteError::LookupFailed(_)
+        | StartViteError::SpawnFailed(_)
+        | StartViteError::OutputCaptureFailed => "check the Node.js installation",
+        StartViteError::WorkingDirMissing(_) => "check the working directory",
+        StartViteError::PortInUse { .. }
+        | StartViteError::NoFreePort { .. }
+        | StartViteError::PortMismatch { .. } => "free the port or configure another one",
+        StartViteError::SpawnSkipped(_) => "start Vite yourself",
+        StartViteError::ReadyTimeout { .. } => "look at the output of Vite",
+        StartViteError::BuildFailed { .. } | StartViteError::BuildTimeout { .. } => {
+            "run the build yourself"
+        }
+        StartViteError::UnknownInstance(_) | StartViteError::AlreadyRunning(_) => {
+            "check the instance name"
+        }
+    }
+}
+
+fn options(project: &Project) -> ProxyViteOptions {
+    ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging()
+}
+
+#[test]
+fn vite_missing_from_the_path_is_not_found() {
+    // Without node_modules/.bin/vite, vite is looked up on the PATH, with `which`.
+    let project = Project::new("lookup");
+    let bin = project.join("bin");
+    std::fs::create_dir_all(&bin).unwrap();
+    // `which` itself is found on the PATH of this process, and handed to the one without it.
+    let which = std::env::var_os("VITE_ACTIX_TEST_WHICH").unwrap_or_else(|| {
+        let which = std::process::Command::new("which")
+            .arg("which")
+            .output()
+            .unwrap()
+            .stdout;
+        String::from_utf8(which).unwrap().trim().into()
+    });
+    std::os::unix::fs::symlink(&which, bin.join("which")).unwrap();
+    if !common::in_own_process(&[("PATH", bin.as_os_str()), ("VITE_ACTIX_TEST_WHICH", &which)]) {
+        return;
+    }
+
+    let error = vite_actix::start_vite_server_with(&options(&project))
+        .err()
+        .unwrap();
+    let StartViteError::ExecutableNotFound { searched } = &error else {
+        panic!("expected ExecutableNotFound: {:?}", error);
+    };
+    assert_eq!(searched, std::slice::from_ref(&bin));
+    assert_eq!(hint(&error), "install the frontend dependencies");
+    std::fs::write(project.join(".pnp.cjs"), "").unwrap();
+    let error = vite_actix::start_vite_server_with(&options(&project))
+        .err()
+        .unwrap();
+    assert!(matches!(&error, StartViteError::YarnNotFound { pnp_root } if *pnp_root == *project));
+    std::fs::remove_file(project.join(".pnp.cjs")).unwrap();
+    std::fs::remove_file(bin.join("which")).unwrap();
+    let error = vite_actix::start_vite_server_with(&options(&project))
+        .err()
+        .unwrap();
+    assert!(
+        matches!(error, StartViteError::LookupFailed(_)),
+        "{:?}",
+        error
+    );
+}
+
+#[test]
+fn vite_that_cannot_run_fails_to_spawn() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let project = Project::new("spawn-failed");
+    let missing = project.join("missing");
+    let error = vite_actix::start_vite_server_with(
+        &ProxyViteOptions::new().working_directory(missing.to_str().unwrap()),
+    )
+    .err()
+    .unwrap();
+    assert!(matches!(&error, StartViteError::WorkingDirMissing(dir) if *dir == missing));
+    assert_eq!(hint(&error), "check the working directory");
+
+    let vite = ViteShim::new().write(&project).unwrap();
+    std::fs::set_permissions(&vite, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let error = vite_actix::start_vite_server_with(&options(&project))
+        .err()
+        .unwrap();
+    let StartViteError::SpawnFailed(e) = &error else {
+        panic!("expected SpawnFailed: {:?}", error);
+    };
+    assert_eq!(e.kind(), std::io::ErrorKind::PermissionDenied);
+    assert_eq!(hint(&error), "check the Node.js installation");
+}
+
+#[test]
+fn port_conflicts_are_found_before_spawning() {
+    use vite_actix::port_conflict::PortConflictStrategy;
+
+    let project = Project::new("port-errors");
+    ViteShim::new().banner(5297).write(&project).unwrap();
+    let _taken = TcpListener::bind(("127.0.0.1", 5297)).unwrap();
+    let strict = || options(&project).strict_port(true);
+    let error = vite_actix::start_vite_server_with(&strict().port(5297))
+        .err()
+        .unwrap();
+    assert!(
+        matches!(error, StartViteError::PortInUse { port: 5297, .. }),
+        "{:?}",
+        error
+    );
+    assert_eq!(hint(&error), "free the port or configure another one");
+
+    let _last = TcpListener::bind(("127.0.0.1", u16::MAX)).unwrap();
+    let error = vite_actix::start_vite_server_with(
+        &strict()
+            .port(u16::MAX)
+            .port_conflict(PortConflictStrategy::NextFreePort),
+    )
+    .err()
+    .unwrap();
+    assert!(
+        matches!(error, StartViteError::NoFreePort { from: u16::MAX }),
+        "{:?}",
+        error
+    );
+}
+
+#[actix_web::test]
+async fn vite_on_another_port_is_stopped_with_the_mismatch() {
+    use vite_actix::vite_state::ViteState;
+
+    let project = Project::new("mismatch");
+    ViteShim::new().banner(5299).write(&project).unwrap();
+    let options = options(&project).port(5298).strict_port(true);
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    let mismatch = StartViteError::PortMismatch {
+        configured: 5298,
+        actual: 5299,
+    };
+    vite.wait_exited().await;
+    actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+    let ViteState::Failed { output, .. } = vite.state() else {
+        panic!("expected a failed Vite: {:?}", vite.state());
+    };
+    assert_eq!(output, [mismatch.to_string()]);
+}
+
+#[test]
+fn named_instances_are_started_once_by_their_name() {
+    use vite_actix::vite_instances::{ViteInstanceOptions, ViteInstances};
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("instance-errors");
+    ViteShim::new().banner(5300).write(&project).unwrap();
+    options(&project)
+        .instance(
+            "admin",
+            ViteInstanceOptions::new().working_directory(project.dir()),
+        )
+        .build()
+        .unwrap();
+    let error = ViteInstances::start("public").err().unwrap();
+    assert!(matches!(&error, StartViteError::UnknownInstance(name) if name == "public"));
+    let _admin = ViteInstances::start("admin").unwrap();
+    let error = ViteInstances::start("admin").err().unwrap();
+    assert!(matches!(&error, StartViteError::AlreadyRunning(name) if name == "admin"));
+    assert_eq!(hint(&error), "check the instance name");
+    ViteInstances::shutdown_all().unwrap();
+}
+
+#[test]
+fn errors_through_anyhow_downcast_back() {
+    use std::sync::Arc;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("anyhow");
+    ViteShim::new().line("starting").write(&project).unwrap();
+    options(&project).build().unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    let error = vite_actix::wait_for_vite_port(Duration::from_millis(500)).unwrap_err();
+    let Some(StartViteError::ReadyTimeout { captured }) = error.downcast_ref() else {
+        panic!("expected a ReadyTimeout: {:?}", error);
+    };
+    assert_eq!(captured, &["starting"]);
+    vite.shutdown().unwrap();
+
+    options(&project)
+        .skip_spawn_when(Arc::new(|| Some("running on CI".into())))
+        .build()
+        .unwrap();
+    let error = vite_actix::start_vite_server().unwrap_err();
+    assert!(matches!(&error, StartViteError::SpawnSkipped(reason) if reason == "running on CI"));
+    assert_eq!(hint(&error), "start Vite yourself");
+}
+
+#[cfg(feature = "prod-serve")]
+#[test]
+fn failed_builds_fail_the_options() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("build-errors");
+    let building = || options(&project).dev_mode(false).build_if_missing(true);
+    ViteShim::new()
+        .stderr_line("error during build:")
+        .exit(1)
+        .write(&project)
+        .unwrap();
+    let error = building().build().unwrap_err();
+    let Some(StartViteError::BuildFailed { status, captured }) = error.downcast_ref() else {
+        panic!("expected a BuildFailed: {:?}", error);
+    };
+    assert_eq!(status.code(), Some(1));
+    assert_eq!(captured, &["error during build:"]);
+
+    ViteShim::new()
+        .pause(Duration::from_secs(5))
+        .builds()
+        .write(&project)
+        .unwrap();
+    let error = building()
+        .build_timeout(Duration::from_millis(200))
+        .build()
+        .unwrap_err();
+    let Some(StartViteError::BuildTimeout { timeout, .. }) = error.downcast_ref() else {
+        panic!("expected a BuildTimeout: {:?}", error);
+    };
+    assert_eq!(*timeout, Duration::from_millis(200));
+}
@@ -0,0 +1,134 @@
+//! How Vite is started in workspaces and through package managers and scripts.
+#![cfg(unix)]
+
+mod common;
+
+use common::Project;
+use std::ffi::OsString;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::test::ViteShim;
+
+// Writes an executable `name` into `bin`, and returns `bin` put in front of the `PATH`.
+fn on_path(bin: &Path, name: &str, script: &str) -> OsString {
+    std::fs::create_dir_all(bin).unwrap();
+    std::fs::write(bin.join(name), script).unwrap();
+    std::fs::set_permissions(bin.join(name), std::fs::Permissions::from_mode(0o755)).unwrap();
+    let path = std::env::var_os("PATH").unwrap();
+    std::env::join_paths(std::iter::once(bin.to_path_buf()).chain(std::env::split_paths(&path)))
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn yarn_pnp_installs_start_vite_through_yarn() {
+    let root = Project::new("pnp");
+    let project = root.join("monorepo");
+    let package = project.join("packages/web");
+    std::fs::create_dir_all(&package).unwrap();
+    std::fs::write(project.join(".pnp.cjs"), "").unwrap();
+    // A vite in the package's own node_modules is not the one of the install.
+    ViteShim::new().banner(5307).write(&package).unwrap();
+
+    let vite = ViteShim::new()
+        .banner(5306)
+        .write(root.join("yarn-cache"))
+        .unwrap();
+    let yarn = format!(
+        "#!/bin/sh\necho \"$@\" >> {}\n[ \"$1\" = vite ] && shift && exec {} \"$@\"\nexit 1\n",
+        root.join("yarn.log").display(),
+        vite.display()
+    );
+    let path = on_path(&root.join("bin"), "yarn", &yarn);
+    if !common::in_own_process(&[("PATH", path.as_os_str())]) {
+        return;
+    }
+
+    let options = ProxyViteOptions::new()
+        .working_directory(package.to_str().unwrap())
+        .disable_logging();
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5306));
+    vite.shutdown().unwrap();
+    let calls = std::fs::read_to_string(root.join("yarn.log")).unwrap();
+    assert!(
+        calls.lines().all(|call| call.starts_with("vite")),
+        "{}",
+        calls
+    );
+}
+
+#[actix_web::test]
+async fn pnpm_workspaces_start_the_vite_of_the_workspace() {
+    let root = Project::new("pnpm");
+    let package = root.join("packages/web");
+    std::fs::create_dir_all(&package).unwrap();
+    std::fs::write(
+        root.join("pnpm-workspace.yaml"),
+        "packages:\n  - packages/*\n",
+    )
+    .unwrap();
+    ViteShim::new().banner(5308).write(&root).unwrap();
+
+    let options = ProxyViteOptions::new()
+        .working_directory(package.to_str().unwrap())
+        .disable_logging();
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5308));
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn dev_scripts_are_run_with_the_forwarded_arguments() {
+    use vite_actix::package_script::{PackageManager, PackageScript};
+
+    let root = Project::new("script");
+    let project = root.join("project");
+    std::fs::create_dir_all(&project).unwrap();
+    let package_json = r#"{"scripts": {"dev": "vite --port 3000"}}"#;
+    std::fs::write(project.join("package.json"), package_json).unwrap();
+    ViteShim::new().banner(5313).write(&project).unwrap();
+    let (manager, script) = PackageScript::find(&project).unwrap();
+    assert_eq!(
+        (manager, script.name.as_str()),
+        (PackageManager::Npm, "dev")
+    );
+
+    // The `npm` logs its arguments and runs the script's vite with the forwarded ones.
+    let vite = ViteShim::new()
+        .banner(5314)
+        .write(root.join("script"))
+        .unwrap();
+    let npm = format!(
+        "#!/bin/sh\necho \"$@\" >> {}\nshift 3\nexec {} \"$@\"\n",
+        root.join("npm.log").display(),
+        vite.display()
+    );
+    let path = on_path(&root.join("bin"), "npm", &npm);
+    if !common::in_own_process(&[("PATH", path.as_os_str())]) {
+        return;
+    }
+
+    let options = ProxyViteOptions::new()
+        .working_directory(project.to_str().unwrap())
+        .port(5314)
+        .strict_port(true)
+        .disable_logging();
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5314));
+    vite.shutdown().unwrap();
+    vite.wait_exited().await;
+
+    // Without it, the project's own vite is started as before.
+    let options = options.clone().port(5313).package_script(false);
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5313));
+    vite.shutdown().unwrap();
+    // The script sets the port itself, so only `--strictPort` is forwarded.
+    let calls = std::fs::read_to_string(root.join("npm.log")).unwrap();
+    assert_eq!(calls, "run dev -- --strictPort\n");
+}
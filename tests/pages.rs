@@ -0,0 +1,480 @@
+//! The pages the crate serves or changes: transformed HTML, entries of multi-page apps,
+//! trailing slashes, and what is shown while Vite is starting, failing or down.
+
+mod common;
+
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::{App, HttpMessage, test};
+use serde_json::json;
+use std::sync::Arc;
+use vite_actix::dev_toolbar::TOOLBAR_MARKER;
+use vite_actix::html::{NONCE_PLACEHOLDER, csp_nonce, initial_state_transform};
+use vite_actix::test::{MockResponse, MockVite};
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+// A page navigation, as a browser asks for it.
+#[cfg(any(feature = "prod-serve", feature = "spawn"))]
+fn page(uri: &str) -> actix_http::Request {
+    test::TestRequest::get()
+        .uri(uri)
+        .insert_header(("accept", "text/html,*/*;q=0.8"))
+        .to_request()
+}
+
+#[cfg(feature = "prod-serve")]
+#[actix_web::test]
+async fn initial_state_is_added_to_proxied_and_built_pages() {
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+
+    let transform = || {
+        initial_state_transform(
+            "INITIAL_STATE",
+            |req| json!({ "user": req.headers().get("x-user").and_then(|user| user.to_str().ok()) }),
+        )
+    };
+    let get = async |app: &_| {
+        let req = test::TestRequest::get()
+            .uri("/")
+            .insert_header(("x-user", "</script>"))
+            .to_request();
+        String::from_utf8(test::call_and_read_body(app, req).await.to_vec()).unwrap()
+    };
+    let tag = "<script>window.__INITIAL_STATE__ = {\"user\":\"\\u003c/script\\u003e\"};</script>";
+
+    // Pages proxied from Vite in dev mode.
+    let vite = MockVite::start().await;
+    vite.respond(
+        "/",
+        MockResponse::ok()
+            .header((CONTENT_TYPE, "text/html"))
+            .body("<html><head><title>App</title></head><body></body></html>"),
+    );
+    let options = vite.options().html_transform(transform());
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    assert_eq!(
+        get(&app).await,
+        format!(
+            "<html><head><title>App</title>{}</head><body></body></html>",
+            tag
+        )
+    );
+
+    // The index.html of the build.
+    let options = ProxyViteOptions::new()
+        .dev_mode(false)
+        .dist_directory("examples/embedded_dist")
+        .html_transform(transform());
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    assert!(get(&app).await.contains(&format!("{}</head>", tag)));
+}
+
+#[actix_web::test]
+async fn csp_nonce_is_escaped_into_every_tag() {
+    // The nonce of a CSP middleware that runs first, hostile to prove it is escaped.
+    #[derive(Clone)]
+    struct Nonce(String);
+    let nonce = "n0nce\"><script>alert(1)</script>";
+    let escaped = "n0nce&quot;&gt;&lt;script&gt;alert(1)&lt;/script&gt;";
+
+    let vite = MockVite::start().await;
+    vite.respond(
+        "/",
+        MockResponse::ok()
+            .header((CONTENT_TYPE, "text/html"))
+            .body(format!(
+                "<html><head><script type=\"module\" src=\"/@vite/client\" nonce=\"{0}\"></script>\
+             </head><body><style nonce=\"{0}\"></style></body></html>",
+                NONCE_PLACEHOLDER
+            )),
+    );
+    let options = vite
+        .options()
+        .dev_toolbar(true)
+        .csp_nonce(Arc::new(|req| {
+            req.extensions().get::<Nonce>().map(|nonce| nonce.0.clone())
+        }))
+        .html_transform(initial_state_transform("STATE", |req| {
+            csp_nonce(req).is_some()
+        }));
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let req = test::TestRequest::get().uri("/").to_request();
+    req.extensions_mut().insert(Nonce(nonce.to_string()));
+    let page = String::from_utf8(test::call_and_read_body(&app, req).await.to_vec()).unwrap();
+
+    // Vite's tags, the initial state and the toolbar all have the nonce, escaped.
+    let nonce = format!("nonce=\"{}\"", escaped);
+    for tag in [
+        format!(
+            "<script type=\"module\" src=\"/@vite/client\" {}></script>",
+            nonce
+        ),
+        format!("<style {}></style>", nonce),
+        format!("<script {}>window.__STATE__ = true;</script>", nonce),
+        format!("<script {} {}>", TOOLBAR_MARKER, nonce),
+    ] {
+        assert!(page.contains(&tag), "no {} in {}", tag, page);
+    }
+    assert!(!page.contains(NONCE_PLACEHOLDER));
+    assert!(!page.contains("<script>alert"));
+}
+
+#[cfg(feature = "prod-serve")]
+#[actix_web::test]
+async fn navigations_get_the_entry_of_their_page() {
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+
+    let entries = [
+        ("/admin", "admin/index.html"),
+        ("/kiosk", "kiosk.html"),
+        ("/", "index.html"),
+    ];
+
+    // In dev mode, Vite is asked for the entry of the page.
+    let vite = MockVite::start().await;
+    let options = vite.options().html_entries(&entries);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let body = test::call_and_read_body(&app, page("/admin/users?sort=name")).await;
+    assert_eq!(body, "mock vite: GET /admin/index.html?sort=name");
+    assert_eq!(
+        test::call_and_read_body(&app, page("/kiosk")).await,
+        "mock vite: GET /kiosk.html"
+    );
+    assert_eq!(
+        test::call_and_read_body(&app, page("/about")).await,
+        "mock vite: GET /index.html"
+    );
+    // Modules are not pages.
+    let req = test::TestRequest::get().uri("/admin/main.ts").to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, req).await,
+        "mock vite: GET /admin/main.ts"
+    );
+
+    // The build falls back to the entry of the page.
+    let dist = common::Project::new("mpa");
+    std::fs::create_dir_all(dist.join("admin")).unwrap();
+    std::fs::write(dist.join("index.html"), "home").unwrap();
+    std::fs::write(dist.join("admin/index.html"), "admin").unwrap();
+    std::fs::write(dist.join("kiosk.html"), "kiosk").unwrap();
+    let options = ProxyViteOptions::new()
+        .dev_mode(false)
+        .dist_directory(dist.dir())
+        .html_entries(&entries);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    assert_eq!(
+        test::call_and_read_body(&app, page("/admin/users/42")).await,
+        "admin"
+    );
+    assert_eq!(
+        test::call_and_read_body(&app, page("/kiosk/screen")).await,
+        "kiosk"
+    );
+    assert_eq!(
+        test::call_and_read_body(&app, page("/settings")).await,
+        "home"
+    );
+}
+
+#[cfg(feature = "prod-serve")]
+#[actix_web::test]
+async fn pages_are_redirected_to_their_preferred_slash() {
+    use actix_web::http::StatusCode;
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+    use vite_actix::trailing_slash::TrailingSlash;
+
+    // Through the dev proxy, before anything reaches Vite.
+    let vite = MockVite::start().await;
+    let options = vite
+        .options()
+        .trailing_slash(TrailingSlash::RedirectToSlash);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let res = test::call_service(&app, page("/about?tab=team")).await;
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(res.headers().get("location").unwrap(), "/about/?tab=team");
+    assert_eq!(
+        test::call_and_read_body(&app, page("/about/")).await,
+        "mock vite: GET /about/"
+    );
+    // Modules are never redirected.
+    let req = test::TestRequest::get().uri("/src/pages").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(vite.requests().len(), 2);
+
+    // The build is served the same way.
+    let options = ProxyViteOptions::new()
+        .dev_mode(false)
+        .dist_directory("examples/embedded_dist")
+        .trailing_slash(TrailingSlash::RedirectToNoSlash);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let res = test::call_service(&app, page("/settings/")).await;
+    assert_eq!(res.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(res.headers().get("location").unwrap(), "/settings");
+    let res = test::call_service(&app, page("/settings")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = test::call_service(&app, page("/")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = test::TestRequest::get()
+        .uri("/assets/index-BxQw2a1z.js")
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn dev_toolbar_is_added_to_pages() {
+    use vite_actix::dev_toolbar::STATUS_PATH;
+
+    let vite = MockVite::start().await;
+    vite.respond(
+        "/",
+        MockResponse::ok()
+            .header((CONTENT_TYPE, "text/html"))
+            .body("<html><body><div id=\"app\"></div></body></html>"),
+    );
+    vite.respond(
+        "/src/main.ts",
+        MockResponse::ok()
+            .header((CONTENT_TYPE, "text/javascript"))
+            .body("document.body.innerHTML = \"</body>\";"),
+    );
+    let get = async |app: &_, path: &str| {
+        let req = test::TestRequest::get().uri(path).to_request();
+        String::from_utf8(test::call_and_read_body(app, req).await.to_vec()).unwrap()
+    };
+
+    let options = vite.options().dev_toolbar(true).enable_admin_routes(true);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let page = get(&app, "/").await;
+    let toolbar = "<html><body><div id=\"app\"></div><script data-vite-actix-toolbar>";
+    assert!(page.starts_with(toolbar), "{}", page);
+    assert!(page.ends_with("</script></body></html>"));
+    assert!(page.contains(TOOLBAR_MARKER));
+    // Only pages get it.
+    assert!(!get(&app, "/src/main.ts").await.contains(TOOLBAR_MARKER));
+
+    let req = test::TestRequest::get()
+        .uri(STATUS_PATH)
+        .peer_addr("127.0.0.1:50000".parse().unwrap())
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(
+        body,
+        json!({ "last_hmr_update": null, "restart": true, "port_mismatch": null })
+    );
+
+    // Without the option, the page is left alone.
+    let app = test::init_service(App::new().configure_vite_with(vite.options())).await;
+    assert!(!get(&app, "/").await.contains(TOOLBAR_MARKER));
+    assert_eq!(vite.requests().len(), 3);
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+#[actix_web::test]
+async fn starting_page_is_shown_until_vite_is_ready() {
+    use actix_web::http::StatusCode;
+    use std::time::Duration;
+    use vite_actix::health::HEALTH_PATH;
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+    use vite_actix::test::ViteShim;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = common::Project::new("starting");
+    // Vite takes a while, then reports the port of the stub.
+    let vite = MockVite::start().await;
+    ViteShim::new()
+        .pause(Duration::from_millis(500))
+        .banner(vite.port())
+        .write(&project)
+        .unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .starting_page(
+            "<html><body><h1 data-n=\"__VITE_ACTIX_NONCE__\">Warming up</h1></body></html>",
+        )
+        .csp_nonce(Arc::new(|_| Some("n0nce".to_string())))
+        .build()
+        .unwrap();
+    let app = test::init_service(App::new().configure_vite()).await;
+    let module = || test::TestRequest::get().uri("/src/main.ts").to_request();
+
+    let handle = vite_actix::start_vite_server_managed().unwrap();
+    let res = test::call_service(&app, page("/settings")).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        res.headers().get(CONTENT_TYPE).unwrap(),
+        "text/html; charset=utf-8"
+    );
+    let html = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    let heading = "<html><body><h1 data-n=\"n0nce\">Warming up</h1><script nonce=\"n0nce\">";
+    assert!(html.starts_with(heading), "{}", html);
+    assert!(html.contains("\"/__vite_actix/health\""));
+
+    let res = test::call_service(&app, module()).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(res.headers().get("retry-after").unwrap(), "1");
+    let req = test::TestRequest::get().uri(HEALTH_PATH).to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+    assert!(vite.requests().is_empty());
+
+    // Once Vite is ready, everything is proxied again.
+    handle.wait_ready().await.unwrap();
+    let req = test::TestRequest::get().uri(HEALTH_PATH).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(
+        test::call_and_read_body(&app, page("/settings")).await,
+        "mock vite: GET /settings"
+    );
+    assert_eq!(
+        test::call_and_read_body(&app, module()).await,
+        "mock vite: GET /src/main.ts"
+    );
+    handle.shutdown().unwrap();
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+#[actix_web::test]
+async fn compile_errors_are_parsed_and_shown_on_the_error_page() {
+    use std::time::Duration;
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+    use vite_actix::test::ViteShim;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    // The error blocks of Vite 5 and 6, as they print them to stderr.
+    let project = common::Project::new("compile");
+    let mut shim = ViteShim::new().banner(5304);
+    for line in [
+        "3:04:12 PM [vite] Internal server error: Transform failed with 1 error:",
+        "/app/src/main.ts:3:10: ERROR: Expected \";\" but found \"world\"",
+        "  Plugin: vite:esbuild",
+        "  File: /app/src/main.ts:3:10",
+        "  ",
+        "  Expected \";\" but found \"world\"",
+        "  1  |  import './style.css'",
+        "  2  |  ",
+        "  3  |  const hello world = 1",
+        "     |            ^",
+        "  4  |  ",
+        "      at failureErrorWithLog (/app/node_modules/esbuild/lib/main.js:1472:15)",
+        "      at /app/node_modules/esbuild/lib/main.js:755:50",
+        "3:05:01 PM [vite] (client) Pre-transform error: Failed to resolve import \"./missing\" \
+         from \"src/main.ts\". Does the file exist?",
+    ] {
+        shim = shim.stderr_line(line);
+    }
+    shim.exit(1).write(&project).unwrap();
+
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .disable_logging()
+        .build()
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    vite.wait_exited().await;
+    actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+
+    let errors = vite.recent_errors();
+    assert_eq!(errors.len(), 2);
+    let file = errors[0].file.as_deref().unwrap();
+    let (path, position) = file.split_once(':').unwrap();
+    assert_eq!((path, position), ("/app/src/main.ts", "3:10"));
+    assert_eq!(
+        errors[0].message,
+        "Transform failed with 1 error:\n\
+         /app/src/main.ts:3:10: ERROR: Expected \";\" but found \"world\""
+    );
+    assert!(
+        errors[0]
+            .frame
+            .as_ref()
+            .unwrap()
+            .contains("3  |  const hello world = 1")
+    );
+    assert_eq!(errors[1].file.as_deref(), Some("src/main.ts"));
+    assert!(
+        errors[1]
+            .message
+            .starts_with("Failed to resolve import \"./missing\"")
+    );
+    assert_eq!(errors[1].frame, None);
+
+    // The error page of the proxy shows the last one.
+    let app = test::init_service(App::new().configure_vite()).await;
+    let req = test::TestRequest::get().uri("/src/main.ts").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), 502);
+    let body = String::from_utf8(test::read_body(res).await.to_vec()).unwrap();
+    assert!(
+        body.ends_with(&format!("Last compile error:\n{}", errors[1])),
+        "{}",
+        body
+    );
+}
+
+#[cfg(all(unix, feature = "spawn", feature = "prod-serve"))]
+#[actix_web::test]
+async fn build_is_served_while_vite_is_down() {
+    use actix_web::http::StatusCode;
+    use vite_actix::proxy_vite_options::ProxyViteOptions;
+    use vite_actix::supervisor::RestartPolicy;
+    use vite_actix::test::ViteShim;
+    use vite_actix::vite_state::ViteState;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = common::Project::new("fallback");
+    let dist = std::fs::canonicalize("examples/embedded_dist").unwrap();
+    // A Vite that crashes right away.
+    ViteShim::new().exit(1).write(&project).unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .dist_directory(dist.to_str().unwrap())
+        .restart_on_crash(RestartPolicy {
+            max_failures: 1,
+            ..RestartPolicy::default()
+        })
+        .fallback_to_dist(true)
+        .build()
+        .unwrap();
+    let app = test::init_service(App::new().configure_vite()).await;
+    let settings = || {
+        test::TestRequest::get()
+            .uri("/settings")
+            .insert_header(("accept", "text/html"))
+            .to_request()
+    };
+
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    let failed = || matches!(ViteState::current(), ViteState::Failed { .. });
+    common::until("Vite failed", failed).await;
+    let index = std::fs::read(dist.join("index.html")).unwrap();
+    assert_eq!(test::call_and_read_body(&app, settings()).await, index);
+    let req = test::TestRequest::get().uri("/robots.txt").to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let req = test::TestRequest::post().uri("/settings").to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    // Vite is fixed and restarted.
+    let mock = MockVite::start().await;
+    ViteShim::new().banner(mock.port()).write(&project).unwrap();
+    assert!(vite.supervisor().resume());
+    common::until("Vite is ready", || ViteState::current().is_ready()).await;
+    assert_eq!(
+        test::call_and_read_body(&app, settings()).await,
+        "mock vite: GET /settings"
+    );
+    vite.shutdown().unwrap();
+}
@@ -0,0 +1,705 @@
+//! What the proxy forwards to Vite, how, and what it keeps from it, against
+//! `vite_actix::test::MockVite`.
+
+mod common;
+
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::http::header::{CONTENT_TYPE, LOCATION};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{App, guard, test, web};
+use std::sync::Arc;
+use vite_actix::proxy;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::request_id::REQUEST_ID_HEADER;
+use vite_actix::test::{MockResponse, MockVite};
+use vite_actix::vite_app_factory::ViteAppFactory;
+
+#[actix_web::test]
+async fn handler_only_gets_what_its_route_lets_through() {
+    let vite = MockVite::start().await;
+    let app = test::init_service(
+        App::new().service(
+            web::scope("/frontend")
+                .guard(guard::Header("authorization", "Bearer secret"))
+                .route(
+                    "/{tail:.*}",
+                    web::route().to(proxy::handler(vite.options())),
+                ),
+        ),
+    )
+    .await;
+
+    let req = test::TestRequest::get()
+        .uri("/frontend/src/main.ts?v=1")
+        .insert_header(("authorization", "Bearer secret"))
+        .to_request();
+    let body = test::call_and_read_body(&app, req).await;
+    assert_eq!(body, "mock vite: GET /frontend/src/main.ts?v=1");
+
+    // Requests the guard turns away, or outside of the route, never reach Vite.
+    let req = test::TestRequest::get()
+        .uri("/frontend/src/main.ts")
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    let req = test::TestRequest::get().uri("/src/main.ts").to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::NOT_FOUND
+    );
+    assert_eq!(vite.requests().len(), 1);
+}
+
+#[actix_web::test]
+async fn large_bodies_arrive_whole_read_first_or_streamed() {
+    let vite = MockVite::start().await;
+    let module: Vec<u8> = (0..3_000_000).map(|i| (i % 251) as u8).collect();
+    let js = MockResponse::ok().header((CONTENT_TYPE, "text/javascript"));
+    vite.respond("/big.js", js.body(module.clone()));
+    let html = MockResponse::ok().header((CONTENT_TYPE, "text/html"));
+    vite.respond("/", html.body("<html></html>"));
+
+    for stream in [false, true] {
+        let options = vite
+            .options()
+            .stream_responses(stream)
+            .html_transform(Arc::new(|_, html| {
+                html.replace("<html>", "<html lang=\"en\">")
+            }));
+        let app = test::init_service(App::new().configure_vite_with(options)).await;
+
+        let req = test::TestRequest::get().uri("/big.js").to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.response().body().size(), BodySize::Sized(3_000_000));
+        assert_eq!(test::read_body(res).await, module);
+
+        // Pages are read whole for the html_transform either way.
+        let req = test::TestRequest::get().uri("/").to_request();
+        assert_eq!(
+            test::call_and_read_body(&app, req).await,
+            "<html lang=\"en\"></html>"
+        );
+
+        let req = test::TestRequest::post()
+            .uri("/upload")
+            .set_payload(module.clone())
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+    let uploads: Vec<_> = vite
+        .requests()
+        .into_iter()
+        .filter(|req| req.uri == "/upload")
+        .collect();
+    assert_eq!(uploads.len(), 2);
+    assert!(uploads.iter().all(|req| req.body == module));
+}
+
+#[actix_web::test]
+async fn node_modules_are_hidden_unless_exposed() {
+    let vite = MockVite::start().await;
+    let module = "/node_modules/vue/dist/vue.runtime.esm-bundler.js";
+    let dep = "/node_modules/.vite/deps/vue.js?v=4f2b7d1a";
+    let source = "/src/main.ts";
+
+    // Exposed, hidden with the dependencies let through, and hidden entirely.
+    for (node_modules, vite_deps, proxied) in [
+        (true, false, [true, true, true]),
+        (false, true, [false, true, true]),
+        (false, false, [false, false, true]),
+    ] {
+        let options = vite
+            .options()
+            .expose_node_modules(node_modules)
+            .expose_vite_deps(vite_deps);
+        let app = test::init_service(App::new().configure_vite_with(options)).await;
+        for (path, proxied) in [module, dep, source].into_iter().zip(proxied) {
+            let req = test::TestRequest::get().uri(path).to_request();
+            let status = test::call_service(&app, req).await.status();
+            assert_eq!(status == StatusCode::OK, proxied, "{}", path);
+        }
+    }
+
+    // Other spellings of the same path are hidden too.
+    let options = vite.options().expose_node_modules(false);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let before = vite.requests().len();
+    for path in [
+        "/node_modules",
+        "//node_modules/vue/package.json",
+        "/Node_Modules/vue/package.json",
+        "/%6Eode_modules/vue/package.json",
+        "/src/../node_modules/vue/package.json",
+        "/node_modules/.vite/deps/../../vue/package.json",
+    ] {
+        let req = test::TestRequest::get().uri(path).to_request();
+        let status = test::call_service(&app, req).await.status();
+        assert_eq!(status, StatusCode::NOT_FOUND, "{}", path);
+    }
+    assert_eq!(vite.requests().len(), before);
+}
+
+#[actix_web::test]
+async fn hop_header_stops_proxy_loops() {
+    let vite = MockVite::start().await;
+    vite.respond(
+        "/echo",
+        MockResponse::ok().header(("x-proxy-hops", "echoed")),
+    );
+    let options = vite.options().hop_header("X-Proxy-Hops").max_hops(3);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let get = |uri: &str, hops: &str| {
+        test::TestRequest::get()
+            .uri(uri)
+            .insert_header(("x-proxy-hops", hops))
+            .to_request()
+    };
+
+    // From two other instances, the request is forwarded with the token of this one.
+    let res = test::call_service(&app, get("/src/main.ts", "a1, b2")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let stamped = vite.requests()[0]
+        .header("x-proxy-hops")
+        .unwrap()
+        .to_string();
+    let tokens: Vec<&str> = stamped.split(", ").collect();
+    assert_eq!(tokens[..2], ["a1", "b2"]);
+    assert_eq!(tokens.len(), 3);
+
+    // A third instance before it makes one too many.
+    let res = test::call_service(&app, get("/src/main.ts", "a1, b2, c3")).await;
+    assert_eq!(res.status(), StatusCode::LOOP_DETECTED);
+
+    // A request with the token of this instance came back to it.
+    let res = test::call_service(&app, get("/src/main.ts", tokens[2])).await;
+    assert_eq!(res.status(), StatusCode::LOOP_DETECTED);
+    assert_eq!(vite.requests().len(), 1);
+
+    // Vite echoing the header does not hand it to the client.
+    let res = test::call_service(&app, get("/echo", "a1")).await;
+    assert!(res.headers().get("x-proxy-hops").is_none());
+}
+
+#[actix_web::test]
+async fn vite_on_the_port_of_the_actix_server_is_a_loop() {
+    use actix_web::HttpServer;
+    use vite_actix::error::ConfigProblem;
+
+    let looping = ProxyViteOptions::new()
+        .dev_mode(true)
+        .port(8080)
+        .actix_port(8080);
+    let error = looping.clone().build().unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("Vite's port 8080 is also the port of the Actix server")
+    );
+    let problems = App::new()
+        .try_configure_vite_with(looping)
+        .err()
+        .unwrap()
+        .problems;
+    assert_eq!(problems, vec![ConfigProblem::ProxyLoop(8080)]);
+
+    // Without the Actix port, the loop is only found at runtime. The server proxies to itself
+    // through the global options.
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let server = HttpServer::new(|| {
+        let options = ProxyViteOptions::global().dev_mode(true);
+        App::new().configure_vite_with(options)
+    })
+    .workers(1)
+    .bind("127.0.0.1:0")
+    .unwrap();
+    let port = server.addrs()[0].port();
+    ProxyViteOptions::new().port(port).build().unwrap();
+    let server = server.run();
+    let handle = server.handle();
+    actix_web::rt::spawn(server);
+
+    let res = awc::Client::new()
+        .get(format!("http://127.0.0.1:{}/src/main.ts", port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::LOOP_DETECTED);
+    handle.stop(false).await;
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+#[actix_web::test]
+async fn own_options_go_by_their_port_not_the_managed_vite() {
+    use serde_json::json;
+    use std::time::Duration;
+    use vite_actix::health::HEALTH_PATH;
+    use vite_actix::test::ViteShim;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = common::Project::new("target");
+    // The Vite of the global options takes its time to start.
+    ViteShim::new()
+        .pause(Duration::from_secs(5))
+        .banner(5197)
+        .write(&project)
+        .unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .enable_health_route(true)
+        .build()
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    let global = test::init_service(App::new().configure_vite()).await;
+    // Another server of the same project, which is up.
+    let mock = MockVite::start().await;
+    let options = mock
+        .options()
+        .working_directory(project.dir())
+        .enable_health_route(true);
+    let own = test::init_service(App::new().configure_vite_with(options)).await;
+
+    let get = || test::TestRequest::get().uri("/src/main.ts").to_request();
+    let body = test::call_and_read_body(&global, get()).await;
+    assert_eq!(body, "Vite is starting, retry shortly");
+    let body = test::call_and_read_body(&own, get()).await;
+    assert_eq!(body, "mock vite: GET /src/main.ts");
+
+    let health = || test::TestRequest::get().uri(HEALTH_PATH).to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&global, health()).await;
+    assert_eq!(body, json!({ "vite": "starting" }));
+    let body: serde_json::Value = test::call_and_read_body_json(&own, health()).await;
+    assert_eq!(
+        body,
+        json!({ "vite": "ready", "port": mock.port(), "pid": null })
+    );
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn excluded_prefixes_are_a_404() {
+    let vite = MockVite::start().await;
+    let options = vite.options().exclude_prefix("/api");
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    for uri in ["/api", "/api/users", "/api/users?page=2"] {
+        assert_eq!(
+            test::call_service(&app, get(uri)).await.status(),
+            404,
+            "{}",
+            uri
+        );
+    }
+    // Only whole segments are a prefix.
+    assert_eq!(
+        test::call_and_read_body(&app, get("/apiary")).await,
+        "mock vite: GET /apiary"
+    );
+    let paths: Vec<_> = vite.requests().iter().map(|r| r.uri.to_string()).collect();
+    assert_eq!(paths, ["/apiary"]);
+}
+
+#[actix_web::test]
+async fn only_files_of_the_proxied_extensions_reach_vite() {
+    let vite = MockVite::start().await;
+    let options = vite.options().proxy_extensions(&["ts", ".CSS"]);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    let proxied = [
+        "/src/main.ts",
+        "/src/App.vue.ts?t=1",
+        "/style.css",
+        "/@vite/client",
+        "/about",
+    ];
+    for uri in proxied {
+        assert_eq!(
+            test::call_service(&app, get(uri)).await.status(),
+            200,
+            "{}",
+            uri
+        );
+    }
+    for uri in ["/wp-login.php", "/.env", "/backup.tar.gz", "/src/main.tsx"] {
+        assert_eq!(
+            test::call_service(&app, get(uri)).await.status(),
+            404,
+            "{}",
+            uri
+        );
+    }
+    let paths: Vec<_> = vite.requests().iter().map(|r| r.uri.to_string()).collect();
+    assert_eq!(paths, proxied);
+}
+
+#[actix_web::test]
+async fn well_known_files_answered_with_the_index_are_a_404() {
+    let vite = MockVite::start().await;
+    let index = || {
+        MockResponse::ok()
+            .header((CONTENT_TYPE, "text/html; charset=utf-8"))
+            .body("<!doctype html><div id=\"app\"></div>")
+    };
+    vite.respond("/favicon.ico", index());
+    vite.respond("/robots.txt", index());
+    vite.respond(
+        "/sitemap.xml",
+        MockResponse::ok().header((CONTENT_TYPE, "application/xml")),
+    );
+    vite.respond("/about", index());
+    let options = vite.options().well_known_path("/humans.txt");
+    vite.respond("/humans.txt", index());
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    for uri in ["/favicon.ico", "/robots.txt", "/humans.txt"] {
+        let res = test::call_service(&app, get(uri)).await;
+        assert_eq!(res.status(), 404, "{}", uri);
+        assert!(test::read_body(res).await.is_empty(), "{}", uri);
+    }
+    assert_eq!(
+        test::call_service(&app, get("/sitemap.xml")).await.status(),
+        200
+    );
+    // Pages are HTML anyway.
+    let body = test::call_and_read_body(&app, get("/about")).await;
+    assert_eq!(body, "<!doctype html><div id=\"app\"></div>");
+}
+
+#[actix_web::test]
+async fn pages_to_change_are_asked_for_uncompressed() {
+    let vite = MockVite::start().await;
+    let options = vite.options().dev_toolbar(true);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    for path in ["/", "/src/main.ts"] {
+        let req = test::TestRequest::get()
+            .uri(path)
+            .insert_header(("accept", "text/html,*/*;q=0.8"))
+            .insert_header(("accept-encoding", "gzip, br"))
+            .to_request();
+        test::call_service(&app, req).await;
+    }
+    let requests = vite.requests();
+    assert_eq!(requests[0].header("accept-encoding"), Some("identity"));
+    assert_eq!(requests[1].header("accept-encoding"), Some("gzip, br"));
+}
+
+#[actix_web::test]
+async fn dev_server_pages_are_not_stored() {
+    use vite_actix::vite_command::ViteCommand;
+
+    let vite = MockVite::start().await;
+    let cached = |content_type: &str| {
+        MockResponse::ok()
+            .header((CONTENT_TYPE, content_type))
+            .header(("cache-control", "max-age=60"))
+    };
+    vite.respond("/", cached("text/html; charset=utf-8"));
+    vite.respond("/src/main.ts", cached("text/javascript"));
+    let cache_control = async |command: ViteCommand, uri: &str| {
+        let options = vite.options().command(command);
+        let app = test::init_service(App::new().configure_vite_with(options)).await;
+        let req = test::TestRequest::get().uri(uri).to_request();
+        let res = test::call_service(&app, req).await;
+        res.headers()
+            .get("cache-control")
+            .map(|value| value.to_str().unwrap().to_string())
+    };
+
+    // The dev server's pages are asked for again after every edit, its modules are not.
+    let dev = ViteCommand::Dev;
+    assert_eq!(cache_control(dev, "/").await.as_deref(), Some("no-store"));
+    let module = cache_control(dev, "/src/main.ts").await;
+    assert_eq!(module.as_deref(), Some("max-age=60"));
+    // The build `vite preview` serves keeps its caching.
+    let preview = ViteCommand::Preview;
+    let page = cache_control(preview, "/").await;
+    assert_eq!(page.as_deref(), Some("max-age=60"));
+}
+
+#[actix_web::test]
+async fn protocol_upgrades_are_not_forwarded() {
+    let vite = MockVite::start().await;
+    let app = test::init_service(App::new().configure_vite_with(vite.options())).await;
+    let req = test::TestRequest::get()
+        .uri("/src/main.ts")
+        .insert_header(("connection", "Upgrade, HTTP2-Settings, Keep-Alive"))
+        .insert_header(("upgrade", "h2c"))
+        .insert_header(("http2-settings", "AAMAAABkAAQAoAAAAAIAAAAA"))
+        .insert_header(("keep-alive", "timeout=5"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+
+    let forwarded = &vite.requests()[0];
+    for header in ["upgrade", "http2-settings", "keep-alive"] {
+        assert_eq!(forwarded.header(header), None, "{}", header);
+    }
+    assert_ne!(
+        forwarded.header("connection"),
+        Some("Upgrade, HTTP2-Settings, Keep-Alive")
+    );
+}
+
+#[actix_web::test]
+async fn clients_past_the_rate_limit_get_a_429() {
+    let vite = MockVite::start().await;
+    let options = vite.options().rate_limit(1, 2);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let get = || test::TestRequest::get().uri("/src/main.ts").to_request();
+
+    assert_eq!(test::call_service(&app, get()).await.status(), 200);
+    assert_eq!(test::call_service(&app, get()).await.status(), 200);
+    let res = test::call_service(&app, get()).await;
+    assert_eq!(res.status(), 429);
+    assert_eq!(res.headers().get("retry-after").unwrap(), "1");
+    assert_eq!(vite.requests().len(), 2);
+}
+
+#[actix_web::test]
+async fn request_ids_are_passed_along_and_logged() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    common::capture_logs();
+    let logged = |id: &str| -> Vec<String> {
+        let id = format!("[{}]", id);
+        common::logged()
+            .into_iter()
+            .filter(|logged| logged.message.contains(&id))
+            .map(|logged| format!("{} {}", logged.level, logged.message))
+            .collect()
+    };
+    let vite = MockVite::start().await;
+    let options = vite.options().log_proxy_spans(true);
+    let app = test::init_service(App::new().configure_vite_with(options.clone())).await;
+
+    // An ID the request came with is passed on to Vite and back.
+    let req = test::TestRequest::get()
+        .uri("/src/main.ts")
+        .insert_header((REQUEST_ID_HEADER, "lb-7f3a"))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "lb-7f3a");
+    assert_eq!(
+        vite.requests()[0].header(REQUEST_ID_HEADER),
+        Some("lb-7f3a")
+    );
+    let lines = logged("lb-7f3a");
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("TRACE [lb-7f3a] proxy start GET /src/main.ts"));
+    assert!(lines[1].starts_with("TRACE [lb-7f3a] proxy end GET /src/main.ts: 200 OK"));
+
+    // Otherwise the proxy makes one up.
+    let req = test::TestRequest::get().uri("/src/App.tsx").to_request();
+    let res = test::call_service(&app, req).await;
+    let id = res
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert_eq!(
+        vite.requests()[1].header(REQUEST_ID_HEADER),
+        Some(id.as_str())
+    );
+    assert_eq!(logged(&id).len(), 2);
+
+    // Errors are logged with it too.
+    let app = test::init_service(App::new().configure_vite_with(options.port(1))).await;
+    let req = test::TestRequest::get()
+        .uri("/src/main.ts")
+        .insert_header((REQUEST_ID_HEADER, "lb-7f3b"))
+        .to_request();
+    assert!(
+        test::call_service(&app, req)
+            .await
+            .status()
+            .is_server_error()
+    );
+    let lines = logged("lb-7f3b");
+    let failed = "WARN [lb-7f3b] Failed to forward /src/main.ts";
+    assert!(
+        lines.iter().any(|line| line.starts_with(failed)),
+        "{:?}",
+        lines
+    );
+}
+
+#[actix_web::test]
+async fn scoped_proxies_forward_the_path_without_the_scope() {
+    let vite = MockVite::start().await;
+    vite.respond(
+        "/old",
+        MockResponse::new(StatusCode::FOUND).header((LOCATION, "/new")),
+    );
+    let nested = web::scope("/ui").configure_vite_with(vite.options());
+    let app = App::new()
+        .service(web::scope("/app").configure_vite_with(vite.options()))
+        .service(web::scope("/tenant/{id}").service(nested));
+    let app = test::init_service(app).await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+
+    let body = test::call_and_read_body(&app, get("/app/src/main.ts?v=1")).await;
+    assert_eq!(body, "mock vite: GET /src/main.ts?v=1");
+    test::call_service(&app, get("/tenant/42/ui/@vite/client")).await;
+    let requests = vite.requests();
+    let seen: Vec<_> = requests.iter().map(|r| r.uri.to_string()).collect();
+    assert_eq!(seen, ["/src/main.ts?v=1", "/@vite/client"]);
+    assert_eq!(requests[0].header("x-forwarded-prefix"), Some("/app"));
+    assert_eq!(
+        requests[1].header("x-forwarded-prefix"),
+        Some("/tenant/42/ui")
+    );
+
+    // A redirect of Vite stays inside the scope.
+    let res = test::call_service(&app, get("/app/old")).await;
+    assert_eq!(res.status(), StatusCode::FOUND);
+    assert_eq!(res.headers().get(LOCATION).unwrap(), "/app/new");
+}
+
+#[actix_web::test]
+async fn requests_of_every_method_are_proxied_with_their_body() {
+    let vite = MockVite::start().await;
+    let options = vite.options().proxy_routes(&["/api/{rest:.*}"]);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    for uri in ["/api/users", "/mock/users.json"] {
+        for method in [Method::POST, Method::PUT, Method::DELETE] {
+            let req = test::TestRequest::default()
+                .method(method.clone())
+                .uri(uri)
+                .set_payload(format!("{} {}", method, uri))
+                .to_request();
+            assert_eq!(test::call_service(&app, req).await.status(), 200);
+        }
+    }
+    let requests = vite.requests();
+    assert_eq!(requests.len(), 6);
+    for request in &requests {
+        let body = format!("{} {}", request.method, request.uri);
+        assert_eq!(request.body, body.as_bytes());
+    }
+}
+
+#[cfg(feature = "spawn")]
+#[actix_web::test]
+async fn failed_requests_are_reported_as_events() {
+    use vite_actix::events::ViteEvent;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    // Nothing listens on port 1.
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .port(1)
+        .build()
+        .unwrap();
+    let mut events = vite_actix::events::subscribe();
+    let app = test::init_service(App::new().configure_vite()).await;
+    let req = test::TestRequest::get().uri("/src/main.ts").to_request();
+    assert!(
+        test::call_service(&app, req)
+            .await
+            .status()
+            .is_server_error()
+    );
+
+    let ViteEvent::ProxyError(error) = events.try_recv().unwrap() else {
+        panic!("not a proxy error");
+    };
+    assert_eq!(
+        (error.method.as_str(), error.path.as_str()),
+        ("GET", "/src/main.ts")
+    );
+    assert!(!error.request_id.is_empty());
+    assert!(!error.message.is_empty());
+}
+
+#[cfg(feature = "otel")]
+#[actix_web::test]
+async fn forwarded_requests_are_traced_as_client_spans() {
+    use opentelemetry::trace::{SpanKind, Status, TracerProvider};
+    use opentelemetry::{KeyValue, global};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+    use tracing::Instrument;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let exporter = InMemorySpanExporter::default();
+    let provider = SdkTracerProvider::builder()
+        .with_simple_exporter(exporter.clone())
+        .build();
+    let subscriber = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("app")));
+    let _subscriber = tracing::subscriber::set_default(subscriber);
+
+    let vite = MockVite::start().await;
+    vite.respond("/missing.js", MockResponse::new(StatusCode::NOT_FOUND));
+    let app = test::init_service(App::new().configure_vite_with(vite.options())).await;
+    // The span of the incoming request, as tracing-actix-web would open it.
+    let request_span = tracing::info_span!("HTTP request", otel.kind = "server");
+    let req = test::TestRequest::get()
+        .uri("/src/main.ts?v=1")
+        .to_request();
+    test::call_service(&app, req).instrument(request_span).await;
+
+    let spans = exporter.get_finished_spans().unwrap();
+    let server = spans
+        .iter()
+        .find(|span| span.span_kind == SpanKind::Server)
+        .unwrap();
+    let client = spans
+        .iter()
+        .find(|span| span.span_kind == SpanKind::Client)
+        .unwrap();
+    assert_eq!(client.name, "GET");
+    assert_eq!(client.parent_span_id, server.span_context.span_id());
+    assert_eq!(
+        client.span_context.trace_id(),
+        server.span_context.trace_id()
+    );
+    let url = format!("http://localhost:{}/src/main.ts?v=1", vite.port());
+    for attribute in [
+        KeyValue::new("http.request.method", "GET"),
+        KeyValue::new("server.address", "localhost"),
+        KeyValue::new("server.port", vite.port() as i64),
+        KeyValue::new("url.full", url),
+        KeyValue::new("http.response.status_code", 200),
+    ] {
+        assert!(client.attributes.contains(&attribute), "{:?}", attribute);
+    }
+    assert_eq!(client.status, Status::Unset);
+
+    // Vite is told about the client span, to continue the trace.
+    let traceparent = format!(
+        "00-{}-{}-01",
+        client.span_context.trace_id(),
+        client.span_context.span_id()
+    );
+    assert_eq!(
+        vite.requests()[0].header("traceparent"),
+        Some(traceparent.as_str())
+    );
+
+    // A 404 from Vite is an error of the client span.
+    let req = test::TestRequest::get().uri("/missing.js").to_request();
+    test::call_service(&app, req).await;
+    let spans = exporter.get_finished_spans().unwrap();
+    let client = spans.last().unwrap();
+    assert!(
+        client
+            .attributes
+            .contains(&KeyValue::new("error.type", "404"))
+    );
+    assert!(matches!(client.status, Status::Error { .. }));
+}
@@ -0,0 +1,55 @@
+//! The proxy and HMR against a real Vite, with `vite_actix::test::RealVite`.
+//!
+//! They install Vite from the npm registry, so they are ignored by default. Run them with
+//! `cargo test --features integration-tests --test real_vite -- --ignored`; without Node.js
+//! or npm they pass without doing anything.
+
+use vite_actix::test::{MAIN_JS, RealVite};
+use vite_actix::vite_state::ViteState;
+
+#[actix_web::test]
+#[ignore = "installs and starts a real Vite"]
+async fn proxies_pages_and_modules() {
+    let Some(vite) = RealVite::start().await.unwrap() else {
+        return;
+    };
+    // The banner was read and the readiness flow ran.
+    assert!(vite.handle().vite_version().is_some());
+    assert_eq!(vite.handle().port(), Some(vite.port()));
+    assert!(matches!(
+        vite.handle().state(),
+        ViteState::Ready | ViteState::FullyReady
+    ));
+
+    let (status, page) = vite.fetch("/").await.unwrap();
+    assert_eq!(status, 200);
+    assert!(page.contains("/@vite/client"), "{}", page);
+    assert!(page.contains("/src/main.js"), "{}", page);
+
+    let (status, module) = vite.fetch("/src/main.js").await.unwrap();
+    assert_eq!(status, 200);
+    assert!(module.contains("hello from vite"), "{}", module);
+    assert!(MAIN_JS.contains("hello from vite"));
+
+    // Client-side routes get the page too.
+    let (status, page) = vite.fetch("/settings/profile").await.unwrap();
+    assert_eq!(status, 200);
+    assert!(page.contains("/src/main.js"), "{}", page);
+}
+
+#[actix_web::test]
+#[ignore = "installs and starts a real Vite"]
+async fn relays_hmr_updates() {
+    let Some(vite) = RealVite::start().await.unwrap() else {
+        return;
+    };
+    let edited = "document.querySelector('#app').textContent = 'edited';
+if (import.meta.hot) import.meta.hot.accept();
+";
+    let message = vite.edit_module(edited).await.unwrap();
+    assert!(message.contains(r#""type":"update""#), "{}", message);
+    assert!(message.contains("/src/main.js"), "{}", message);
+
+    let (_, module) = vite.fetch("/src/main.js").await.unwrap();
+    assert!(module.contains("edited"), "{}", module);
+}
@@ -0,0 +1,341 @@
+//! The routes the crate answers itself: the health route, the admin routes and Vite's pings.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{App, test};
+use serde_json::json;
+use std::time::Duration;
+use vite_actix::health::HEALTH_PATH;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::request_id::REQUEST_ID_HEADER;
+use vite_actix::test::MockVite;
+use vite_actix::vite_app_factory::ViteAppFactory;
+use vite_actix::vite_ping::PING_PATH;
+
+#[actix_web::test]
+async fn health_route_reports_a_vite_started_elsewhere() {
+    let vite = MockVite::start().await;
+    let options = vite.options().enable_health_route(true);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let req = test::TestRequest::get().uri(HEALTH_PATH).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(
+        body,
+        json!({ "vite": "ready", "port": vite.port(), "pid": null })
+    );
+    assert!(vite.requests().is_empty());
+
+    // Or not.
+    let options = ProxyViteOptions::new()
+        .dev_mode(true)
+        .port(1)
+        .enable_health_route(true);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let req = test::TestRequest::get().uri(HEALTH_PATH).to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body, json!({ "vite": "idle" }));
+}
+
+#[cfg(feature = "prod-serve")]
+#[actix_web::test]
+async fn health_route_reports_whether_the_build_is_servable() {
+    for (dist, status) in [
+        ("examples/embedded_dist", StatusCode::OK),
+        ("examples/no_such_dist", StatusCode::SERVICE_UNAVAILABLE),
+    ] {
+        let options = ProxyViteOptions::new()
+            .dev_mode(false)
+            .dist_directory(dist)
+            .enable_health_route(true);
+        let app = test::init_service(App::new().configure_vite_with(options)).await;
+        let req = test::TestRequest::get().uri(HEALTH_PATH).to_request();
+        let res = test::call_service(&app, req).await;
+        assert_eq!(res.status(), status);
+        let body: serde_json::Value = test::read_body_json(res).await;
+        assert_eq!(body["mode"], "static");
+    }
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+#[actix_web::test]
+async fn health_route_reports_the_managed_vite() {
+    use vite_actix::test::ViteShim;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = common::Project::new("health");
+    ViteShim::new()
+        .pause(Duration::from_millis(500))
+        .banner(5199)
+        .write(&project)
+        .unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .enable_health_route(true)
+        .build()
+        .unwrap();
+    let app = test::init_service(App::new().configure_vite()).await;
+    let health = async || {
+        let req = test::TestRequest::get().uri(HEALTH_PATH).to_request();
+        let res = test::call_service(&app, req).await;
+        let status = res.status();
+        (
+            status,
+            test::read_body_json::<serde_json::Value, _>(res).await,
+        )
+    };
+
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    let (status, body) = health().await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body, json!({ "vite": "starting" }));
+
+    vite.wait_ready().await.unwrap();
+    let (status, body) = health().await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body,
+        json!({ "vite": "ready", "port": 5199, "pid": vite.pid() })
+    );
+    vite.shutdown().unwrap();
+    vite.wait_exited().await;
+
+    // A Vite that exits after an error.
+    ViteShim::new()
+        .stderr_line("12:00:00 [vite] Internal server error: Unexpected token")
+        .stderr_line("  File: /app/src/main.ts:3:10")
+        .exit(1)
+        .write(&project)
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    vite.wait_ready().await.unwrap_err();
+    common::until("the error is read", || !vite.recent_errors().is_empty()).await;
+
+    let (status, body) = health().await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(
+        body,
+        json!({
+            "vite": "exited",
+            "exit_code": 1,
+            "last_error": "/app/src/main.ts:3:10: Unexpected token",
+        })
+    );
+}
+
+#[actix_web::test]
+async fn admin_routes_answer_this_machine_with_the_secret() {
+    use vite_actix::admin_routes::SECRET_HEADER;
+
+    let options = ProxyViteOptions::new()
+        .port(5173)
+        .dev_mode(true)
+        .enable_admin_routes(true)
+        .admin_secret("s3cret");
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let restart = |peer: &str| {
+        test::TestRequest::post()
+            .uri("/__vite_actix/restart")
+            .peer_addr(peer.parse().unwrap())
+    };
+
+    let res = test::call_service(&app, restart("192.168.1.20:50000").to_request()).await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = test::call_service(&app, restart("127.0.0.1:50000").to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let body: serde_json::Value = test::read_body_json(res).await;
+    assert_eq!(body["error"], "Missing or wrong X-Vite-Actix-Secret header");
+
+    let req = restart("[::1]:50000")
+        .insert_header((SECRET_HEADER, "guess"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::UNAUTHORIZED
+    );
+
+    // With the secret, only POST restarts.
+    let req = test::TestRequest::get()
+        .uri("/__vite_actix/restart")
+        .peer_addr("127.0.0.1:50000".parse().unwrap())
+        .insert_header((SECRET_HEADER, "s3cret"))
+        .to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::METHOD_NOT_ALLOWED
+    );
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+#[actix_web::test]
+async fn admin_route_restarts_the_managed_vite() {
+    use vite_actix::test::ViteShim;
+    use vite_actix::vite_state::ViteState;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = common::Project::new("admin");
+    ViteShim::new().banner(5199).write(&project).unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .enable_admin_routes(true)
+        .build()
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    common::until("Vite is ready", || ViteState::current().is_ready()).await;
+    let old_pid = vite.pid();
+
+    let app = test::init_service(App::new().configure_vite()).await;
+    let req = test::TestRequest::post()
+        .uri("/__vite_actix/restart")
+        .peer_addr("127.0.0.1:50000".parse().unwrap())
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["old_pid"], old_pid);
+    assert_eq!(body["new_pid"], vite.pid());
+    assert_ne!(vite.pid(), old_pid);
+    assert!(body["duration_ms"].is_u64());
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn faults_are_injected_and_changed_at_runtime() {
+    use std::time::Instant;
+
+    let vite = MockVite::start().await;
+    let options = vite
+        .options()
+        .enable_admin_routes(true)
+        .inject_latency(Duration::from_millis(50)..Duration::from_millis(80))
+        .inject_errors(1.0, 502)
+        .inject_faults_under("/api");
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+
+    let started = Instant::now();
+    let req = test::TestRequest::get().uri("/api/users").to_request();
+    assert_eq!(
+        test::call_service(&app, req).await.status(),
+        StatusCode::BAD_GATEWAY
+    );
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    let req = test::TestRequest::get().uri("/src/main.ts").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(vite.requests().len(), 1);
+
+    // Turn the errors off, keeping a fixed latency.
+    let faults = json!({ "min_latency_ms": 10, "max_latency_ms": 10, "paths": ["/api"] });
+    let req = test::TestRequest::put()
+        .uri("/__vite_actix/faults")
+        .peer_addr("127.0.0.1:50000".parse().unwrap())
+        .set_json(&faults)
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["error_rate"], 0.0);
+    let req = test::TestRequest::get().uri("/api/users").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::delete()
+        .uri("/__vite_actix/faults")
+        .peer_addr("127.0.0.1:50000".parse().unwrap())
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["max_latency_ms"], 0);
+}
+
+#[actix_web::test]
+async fn pings_reach_a_ready_vite_without_being_logged() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    common::capture_logs();
+    let logged = |id: &str| {
+        common::logged()
+            .iter()
+            .any(|logged| logged.message.contains(id))
+    };
+
+    let vite = MockVite::start().await;
+    let options = vite
+        .options()
+        .log_proxy_spans(true)
+        .navigation_fallback_only(true);
+    let app = test::init_service(App::new().configure_vite_with(options.clone())).await;
+    let ping = test::TestRequest::get()
+        .uri(PING_PATH)
+        .insert_header((REQUEST_ID_HEADER, "ping-1"))
+        .to_request();
+    let res = test::call_service(&app, ping).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(test::read_body(res).await, "mock vite: GET /__vite_ping");
+    let ping = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("accept", "text/x-vite-ping"))
+        .insert_header((REQUEST_ID_HEADER, "ping-2"))
+        .to_request();
+    assert_eq!(test::call_service(&app, ping).await.status(), 200);
+    assert_eq!(vite.requests().len(), 2);
+    assert_eq!(vite.requests()[1].header(REQUEST_ID_HEADER), Some("ping-2"));
+    assert!(!logged("ping-1") && !logged("ping-2"));
+
+    let options = options.log_vite_pings(true);
+    let app = test::init_service(App::new().configure_vite_with(options)).await;
+    let ping = test::TestRequest::get()
+        .uri(PING_PATH)
+        .insert_header((REQUEST_ID_HEADER, "ping-3"))
+        .to_request();
+    assert_eq!(test::call_service(&app, ping).await.status(), 200);
+    assert!(logged("ping-3"));
+}
+
+#[cfg(all(unix, feature = "spawn"))]
+#[actix_web::test]
+async fn pings_are_answered_while_the_managed_vite_is_down() {
+    use vite_actix::test::ViteShim;
+    use vite_actix::vite_state::ViteState;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    // A Vite that exits right away, and a server on its port that must not be asked.
+    let project = common::Project::new("ping");
+    let mock = MockVite::start().await;
+    ViteShim::new().exit(1).write(&project).unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .port(mock.port())
+        .build()
+        .unwrap();
+    let app = test::init_service(App::new().configure_vite()).await;
+
+    let _vite = vite_actix::start_vite_server_managed().unwrap();
+    let exited = || matches!(ViteState::current(), ViteState::Exited(_));
+    common::until("Vite exited", exited).await;
+    let ping = test::TestRequest::get().uri(PING_PATH).to_request();
+    assert_eq!(test::call_service(&app, ping).await.status(), 503);
+    let ping = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("accept", "text/x-vite-ping"))
+        .to_request();
+    assert_eq!(test::call_service(&app, ping).await.status(), 503);
+    let ping = test::TestRequest::get()
+        .uri("/")
+        .insert_header(("connection", "Upgrade"))
+        .insert_header(("upgrade", "websocket"))
+        .insert_header(("sec-websocket-version", "13"))
+        .insert_header(("sec-websocket-key", "dGhlIHNhbXBsZSBub25jZQ=="))
+        .insert_header(("sec-websocket-protocol", "vite-ping"))
+        .to_request();
+    assert_eq!(test::call_service(&app, ping).await.status(), 503);
+    assert!(mock.requests().is_empty());
+}
@@ -0,0 +1,117 @@
+//! Not spawning Vite where it is unwanted, e.g. on CI.
+#![cfg(unix)]
+
+mod common;
+
+use common::Project;
+use std::ffi::OsStr;
+use vite_actix::skip_spawn::default_detector;
+
+// Set in the copies of this binary that `probe` runs, which print what they detect.
+const PROBE: &str = "VITE_ACTIX_SKIP_SPAWN_PROBE";
+
+#[test]
+fn default_detector_checks_the_environment() {
+    if std::env::var_os(PROBE).is_some() {
+        // The test harness prints its progress to stdout.
+        eprint!("{:?}", default_detector());
+        std::process::exit(0);
+    }
+    // `cargo test` is recognized by where the binary lives, so this one, in `deps`, always
+    // is, and copies of it are run from a `deps` directory and from another one.
+    let project = Project::new("skip-spawn");
+    let copies = ["deps", "bin"].map(|dir| {
+        let exe = project.join(dir).join("probe");
+        std::fs::create_dir_all(exe.parent().unwrap()).unwrap();
+        std::fs::copy(std::env::current_exe().unwrap(), &exe).unwrap();
+        exe
+    });
+    let probe = |under_cargo_test: bool, env: &[(&str, &str)]| {
+        let output = std::process::Command::new(&copies[usize::from(!under_cargo_test)])
+            .args([
+                "default_detector_checks_the_environment",
+                "--exact",
+                "--nocapture",
+            ])
+            .env(PROBE, "1")
+            .env_remove("CI")
+            .env_remove("NEXTEST")
+            .env_remove("VITE_ACTIX_SKIP_SPAWN")
+            .envs(env.iter().copied())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stderr).unwrap()
+    };
+
+    assert_eq!(probe(false, &[]), "None");
+    assert_eq!(probe(true, &[]), r#"Some("running under cargo test")"#);
+    assert_eq!(
+        probe(false, &[("NEXTEST", "1")]),
+        r#"Some("running under cargo test")"#
+    );
+
+    for (value, skips) in [("true", true), ("1", true), ("false", false), ("0", false)] {
+        assert_eq!(
+            probe(false, &[("CI", value)]) != "None",
+            skips,
+            "CI={}",
+            value
+        );
+    }
+    assert_eq!(
+        probe(false, &[("CI", "true")]),
+        r#"Some("running on CI (CI is set)")"#
+    );
+    let env = [("CI", "true"), ("VITE_ACTIX_SKIP_SPAWN", "yes")];
+    assert_eq!(
+        probe(false, &env),
+        r#"Some("VITE_ACTIX_SKIP_SPAWN is set")"#
+    );
+    // Forcing the spawn wins over the other checks.
+    assert_eq!(
+        probe(false, &[("CI", "true"), ("VITE_ACTIX_SKIP_SPAWN", "0")]),
+        "None"
+    );
+    assert_eq!(probe(true, &[("VITE_ACTIX_SKIP_SPAWN", "false")]), "None");
+}
+
+#[actix_web::test]
+async fn skipped_vite_is_expected_to_run_elsewhere() {
+    use actix_web::{App, test};
+    use std::sync::Arc;
+    use vite_actix::test::MockVite;
+    use vite_actix::vite_app_factory::ViteAppFactory;
+    use vite_actix::vite_state::ViteState;
+
+    if !common::in_own_process(&[("CI", OsStr::new("true"))]) {
+        return;
+    }
+    common::capture_logs();
+
+    // Nothing is spawned and the proxy goes to a Vite started elsewhere, on the configured
+    // port.
+    let external = MockVite::start().await;
+    external
+        .options()
+        .skip_spawn_when(Arc::new(default_detector))
+        .build()
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    assert_eq!(vite.pid(), 0);
+    assert_eq!(vite.state(), ViteState::Idle);
+    let skipped = "Not starting Vite instance 'default': running on CI (CI is set)";
+    assert!(
+        common::logged()
+            .iter()
+            .any(|logged| logged.message == skipped)
+    );
+
+    let app = test::init_service(App::new().configure_vite()).await;
+    let request = test::TestRequest::get().uri("/about").to_request();
+    assert_eq!(
+        test::call_and_read_body(&app, request).await,
+        "mock vite: GET /about"
+    );
+    vite.shutdown().unwrap();
+}
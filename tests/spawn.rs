@@ -0,0 +1,1299 @@
+//! Starting, reading, restarting and stopping Vite, with the fake `vite` of
+//! `vite_actix::test::ViteShim`.
+#![cfg(unix)]
+
+mod common;
+
+use common::Project;
+use std::net::TcpListener;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use vite_actix::error::StartViteError;
+use vite_actix::events::ViteEvent;
+use vite_actix::proxy_vite_options::ProxyViteOptions;
+use vite_actix::test::{MockVite, ViteShim};
+use vite_actix::vite_server_handle::ViteServerHandle;
+use vite_actix::vite_state::{ReadyPhase, ViteReadyInfo, ViteState};
+
+// Whether the process `pid` is still running, as far as `kill -0` can tell.
+fn alive(pid: u32) -> bool {
+    let pid = pid.to_string();
+    Command::new("kill")
+        .args(["-0", &pid])
+        .status()
+        .unwrap()
+        .success()
+}
+
+#[test]
+fn proxy_follows_vite_to_another_port() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("moved");
+    ViteShim::new().banner(5199).write(&project).unwrap();
+    let options = || {
+        ProxyViteOptions::new()
+            .dev_mode(true)
+            .working_directory(project.dir())
+            .port(5198)
+    };
+
+    options().build().unwrap();
+    let mut events = vite_actix::events::subscribe();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    assert_eq!(
+        vite_actix::wait_for_vite_port(Duration::from_secs(10)).unwrap(),
+        5199
+    );
+    assert_eq!(ProxyViteOptions::global().port, Some(5199));
+    assert!(matches!(events.try_recv(), Ok(ViteEvent::Spawned { .. })));
+    assert_eq!(events.try_recv().unwrap(), ViteEvent::PortChanged(5199));
+    vite.shutdown().unwrap();
+
+    // With a strict port, Vite is stopped instead.
+    options().strict_port(true).build().unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    assert!(vite_actix::wait_for_vite_port(Duration::from_secs(10)).is_err());
+    while !matches!(vite.state(), ViteState::Failed { .. }) {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    let ViteState::Failed { output, .. } = vite.state() else {
+        unreachable!()
+    };
+    assert_eq!(
+        output,
+        [
+            "Vite listens on port 5199 instead of the configured port 5198, which strict_port \
+          does not allow"
+        ]
+    );
+    assert_eq!(ProxyViteOptions::global().port, Some(5198));
+}
+
+#[actix_web::test]
+async fn start_with_own_options_leaves_the_global_state_alone() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("with");
+    ViteShim::new().banner(5198).write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .port(5198);
+
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5198));
+    assert_eq!(ProxyViteOptions::global().port, None);
+    assert_eq!(ViteState::current(), ViteState::Idle);
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn odd_banner_lines_are_read_without_harm() {
+    let project = Project::new("corpus");
+    let huge = "x".repeat(1 << 20);
+    let shim = ViteShim::new()
+        .pause(Duration::from_millis(500))
+        .line("  ➜  Local:   http://localhost:")
+        .line("  ➜  Local:   http://localhost:70000/")
+        .line("Local network access disabled")
+        .line(huge.as_str())
+        .line("NOT UTF-8")
+        .line("  ➜  \x1b[1mLocal\x1b[22m:   http://localhost:\x1b[1m5301\x1b[22m/");
+    // `café` in Latin-1, which `printf` writes as the byte it is.
+    let script = shim
+        .script()
+        .replace("'NOT UTF-8'", r"'caf'$(printf '\351')' Local:'");
+    std::fs::write(shim.write(&project).unwrap(), script).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging();
+
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    let mut output = vite.subscribe_output();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5301));
+
+    let mut lines = Vec::new();
+    while let Ok(line) = output.try_recv() {
+        lines.push(line.line);
+    }
+    assert_eq!(lines.len(), 6, "{:?}", lines);
+    assert_eq!(lines[3], huge);
+    assert_eq!(lines[4], "caf\u{FFFD} Local:");
+    assert_eq!(lines[5], "  ➜  Local:   http://localhost:5301/");
+    vite.shutdown().unwrap();
+}
+
+#[test]
+fn wait_for_vite_port_fails_with_what_vite_printed() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("port");
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .build()
+        .unwrap();
+
+    // The port is returned once the banner is printed.
+    ViteShim::new()
+        .pause(Duration::from_millis(200))
+        .banner(5199)
+        .write(&project)
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    assert_eq!(
+        vite_actix::wait_for_vite_port(Duration::from_secs(10)).unwrap(),
+        5199
+    );
+    vite.shutdown().unwrap();
+
+    // A Vite that never gets there fails with what it printed.
+    ViteShim::new()
+        .line("  Pre-bundling dependencies...")
+        .write(&project)
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    actix_web::rt::System::new().block_on(async {
+        let error = vite_actix::wait_for_vite_port_async(Duration::from_millis(300))
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("did not report its port within 300ms"),
+            "{}",
+            error
+        );
+        assert!(error.contains("Pre-bundling dependencies..."), "{}", error);
+    });
+    let error = vite_actix::wait_for_vite_port(Duration::from_millis(100)).unwrap_err();
+    let Some(StartViteError::ReadyTimeout { captured }) = error.downcast_ref() else {
+        panic!("expected a ReadyTimeout: {:?}", error);
+    };
+    assert_eq!(captured, &["  Pre-bundling dependencies..."]);
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn serve_with_vite_stops_vite_with_the_server() {
+    use actix_web::{App, HttpServer};
+
+    // The server is also stopped by a SIGTERM to the whole process.
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("stop");
+    ViteShim::new().banner(5309).write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging();
+
+    for by_signal in [false, true] {
+        let vite = vite_actix::start_vite_server_with(&options).unwrap();
+        vite.wait_ready().await.unwrap();
+        let pid = vite.pid();
+        let server = HttpServer::new(App::new)
+            .workers(1)
+            .disable_signals()
+            .bind(("127.0.0.1", 0))
+            .unwrap()
+            .run();
+        let handle = server.handle();
+        let served = actix_web::rt::spawn(vite_actix::serve_with_vite(server, vite));
+        actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+        assert!(alive(pid));
+
+        if by_signal {
+            let me = std::process::id().to_string();
+            assert!(
+                Command::new("kill")
+                    .args(["-TERM", &me])
+                    .status()
+                    .unwrap()
+                    .success()
+            );
+        } else {
+            handle.stop(true).await;
+        }
+        served.await.unwrap().unwrap();
+        assert!(
+            !alive(pid),
+            "Vite is still running after the server stopped"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn serve_starts_vite_and_stops_it_with_the_server() {
+    use actix_web::{App, HttpResponse, web};
+    use vite_actix::vite_app_factory::ViteAppFactory;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("serve");
+    ViteShim::new().banner(5199).write(&project).unwrap();
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let options = ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir());
+    let app = || {
+        App::new()
+            .route(
+                "/api/ping",
+                web::get().to(|| async { HttpResponse::Ok().body("pong") }),
+            )
+            .configure_vite()
+    };
+    let server = actix_web::rt::spawn(vite_actix::serve(app, ("127.0.0.1", port), options));
+
+    // Vite is started, the proxy follows it to its port, and the app answers.
+    common::until("Vite is ready", || ViteState::current().is_ready()).await;
+    assert_eq!(ProxyViteOptions::global().port, Some(5199));
+    let mut res = awc::Client::new()
+        .get(format!("http://127.0.0.1:{}/api/ping", port))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.body().await.unwrap(), "pong");
+
+    // Stopping the server stops Vite.
+    let me = std::process::id().to_string();
+    Command::new("kill").args(["-TERM", &me]).status().unwrap();
+    server.await.unwrap().unwrap();
+    assert!(matches!(ViteState::current(), ViteState::Exited(_)));
+}
+
+#[actix_web::test]
+async fn lan_urls_follow_the_network_lines_of_the_banner() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("lan");
+    let mut shim = ViteShim::new();
+    for line in [
+        "",
+        "  VITE v5.4.0  ready in 312 ms",
+        "",
+        "  ➜  Local:   http://localhost:5305/",
+        "  ➜  Network: http://192.168.1.5:5305/",
+        "  ➜  Network: http://10.0.0.7:5305/",
+        "  ➜  press h + enter to show help",
+    ] {
+        shim = shim.line(line);
+    }
+    shim.write(&project).unwrap();
+
+    assert!(vite_actix::lan_urls(8080).is_empty());
+    ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging()
+        .arg("--host")
+        .build()
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    vite.wait_ready().await.unwrap();
+    let urls = vite_actix::lan_urls(8080);
+    let urls: Vec<&str> = urls.iter().map(|url| url.as_str()).collect();
+    assert_eq!(urls, ["http://192.168.1.5:8080/", "http://10.0.0.7:8080/"]);
+    vite.shutdown().unwrap();
+}
+
+#[test]
+fn output_is_read_with_and_without_a_runtime() {
+    let project = Project::new("pipeline");
+    let mut shim = ViteShim::new();
+    for line in 0..200 {
+        shim = if line % 2 == 0 {
+            shim.line(format!("stdout {}", line))
+        } else {
+            shim.stderr_line(format!("stderr {}", line))
+        };
+    }
+    shim.banner(5310).write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging();
+
+    // The 200 lines and the 6 of the banner, once the port is known.
+    let check = |vite: &ViteServerHandle| {
+        let started = Instant::now();
+        while vite.port().is_none() && started.elapsed() < Duration::from_secs(10) {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(vite.port(), Some(5310));
+        std::thread::sleep(Duration::from_millis(200));
+        let logs = vite.recent_logs(500);
+        assert_eq!(logs.len(), 206);
+        for line in 0..200 {
+            let stream = if line % 2 == 0 { "stdout" } else { "stderr" };
+            let text = format!("{} {}", stream, line);
+            assert!(
+                logs.iter().any(|log| log.line == text),
+                "{} was dropped",
+                text
+            );
+        }
+    };
+
+    assert!(tokio::runtime::Handle::try_current().is_err());
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    check(&vite);
+    vite.shutdown().unwrap();
+
+    actix_web::rt::System::new().block_on(async {
+        let vite = vite_actix::start_vite_server_with(&options).unwrap();
+        vite.wait_ready().await.unwrap();
+        // The pipeline runs on this runtime, so wait for it off its thread.
+        let vite = actix_web::rt::task::spawn_blocking(move || {
+            check(&vite);
+            vite
+        })
+        .await
+        .unwrap();
+        vite.shutdown().unwrap();
+    });
+}
+
+#[actix_web::test]
+async fn busy_ports_fail_the_start_or_move_vite() {
+    use vite_actix::port_conflict::{PortConflictStrategy, find_free_port, is_port_free};
+
+    let project = Project::new("conflict");
+    let taken = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+    let busy = taken.local_addr().unwrap().port();
+    let next = find_free_port(busy + 1).unwrap();
+    ViteShim::new().banner(next).write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .port(busy)
+        .strict_port(true);
+
+    // By default, starting fails, naming the port.
+    match vite_actix::start_vite_server_with(&options) {
+        Err(StartViteError::PortInUse { port, pid }) => {
+            assert_eq!(port, busy);
+            // If `lsof` can tell, the port is ours.
+            assert!(pid.is_none_or(|pid| pid == std::process::id()));
+        }
+        other => panic!(
+            "expected PortInUse, got {:?}",
+            other.map(|vite| vite.port())
+        ),
+    }
+
+    // Or Vite moves to the next free port, which the handle reports.
+    let options = options.port_conflict(PortConflictStrategy::NextFreePort);
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(next));
+    vite.shutdown().unwrap();
+
+    // A Vite listening on the IPv6 loopback, or on every interface, has the port too.
+    for address in ["::1", "0.0.0.0"] {
+        if let Ok(taken) = TcpListener::bind((address, 0)) {
+            assert!(
+                !is_port_free(taken.local_addr().unwrap().port()),
+                "{}",
+                address
+            );
+        }
+    }
+
+    // Past the last port, there is no next one.
+    if let Ok(_last) = TcpListener::bind(("127.0.0.1", u16::MAX)) {
+        let options = options.port(u16::MAX);
+        assert!(matches!(
+            vite_actix::start_vite_server_with(&options),
+            Err(StartViteError::NoFreePort { from: u16::MAX })
+        ));
+    }
+}
+
+#[test]
+fn pid_file_records_the_running_vite() {
+    use vite_actix::pid_file::PidFile;
+
+    let project = Project::new("pid-file");
+    ViteShim::new().banner(5296).write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .port(5296);
+
+    // The file records the process while it runs, and goes away with it.
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    let recorded = PidFile {
+        pid: vite.pid(),
+        port: Some(5296),
+    };
+    assert_eq!(PidFile::read(&project), Some(recorded));
+    vite.shutdown().unwrap();
+    assert_eq!(PidFile::read(&project), None);
+
+    // The file of a process that is gone is replaced.
+    let mut gone = Command::new("true").spawn().unwrap();
+    gone.wait().unwrap();
+    PidFile {
+        pid: gone.id(),
+        port: Some(5296),
+    }
+    .write(&project)
+    .unwrap();
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    assert_eq!(PidFile::read(&project).unwrap().pid, vite.pid());
+    vite.shutdown().unwrap();
+
+    // A live process that is not Vite, which got the recorded PID, is left alone.
+    let mut unrelated = Command::new("sleep").arg("30").spawn().unwrap();
+    PidFile {
+        pid: unrelated.id(),
+        port: Some(5296),
+    }
+    .write(&project)
+    .unwrap();
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    assert_eq!(PidFile::read(&project).unwrap().pid, vite.pid());
+    vite.shutdown().unwrap();
+    assert!(unrelated.try_wait().unwrap().is_none());
+    unrelated.kill().unwrap();
+    unrelated.wait().unwrap();
+}
+
+#[actix_web::test]
+async fn inherited_stdio_is_ready_once_the_port_answers() {
+    use actix_web::{App, HttpServer};
+    use vite_actix::vite_stdio::ViteStdio;
+
+    let project = Project::new("stdio");
+    ViteShim::new().write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .stdio(ViteStdio::Inherit);
+
+    // Without a port, there is nothing to probe.
+    assert!(std::env::var_os("VITE_PORT").is_none());
+    assert!(options.clone().build().is_err());
+
+    let vite = vite_actix::start_vite_server_with(&options.clone().port(5311)).unwrap();
+    let ready = actix_web::rt::spawn(vite.wait_ready());
+    actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+    assert!(!ready.is_finished());
+    assert!(!vite.state().is_ready());
+
+    // Ready once something answers HTTP on the port, like Vite would.
+    let server = HttpServer::new(App::new)
+        .workers(1)
+        .bind(("127.0.0.1", 5311))
+        .unwrap()
+        .run();
+    let server_handle = server.handle();
+    actix_web::rt::spawn(server);
+    actix_web::rt::time::timeout(Duration::from_secs(10), ready)
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(vite.port(), Some(5311));
+    // Nothing was captured.
+    assert!(vite.recent_logs(10).is_empty());
+    vite.shutdown().unwrap();
+    server_handle.stop(true).await;
+}
+
+#[test]
+fn untested_vite_versions_are_warned_about() {
+    use vite_actix::vite_version::ViteVersion;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    common::capture_logs();
+    let is_warning = |logged: &common::Logged| {
+        logged.level <= log::Level::Warn && logged.target == "vite_actix::vite_version"
+    };
+    let warnings = || -> Vec<String> {
+        let logged = common::take_logged().into_iter();
+        logged
+            .filter(is_warning)
+            .map(|logged| logged.message)
+            .collect()
+    };
+
+    let project = Project::new("version");
+    for (version, port, warning) in [
+        ("5.4.0", 5319, None),
+        (
+            "3.2.1",
+            5320,
+            Some("Vite 3.2.1 is older than the versions vite-actix is tested with"),
+        ),
+        (
+            "7.0.0",
+            5321,
+            Some("Vite 7.0.0 is newer than the versions vite-actix is tested with"),
+        ),
+    ] {
+        ViteShim::new()
+            .version(version)
+            .banner(port)
+            .write(&project)
+            .unwrap();
+        ProxyViteOptions::new()
+            .working_directory(project.dir())
+            .disable_logging()
+            .build()
+            .unwrap();
+        actix_web::rt::System::new().block_on(async {
+            let vite = vite_actix::start_vite_server_managed().unwrap();
+            vite.wait_ready().await.unwrap();
+            // The version is stored just before the warning is logged.
+            let logged = || {
+                let warned = warning.is_none() || common::logged().iter().any(is_warning);
+                vite.vite_version().is_some() && warned
+            };
+            common::until("the version is read", logged).await;
+            assert_eq!(vite.vite_version(), ViteVersion::parse(version));
+            assert_eq!(ViteVersion::current(), ViteVersion::parse(version));
+            vite.shutdown().unwrap();
+            vite.wait_exited().await;
+        });
+        let warned = warnings();
+        match warning {
+            Some(warning) => {
+                assert_eq!(warned.len(), 1, "{:?}", warned);
+                assert!(warned[0].starts_with(warning), "{}", warned[0]);
+                assert!(warned[0].contains("https://vite.dev/guide/migration"));
+            }
+            None => assert!(warned.is_empty(), "{:?}", warned),
+        }
+    }
+}
+
+#[actix_web::test]
+async fn startup_banner_is_logged_once_both_addresses_are_known() {
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    common::capture_logs();
+    let banners = || -> Vec<String> {
+        common::logged()
+            .into_iter()
+            .map(|logged| logged.message)
+            .filter(|message| message.starts_with("vite-actix is ready"))
+            .collect()
+    };
+    let project = Project::new("banner");
+    ViteShim::new().banner(5312).write(&project).unwrap();
+
+    ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .print_startup_banner(true)
+        .build()
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    vite.wait_ready().await.unwrap();
+    assert!(banners().is_empty(), "the server address is not known yet");
+
+    vite_actix::report_server_addr("127.0.0.1:8080".parse().unwrap());
+    vite_actix::report_server_addr("127.0.0.1:8081".parse().unwrap());
+    let logged = banners();
+    assert_eq!(logged.len(), 1);
+    assert!(logged[0].contains("App:   http://127.0.0.1:8080/"));
+    assert!(logged[0].contains("Vite:  http://localhost:5312/"));
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn output_log_file_is_rotated() {
+    let project = Project::new("log");
+    let log = project.join("logs/vite.log");
+    let mut shim = ViteShim::new();
+    for line in 0..20 {
+        shim = shim.line(format!("line {:02} {}", line, "-".repeat(50)));
+    }
+    shim.banner(5302).write(&project).unwrap();
+    // About 95 bytes a line, so two fit into a file.
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging()
+        .log_to_file(&log)
+        .log_file_rotation(200, 3);
+
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    actix_web::rt::time::sleep(Duration::from_millis(500)).await;
+    vite.shutdown().unwrap();
+
+    let read = |suffix: &str| std::fs::read_to_string(format!("{}{}", log.display(), suffix));
+    // Only the newest three rotated files are kept, none of them past the size.
+    assert!(read(".4").is_err());
+    let kept: Vec<String> = ["", ".1", ".2", ".3"]
+        .map(|suffix| read(suffix).unwrap())
+        .into();
+    assert!(
+        kept.iter()
+            .all(|text| !text.is_empty() && text.len() <= 200)
+    );
+    // Read from the oldest, they end with the banner, and the first lines are gone.
+    let newest_last: String = kept.iter().rev().map(String::as_str).collect();
+    assert!(
+        newest_last
+            .trim_end()
+            .ends_with("[stdout]   ➜  press h + enter to show help")
+    );
+    assert!(!newest_last.contains("[stdout] line 00"));
+}
+
+#[cfg(target_os = "linux")]
+#[actix_web::test]
+async fn low_priority_vite_runs_nicer() {
+    // The niceness of a process, the 19th field of `/proc/<pid>/stat`, counted after the
+    // command name in parentheses, which may hold spaces.
+    fn niceness(pid: &str) -> i32 {
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+        let fields: Vec<&str> = stat
+            .rsplit_once(')')
+            .unwrap()
+            .1
+            .split_whitespace()
+            .collect();
+        fields[16].parse().unwrap()
+    }
+
+    let project = Project::new("nice");
+    ViteShim::new().banner(5303).write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging()
+        .low_priority(true);
+
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    let own = niceness("self");
+    let nice = niceness(&vite.pid().to_string());
+    assert!(nice > 0);
+    assert_eq!(nice, (own + 10).min(19));
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn fully_ready_waits_for_the_dependencies() {
+    let project = Project::new("phase");
+    let forced = "Forced re-optimization of dependencies";
+    let optimized = concat!(
+        "\x1b[2m4:05:13 PM\x1b[22m \x1b[36m\x1b[1m[vite]\x1b[22m\x1b[39m ",
+        "\x1b[32m✨ new dependencies optimized: \x1b[33mvue, vue-router\x1b[32m\x1b[39m",
+    );
+    let reloading = "4:05:13 PM [vite] ✨ optimized dependencies changed. reloading";
+    let secs = |elapsed: Duration| elapsed.as_secs_f64();
+
+    // How long after spawning `on_ready` was called, and for which port.
+    let start = |phase| {
+        let readies = Arc::new(Mutex::new(Vec::new()));
+        let (spawned, recorded) = (Instant::now(), readies.clone());
+        let options = ProxyViteOptions::new()
+            .working_directory(project.dir())
+            .ready_phase(phase)
+            .on_ready(Arc::new(move |info: ViteReadyInfo| {
+                recorded
+                    .lock()
+                    .unwrap()
+                    .push((spawned.elapsed(), info.port));
+            }))
+            .disable_logging();
+        (
+            vite_actix::start_vite_server_with(&options).unwrap(),
+            readies,
+        )
+    };
+
+    // Nothing answers on 5322, so only the line tells that the dependencies are optimized.
+    ViteShim::new()
+        .line(forced)
+        .banner(5322)
+        .pause(Duration::from_secs(1))
+        .line(optimized)
+        .write(&project)
+        .unwrap();
+    let (vite, readies) = start(ReadyPhase::FullyReady);
+    common::until("Vite is ready", || vite.state() == ViteState::Ready).await;
+    assert!(readies.lock().unwrap().is_empty());
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.state(), ViteState::FullyReady);
+    // `on_ready` is called right after the state changes.
+    common::until("on_ready is called", || !readies.lock().unwrap().is_empty()).await;
+    let ready = readies.lock().unwrap().clone();
+    assert_eq!(ready.len(), 1);
+    assert!((0.9..5.0).contains(&secs(ready[0].0)), "{:?}", ready);
+    assert_eq!(ready[0].1, Some(5322));
+    vite.shutdown().unwrap();
+    vite.wait_exited().await;
+
+    // Vite serving its client quickly also does, unless it is still optimizing.
+    let mock = MockVite::start().await;
+    ViteShim::new().banner(mock.port()).write(&project).unwrap();
+    let (vite, readies) = start(ReadyPhase::FullyReady);
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.state(), ViteState::FullyReady);
+    common::until("on_ready is called", || !readies.lock().unwrap().is_empty()).await;
+    assert!(secs(readies.lock().unwrap()[0].0) < 0.9, "{:?}", readies);
+    assert!(
+        mock.requests()
+            .iter()
+            .any(|request| request.uri == "/@vite/client")
+    );
+    vite.shutdown().unwrap();
+    vite.wait_exited().await;
+
+    ViteShim::new()
+        .line(forced)
+        .banner(mock.port())
+        .pause(Duration::from_secs(1))
+        .line(reloading)
+        .write(&project)
+        .unwrap();
+    let (vite, readies) = start(ReadyPhase::FullyReady);
+    vite.wait_ready().await.unwrap();
+    common::until("on_ready is called", || !readies.lock().unwrap().is_empty()).await;
+    assert!(
+        (0.9..5.0).contains(&secs(readies.lock().unwrap()[0].0)),
+        "{:?}",
+        readies
+    );
+    vite.shutdown().unwrap();
+    vite.wait_exited().await;
+
+    // By default, the banner is enough.
+    ViteShim::new().banner(5322).write(&project).unwrap();
+    let (vite, readies) = start(ReadyPhase::Ready);
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.state(), ViteState::Ready);
+    common::until("on_ready is called", || !readies.lock().unwrap().is_empty()).await;
+    assert_eq!(readies.lock().unwrap().len(), 1);
+    vite.shutdown().unwrap();
+}
+
+#[test]
+fn events_follow_the_managed_vite() {
+    use tokio::sync::broadcast::error::TryRecvError;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let project = Project::new("events");
+    ViteShim::new()
+        .banner(5199)
+        // Output of stdout and stderr is read side by side, keep them apart.
+        .pause(Duration::from_millis(300))
+        .stderr_line("12:00:00 [vite] Internal server error: Unexpected token")
+        .stderr_line("  File: /app/src/main.ts:3:10")
+        .stderr_line("12:00:01 [vite] page reload src/main.ts")
+        .write(&project)
+        .unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .working_directory(project.dir())
+        .build()
+        .unwrap();
+    let mut events = vite_actix::events::subscribe();
+
+    // Started, restarted and stopped.
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    let first = vite.pid();
+    vite_actix::wait_for_vite_port(Duration::from_secs(10)).unwrap();
+    while vite.recent_errors().is_empty() {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    vite.restart().unwrap();
+    let second = vite.pid();
+    while vite.recent_errors().is_empty() {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    vite.shutdown().unwrap();
+
+    let mut received = Vec::new();
+    loop {
+        match events.try_recv() {
+            Ok(event) => received.push(event),
+            Err(TryRecvError::Empty) => break,
+            Err(e) => panic!("{}", e),
+        }
+    }
+    let run = |pid: u32, error: &ViteEvent| {
+        vec![
+            ViteEvent::Spawned { pid },
+            ViteEvent::PortChanged(5199),
+            ViteEvent::Ready(ViteReadyInfo { port: Some(5199) }),
+            error.clone(),
+        ]
+    };
+    let ViteEvent::CompileError(error) = &received[3] else {
+        panic!("{:?}", received);
+    };
+    assert_eq!(error.file.as_deref(), Some("/app/src/main.ts:3:10"));
+    let error = received[3].clone();
+    let ViteEvent::Exited(status) = received[4] else {
+        panic!("{:?}", received);
+    };
+    let mut expected = run(first, &error);
+    expected.push(ViteEvent::Exited(status));
+    expected.extend(run(second, &error));
+    expected.insert(
+        6,
+        ViteEvent::Restarted {
+            old_pid: first,
+            new_pid: second,
+        },
+    );
+    assert!(
+        matches!(received.last(), Some(ViteEvent::Exited(_))),
+        "{:?}",
+        received
+    );
+    received.pop();
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn recent_logs_keep_the_newest_lines() {
+    let project = Project::new("recent-logs");
+    let mut shim = ViteShim::new().banner(5197);
+    for i in 1..=8 {
+        shim = shim.line(format!("\x1b[32mline {}\x1b[39m", i));
+    }
+    shim.write(&project).unwrap();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .port(5197)
+        .output_buffer_lines(5);
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    let lines = || -> Vec<String> {
+        vite.recent_logs(100)
+            .into_iter()
+            .map(|line| line.line)
+            .collect()
+    };
+    while lines().last().map(String::as_str) != Some("line 8") {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+
+    // Past its capacity, the buffer drops the oldest lines and keeps the order.
+    assert_eq!(lines(), ["line 4", "line 5", "line 6", "line 7", "line 8"]);
+    let logs = vite.recent_logs(2);
+    assert_eq!(logs.len(), 2);
+    assert!(logs[0].at <= logs[1].at);
+    vite.shutdown().unwrap();
+}
+
+#[actix_web::test]
+async fn every_waiter_gets_the_exit_status() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let project = Project::new("exited");
+    ViteShim::new().banner(5293).write(&project).unwrap();
+    let exits = Arc::new(AtomicUsize::new(0));
+    let counted = exits.clone();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .port(5293)
+        .on_exit(Arc::new(move |_| {
+            counted.fetch_add(1, Ordering::SeqCst);
+        }));
+
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    let waiters = [vite.wait_exited(), vite.wait_exited(), vite.wait_exited()];
+    Command::new("kill")
+        .args(["-9", &vite.pid().to_string()])
+        .status()
+        .unwrap();
+    // Every waiter gets the status, and the callback runs once.
+    for status in futures_util::future::join_all(waiters).await {
+        assert_eq!(status.signal(), Some(9));
+    }
+    actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(exits.load(Ordering::SeqCst), 1);
+
+    // Dropping the last handle kills the process, which resolves the future.
+    let vite = vite_actix::start_vite_server_with(&options).unwrap();
+    vite.wait_ready().await.unwrap();
+    let exited = vite.wait_exited();
+    let copy = vite.clone();
+    drop(vite);
+    assert!(
+        copy.try_wait().unwrap().is_none(),
+        "a clone keeps the process alive"
+    );
+    drop(copy);
+    let status = actix_web::rt::time::timeout(Duration::from_secs(5), exited).await;
+    assert!(!status.unwrap().success());
+    actix_web::rt::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(exits.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn concurrent_restarts_wait_for_the_running_one() {
+    let project = Project::new("restart");
+    let started = project.join("started.log");
+    let vite = ViteShim::new()
+        .pause(Duration::from_millis(300))
+        .banner(5324);
+    // Count the starts, but not the `vite --version` calls.
+    let count = format!("&& exit\necho >> {}\n", started.display());
+    let script = vite.script().replacen("&& exit\n", &count, 1);
+    std::fs::write(vite.write(&project).unwrap(), script).unwrap();
+    let starts = || std::fs::read_to_string(&started).unwrap().lines().count();
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging();
+
+    let vite = actix_web::rt::System::new().block_on(async {
+        let vite = vite_actix::start_vite_server_with(&options).unwrap();
+        vite.wait_ready().await.unwrap();
+        let old_pid = vite.pid();
+        let mut events = vite.subscribe_events();
+        let mut output = vite.subscribe_output();
+
+        // The states the process goes through, until it is ready again.
+        let watching = vite.clone();
+        let states = actix_web::rt::spawn(async move {
+            let mut states = vec![watching.state()];
+            while states.len() < 2 || !states.last().unwrap().is_ready() {
+                actix_web::rt::time::sleep(Duration::from_millis(5)).await;
+                let state = watching.state();
+                if states.last() != Some(&state) {
+                    states.push(state);
+                }
+            }
+            states
+        });
+        // Calls made during a restart wait for it.
+        let restarts = tokio::join!(vite.restart_async(), vite.restart_async(), async {
+            actix_web::rt::time::sleep(Duration::from_millis(100)).await;
+            vite.restart_async().await
+        });
+        assert!(restarts.0.is_ok() && restarts.1.is_ok() && restarts.2.is_ok());
+        assert_eq!(starts(), 2);
+        let new_pid = vite.pid();
+        assert_ne!(new_pid, old_pid);
+        assert_eq!((vite.state(), vite.port()), (ViteState::Ready, Some(5324)));
+
+        let states = states.await.unwrap();
+        assert!(
+            matches!(states.first(), Some(ViteState::Ready)),
+            "{:?}",
+            states
+        );
+        assert!(
+            states.ends_with(&[ViteState::Starting, ViteState::Ready]),
+            "{:?}",
+            states
+        );
+        // Events are published after the state changes, so the last one may still be on its way.
+        let mut seen = Vec::new();
+        let ready = actix_web::rt::time::timeout(Duration::from_secs(5), async {
+            while let Ok(event) = events.recv().await {
+                let ready = matches!(event, ViteEvent::Ready(_));
+                seen.push(event);
+                if ready {
+                    return;
+                }
+            }
+        })
+        .await;
+        assert!(ready.is_ok(), "{:?}", seen);
+        assert!(
+            matches!(seen.first(), Some(ViteEvent::Exited(_))),
+            "{:?}",
+            seen
+        );
+        assert!(
+            seen.contains(&ViteEvent::Restarted { old_pid, new_pid }),
+            "{:?}",
+            seen
+        );
+        assert!(
+            matches!(seen.last(), Some(ViteEvent::Ready(_))),
+            "{:?}",
+            seen
+        );
+        // Subscribers get the output of the new process. The banner line is published right
+        // after the port it announces, so it may still be on its way.
+        let mut lines = Vec::new();
+        let banner = actix_web::rt::time::timeout(Duration::from_secs(5), async {
+            while let Ok(line) = output.recv().await {
+                let found = line.line.contains("localhost:5324");
+                lines.push(line.line);
+                if found {
+                    return;
+                }
+            }
+        })
+        .await;
+        assert!(banner.is_ok(), "{:?}", lines);
+        vite
+    });
+
+    // Outside of async code, `restart` blocks until the new process is ready.
+    let old_pid = vite.pid();
+    vite.restart().unwrap();
+    assert_ne!(vite.pid(), old_pid);
+    assert_eq!((vite.state(), starts()), (ViteState::Ready, 3));
+    vite.shutdown().unwrap();
+}
+
+#[test]
+fn named_instances_run_side_by_side() {
+    use vite_actix::vite_instances::{ViteInstanceOptions, ViteInstances};
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let root = Project::new("instances");
+    let (admin, public) = (root.join("admin"), root.join("public"));
+    ViteShim::new().banner(5291).write(&admin).unwrap();
+    ViteShim::new().banner(5292).write(&public).unwrap();
+    ProxyViteOptions::new()
+        .dev_mode(true)
+        .instance(
+            "admin",
+            ViteInstanceOptions::new()
+                .working_directory(admin.to_str().unwrap())
+                .port(5291),
+        )
+        // Without a port, the instance's is the one its Vite reports.
+        .instance(
+            "public",
+            ViteInstanceOptions::new().working_directory(public.to_str().unwrap()),
+        )
+        .build()
+        .unwrap();
+
+    let admin = ViteInstances::start("admin").unwrap();
+    ViteInstances::start("public").unwrap();
+    while ViteInstances::port("public").is_none() {
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(admin.port(), Some(5291));
+    assert_eq!(ViteInstances::get("public").unwrap().port(), Some(5292));
+    assert_eq!(ViteInstances::port("admin"), Some(5291));
+    let ports = ViteInstances::ports();
+    assert_eq!(
+        (ports.len(), ports["admin"], ports["public"]),
+        (2, 5291, 5292)
+    );
+    assert!(
+        ViteInstances::start("admin").is_err(),
+        "admin is already running"
+    );
+
+    // Stopping one instance leaves the other running.
+    ViteInstances::shutdown("admin").unwrap();
+    assert!(admin.try_wait().unwrap().is_some());
+    assert!(ViteInstances::get("admin").is_none());
+    let public = ViteInstances::get("public").unwrap();
+    assert!(public.try_wait().unwrap().is_none());
+    let ports: Vec<_> = ViteInstances::ports().into_iter().collect();
+    assert_eq!(ports, [("public".to_string(), 5292)]);
+
+    ViteInstances::shutdown_all().unwrap();
+    assert!(public.try_wait().unwrap().is_some());
+}
+
+#[cfg(feature = "watch")]
+#[actix_web::test]
+async fn config_changes_find_vite_again() {
+    use actix_web::{App, test};
+    use vite_actix::test::{MOCK_HEADER, MockResponse};
+    use vite_actix::vite_app_factory::ViteAppFactory;
+
+    if !common::in_own_process(&[]) {
+        return;
+    }
+    let signal = |pid: u32, signal| assert_eq!(unsafe { libc::kill(pid as i32, signal) }, 0);
+    let project = Project::new("watch");
+    let config = project.join("vite.config.ts");
+    let started = project.join("started.log");
+    std::fs::write(&config, "export default {}\n").unwrap();
+
+    // Vite restarting itself on another port prints its banner again, here on SIGHUP for
+    // the first mock and SIGUSR2 for the second. SIGUSR1 makes it crash.
+    let (old, new) = (MockVite::start().await, MockVite::start().await);
+    let local = |port: u16| format!("echo '  ➜  Local:   http://localhost:{}/'", port);
+    let vite = ViteShim::new().banner(5318).write(&project).unwrap();
+    let script = ViteShim::new().banner(5318).script().replace(
+        "exec sleep 600\n",
+        &format!(
+            "echo >> {}\ntrap \"{}\" HUP\ntrap \"{}\" USR2\ntrap 'exit 1' USR1\n{}",
+            started.display(),
+            local(old.port()),
+            local(new.port()),
+            "while :; do sleep 0.1; done\n"
+        ),
+    );
+    std::fs::write(&vite, script).unwrap();
+    ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .disable_logging()
+        .build()
+        .unwrap();
+    let vite = vite_actix::start_vite_server_managed().unwrap();
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5318));
+
+    // Nothing answers on 5318, so the change sends Vite back to starting until its banner.
+    std::fs::write(&config, "export default { server: { port: 3000 } }\n").unwrap();
+    common::until("Vite is starting", || !vite.state().is_ready()).await;
+    signal(vite.pid(), libc::SIGHUP);
+    common::until("Vite is on the new port", || {
+        vite.port() == Some(old.port())
+    })
+    .await;
+    vite.wait_ready().await.unwrap();
+
+    // A request in flight finishes against the old port, new ones go to the new one.
+    old.respond("/slow", MockResponse::ok().delay(Duration::from_secs(1)));
+    let app = test::init_service(App::new().configure_vite()).await;
+    let get = |uri: &str| test::TestRequest::get().uri(uri).to_request();
+    let (slow, fast) = tokio::join!(test::call_service(&app, get("/slow")), async {
+        actix_web::rt::time::sleep(Duration::from_millis(300)).await;
+        signal(vite.pid(), libc::SIGUSR2);
+        common::until("Vite moved again", || vite.port() == Some(new.port())).await;
+        test::call_service(&app, get("/about")).await
+    });
+    assert_eq!(
+        slow.headers().get(MOCK_HEADER).unwrap(),
+        &old.port().to_string()
+    );
+    assert_eq!(
+        fast.headers().get(MOCK_HEADER).unwrap(),
+        &new.port().to_string()
+    );
+
+    // A Vite that exited is started again.
+    signal(vite.pid(), libc::SIGUSR1);
+    vite.wait_exited().await;
+    std::fs::write(&config, "export default {}\n").unwrap();
+    let restarted = || std::fs::read_to_string(&started).unwrap().lines().count() == 2;
+    common::until("Vite is started again", restarted).await;
+    vite.wait_ready().await.unwrap();
+    assert_eq!(vite.port(), Some(5318));
+    vite.shutdown().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn vite_is_stopped_when_its_parent_is_killed() {
+    // The copy of this test binary that starts Vite and is SIGKILLed.
+    if let Some(project) = std::env::var_os("VITE_ACTIX_WRAPPER") {
+        let options = ProxyViteOptions::new()
+            .working_directory(project.to_str().unwrap())
+            .kill_on_parent_exit(true)
+            .disable_logging();
+        let _vite = vite_actix::start_vite_server_with(&options).unwrap();
+        std::thread::sleep(Duration::from_secs(600));
+    }
+
+    let project = Project::new("pdeath");
+    let log = project.join("vite.log");
+    let vite = ViteShim::new().banner(5315).write(&project).unwrap();
+    let script = ViteShim::new().banner(5315).script().replace(
+        "exec sleep 600\n",
+        &format!(
+            "trap 'echo TERM >> {0}; exit' TERM\necho $$ >> {0}\nwhile :; do sleep 0.1; done\n",
+            log.display()
+        ),
+    );
+    std::fs::write(&vite, script).unwrap();
+
+    let mut wrapper = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "vite_is_stopped_when_its_parent_is_killed",
+            "--exact",
+            "--nocapture",
+        ])
+        .env("VITE_ACTIX_WRAPPER", &*project)
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let read_log = |until: &dyn Fn(&str) -> bool| {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let lines = std::fs::read_to_string(&log).unwrap_or_default();
+            if until(&lines) || Instant::now() > deadline {
+                return lines;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    };
+    let started = read_log(&|lines| !lines.is_empty());
+    let pid: u32 = started.trim().parse().unwrap();
+    assert_ne!(pid, wrapper.id());
+
+    wrapper.kill().unwrap();
+    wrapper.wait().unwrap();
+    let lines = read_log(&|lines| lines.contains("TERM"));
+    assert_eq!(lines, format!("{}\nTERM\n", pid));
+}
+
+#[actix_web::test]
+async fn shutdown_stops_the_whole_process_group() {
+    // Whether the process is gone, or only waits to be reaped by someone else.
+    let gone = |pid: &str| {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid)).map_or(true, |stat| {
+            stat.rsplit(") ").next().unwrap().starts_with('Z')
+        })
+    };
+    let project = Project::new("group");
+    let log = project.join("vite.log");
+    let options = ProxyViteOptions::new()
+        .working_directory(project.dir())
+        .kill_on_parent_exit(true)
+        .disable_logging();
+
+    // A Vite that exits on SIGTERM is stopped right away, one that ignores it is killed after
+    // the grace period, along with a process it started in the background.
+    for (port, on_term, grace) in [(5316, "echo TERM >> {log}; exit", false), (5317, "", true)] {
+        let vite = ViteShim::new().banner(port).write(&project).unwrap();
+        let script = ViteShim::new().banner(port).script().replace(
+            "exec sleep 600\n",
+            &format!(
+                "trap '{}' TERM\nsleep 600 &\necho $! > {}\nwhile :; do sleep 0.1; done\n",
+                on_term.replace("{log}", &log.display().to_string()),
+                log.display()
+            ),
+        );
+        std::fs::write(&vite, script).unwrap();
+
+        let vite = vite_actix::start_vite_server_with(&options).unwrap();
+        vite.wait_ready().await.unwrap();
+        let background = std::fs::read_to_string(&log).unwrap();
+        let background = background.trim();
+        assert!(!gone(background));
+
+        let stopping = Instant::now();
+        vite.shutdown().unwrap();
+        vite.wait_exited().await;
+        assert_eq!(
+            stopping.elapsed() >= Duration::from_secs(3),
+            grace,
+            "{:?}",
+            stopping
+        );
+        common::until("the background process is gone", || gone(background)).await;
+
+        let lines = std::fs::read_to_string(&log).unwrap();
+        assert_eq!(lines.ends_with("TERM\n"), !grace, "{}", lines);
+        std::fs::remove_file(&log).unwrap();
+    }
+}